@@ -0,0 +1,54 @@
+//! Compares accumulating into the sorted `BTreeMap` with `--use-hashmap`
+//!
+//! Run with `cargo bench`.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use word_count::{count_reader, WordCountConfig};
+
+/// The number of words in the generated corpus
+const CORPUS_WORDS: usize = 10_000_000;
+
+/// The number of distinct words the corpus is drawn from
+const VOCABULARY: u64 = 200_000;
+
+/// Generate a corpus of pseudo random words, ten words per line
+fn corpus() -> String {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut corpus = String::with_capacity(CORPUS_WORDS * 8);
+    for i in 0..CORPUS_WORDS {
+        // xorshift keeps the corpus reproducible without a rng dependency
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        corpus.push_str(&format!("w{:x}", state % VOCABULARY));
+        corpus.push(if i % 10 == 9 { '\n' } else { ' ' });
+    }
+    corpus
+}
+
+fn bench_maps(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("count_10m_words");
+    group.sample_size(10);
+    for (name, use_hashmap) in [("btreemap", false), ("hashmap", true)] {
+        let config = WordCountConfig {
+            sleep: None,
+            use_hashmap,
+            ..Default::default()
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut word_counts = BTreeMap::new();
+                count_reader(Cursor::new(corpus.as_bytes()), &config, &mut word_counts).unwrap();
+                word_counts
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_maps);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+//! Measures how much of the counting time of a 100 MB corpus is spent in the
+//! sleep between chunks
+//!
+//! Run with `cargo bench --bench sleep`.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use word_count::{count_reader, WordCountConfig, DEFAULT_SLEEP_MS};
+
+/// The size in bytes of the generated corpus
+const CORPUS_BYTES: usize = 100 * 1000 * 1000;
+
+/// The number of distinct words the corpus is drawn from
+const VOCABULARY: u64 = 200_000;
+
+/// Generate a corpus of pseudo random words, ten words per line
+fn corpus() -> String {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut corpus = String::with_capacity(CORPUS_BYTES + 16);
+    let mut i = 0;
+    while corpus.len() < CORPUS_BYTES {
+        // xorshift keeps the corpus reproducible without a rng dependency
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        corpus.push_str(&format!("w{:x}", state % VOCABULARY));
+        corpus.push(if i % 10 == 9 { '\n' } else { ' ' });
+        i += 1;
+    }
+    corpus
+}
+
+fn bench_sleep(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("count_100mb");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(corpus.len() as u64));
+    let sleeps = [
+        ("sleep", Some(Duration::from_millis(DEFAULT_SLEEP_MS))),
+        ("no_sleep", None),
+    ];
+    for (name, sleep) in sleeps {
+        let config = WordCountConfig {
+            sleep,
+            use_hashmap: true,
+            ..Default::default()
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut word_counts = BTreeMap::new();
+                count_reader(Cursor::new(corpus.as_bytes()), &config, &mut word_counts).unwrap();
+                word_counts
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sleep);
+criterion_main!(benches);
@@ -0,0 +1,250 @@
+//! An asynchronous counting backend on top of tokio
+//!
+//! The lines are read asynchronously while the previous chunk is counted on
+//! a blocking worker, so slow storage and counting overlap. The counting
+//! itself is the same as on the synchronous path; `parallel`, `mmap` and
+//! `use_hashmap` have no effect and compressed inputs are not decompressed.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
+
+use crate::{
+    chunk_sentences, count_chunk, count_graphemes, decode, decode_utf8_lossy, elapsed,
+    observe_lines, retain_matching_lines, split_line_ending, start_timer, strip_chunk_comments,
+    ChunkObserver, Encoding, LineSample, NthLines, SeenLines, Totals, WordCountConfig, STDIN_PATH,
+};
+
+/// Count the words of a file asynchronously
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read, `-` for stdin
+/// * `config` - The options controlling how each token is normalised
+pub async fn count_words_async(
+    path: PathBuf,
+    config: WordCountConfig,
+) -> io::Result<BTreeMap<String, usize>> {
+    let mut word_counts = BTreeMap::new();
+    count_path_async(&path, &config, &mut word_counts, &mut ()).await?;
+    Ok(word_counts)
+}
+
+/// Count the words of a file or of stdin asynchronously, notifying
+/// `observer` after every chunk
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read, `-` for stdin
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+/// * `observer` - Gets notified after every chunk
+pub async fn count_path_async(
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    if config.encoding != Encoding::Utf8 {
+        let bytes = if path == Path::new(STDIN_PATH) {
+            let mut bytes = Vec::new();
+            tokio::io::stdin().read_to_end(&mut bytes).await?;
+            bytes
+        } else {
+            tokio::fs::read(path).await?
+        };
+        let text = decode(&bytes, config.encoding)?;
+        count_reader_async(io::Cursor::new(text), config, word_counts, observer).await
+    } else if path == Path::new(STDIN_PATH) {
+        let reader = BufReader::with_capacity(config.buffer_size, tokio::io::stdin());
+        count_reader_async(reader, config, word_counts, observer).await
+    } else {
+        let file = tokio::fs::File::open(path).await?;
+        let reader = BufReader::with_capacity(config.buffer_size, file);
+        count_reader_async(reader, config, word_counts, observer).await
+    }
+}
+
+/// Count the words of an asynchronous reader in chunks
+///
+/// # Arguments
+///
+/// * `reader` - The reader to consume line by line
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+/// * `observer` - Gets notified after every chunk
+pub async fn count_reader_async<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let shared = Arc::new(config.clone());
+    let mut totals = Totals::default();
+    let mut state = Some((mem::take(word_counts), VecDeque::new(), 0));
+    let mut pending = None;
+    // the number of lines read so far, for the `line_range`
+    let mut read = config.skip_lines;
+    let mut seen = config.dedup_lines.map(SeenLines::new);
+    let mut in_comment = false;
+    let mut sample = config
+        .sample_rate
+        .map(|rate| LineSample::new(rate, config.hash_seed));
+    let mut nth_lines = config.every_nth_line.map(NthLines::new);
+
+    // lines counted by an earlier run are neither counted nor totalled again
+    let mut line = String::new();
+    for _ in 0..config.skip_lines {
+        line.clear();
+        if read_line_async(&mut reader, config, &mut line, &mut totals).await? == 0 {
+            break;
+        }
+    }
+
+    loop {
+        let mut chunk_totals = Totals::default();
+        let reading = start_timer();
+        let mut chunk = read_chunk_async(&mut reader, config, &mut read, &mut chunk_totals).await?;
+        let reading = elapsed(reading);
+
+        // the previous chunk was counted while this one was read
+        if let Some((handle, previous_totals)) = pending.take() {
+            let (counts, window, position, (words, dropped), counting) = join(handle).await?;
+            totals += previous_totals;
+            totals.words += words;
+            totals.words_dropped += dropped;
+            observer.chunk_done(&totals, &counts)?;
+            state = Some((counts, window, position));
+            let sleeping = start_timer();
+            if let Some(sleep) = config.sleep {
+                tokio::time::sleep(sleep).await;
+            }
+            observer.round_timed(counting, elapsed(sleeping))?;
+            if config
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                // the chunk read meanwhile is left uncounted
+                totals.timed_out = true;
+                break;
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        let collecting = start_timer();
+        strip_chunk_comments(&mut chunk, config, &mut in_comment);
+        retain_matching_lines(&mut chunk, config);
+        if let Some(seen) = &mut seen {
+            chunk_totals.duplicate_lines += seen.remove_seen(&mut chunk);
+        }
+        if let Some(sample) = &mut sample {
+            sample.retain_sampled(&mut chunk);
+        }
+        if let Some(nth_lines) = &mut nth_lines {
+            nth_lines.retain_nth(&mut chunk);
+        }
+
+        chunk_totals.lines = observe_lines(&mut chunk, config, observer);
+        if config.sentences {
+            chunk_totals.sentences += chunk_sentences(&chunk, config);
+        }
+        observer.round_read(reading, elapsed(collecting));
+        let (mut counts, mut window, mut position) =
+            state.take().expect("the previous chunk was joined");
+        let config = Arc::clone(&shared);
+        let handle = tokio::task::spawn_blocking(move || {
+            let counting = start_timer();
+            let counted = count_chunk(&chunk, &config, &mut window, &mut position, &mut counts);
+            (counts, window, position, counted, elapsed(counting))
+        });
+        pending = Some((handle, chunk_totals));
+    }
+
+    if let Some((counts, _, _)) = state {
+        *word_counts = counts;
+    }
+    Ok(totals)
+}
+
+/// The result of counting a chunk on a blocking worker, with the time it
+/// took
+type Counted = (
+    BTreeMap<String, usize>,
+    VecDeque<String>,
+    usize,
+    (usize, usize),
+    Duration,
+);
+
+/// Wait for a blocking worker, turning a panic into an error
+async fn join(handle: tokio::task::JoinHandle<Counted>) -> io::Result<Counted> {
+    handle.await.map_err(io::Error::other)
+}
+
+/// Read the next line into `line` like `AsyncBufReadExt::read_line`
+///
+/// With `force_utf8` invalid UTF-8 is replaced instead of failing, adding
+/// the replaced sequences to `totals`.
+async fn read_line_async<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    config: &WordCountConfig,
+    line: &mut String,
+    totals: &mut Totals,
+) -> io::Result<usize> {
+    if !config.force_utf8 {
+        return reader.read_line(line).await;
+    }
+    let mut bytes = Vec::new();
+    let read = reader.read_until(b'\n', &mut bytes).await?;
+    let (text, invalid_utf8) = decode_utf8_lossy(&bytes);
+    line.push_str(&text);
+    totals.invalid_utf8 += invalid_utf8;
+    Ok(read)
+}
+
+/// Read up to `chunk_size` lines without their terminators, adding their
+/// bytes, chars and number to `totals`
+///
+/// Like on the synchronous path, lines outside the `line_range` are only
+/// added to `lines_read`. `read` is the number of lines read before.
+async fn read_chunk_async<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    config: &WordCountConfig,
+    read: &mut usize,
+    totals: &mut Totals,
+) -> io::Result<Vec<String>> {
+    let mut chunk = Vec::with_capacity(config.chunk_size);
+    while chunk.len() < config.chunk_size {
+        let range = config.line_range;
+        if range.is_some_and(|range| range.is_past(*read + 1)) {
+            break;
+        }
+        let mut line = String::new();
+        if read_line_async(reader, config, &mut line, totals).await? == 0 {
+            break;
+        }
+        *read += 1;
+        if range.is_some_and(|range| !range.contains(*read)) {
+            totals.lines_read += 1;
+            continue;
+        }
+        let length = split_line_ending(&line).0.len();
+        let ending = line.len() - length;
+        line.truncate(length);
+        // line terminators are ASCII, so their byte and char lengths agree
+        totals.bytes += length + ending;
+        totals.chars += line.chars().count() + ending;
+        if config.count_graphemes {
+            totals.graphemes += count_graphemes(&line) + usize::from(ending > 0);
+        }
+        totals.lines_read += 1;
+        chunk.push(line);
+    }
+    Ok(chunk)
+}
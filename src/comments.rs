@@ -0,0 +1,108 @@
+//! Stripping the comments of source code before it is counted, see
+//! `CommentLanguage`
+//!
+//! This is no parser: a comment marker inside a string literal starts a
+//! comment all the same, and the block comments of Rust do not nest.
+
+use std::borrow::Cow;
+
+use crate::CommentLanguage;
+
+/// The comment markers of a `CommentLanguage`
+struct Syntax {
+    /// Starts a comment running to the end of the line
+    line: Option<&'static str>,
+    /// Start and end a comment that may span lines
+    block: Option<(&'static str, &'static str)>,
+    /// Whether the `<...>` tags are stripped as well
+    tags: bool,
+}
+
+impl CommentLanguage {
+    fn syntax(self) -> Syntax {
+        match self {
+            Self::Rust => Syntax {
+                line: Some("//"),
+                block: Some(("/*", "*/")),
+                tags: false,
+            },
+            Self::Python => Syntax {
+                line: Some("#"),
+                block: None,
+                tags: false,
+            },
+            Self::Lua => Syntax {
+                line: Some("--"),
+                block: Some(("--[[", "]]")),
+                tags: false,
+            },
+            Self::Html => Syntax {
+                line: None,
+                block: Some(("<!--", "-->")),
+                tags: true,
+            },
+        }
+    }
+}
+
+/// `line` without its comments in `language`, each replaced by a space so
+/// the words around it are not joined
+///
+/// `in_block` tells whether the line starts inside a block comment and is
+/// updated to whether the next one does. An HTML tag ends at the end of its
+/// line if it is not closed before.
+pub(crate) fn strip_comments<'a>(
+    line: &'a str,
+    language: CommentLanguage,
+    in_block: &mut bool,
+) -> Cow<'a, str> {
+    let syntax = language.syntax();
+    let mut kept = String::new();
+    let mut rest = line;
+    loop {
+        if *in_block {
+            let (_, close) = syntax.block.expect("only block comments span lines");
+            match rest.find(close) {
+                Some(end) => {
+                    *in_block = false;
+                    kept.push(' ');
+                    rest = &rest[end + close.len()..];
+                }
+                None => return Cow::Owned(kept),
+            }
+        }
+        let block = syntax
+            .block
+            .and_then(|(open, _)| rest.find(open).map(|start| (start, open.len())));
+        let marker = syntax.line.and_then(|marker| rest.find(marker));
+        let tag = rest.find('<').filter(|_| syntax.tags);
+        // a block comment wins a tie, like `--[[` over `--` in Lua
+        let first = [marker, tag].into_iter().flatten().min();
+        match (block, first) {
+            (Some((start, open)), first) if first.is_none_or(|first| start <= first) => {
+                kept.push_str(&rest[..start]);
+                *in_block = true;
+                rest = &rest[start + open..];
+            }
+            (_, None) if kept.is_empty() && rest.len() == line.len() => {
+                return Cow::Borrowed(line);
+            }
+            (_, None) => {
+                kept.push_str(rest);
+                return Cow::Owned(kept);
+            }
+            (_, Some(start)) if marker == Some(start) => {
+                kept.push_str(&rest[..start]);
+                return Cow::Owned(kept);
+            }
+            (_, Some(start)) => {
+                kept.push_str(&rest[..start]);
+                kept.push(' ');
+                rest = match rest[start..].find('>') {
+                    Some(end) => &rest[start + end + 1..],
+                    None => "",
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Counting the user-perceived characters of a text, its grapheme clusters
+//!
+//! This follows the extended grapheme cluster rules of Unicode Standard
+//! Annex #29 for the marks, emoji and joiners of the most common scripts,
+//! told apart by the ranges below instead of the full Unicode property
+//! tables. Spacing marks and the Indic conjunct rules are not covered, so
+//! some clusters of Indic scripts are counted as more than one.
+
+/// Count the grapheme clusters of `text`, e.g. 1 for "é" written as an `e`
+/// and a combining accent, or for the family emoji "👨‍👩‍👧" made of five
+/// chars
+///
+/// A `\r\n` counts as one cluster, like any other line terminator.
+///
+/// ```
+/// use word_count::count_graphemes;
+///
+/// assert_eq!(count_graphemes("e\u{301}te\u{301}"), 3);
+/// assert_eq!(count_graphemes("👍🏽!"), 2);
+/// ```
+pub fn count_graphemes(text: &str) -> usize {
+    let mut clusters = 0;
+    let mut previous: Option<char> = None;
+    // the regional indicators of the current run, which pair up into flags
+    let mut indicators = 0;
+    // whether the chars since the last pictograph only extend it, so a
+    // joiner after them joins the next pictograph to the cluster
+    let mut after_pictograph = false;
+    for c in text.chars() {
+        let joined = match previous {
+            None => false,
+            Some('\r') => c == '\n',
+            Some('\n') => false,
+            Some('\u{200D}') if is_pictographic(c) => after_pictograph,
+            Some(_) if is_regional_indicator(c) => indicators % 2 == 1,
+            Some(_) => is_extend(c),
+        };
+        indicators = if is_regional_indicator(c) {
+            indicators + 1
+        } else {
+            0
+        };
+        after_pictograph = is_pictographic(c) || after_pictograph && joined && is_extend(c);
+        if !joined {
+            clusters += 1;
+        }
+        previous = Some(c);
+    }
+    clusters
+}
+
+/// Whether `c` is a pictograph, like most emoji, which a joiner joins to the
+/// pictograph before it
+fn is_pictographic(c: char) -> bool {
+    const RANGES: [(char, char); 30] = [
+        ('\u{00A9}', '\u{00A9}'),
+        ('\u{00AE}', '\u{00AE}'),
+        ('\u{203C}', '\u{203C}'),
+        ('\u{2049}', '\u{2049}'),
+        ('\u{2122}', '\u{2122}'),
+        ('\u{2139}', '\u{2139}'),
+        ('\u{2194}', '\u{2199}'),
+        ('\u{21A9}', '\u{21AA}'),
+        ('\u{231A}', '\u{231B}'),
+        ('\u{2328}', '\u{2328}'),
+        ('\u{23CF}', '\u{23CF}'),
+        ('\u{23E9}', '\u{23F3}'),
+        ('\u{23F8}', '\u{23FA}'),
+        ('\u{24C2}', '\u{24C2}'),
+        ('\u{25AA}', '\u{25AB}'),
+        ('\u{25B6}', '\u{25B6}'),
+        ('\u{25C0}', '\u{25C0}'),
+        ('\u{25FB}', '\u{25FE}'),
+        ('\u{2600}', '\u{27BF}'),
+        ('\u{2934}', '\u{2935}'),
+        ('\u{2B05}', '\u{2B07}'),
+        ('\u{2B1B}', '\u{2B1C}'),
+        ('\u{2B50}', '\u{2B50}'),
+        ('\u{2B55}', '\u{2B55}'),
+        ('\u{3030}', '\u{3030}'),
+        ('\u{303D}', '\u{303D}'),
+        ('\u{3297}', '\u{3299}'),
+        ('\u{1F000}', '\u{1F1E5}'),
+        ('\u{1F200}', '\u{1FAFF}'),
+        ('\u{1FC00}', '\u{1FFFD}'),
+    ];
+    RANGES
+        .iter()
+        .any(|&(first, last)| (first..=last).contains(&c))
+}
+
+/// Whether `c` is a regional indicator, two of which make a flag emoji
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// Whether `c` extends the cluster before it: a combining mark, a variation
+/// selector, an emoji modifier or tag, a joiner or a Hangul vowel or final
+/// consonant
+pub(crate) fn is_extend(c: char) -> bool {
+    const RANGES: [(char, char); 21] = [
+        ('\u{0300}', '\u{036F}'),
+        ('\u{0483}', '\u{0489}'),
+        ('\u{0591}', '\u{05C7}'),
+        ('\u{0610}', '\u{061A}'),
+        ('\u{064B}', '\u{065F}'),
+        ('\u{0670}', '\u{0670}'),
+        ('\u{06D6}', '\u{06DC}'),
+        ('\u{06DF}', '\u{06E4}'),
+        ('\u{093A}', '\u{094F}'),
+        ('\u{0E31}', '\u{0E3A}'),
+        ('\u{0E47}', '\u{0E4E}'),
+        ('\u{1160}', '\u{11FF}'),
+        ('\u{1AB0}', '\u{1AFF}'),
+        ('\u{1DC0}', '\u{1DFF}'),
+        ('\u{200C}', '\u{200D}'),
+        ('\u{20D0}', '\u{20FF}'),
+        ('\u{FE00}', '\u{FE0F}'),
+        ('\u{FE20}', '\u{FE2F}'),
+        ('\u{1F3FB}', '\u{1F3FF}'),
+        ('\u{E0020}', '\u{E007F}'),
+        ('\u{E0100}', '\u{E01EF}'),
+    ];
+    RANGES
+        .iter()
+        .any(|&(first, last)| (first..=last).contains(&c))
+}
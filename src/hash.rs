@@ -0,0 +1,147 @@
+//! The hashers the `HashMap`s of `use_hashmap` and `parallel` can count
+//! with, see `HashAlgorithm`
+//!
+//! FxHash and FNV-1a are small enough to be written out here instead of
+//! pulling in a crate each. Both are fast on short keys like words but not
+//! resistant to collisions chosen by an attacker.
+
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hasher};
+
+use foldhash::fast::{FoldHasher, SeedableRandomState};
+use foldhash::SharedSeed;
+
+use crate::HashAlgorithm;
+
+/// Builds the hashers of a `HashAlgorithm`, seeded with the `hash_seed` of
+/// the config or randomly
+#[derive(Debug, Clone)]
+pub(crate) enum CountHashState {
+    Foldhash(SeedableRandomState),
+    /// The keys of std's `RandomState`, or a fixed key and a seed written
+    /// into every hasher first, as SipHash cannot be seeded otherwise
+    Siphash(Option<RandomState>, Option<u64>),
+    Fxhash(u64),
+    Fnv(u64),
+}
+
+impl CountHashState {
+    /// The state of `algorithm`, the same on every run with a `seed`
+    ///
+    /// FxHash and FNV-1a are only seeded with a `seed`: without one they
+    /// start from their standard initial states.
+    pub(crate) fn new(algorithm: HashAlgorithm, seed: Option<u64>) -> Self {
+        match algorithm {
+            // the seed is the per-hasher seed of foldhash 0.2's
+            // `fast::FoldHasher`, combined with its fixed shared seed
+            HashAlgorithm::Foldhash => Self::Foldhash(match seed {
+                Some(seed) => SeedableRandomState::with_seed(seed, SharedSeed::global_fixed()),
+                None => SeedableRandomState::random(),
+            }),
+            HashAlgorithm::Siphash => match seed {
+                Some(seed) => Self::Siphash(None, Some(seed)),
+                None => Self::Siphash(Some(RandomState::new()), None),
+            },
+            HashAlgorithm::Fxhash => Self::Fxhash(seed.unwrap_or(0)),
+            HashAlgorithm::Fnv => Self::Fnv(FNV_OFFSET_BASIS ^ seed.unwrap_or(0)),
+        }
+    }
+}
+
+impl BuildHasher for CountHashState {
+    type Hasher = CountHasher;
+
+    fn build_hasher(&self) -> CountHasher {
+        match self {
+            Self::Foldhash(state) => CountHasher::Foldhash(state.build_hasher()),
+            Self::Siphash(state, seed) => {
+                let mut hasher = state
+                    .as_ref()
+                    .map_or_else(DefaultHasher::new, RandomState::build_hasher);
+                if let Some(seed) = seed {
+                    hasher.write_u64(*seed);
+                }
+                CountHasher::Siphash(hasher)
+            }
+            Self::Fxhash(hash) => CountHasher::Fxhash(*hash),
+            Self::Fnv(hash) => CountHasher::Fnv(*hash),
+        }
+    }
+}
+
+/// A hasher of a `CountHashState`, dispatching to its algorithm
+#[derive(Clone)]
+pub(crate) enum CountHasher {
+    Foldhash(FoldHasher<'static>),
+    Siphash(DefaultHasher),
+    /// The hash so far
+    Fxhash(u64),
+    /// The hash so far
+    Fnv(u64),
+}
+
+/// The multiplier of FxHash, as in rustc's `FxHasher` on 64-bit targets
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+/// The initial state of 64-bit FNV-1a
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+/// The prime of 64-bit FNV-1a
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Add `word` to the FxHash `hash`
+fn fx_add(hash: u64, word: u64) -> u64 {
+    (hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED)
+}
+
+impl Hasher for CountHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Foldhash(hasher) => hasher.write(bytes),
+            Self::Siphash(hasher) => hasher.write(bytes),
+            Self::Fxhash(hash) => {
+                // eight bytes at a time, then the rest in decreasing sizes
+                let mut chunks = bytes.chunks_exact(8);
+                for chunk in &mut chunks {
+                    let word = u64::from_le_bytes(chunk.try_into().expect("8 bytes"));
+                    *hash = fx_add(*hash, word);
+                }
+                let mut rest = chunks.remainder();
+                if rest.len() >= 4 {
+                    let word = u32::from_le_bytes(rest[..4].try_into().expect("4 bytes"));
+                    *hash = fx_add(*hash, u64::from(word));
+                    rest = &rest[4..];
+                }
+                if rest.len() >= 2 {
+                    let word = u16::from_le_bytes(rest[..2].try_into().expect("2 bytes"));
+                    *hash = fx_add(*hash, u64::from(word));
+                    rest = &rest[2..];
+                }
+                if let [byte] = rest {
+                    *hash = fx_add(*hash, u64::from(*byte));
+                }
+            }
+            Self::Fnv(hash) => {
+                for &byte in bytes {
+                    *hash ^= u64::from(byte);
+                    *hash = hash.wrapping_mul(FNV_PRIME);
+                }
+            }
+        }
+    }
+
+    // a `str` key is hashed as its bytes and a single `write_u8`, which
+    // foldhash handles on its own
+    fn write_u8(&mut self, byte: u8) {
+        match self {
+            Self::Foldhash(hasher) => hasher.write_u8(byte),
+            _ => self.write(&[byte]),
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        match self {
+            Self::Foldhash(hasher) => hasher.finish(),
+            Self::Siphash(hasher) => hasher.finish(),
+            Self::Fxhash(hash) | Self::Fnv(hash) => *hash,
+        }
+    }
+}
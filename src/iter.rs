@@ -0,0 +1,168 @@
+//! A lazy iterator over the word counts of an input, chunk by chunk
+
+use std::collections::{btree_map, BTreeMap, VecDeque};
+use std::io::{self, BufRead};
+
+use crate::{
+    chunk_sentences, count_chunk, count_lines_in_chunk, merge_counts_capped, read_chunk,
+    TerminatedLines, Totals, WordCountConfig,
+};
+
+/// Yields the word counts of an input one chunk at a time
+///
+/// A chunk is only read once the entries of the previous one are used up, so
+/// the input is never read further than needed. Every entry is the count of
+/// a word within its chunk only: a word occurring in several chunks is
+/// yielded once per chunk and no count is final before the input is done.
+/// `collect_final` returns the complete counts instead.
+///
+/// The input is counted sequentially, so `parallel`, `mmap` and
+/// `use_hashmap` have no effect. Iteration stops at the first read error,
+/// which `collect_final` returns.
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use word_count::WordCounter;
+///
+/// let mut counter = WordCounter::new();
+/// counter.chunk_size(1).sleep(None);
+/// let mut iter = counter.iter_reader(Cursor::new("a b\na"));
+/// assert_eq!(iter.next(), Some((String::from("a"), 1)));
+/// assert_eq!(iter.next(), Some((String::from("b"), 1)));
+/// assert_eq!(iter.next(), Some((String::from("a"), 1)));
+/// assert_eq!(iter.collect_final().unwrap().get("a"), Some(&2));
+/// ```
+pub struct WordCountIter<R> {
+    lines: TerminatedLines<R>,
+    config: WordCountConfig,
+    /// The last `ngrams - 1` tokens of the previous chunk
+    window: VecDeque<String>,
+    /// The number of units seen so far, for `every_nth_word`
+    position: usize,
+    /// The entries of the current chunk not yielded yet
+    chunk: btree_map::IntoIter<String, usize>,
+    /// The counts of all chunks read so far
+    word_counts: BTreeMap<String, usize>,
+    totals: Totals,
+    /// Whether the lines of `skip_lines` were skipped already
+    skipped: bool,
+    /// Whether the input is exhausted or failed
+    done: bool,
+    /// The error the input failed with, if any
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> WordCountIter<R> {
+    /// Iterate over the word counts of `reader`, counted as set in `config`
+    pub fn new(reader: R, config: WordCountConfig) -> Self {
+        Self {
+            lines: TerminatedLines::new(reader, config.force_utf8),
+            config,
+            window: VecDeque::new(),
+            position: 0,
+            chunk: BTreeMap::new().into_iter(),
+            word_counts: BTreeMap::new(),
+            totals: Totals::default(),
+            skipped: false,
+            done: false,
+            error: None,
+        }
+    }
+
+    /// The totals of the chunks read so far
+    pub fn totals(&self) -> &Totals {
+        &self.totals
+    }
+
+    /// Read the rest of the input and return the complete word counts,
+    /// including the chunks already yielded
+    pub fn collect_final(mut self) -> io::Result<BTreeMap<String, usize>> {
+        while !self.done {
+            self.chunk = BTreeMap::new().into_iter();
+            self.advance();
+        }
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.word_counts),
+        }
+    }
+
+    /// Count the next chunk, marking the iterator done at the end of the
+    /// input or on an error
+    fn advance(&mut self) {
+        match self.read_next_chunk() {
+            Ok(true) => {}
+            Ok(false) => self.done = true,
+            Err(err) => {
+                self.error = Some(err);
+                self.done = true;
+            }
+        }
+    }
+
+    /// Count the next chunk into `chunk`, returning whether there was one
+    fn read_next_chunk(&mut self) -> io::Result<bool> {
+        if !self.skipped {
+            self.skipped = true;
+            // lines counted by an earlier run are neither counted nor totalled again
+            for _ in 0..self.config.skip_lines {
+                match self.lines.next() {
+                    Some(line) => drop(line?),
+                    None => break,
+                }
+            }
+        }
+
+        let chunk = read_chunk(&mut self.lines, &self.config, &mut self.totals)?;
+        self.totals.invalid_utf8 = self.lines.invalid_utf8;
+        if chunk.is_empty() {
+            return Ok(false);
+        }
+        self.totals.lines += count_lines_in_chunk(&chunk);
+        if self.config.sentences {
+            self.totals.sentences += chunk_sentences(&chunk, &self.config);
+        }
+        let mut counts = BTreeMap::new();
+        let (counted, dropped) = count_chunk(
+            &chunk,
+            &self.config,
+            &mut self.window,
+            &mut self.position,
+            &mut counts,
+        );
+        self.totals.words += counted;
+        self.totals.words_dropped += dropped;
+        let dropped = merge_counts_capped(
+            &mut self.word_counts,
+            counts.clone(),
+            self.config.max_unique_words,
+        );
+        self.totals.words -= dropped;
+        self.totals.words_dropped += dropped;
+        // only the words that made it into the counts are yielded
+        counts.retain(|word, _| self.word_counts.contains_key(word));
+        self.chunk = counts.into_iter();
+
+        if let Some(sleep) = self.config.sleep {
+            std::thread::sleep(sleep);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Iterator for WordCountIter<R> {
+    type Item = (String, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.chunk.next() {
+                return Some(entry);
+            }
+            if self.done {
+                return None;
+            }
+            self.advance();
+        }
+    }
+}
@@ -0,0 +1,12266 @@
+//! Count the words of text files in power-friendly chunks.
+//!
+//! Input is read in chunks of lines with a short sleep after each one, so the
+//! cpu gets a chance to drop into a lower power state between bursts of work.
+//!
+//! ```
+//! use std::io::Cursor;
+//!
+//! use word_count::WordCounter;
+//!
+//! let word_counts = WordCounter::new()
+//!     .ignore_case(true)
+//!     .count_reader(Cursor::new("The cat saw the dog"))
+//!     .unwrap();
+//! assert_eq!(word_counts.get("the"), Some(&2));
+//! ```
+
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write};
+use std::mem;
+use std::ops::{AddAssign, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use foldhash::fast::SeedableRandomState;
+use foldhash::SharedSeed;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serializer as _;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "tokio")]
+pub use async_io::{count_path_async, count_reader_async, count_words_async};
+mod comments;
+use comments::strip_comments;
+mod grapheme;
+pub use grapheme::count_graphemes;
+mod width;
+pub use width::display_width;
+use width::take_columns;
+mod hash;
+use hash::CountHashState;
+mod iter;
+pub use iter::WordCountIter;
+#[cfg(feature = "plot")]
+mod plot;
+#[cfg(feature = "plot")]
+pub use plot::write_frequency_plot;
+mod normalization;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+pub use normalization::normalize_unicode;
+mod stem;
+#[cfg(feature = "transliterate")]
+mod translit;
+#[cfg(feature = "sqlite")]
+pub use sqlite::export_sqlite;
+pub use stem::porter_stem;
+#[cfg(feature = "transliterate")]
+pub use translit::transliterate_gost;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::wasm_count_text;
+
+/// The number of lines processed between two sleeps unless configured otherwise
+pub const DEFAULT_CHUNK_SIZE: usize = 10000;
+
+/// The milliseconds slept after every chunk unless configured otherwise
+pub const DEFAULT_SLEEP_MS: u64 = 10;
+
+/// The bytes buffered per input unless configured otherwise
+pub const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// The path that stands for stdin on the command line
+pub const STDIN_PATH: &str = "-";
+
+/// The version of the checkpoint format written by `write_checkpoint`
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// Options controlling how the input is read and how tokens are counted
+#[derive(Debug, Clone)]
+pub struct WordCountConfig {
+    /// Lowercase every token so the map key is its canonical lowercase form
+    pub ignore_case: bool,
+    /// Trim leading and trailing non-alphanumeric characters from every token
+    pub strip_punct: bool,
+    /// The number of lines processed between two sleeps
+    pub chunk_size: usize,
+    /// The capacity in bytes of the buffer every input is read through
+    pub buffer_size: usize,
+    /// The pause after every chunk, or `None` to never yield to the scheduler
+    pub sleep: Option<Duration>,
+    /// Process several chunks at once on the rayon thread pool
+    pub parallel: bool,
+    /// Map input files into memory instead of reading them through a buffer
+    pub mmap: bool,
+    /// Skip tokens with fewer Unicode scalar values than this
+    pub min_length: Option<usize>,
+    /// Skip tokens with more Unicode scalar values than this
+    pub max_length: Option<usize>,
+    /// Cut tokens with more Unicode scalar values than this down to that
+    /// many, appending the `truncate_marker`; checked after `max_length`
+    pub truncate_length: Option<usize>,
+    /// Appended to every token cut by `truncate_length`, `…` by default
+    pub truncate_marker: String,
+    /// Split lines on the matches of this regex instead of on whitespace
+    pub split_regex: Option<Regex>,
+    /// Split lines on every occurrence of this string instead of on
+    /// whitespace, unless it is whitespace itself; `split_regex` takes
+    /// precedence
+    pub field_separator: Option<String>,
+    /// Split every token where a lowercase letter is followed by an
+    /// uppercase one, like `processChunk`, see `split_identifier`
+    pub split_camel_case: bool,
+    /// Split every token at its underscores, like `snake_case`
+    pub split_underscore: bool,
+    /// Split every token at its hyphens, like `well-known`, see
+    /// `split_hyphenated`
+    pub split_hyphenated: bool,
+    /// Keep the tokens with a digit whole when `split_hyphenated`, like
+    /// `2023-01-01` or `3-D`
+    pub keep_hyphenated_numbers: bool,
+    /// Tokenize the lines as source code of this language instead of by
+    /// whitespace, see `is_code_identifier_char`; takes precedence over
+    /// `split_regex` and `field_separator`
+    pub code_lang: Option<CodeLang>,
+    /// Reduce every token to its stem in this language after lowercasing
+    /// and before the `stopwords`, which must then be stems themselves
+    pub stem: Option<StemLang>,
+    /// Remove each of these prefixes in turn from the tokens starting with
+    /// it, after lowercasing and transliteration and before stemming; a
+    /// token left empty is skipped
+    pub strip_prefixes: Vec<String>,
+    /// Remove each of these suffixes in turn from the tokens ending with it,
+    /// after the `strip_prefixes`, so "running" becomes "runn" for `ing`
+    pub strip_suffixes: Vec<String>,
+    /// Transliterate every token into Latin letters with this scheme after
+    /// lowercasing and before stemming and the `stopwords`; chars the scheme
+    /// has no letters for are kept
+    #[cfg(feature = "transliterate")]
+    pub transliterate: Option<TranslitScheme>,
+    /// Normalize every token into this Unicode normalization form first, so
+    /// that differently encoded but equivalent words are counted as one
+    pub unicode_form: Option<UnicodeForm>,
+    /// Skip tokens contained in this set, compared after normalisation, so the
+    /// entries must be lowercase if `ignore_case` is set
+    pub stopwords: HashSet<String>,
+    /// Only count tokens contained in this set, if set, checked after
+    /// `stopwords` and compared after normalisation like them
+    pub vocab: Option<HashSet<String>>,
+    /// Skip tokens made of digits, `.` and `-` only, like IP addresses,
+    /// dates or version numbers
+    pub remove_numbers: bool,
+    /// Count every token made of ASCII digits only, or matching
+    /// `number_regex` if set, as this placeholder instead, e.g. `<NUM>`
+    ///
+    /// The token is replaced after `strip_punct` and before any other
+    /// normalisation or filter, none of which apply to the placeholder.
+    pub replace_numbers: Option<String>,
+    /// Recognise the numbers of `replace_numbers` by this regex instead,
+    /// which should be anchored to match whole tokens only
+    pub number_regex: Option<Regex>,
+    /// Count every token starting with `http://` or `https://` as this
+    /// placeholder instead, e.g. `<URL>`, replaced like `replace_numbers`
+    pub replace_urls: Option<String>,
+    /// Remove the URLs starting with `http://` or `https://` from every line
+    /// before it is tokenized, for `ExtractedTokens` to count apart
+    ///
+    /// The URLs are found among the whitespace-separated parts of the line,
+    /// without the brackets, quotes and punctuation around them.
+    pub extract_urls: bool,
+    /// Remove the email addresses from every line before it is tokenized,
+    /// found like the URLs of `extract_urls`
+    pub extract_emails: bool,
+    /// Skip decimal integers like `42` or `-7`
+    pub remove_integers: bool,
+    /// Skip floating-point literals like `3.14`, `-.5` or `1e-9`
+    pub remove_floats: bool,
+    /// Only count tokens made of alphabetic chars only, checked after
+    /// `strip_punct`
+    pub only_alpha: bool,
+    /// Only count tokens made of alphabetic chars and digits only, checked
+    /// after `strip_punct`
+    pub only_alphanumeric: bool,
+    /// Only count tokens matching this regex, checked after normalisation
+    pub include_pattern: Option<Regex>,
+    /// Skip tokens matching this regex, checked after normalisation
+    pub exclude_pattern: Option<Regex>,
+    /// Accumulate the tokens of an input in a `HashMap` and only merge the
+    /// unique words into the sorted map once the input is done, so observers
+    /// see the counts of an input only after its last round
+    pub use_hashmap: bool,
+    /// Count sequences of this many consecutive tokens, joined as set in
+    /// `ngram_join`, instead of single tokens
+    pub ngrams: usize,
+    /// How the tokens of the `ngrams` are joined into the word they are
+    /// counted as, a space by default
+    pub ngram_join: NgramJoin,
+    /// Count every Unicode scalar value of a line as a token of its own
+    /// instead of splitting the line into words
+    pub chars: bool,
+    /// Leave out whitespace when counting `chars`
+    pub skip_whitespace_chars: bool,
+    /// Count the sequences of this many consecutive chars of every token
+    /// instead of the tokens themselves; `ngrams` is ignored then
+    pub char_ngrams: Option<usize>,
+    /// Pad every token with `char_ngrams - 1` `^` before and `$` after it,
+    /// so the n-grams at its edges are told apart from those inside it
+    pub char_ngram_boundaries: bool,
+    /// Skip this many lines at the start of the input without counting them,
+    /// e.g. because they were already counted before a checkpoint
+    pub skip_lines: usize,
+    /// Count the sentences of the input into `Totals::sentences`
+    pub sentences: bool,
+    /// Count the `sentences` with `count_sentences_in_chunk` at these endings
+    /// instead of with the heuristic of `count_sentences`
+    pub sentence_endings: Option<Vec<char>>,
+    /// Count the grapheme clusters of the input into `Totals::graphemes`,
+    /// see `count_graphemes`
+    pub count_graphemes: bool,
+    /// Count every line with a token as one of the `sentences`, for input
+    /// with one sentence per line, instead of looking for sentence ends
+    pub sentence_per_line: bool,
+    /// Only tokenize this field of every line, 1-based, skipping the lines
+    /// with fewer fields, see `extract_column`
+    pub column: Option<usize>,
+    /// Separate the fields of `column` by this char instead of by whitespace
+    pub column_separator: Option<char>,
+    /// The encoding of the inputs; all but UTF-8 are decoded as a whole
+    /// before they are split into lines, so `mmap` has no effect on them
+    pub encoding: Encoding,
+    /// Replace the invalid sequences of UTF-8 inputs with U+FFFD instead of
+    /// failing on them, counting them in `Totals::invalid_utf8`
+    pub force_utf8: bool,
+    /// Only count the lines within this range, numbered from the start of
+    /// the input including the `skip_lines`; the input is not read past its
+    /// end
+    pub line_range: Option<LineRange>,
+    /// Skip the lines longer than this many chars as if they were empty,
+    /// see `is_longer_than`; they are still totalled, but neither counted
+    /// nor passed to `ChunkObserver::line_read` but to `line_skipped`
+    pub skip_long_lines: Option<usize>,
+    /// Seed the hasher of the `HashMap`s counted into with `use_hashmap` and
+    /// `parallel` instead of seeding it randomly, see `hashed_counts`
+    pub hash_seed: Option<u64>,
+    /// The hash function of the `HashMap`s counted into with `use_hashmap`
+    /// and `parallel`
+    pub hash_algorithm: HashAlgorithm,
+    /// Stop adding new words once the counts hold this many, counting their
+    /// tokens into `Totals::words_dropped` instead; the words already held
+    /// are still counted
+    pub max_unique_words: Option<usize>,
+    /// Stop adding new words once the counts take up more than about this
+    /// many bytes, as estimated by `estimate_map_bytes` after every round of
+    /// chunks, so the new words of a round may exceed it; unlike
+    /// `max_unique_words` it is ignored by `WordCountIter` and the
+    /// asynchronous backend
+    pub max_memory: Option<usize>,
+    /// Count every line only once per input, skipping the lines seen before
+    /// and adding them to `Totals::duplicate_lines` instead; ignored by
+    /// `WordCountIter`
+    pub dedup_lines: Option<LineDedup>,
+    /// Only count the lines this regex matches anywhere in, or with
+    /// `line_regex_invert` the lines it does not match; the other lines are
+    /// read but neither counted nor seen by the observers. Ignored by
+    /// `WordCountIter`
+    pub line_regex: Option<Regex>,
+    /// Skip the lines `line_regex` matches instead of the others
+    pub line_regex_invert: bool,
+    /// Strip the comments of this language from every line before the
+    /// `line_regex` is matched, keeping track of the block comments across
+    /// lines and chunks. Ignored by `WordCountIter`
+    pub comment_language: Option<CommentLanguage>,
+    /// Only count a random sample of the lines, each kept with this
+    /// probability above 0 and up to 1, drawn from a pseudo-random sequence
+    /// seeded by `hash_seed` or randomly without one; the other lines are read but neither counted
+    /// nor seen by the observers, see `scale_counts`. Ignored by
+    /// `WordCountIter`
+    pub sample_rate: Option<f64>,
+    /// Only count the first of every this many lines, the others read but
+    /// neither counted nor seen by the observers like with `sample_rate`.
+    /// Ignored by `WordCountIter`
+    pub every_nth_line: Option<usize>,
+    /// Only count the first of every this many tokens or n-grams of an input,
+    /// whichever word they are, see `scale_counts`; cannot be combined with
+    /// `parallel`
+    pub every_nth_word: Option<usize>,
+    /// Stop reading an input once this instant has passed, checked after
+    /// every round of chunks, and set `Totals::timed_out`; ignored by
+    /// `WordCountIter`
+    pub deadline: Option<Instant>,
+}
+
+impl Default for WordCountConfig {
+    fn default() -> Self {
+        Self {
+            ignore_case: false,
+            strip_punct: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            // WebAssembly in a browser cannot block the thread
+            sleep: (!cfg!(target_arch = "wasm32")).then(|| Duration::from_millis(DEFAULT_SLEEP_MS)),
+            parallel: false,
+            mmap: false,
+            min_length: None,
+            max_length: None,
+            truncate_length: None,
+            truncate_marker: String::from("…"),
+            split_regex: None,
+            field_separator: None,
+            split_camel_case: false,
+            split_underscore: false,
+            split_hyphenated: false,
+            keep_hyphenated_numbers: false,
+            code_lang: None,
+            stem: None,
+            strip_prefixes: Vec::new(),
+            strip_suffixes: Vec::new(),
+            #[cfg(feature = "transliterate")]
+            transliterate: None,
+            unicode_form: None,
+            stopwords: HashSet::new(),
+            vocab: None,
+            remove_numbers: false,
+            replace_numbers: None,
+            number_regex: None,
+            replace_urls: None,
+            extract_urls: false,
+            extract_emails: false,
+            remove_integers: false,
+            remove_floats: false,
+            only_alpha: false,
+            only_alphanumeric: false,
+            include_pattern: None,
+            exclude_pattern: None,
+            use_hashmap: false,
+            ngrams: 1,
+            ngram_join: NgramJoin::default(),
+            chars: false,
+            skip_whitespace_chars: false,
+            char_ngrams: None,
+            char_ngram_boundaries: false,
+            skip_lines: 0,
+            sentences: false,
+            sentence_endings: None,
+            count_graphemes: false,
+            sentence_per_line: false,
+            column: None,
+            column_separator: None,
+            encoding: Encoding::Utf8,
+            force_utf8: false,
+            line_range: None,
+            skip_long_lines: None,
+            hash_seed: None,
+            hash_algorithm: HashAlgorithm::Foldhash,
+            max_unique_words: None,
+            max_memory: None,
+            dedup_lines: None,
+            line_regex: None,
+            line_regex_invert: false,
+            comment_language: None,
+            sample_rate: None,
+            every_nth_line: None,
+            every_nth_word: None,
+            deadline: None,
+        }
+    }
+}
+
+/// Check that the numbers of `config` make sense, returning a message for
+/// every setting that does not
+///
+/// Counting does not check them itself: a `chunk_size` of 0 for example
+/// would never read a line. The regexes and word lists of the config are
+/// already compiled and loaded, so only the values they depend on are
+/// checked.
+///
+/// ```
+/// use word_count::{validate_config, WordCountConfig};
+///
+/// let config = WordCountConfig {
+///     chunk_size: 0,
+///     ..Default::default()
+/// };
+/// assert_eq!(validate_config(&config), ["chunk_size must be at least 1"]);
+/// ```
+pub fn validate_config(config: &WordCountConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    if config.chunk_size == 0 {
+        errors.push(String::from("chunk_size must be at least 1"));
+    }
+    if config.buffer_size == 0 {
+        errors.push(String::from("buffer_size must be at least 1"));
+    }
+    if let (Some(min), Some(max)) = (config.min_length, config.max_length) {
+        if min > max {
+            errors.push(format!(
+                "min_length {} is greater than max_length {}",
+                min, max
+            ));
+        }
+    }
+    if config.ngrams == 0 {
+        errors.push(String::from("ngrams must be at least 1"));
+    }
+    if config.ngram_join.max_position() > config.ngrams {
+        errors.push(format!(
+            "the n-gram template refers to token {{{}}} of n-grams of {} tokens",
+            config.ngram_join.max_position(),
+            config.ngrams
+        ));
+    }
+    if config.char_ngrams == Some(0) {
+        errors.push(String::from("char_ngrams must be at least 1"));
+    }
+    if config.column == Some(0) {
+        errors.push(String::from(
+            "column must be at least 1, columns are 1-based",
+        ));
+    }
+    if let Some(range) = config.line_range {
+        if range.start == 0 {
+            errors.push(String::from("line_range must start at line 1 or later"));
+        }
+        if range.end.is_some_and(|end| end < range.start) {
+            errors.push(String::from("line_range must not end before its start"));
+        }
+    }
+    if config.max_unique_words == Some(0) {
+        errors.push(String::from("max_unique_words must be at least 1"));
+    }
+    if config
+        .sample_rate
+        .is_some_and(|rate| !(rate > 0.0 && rate <= 1.0))
+    {
+        errors.push(String::from("sample_rate must be above 0 and at most 1"));
+    }
+    if config.every_nth_line == Some(0) {
+        errors.push(String::from("every_nth_line must be at least 1"));
+    }
+    if config.every_nth_word == Some(0) {
+        errors.push(String::from("every_nth_word must be at least 1"));
+    }
+    if config.every_nth_word.is_some() && config.parallel {
+        errors.push(String::from(
+            "every_nth_word cannot be combined with parallel",
+        ));
+    }
+    if config.truncate_length == Some(0) {
+        errors.push(String::from("truncate_length must be at least 1"));
+    }
+    if config.field_separator.as_deref() == Some("") {
+        errors.push(String::from("field_separator must not be empty"));
+    }
+    errors
+}
+
+/// The order in which the counted words are printed, see `compare_entries`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    /// Alphabetically by word
+    #[default]
+    Word,
+    /// By count, most frequent first
+    #[value(alias = "rank")]
+    CountDesc,
+    /// By count, least frequent first
+    CountAsc,
+    /// By the number of chars, longest first
+    LengthDesc,
+    /// By the number of chars, shortest first
+    LengthAsc,
+    /// By the line the word was first seen on, see `FirstSeen`
+    FirstSeen,
+}
+
+/// A Unicode normalization form, see `normalize_unicode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UnicodeForm {
+    /// Canonical composition, e.g. an `e` and a combining accent into "é"
+    Nfc,
+    /// Canonical decomposition, e.g. "é" into an `e` and a combining accent
+    Nfd,
+    /// Compatibility composition, also e.g. "ﬁ" into "fi" and "²" into "2"
+    Nfkc,
+    /// Compatibility decomposition
+    Nfkd,
+}
+
+/// A language whose comments are skipped, see `WordCountConfig::comment_language`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CommentLanguage {
+    /// `//` line and `/* */` block comments
+    Rust,
+    /// `#` line comments
+    Python,
+    /// `--` line and `--[[ ]]` block comments
+    Lua,
+    /// `<!-- -->` comments, and every `<...>` tag as well
+    Html,
+}
+
+/// The format of the inputs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Plain text
+    #[default]
+    Text,
+    /// One JSON value per line, whose strings are counted, see `json_text`
+    Json,
+    /// CSV records, whose text columns are counted
+    Csv,
+}
+
+/// The format in which the counted words are printed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One `word: count` line per word
+    #[default]
+    Text,
+    /// A single JSON object mapping every word to its count
+    Json,
+    /// A JSON array of `{"word": ..., "count": ...}` objects, most frequent first
+    JsonArray,
+    /// One `{"word": ..., "count": ...}` object per line (NDJSON)
+    #[value(name = "jsonlines")]
+    JsonLines,
+    /// A `word,count` header followed by one csv row per word
+    Csv,
+    /// A self-contained HTML page with a word cloud of the most frequent words
+    Html,
+    /// One tab-separated `word\tcount` row per word, without a header
+    Tsv,
+    /// Like jsonlines, but streamed by `NdjsonStream` while the input is
+    /// counted, so only a final `{"_summary": ...}` object is left to print
+    Ndjson,
+    /// A `<word_counts>` XML document with a `<word value="..." count="..."
+    /// rank="..."/>` element per word, see `write_xml`
+    Xml,
+}
+
+/// When the text format is printed with ANSI colors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when printing to a terminal
+    #[default]
+    Auto,
+    /// Always, even into files and pipes
+    Always,
+    /// Never
+    Never,
+}
+
+/// The character encoding of the inputs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// UTF-8, read line by line
+    #[default]
+    Utf8,
+    /// UTF-16 little endian, as written by many Windows applications
+    Utf16le,
+    /// UTF-16 big endian
+    Utf16be,
+    /// ISO-8859-1, every byte being the char of the same value
+    #[value(alias = "iso-8859-1")]
+    Latin1,
+}
+
+/// The language `WordCountConfig::stem` stems the tokens of
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StemLang {
+    /// English, with the Porter stemmer, see `porter_stem`
+    #[default]
+    En,
+}
+
+/// The hash function of the `HashMap`s of `WordCountConfig::use_hashmap`
+///
+/// The counts are the same with any of them, and so is the speed, as most
+/// of the time goes into tokenizing rather than hashing. Counting 10 M
+/// tokens of 40 k unique words with `use_hashmap` in a release build took,
+/// at best of three runs:
+///
+/// | algorithm | time   |
+/// |-----------|--------|
+/// | foldhash  | 0.81 s |
+/// | FNV-1a    | 0.84 s |
+/// | FxHash    | 0.89 s |
+/// | SipHash   | 0.90 s |
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    /// foldhash, fast on short keys like words
+    #[default]
+    Foldhash,
+    /// SipHash-1-3 of std's `HashMap`, resistant to collisions chosen by
+    /// an attacker but slower
+    Siphash,
+    /// FxHash of rustc, a multiply and rotate per eight bytes
+    Fxhash,
+    /// FNV-1a, a multiply per byte
+    Fnv,
+}
+
+/// The scheme `WordCountConfig::transliterate` transliterates the tokens
+/// with
+#[cfg(feature = "transliterate")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TranslitScheme {
+    /// Russian Cyrillic after GOST R 52535.1-2006, see `transliterate_gost`
+    Gost,
+}
+
+/// A programming language whose source code `WordCountConfig::code_lang`
+/// tokenizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CodeLang {
+    Rust,
+    Python,
+    /// JavaScript
+    Js,
+}
+
+/// A language with a built-in list of stopwords, see `builtin_stopwords`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StopwordLang {
+    /// English
+    En,
+    /// German
+    De,
+    /// French
+    Fr,
+    /// Spanish
+    Es,
+}
+
+/// The stopwords of `lang` compiled into the binary from `data/stopwords/`
+///
+/// The lists are lowercase. Unless `ignore_case` is set, every stopword is
+/// added capitalised as well, so the word starting a sentence is skipped
+/// too.
+pub fn builtin_stopwords(lang: StopwordLang, ignore_case: bool) -> HashSet<String> {
+    let list = match lang {
+        StopwordLang::En => include_str!("../data/stopwords/en.txt"),
+        StopwordLang::De => include_str!("../data/stopwords/de.txt"),
+        StopwordLang::Fr => include_str!("../data/stopwords/fr.txt"),
+        StopwordLang::Es => include_str!("../data/stopwords/es.txt"),
+    };
+    let mut stopwords = HashSet::new();
+    for word in list.lines().map(str::trim).filter(|word| !word.is_empty()) {
+        if !ignore_case {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                stopwords.insert(first.to_uppercase().chain(chars).collect());
+            }
+        }
+        stopwords.insert(word.to_string());
+    }
+    stopwords
+}
+
+/// The bytes every entry of a word count map is assumed to take up besides
+/// the bytes of its word and its count, see `estimate_map_bytes`
+///
+/// This is a rough approximation rather than a measurement: it covers the
+/// `String` header of the key and a share of the node the entry is stored
+/// in, whose exact layout is an implementation detail of the standard library.
+pub const BTREEMAP_NODE_OVERHEAD: usize = 40;
+
+/// The number of most frequent entries printed in bold when colored
+pub const BOLD_TOP: usize = 10;
+
+/// The number of most frequent entries shown in the HTML word cloud
+pub const HTML_TOP: usize = 200;
+
+/// The page of the HTML format, `{WORD_DATA_JSON}` is replaced by the entries
+///
+/// The font size of a word grows with the logarithm of its count, so the most
+/// frequent words do not crowd out all others.
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Word cloud</title>
+<style>
+  body { font-family: sans-serif; margin: 2em; background: #fafafa; }
+  #cloud { display: flex; flex-wrap: wrap; align-items: center; justify-content: center; gap: 0.2em 0.6em; }
+  #cloud span { color: #245; line-height: 1.1; }
+</style>
+</head>
+<body>
+<div id="cloud"></div>
+<script>
+  const words = {WORD_DATA_JSON};
+  const max = Math.log(1 + Math.max(1, ...words.map((entry) => entry.count)));
+  const cloud = document.getElementById("cloud");
+  // alphabetical order mixes large and small words
+  for (const entry of [...words].sort((a, b) => a.word.localeCompare(b.word))) {
+    const span = document.createElement("span");
+    span.textContent = entry.word;
+    span.title = entry.word + ": " + entry.count;
+    span.style.fontSize = (0.8 + 3.2 * Math.log(1 + entry.count) / max).toFixed(2) + "em";
+    cloud.appendChild(span);
+  }
+</script>
+</body>
+</html>
+"#;
+
+/// A range of 1-based line numbers, both ends included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    /// The first line of the range, at least 1
+    pub start: usize,
+    /// The last line of the range, `None` for the end of the input
+    pub end: Option<usize>,
+}
+
+impl LineRange {
+    /// Whether line `number` lies within the range
+    pub fn contains(&self, number: usize) -> bool {
+        number >= self.start && !self.is_past(number)
+    }
+
+    /// Whether line `number` comes after the end of the range, so no later
+    /// line needs to be read
+    pub fn is_past(&self, number: usize) -> bool {
+        self.end.is_some_and(|end| number > end)
+    }
+}
+
+/// How `WordCountConfig::ngram_join` joins the tokens of an n-gram into the
+/// word it is counted as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NgramJoin {
+    /// The tokens with this separator between every two of them
+    Separator(String),
+    /// The parts of a template like `{1}_{2}`, see `NgramJoin::template`
+    Template(Vec<TemplatePart>),
+}
+
+/// A part of an `NgramJoin::Template`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplatePart {
+    /// Text copied as it is
+    Text(String),
+    /// The token at this 1-based position of the n-gram
+    Token(usize),
+}
+
+impl Default for NgramJoin {
+    fn default() -> Self {
+        Self::Separator(String::from(" "))
+    }
+}
+
+impl NgramJoin {
+    /// Parse a template in which `{1}`, `{2}` and so on stand for the first,
+    /// second and so on token of an n-gram, e.g. `{1}_{2}` or `{2}<-{1}`
+    ///
+    /// Any other text is copied as it is. The template needs at least one
+    /// token, and only a `{` that starts a token is allowed.
+    pub fn template(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| format!("unclosed {{ in n-gram template {}", template))?;
+            let position = rest[start + 1..end]
+                .parse()
+                .ok()
+                .filter(|&position: &usize| position >= 1)
+                .ok_or_else(|| {
+                    format!(
+                        "{} is no token of an n-gram, tokens are numbered like {{1}}",
+                        &rest[start..=end]
+                    )
+                })?;
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            parts.push(TemplatePart::Token(position));
+            rest = &rest[end + 1..];
+        }
+        if !parts
+            .iter()
+            .any(|part| matches!(part, TemplatePart::Token(_)))
+        {
+            return Err(format!(
+                "n-gram template {} has no token like {{1}}",
+                template
+            ));
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+        Ok(Self::Template(parts))
+    }
+
+    /// The highest token position of a template, 0 for a separator
+    pub fn max_position(&self) -> usize {
+        match self {
+            Self::Separator(_) => 0,
+            Self::Template(parts) => parts
+                .iter()
+                .map(|part| match part {
+                    TemplatePart::Token(position) => *position,
+                    TemplatePart::Text(_) => 0,
+                })
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Join `tokens` into a single word; a template token beyond the n-gram
+    /// is left out
+    fn join(&self, tokens: &VecDeque<String>) -> String {
+        match self {
+            Self::Separator(separator) => {
+                let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+                tokens.join(separator)
+            }
+            Self::Template(parts) => {
+                let mut joined = String::new();
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(text) => joined.push_str(text),
+                        TemplatePart::Token(position) => {
+                            joined.extend(tokens.get(position - 1).map(String::as_str))
+                        }
+                    }
+                }
+                joined
+            }
+        }
+    }
+}
+
+/// How `WordCountConfig::dedup_lines` recognises the lines seen before
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineDedup {
+    /// Keep every distinct line in a set, which never mistakes a line
+    Exact,
+    /// Keep a Bloom filter of the lines, which takes a fixed amount of memory
+    /// but mistakes a new line for a duplicate with about this probability
+    ///
+    /// The filter is sized for `DEDUP_APPROX_LINES` distinct lines; beyond
+    /// that the probability rises.
+    Approx(f64),
+}
+
+/// The number of distinct lines the Bloom filter of `LineDedup::Approx` is
+/// sized for
+pub const DEDUP_APPROX_LINES: usize = 1_000_000;
+
+/// The lines of an input seen so far, see `LineDedup`
+#[derive(Debug)]
+pub(crate) enum SeenLines {
+    Exact(HashSet<String>),
+    Approx(BloomFilter),
+}
+
+impl SeenLines {
+    pub(crate) fn new(dedup: LineDedup) -> Self {
+        match dedup {
+            LineDedup::Exact => Self::Exact(HashSet::new()),
+            LineDedup::Approx(rate) => Self::Approx(BloomFilter::new(DEDUP_APPROX_LINES, rate)),
+        }
+    }
+
+    /// Remove the lines of `chunk` seen before, returning how many there were
+    pub(crate) fn remove_seen<S: AsRef<str>>(&mut self, chunk: &mut Vec<S>) -> usize {
+        let before = chunk.len();
+        match self {
+            Self::Exact(seen) => chunk.retain(|line| seen.insert(line.as_ref().to_string())),
+            Self::Approx(filter) => chunk.retain(|line| filter.insert(line.as_ref())),
+        }
+        before - chunk.len()
+    }
+}
+
+/// A Bloom filter of strings
+///
+/// Every string sets `hashes` bits, derived from two foldhash hashes of it
+/// by double hashing; a string is taken for a member if all of its bits are
+/// set.
+#[derive(Debug)]
+pub(crate) struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: usize,
+    first: SeedableRandomState,
+    second: SeedableRandomState,
+}
+
+impl BloomFilter {
+    /// A filter holding `items` strings with a false positive probability of
+    /// `rate`, with the optimal number of bits and hashes
+    fn new(items: usize, rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(items as f64) * rate.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let hashes = ((bits as f64 / items as f64) * ln2).round().max(1.0) as usize;
+        Self {
+            bits: vec![0; bits.div_ceil(64)],
+            hashes,
+            first: SeedableRandomState::with_seed(1, SharedSeed::global_fixed()),
+            second: SeedableRandomState::with_seed(2, SharedSeed::global_fixed()),
+        }
+    }
+
+    /// Add `item`, returning whether it was not a member before
+    fn insert(&mut self, item: &str) -> bool {
+        let len = self.bits.len() as u64 * 64;
+        let first = self.first.hash_one(item);
+        // an odd step visits different bits for every hash
+        let second = self.second.hash_one(item) | 1;
+        let mut new = false;
+        for i in 0..self.hashes as u64 {
+            let bit = first.wrapping_add(i.wrapping_mul(second)) % len;
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            new |= self.bits[word] & mask == 0;
+            self.bits[word] |= mask;
+        }
+        new
+    }
+}
+
+/// Running totals gathered while the input is processed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Totals {
+    /// The number of lines containing anything but whitespace
+    pub lines: usize,
+    /// The number of counted tokens
+    pub words: usize,
+    /// The number of Unicode scalar values, including line terminators
+    pub chars: usize,
+    /// The number of bytes, including line terminators
+    pub bytes: usize,
+    /// The number of grapheme clusters, including line terminators, if
+    /// counted, see `WordCountConfig::count_graphemes`
+    #[serde(default)]
+    pub graphemes: usize,
+    /// The number of lines read, including blank ones
+    pub lines_read: usize,
+    /// The number of sentences, if counted, see `count_sentences`
+    #[serde(default)]
+    pub sentences: usize,
+    /// The number of tokens not counted because their word was new once
+    /// `max_unique_words` was reached
+    #[serde(default)]
+    pub words_dropped: usize,
+    /// The number of lines skipped because they were read before, see
+    /// `WordCountConfig::dedup_lines`
+    #[serde(default)]
+    pub duplicate_lines: usize,
+    /// Whether the input was not read to its end because the
+    /// `WordCountConfig::deadline` passed
+    #[serde(default)]
+    pub timed_out: bool,
+    /// The number of invalid UTF-8 sequences replaced with U+FFFD, see
+    /// `WordCountConfig::force_utf8`
+    #[serde(default)]
+    pub invalid_utf8: usize,
+}
+
+impl AddAssign for Totals {
+    fn add_assign(&mut self, other: Self) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.graphemes += other.graphemes;
+        self.lines_read += other.lines_read;
+        self.sentences += other.sentences;
+        self.words_dropped += other.words_dropped;
+        self.duplicate_lines += other.duplicate_lines;
+        self.timed_out |= other.timed_out;
+        self.invalid_utf8 += other.invalid_utf8;
+    }
+}
+
+/// Gets notified by the chunk loop after every round of chunks
+pub trait ChunkObserver {
+    /// Called once the chunks of a round are counted, before sleeping
+    ///
+    /// # Arguments
+    ///
+    /// * `totals` - The totals of the current input so far
+    /// * `word_counts` - The word counts so far
+    ///
+    /// An error aborts the processing of the input.
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()>;
+
+    /// Called with every line of a round in input order, before the round
+    /// is counted
+    ///
+    /// Observers that need the tokens in order, which the word counts do
+    /// not keep, can tokenize the lines themselves.
+    fn line_read(&mut self, _line: &str) {}
+
+    /// Called in place of `line_read` with every line skipped because of
+    /// `WordCountConfig::skip_long_lines`
+    fn line_skipped(&mut self, _line: &str) {}
+
+    /// Called once the lines of a round are seen by `line_read`, before the
+    /// round is counted, with the time it took to read them and the time it
+    /// took to filter them and notify the observers of them
+    ///
+    /// Both are zero on WebAssembly, which has no clock.
+    fn round_read(&mut self, _reading: Duration, _collecting: Duration) {}
+
+    /// Called once the pause after a round is over, with the time it took to
+    /// count the round, not including reading it, and the time slept after
+    /// it
+    ///
+    /// Both are zero on WebAssembly, which has no clock. An error aborts the
+    /// processing of the input.
+    fn round_timed(&mut self, _counting: Duration, _slept: Duration) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The unit observer ignores all notifications
+impl ChunkObserver for () {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A pair of observers notifies both of them in turn
+impl<A: ChunkObserver, B: ChunkObserver> ChunkObserver for (A, B) {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        self.0.chunk_done(totals, word_counts)?;
+        self.1.chunk_done(totals, word_counts)
+    }
+
+    fn line_read(&mut self, line: &str) {
+        self.0.line_read(line);
+        self.1.line_read(line);
+    }
+
+    fn line_skipped(&mut self, line: &str) {
+        self.0.line_skipped(line);
+        self.1.line_skipped(line);
+    }
+
+    fn round_read(&mut self, reading: Duration, collecting: Duration) {
+        self.0.round_read(reading, collecting);
+        self.1.round_read(reading, collecting);
+    }
+
+    fn round_timed(&mut self, counting: Duration, slept: Duration) -> io::Result<()> {
+        self.0.round_timed(counting, slept)?;
+        self.1.round_timed(counting, slept)
+    }
+}
+
+/// A borrowed observer is notified in place of the observer itself
+impl<T: ChunkObserver + ?Sized> ChunkObserver for &mut T {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        (**self).chunk_done(totals, word_counts)
+    }
+
+    fn line_read(&mut self, line: &str) {
+        (**self).line_read(line);
+    }
+
+    fn line_skipped(&mut self, line: &str) {
+        (**self).line_skipped(line);
+    }
+
+    fn round_read(&mut self, reading: Duration, collecting: Duration) {
+        (**self).round_read(reading, collecting);
+    }
+
+    fn round_timed(&mut self, counting: Duration, slept: Duration) -> io::Result<()> {
+        (**self).round_timed(counting, slept)
+    }
+}
+
+/// An absent observer ignores all notifications
+impl<T: ChunkObserver> ChunkObserver for Option<T> {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        match self {
+            Some(observer) => observer.chunk_done(totals, word_counts),
+            None => Ok(()),
+        }
+    }
+
+    fn line_read(&mut self, line: &str) {
+        if let Some(observer) = self {
+            observer.line_read(line);
+        }
+    }
+
+    fn line_skipped(&mut self, line: &str) {
+        if let Some(observer) = self {
+            observer.line_skipped(line);
+        }
+    }
+
+    fn round_read(&mut self, reading: Duration, collecting: Duration) {
+        if let Some(observer) = self {
+            observer.round_read(reading, collecting);
+        }
+    }
+
+    fn round_timed(&mut self, counting: Duration, slept: Duration) -> io::Result<()> {
+        match self {
+            Some(observer) => observer.round_timed(counting, slept),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Computes the moving average type-token ratio (MATTR) of the tokens read
+///
+/// The type-token ratio of every window of `window` consecutive tokens is
+/// averaged, which unlike the plain ratio does not fall as the text grows.
+/// Higher values indicate a greater lexical variety. Texts shorter than the
+/// window get the plain type-token ratio of all their tokens.
+#[derive(Debug, Clone)]
+pub struct Mattr<'a> {
+    /// The options the lines are tokenized with
+    config: &'a WordCountConfig,
+    /// The number of tokens per window
+    window: usize,
+    /// The tokens of the current window
+    tokens: VecDeque<String>,
+    /// How often every token occurs in the current window
+    types: HashMap<String, usize>,
+    /// The sum of the ratios of all complete windows
+    ratio_sum: f64,
+    /// The number of complete windows
+    windows: usize,
+}
+
+impl<'a> Mattr<'a> {
+    /// Create a MATTR over windows of `window` tokens, at least 1
+    pub fn new(config: &'a WordCountConfig, window: usize) -> Self {
+        Self {
+            config,
+            window: window.max(1),
+            tokens: VecDeque::new(),
+            types: HashMap::new(),
+            ratio_sum: 0.0,
+            windows: 0,
+        }
+    }
+
+    /// Move the window on by `token`
+    pub fn push(&mut self, token: String) {
+        *self.types.entry(token.clone()).or_insert(0) += 1;
+        self.tokens.push_back(token);
+        if self.tokens.len() > self.window {
+            let oldest = self.tokens.pop_front().unwrap();
+            if let Some(count) = self.types.get_mut(&oldest) {
+                *count -= 1;
+                if *count == 0 {
+                    self.types.remove(&oldest);
+                }
+            }
+        }
+        if self.tokens.len() == self.window {
+            self.ratio_sum += self.types.len() as f64 / self.window as f64;
+            self.windows += 1;
+        }
+    }
+
+    /// The MATTR of the tokens pushed so far
+    pub fn value(&self) -> f64 {
+        if self.windows > 0 {
+            self.ratio_sum / self.windows as f64
+        } else if self.tokens.is_empty() {
+            0.0
+        } else {
+            self.types.len() as f64 / self.tokens.len() as f64
+        }
+    }
+}
+
+impl ChunkObserver for Mattr<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let config = self.config;
+        for_each_token(line, config, |token| self.push(token));
+    }
+}
+
+/// An observer that records the line every counted unit is first seen on
+///
+/// The lines are tokenized a second time, the same way they are counted,
+/// so the counting itself is unaffected in every mode.
+#[derive(Debug)]
+pub struct FirstSeen<'a> {
+    config: &'a WordCountConfig,
+    /// The n-gram window of the current input
+    window: VecDeque<String>,
+    /// The number of the last line read, 1-based
+    line: usize,
+    /// The first line of every unit seen so far
+    first_lines: &'a mut BTreeMap<String, usize>,
+}
+
+impl<'a> FirstSeen<'a> {
+    /// Record the first lines of an input into `first_lines`, numbering its
+    /// first line read `skipped + 1`
+    pub fn new(
+        config: &'a WordCountConfig,
+        skipped: usize,
+        first_lines: &'a mut BTreeMap<String, usize>,
+    ) -> Self {
+        Self {
+            config,
+            window: VecDeque::new(),
+            line: skipped,
+            first_lines,
+        }
+    }
+}
+
+impl ChunkObserver for FirstSeen<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        self.line += 1;
+        let number = self.line;
+        let first_lines = &mut *self.first_lines;
+        for_each_unit(line, self.config, &mut self.window, |unit| {
+            first_lines.entry(unit).or_insert(number);
+        });
+    }
+}
+
+/// An observer that numbers every counted unit in the order it is first
+/// seen in, from 0, across lines and inputs alike
+///
+/// Unlike the lines of `FirstSeen`, the positions tell apart the units first
+/// seen on the same line. The lines are tokenized a second time like for
+/// `FirstSeen`.
+#[derive(Debug)]
+pub struct FirstOccurrences<'a> {
+    config: &'a WordCountConfig,
+    /// The n-gram window of the current input
+    window: VecDeque<String>,
+    /// The position of every unit seen so far
+    positions: &'a mut BTreeMap<String, usize>,
+}
+
+impl<'a> FirstOccurrences<'a> {
+    /// Record the positions of an input into `positions`, numbering its new
+    /// units on from those already in there
+    pub fn new(config: &'a WordCountConfig, positions: &'a mut BTreeMap<String, usize>) -> Self {
+        Self {
+            config,
+            window: VecDeque::new(),
+            positions,
+        }
+    }
+}
+
+impl ChunkObserver for FirstOccurrences<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let positions = &mut *self.positions;
+        for_each_unit(line, self.config, &mut self.window, |unit| {
+            let next = positions.len();
+            positions.entry(unit).or_insert(next);
+        });
+    }
+}
+
+/// The weight of a unit on the 1-based line `line` of its input for
+/// `PositionWeights`, `1 / (1 + ln(line))`
+///
+/// The first line weighs 1 and the weights fall slowly after it, e.g. to
+/// about 0.30 on line 10 and 0.13 on line 1000.
+pub fn position_weight(line: usize) -> f64 {
+    1.0 / (1.0 + (line.max(1) as f64).ln())
+}
+
+/// An observer that sums up the `position_weight` of every occurrence of a
+/// counted unit, so units near the start of an input weigh more
+///
+/// Every input is a document of its own that starts at line 1. Like with
+/// `FirstSeen`, the lines are tokenized a second time, so the counts
+/// themselves are unaffected.
+#[derive(Debug)]
+pub struct PositionWeights<'a> {
+    config: &'a WordCountConfig,
+    /// The n-gram window of the current input
+    window: VecDeque<String>,
+    /// The number of the last line read, 1-based
+    line: usize,
+    /// The summed weights of every unit seen so far
+    weights: &'a mut BTreeMap<String, f64>,
+}
+
+impl<'a> PositionWeights<'a> {
+    /// Add the weights of an input to `weights`, numbering its first line
+    /// read `skipped + 1`
+    pub fn new(
+        config: &'a WordCountConfig,
+        skipped: usize,
+        weights: &'a mut BTreeMap<String, f64>,
+    ) -> Self {
+        Self {
+            config,
+            window: VecDeque::new(),
+            line: skipped,
+            weights,
+        }
+    }
+}
+
+impl ChunkObserver for PositionWeights<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        self.line += 1;
+        let weight = position_weight(self.line);
+        let weights = &mut *self.weights;
+        for_each_unit(line, self.config, &mut self.window, |unit| {
+            *weights.entry(unit).or_insert(0.0) += weight;
+        });
+    }
+}
+
+/// An occurrence of the word of a `Concordance` between its context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KwicLine {
+    /// The fields before the word, the nearest one last, padded at the front
+    /// with empty strings near the start of a line
+    pub left: Vec<String>,
+    /// The field the word occurs as, e.g. `Cat,` for the word "cat"
+    pub keyword: String,
+    /// The fields after the word, padded at the end with empty strings near
+    /// the end of a line
+    pub right: Vec<String>,
+}
+
+/// An observer that records every occurrence of a word with the `context`
+/// whitespace-separated fields before and after it on its line, a key word
+/// in context (KWIC) concordance
+///
+/// A field is an occurrence if it normalises to the word like a counted
+/// token would, e.g. in lowercase with `ignore_case` and without its
+/// punctuation with `strip_punct`. The context is kept as written. Only the
+/// context of the occurrences is stored, not the lines they occur on.
+#[derive(Debug)]
+pub struct Concordance<'a> {
+    config: &'a WordCountConfig,
+    /// The word as it is counted
+    word: &'a str,
+    /// The number of fields before and after every occurrence
+    context: usize,
+    /// The occurrences seen so far
+    lines: &'a mut Vec<KwicLine>,
+}
+
+impl<'a> Concordance<'a> {
+    /// Record the occurrences of `word` with `context` fields on each side
+    /// into `lines`
+    pub fn new(
+        config: &'a WordCountConfig,
+        word: &'a str,
+        context: usize,
+        lines: &'a mut Vec<KwicLine>,
+    ) -> Self {
+        Self {
+            config,
+            word,
+            context,
+            lines,
+        }
+    }
+}
+
+impl ChunkObserver for Concordance<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let context = self.context;
+        // the fields beyond either end of the line
+        let padded = |index: usize| -> String {
+            index
+                .checked_sub(context)
+                .and_then(|index| fields.get(index))
+                .map_or_else(String::new, |field| field.to_string())
+        };
+        for (index, field) in fields.iter().enumerate() {
+            if normalize_token(field, self.config).as_deref() != Some(self.word) {
+                continue;
+            }
+            self.lines.push(KwicLine {
+                left: (index..index + context).map(padded).collect(),
+                keyword: field.to_string(),
+                right: (index + context + 1..=index + 2 * context)
+                    .map(padded)
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// The occurrences of the word of a `ContextDiversity` and the distinct
+/// contexts it occurred in
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WordContexts {
+    /// How often the word occurred
+    pub occurrences: usize,
+    /// The hash of every distinct context, instead of its tokens
+    pub contexts: HashSet<u64>,
+}
+
+impl WordContexts {
+    /// The number of distinct contexts per occurrence, from close to 0 for a
+    /// word used in the same phrase again and again up to 1 for a word that
+    /// never occurs in the same context twice
+    pub fn diversity(&self) -> f64 {
+        if self.occurrences == 0 {
+            return 0.0;
+        }
+        self.contexts.len() as f64 / self.occurrences as f64
+    }
+}
+
+/// An observer that records the distinct contexts a word occurs in, the
+/// `window` tokens before and after it on its line, as a measure of how
+/// broadly it is used
+///
+/// The tokens are counted ones, so "The cat sat" and "the cat sat" are one
+/// context with `ignore_case`. Near the ends of a line the context is cut
+/// short, so "cat sat" at the start of a line and "a cat sat" are two.
+#[derive(Debug)]
+pub struct ContextDiversity<'a> {
+    config: &'a WordCountConfig,
+    /// The word as it is counted
+    word: &'a str,
+    /// The number of tokens before and after every occurrence
+    window: usize,
+    /// The occurrences and contexts seen so far
+    contexts: &'a mut WordContexts,
+}
+
+impl<'a> ContextDiversity<'a> {
+    /// Record the contexts of `word` with `window` tokens on each side into
+    /// `contexts`
+    pub fn new(
+        config: &'a WordCountConfig,
+        word: &'a str,
+        window: usize,
+        contexts: &'a mut WordContexts,
+    ) -> Self {
+        Self {
+            config,
+            word,
+            window,
+            contexts,
+        }
+    }
+}
+
+impl ChunkObserver for ContextDiversity<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let mut tokens = Vec::new();
+        for_each_token(line, self.config, |token| tokens.push(token));
+        for (index, token) in tokens.iter().enumerate() {
+            if token != self.word {
+                continue;
+            }
+            // the slices hash their lengths too, so the sides cannot run
+            // into each other
+            let mut hasher = DefaultHasher::new();
+            tokens[index.saturating_sub(self.window)..index].hash(&mut hasher);
+            tokens[index + 1..tokens.len().min(index + 1 + self.window)].hash(&mut hasher);
+            self.contexts.occurrences += 1;
+            self.contexts.contexts.insert(hasher.finish());
+        }
+    }
+}
+
+/// An observer that records the growth of the vocabulary, the number of
+/// unique words after every round against the number of words counted so
+/// far, for fitting Heaps' law with `heaps_fit`
+///
+/// The points run on over all inputs: the words and unique words of the
+/// inputs counted before into `counted` are added to those of the current
+/// one. A round that adds no words records no new point.
+#[derive(Debug)]
+pub struct VocabularyGrowth<'a> {
+    /// The words of the inputs counted before
+    counted: &'a BTreeMap<String, usize>,
+    /// The number of words of the inputs counted before
+    words_before: usize,
+    /// The `(words, unique_words)` points so far
+    points: &'a mut Vec<(usize, usize)>,
+}
+
+impl<'a> VocabularyGrowth<'a> {
+    /// Record the growth of the vocabulary after the `words_before` words
+    /// counted into `counted` into `points`
+    pub fn new(
+        counted: &'a BTreeMap<String, usize>,
+        words_before: usize,
+        points: &'a mut Vec<(usize, usize)>,
+    ) -> Self {
+        Self {
+            counted,
+            words_before,
+            points,
+        }
+    }
+}
+
+impl ChunkObserver for VocabularyGrowth<'_> {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        let words = self.words_before + totals.words;
+        if self.points.last().is_some_and(|&(last, _)| last == words) {
+            return Ok(());
+        }
+        let new_words = word_counts
+            .keys()
+            .filter(|word| !self.counted.contains_key(*word))
+            .count();
+        self.points.push((words, self.counted.len() + new_words));
+        Ok(())
+    }
+}
+
+/// An observer that counts the pairs of tokens occurring close together on
+/// a line
+///
+/// Every token is paired with each of the `window - 1` tokens after it on the
+/// same line, so a window at least as long as a line pairs all its tokens.
+/// A pair is keyed in sorted order, so `(a, b)` and `(b, a)` are one pair.
+#[derive(Debug)]
+pub struct Cooccurrences<'a> {
+    config: &'a WordCountConfig,
+    /// The number of tokens a pair may span, at least 2
+    window: usize,
+    /// How often every pair occurred so far
+    pairs: &'a mut BTreeMap<(String, String), usize>,
+}
+
+impl<'a> Cooccurrences<'a> {
+    /// Count the pairs within `window` tokens into `pairs`
+    pub fn new(
+        config: &'a WordCountConfig,
+        window: usize,
+        pairs: &'a mut BTreeMap<(String, String), usize>,
+    ) -> Self {
+        Self {
+            config,
+            window: window.max(2),
+            pairs,
+        }
+    }
+}
+
+impl ChunkObserver for Cooccurrences<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let mut tokens = Vec::new();
+        for_each_token(line, self.config, |token| tokens.push(token));
+        for (i, first) in tokens.iter().enumerate() {
+            for second in tokens.iter().skip(i + 1).take(self.window - 1) {
+                let pair = if first <= second {
+                    (first.clone(), second.clone())
+                } else {
+                    (second.clone(), first.clone())
+                };
+                *self.pairs.entry(pair).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+/// An observer that counts the tokens of every paragraph of an input
+///
+/// A paragraph is a run of lines that are not blank, i.e. neither empty nor
+/// whitespace only, so leading, trailing and consecutive blank lines add no
+/// empty paragraphs. The lines are seen one at a time, so a paragraph
+/// continues across chunk boundaries; `finish` records the last one.
+#[derive(Debug)]
+pub struct Paragraphs<'a> {
+    config: &'a WordCountConfig,
+    /// The tokens of the paragraph being read, `None` between paragraphs
+    current: Option<usize>,
+    /// The number of tokens of every finished paragraph, in input order
+    words: &'a mut Vec<usize>,
+}
+
+impl<'a> Paragraphs<'a> {
+    /// Count the tokens of every paragraph into `words`
+    pub fn new(config: &'a WordCountConfig, words: &'a mut Vec<usize>) -> Self {
+        Self {
+            config,
+            current: None,
+            words,
+        }
+    }
+
+    /// End the paragraph being read at the end of the input
+    pub fn finish(&mut self) {
+        if let Some(words) = self.current.take() {
+            self.words.push(words);
+        }
+    }
+}
+
+impl ChunkObserver for Paragraphs<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return self.finish();
+        }
+        let words = self.current.get_or_insert(0);
+        for_each_token(line, self.config, |_| *words += 1);
+    }
+}
+
+/// The word counts of a run of consecutive lines, as recorded by `Windows`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    /// The number of the first line of the window, 1-based
+    pub first_line: usize,
+    /// The number of the last line of the window, 1-based
+    pub last_line: usize,
+    pub word_counts: BTreeMap<String, usize>,
+}
+
+/// An observer that counts the units of every window of a fixed number of
+/// lines separately
+///
+/// A new window starts every `size - overlap` lines, so consecutive windows
+/// share `overlap` lines. The lines are seen one at a time, so a window
+/// continues across chunk boundaries; `finish` records the last, shorter
+/// one unless its lines are all part of an earlier window.
+#[derive(Debug)]
+pub struct Windows<'a> {
+    config: &'a WordCountConfig,
+    size: usize,
+    overlap: usize,
+    /// The n-gram window of the input
+    window: VecDeque<String>,
+    /// The number of the last line read, 1-based
+    line: usize,
+    /// The windows not complete yet, the oldest first
+    open: VecDeque<Window>,
+    /// The finished windows, in input order
+    windows: &'a mut Vec<Window>,
+}
+
+impl<'a> Windows<'a> {
+    /// Count the units of every window of `size` lines into `windows`, each
+    /// window sharing `overlap` lines with the one before
+    ///
+    /// # Panics
+    ///
+    /// If `overlap` is not smaller than `size`.
+    pub fn new(
+        config: &'a WordCountConfig,
+        size: usize,
+        overlap: usize,
+        windows: &'a mut Vec<Window>,
+    ) -> Self {
+        assert!(
+            overlap < size,
+            "windows must overlap by less than their size"
+        );
+        Self {
+            config,
+            size,
+            overlap,
+            window: VecDeque::new(),
+            line: 0,
+            open: VecDeque::new(),
+            windows,
+        }
+    }
+
+    /// End the window being read at the end of the input
+    pub fn finish(&mut self) {
+        let covered = self.windows.last().map_or(0, |window| window.last_line);
+        if let Some(window) = self.open.pop_front().filter(|w| w.last_line > covered) {
+            self.windows.push(window);
+        }
+        self.open.clear();
+    }
+}
+
+impl ChunkObserver for Windows<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        self.line += 1;
+        if (self.line - 1).is_multiple_of(self.size - self.overlap) {
+            self.open.push_back(Window {
+                first_line: self.line,
+                last_line: self.line,
+                word_counts: BTreeMap::new(),
+            });
+        }
+        let mut units = Vec::new();
+        for_each_unit(line, self.config, &mut self.window, |unit| units.push(unit));
+        for window in &mut self.open {
+            window.last_line = self.line;
+            for unit in &units {
+                *window.word_counts.entry(unit.clone()).or_insert(0) += 1;
+            }
+        }
+        if self.open[0].last_line - self.open[0].first_line + 1 == self.size {
+            let window = self.open.pop_front().expect("a window is open");
+            self.windows.push(window);
+        }
+    }
+}
+
+/// An observer that counts the tokens of every line, or of a random sample of
+/// the lines, into a histogram for `line_words`
+///
+/// A blank line is a line of 0 tokens. The histogram maps a number of tokens
+/// to its lines, so it stays small however many lines are read. The sample
+/// keeps every line with the probability of the sample rate, drawn from a
+/// pseudo-random sequence seeded by `WordCountConfig::hash_seed`, so a run
+/// can be repeated with the same sample, or seeded randomly without one.
+#[derive(Debug)]
+pub struct WordsPerLine<'a> {
+    config: &'a WordCountConfig,
+    /// The probability a line is sampled with, 1 for every line
+    rate: f64,
+    /// The pseudo-random sequence the sample is drawn from
+    rng: SplitMix64,
+    histogram: &'a mut BTreeMap<usize, usize>,
+}
+
+impl<'a> WordsPerLine<'a> {
+    /// Count the tokens of the lines sampled with probability `rate` into
+    /// `histogram`
+    pub fn new(
+        config: &'a WordCountConfig,
+        rate: f64,
+        histogram: &'a mut BTreeMap<usize, usize>,
+    ) -> Self {
+        Self {
+            config,
+            rate,
+            rng: SplitMix64::seeded(config.hash_seed),
+            histogram,
+        }
+    }
+
+    /// Whether to sample the next line, advancing the pseudo-random sequence
+    fn sample(&mut self) -> bool {
+        self.rate >= 1.0 || self.rng.next_f64() < self.rate
+    }
+}
+
+/// The SplitMix64 pseudo-random sequence, fast and good enough to sample
+/// lines, by its state
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    /// The sequence starting at `seed`, or at a random state without one
+    pub(crate) fn seeded(seed: Option<u64>) -> Self {
+        Self(seed.unwrap_or_else(|| RandomState::new().hash_one(0)))
+    }
+
+    /// The next number of the sequence as a uniform float in [0, 1)
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // the upper 53 bits make a uniform float in [0, 1)
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// An observer keeping a sample of a fixed number of lines, every line
+/// read having the same chance to be in it, by reservoir sampling
+///
+/// Like for `WordsPerLine`, the sample is drawn from the pseudo-random
+/// sequence seeded by `WordCountConfig::hash_seed`. The observer goes on
+/// over several inputs, so it is kept apart from them.
+#[derive(Debug)]
+pub struct LineReservoir {
+    size: usize,
+    /// The number of lines read so far
+    seen: usize,
+    rng: SplitMix64,
+    /// The sampled lines with their positions among the lines read
+    lines: Vec<(usize, String)>,
+}
+
+impl LineReservoir {
+    /// Sample `size` lines, drawn from the sequence seeded with `seed`, or
+    /// seeded randomly without one
+    pub fn new(size: usize, seed: Option<u64>) -> Self {
+        Self {
+            size,
+            seen: 0,
+            rng: SplitMix64::seeded(seed),
+            lines: Vec::with_capacity(size),
+        }
+    }
+
+    /// The sampled lines, in the order they were read in
+    pub fn into_lines(mut self) -> Vec<String> {
+        self.lines.sort_unstable_by_key(|&(position, _)| position);
+        self.lines.into_iter().map(|(_, line)| line).collect()
+    }
+}
+
+impl ChunkObserver for LineReservoir {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let position = self.seen;
+        self.seen += 1;
+        if self.lines.len() < self.size {
+            self.lines.push((position, line.to_string()));
+            return;
+        }
+        // the line replaces a sampled one with probability `size / seen`
+        let index = (self.rng.next_f64() * self.seen as f64) as usize;
+        if index < self.size {
+            self.lines[index] = (position, line.to_string());
+        }
+    }
+}
+
+/// The random sample of the lines of an input, see
+/// `WordCountConfig::sample_rate`
+#[derive(Debug)]
+pub(crate) struct LineSample {
+    rate: f64,
+    rng: SplitMix64,
+}
+
+impl LineSample {
+    pub(crate) fn new(rate: f64, seed: Option<u64>) -> Self {
+        Self {
+            rate,
+            rng: SplitMix64::seeded(seed),
+        }
+    }
+
+    /// Remove the lines of `chunk` left out of the sample
+    pub(crate) fn retain_sampled<S>(&mut self, chunk: &mut Vec<S>) {
+        if self.rate < 1.0 {
+            chunk.retain(|_| self.rng.next_f64() < self.rate);
+        }
+    }
+}
+
+/// The every nth line of an input, see `WordCountConfig::every_nth_line`
+#[derive(Debug)]
+pub(crate) struct NthLines {
+    every: usize,
+    /// The number of lines seen before
+    position: usize,
+}
+
+impl NthLines {
+    pub(crate) fn new(every: usize) -> Self {
+        Self { every, position: 0 }
+    }
+
+    /// Remove the lines of `chunk` other than the first of every `every`,
+    /// counting on from the chunk before
+    pub(crate) fn retain_nth<S>(&mut self, chunk: &mut Vec<S>) {
+        chunk.retain(|_| {
+            let kept = self.position.is_multiple_of(self.every);
+            self.position += 1;
+            kept
+        });
+    }
+}
+
+impl ChunkObserver for WordsPerLine<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        if !self.sample() {
+            return;
+        }
+        let mut words = 0;
+        for_each_token(line, self.config, |_| words += 1);
+        *self.histogram.entry(words).or_insert(0) += 1;
+    }
+}
+
+/// An observer counting the words of every line that no earlier line had,
+/// into `NewWordLines`
+///
+/// Every word is counted once per line however often it occurs on it. The
+/// lines are tokenized a second time like for `WordsPerLine`, and the words
+/// seen so far are kept apart from the counts, so the observer can go on
+/// over several inputs.
+#[derive(Debug)]
+pub struct NewWordsPerLine<'a> {
+    config: &'a WordCountConfig,
+    /// The words of all lines so far
+    seen: HashSet<String>,
+    lines: &'a mut NewWordLines,
+}
+
+impl<'a> NewWordsPerLine<'a> {
+    /// Count the new words of every line into `lines`
+    pub fn new(config: &'a WordCountConfig, lines: &'a mut NewWordLines) -> Self {
+        Self {
+            config,
+            seen: HashSet::new(),
+            lines,
+        }
+    }
+}
+
+impl ChunkObserver for NewWordsPerLine<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let mut new = 0;
+        for_each_token(line, self.config, |word| {
+            if !self.seen.contains(&word) {
+                self.seen.insert(word);
+                new += 1;
+            }
+        });
+        self.lines.add(new);
+    }
+}
+
+/// An observer counting the URLs and email addresses that `extract_urls`
+/// and `extract_emails` remove from the lines, as they are written
+///
+/// Like the lines, the counts go on over several inputs.
+#[derive(Debug)]
+pub struct ExtractedTokens<'a> {
+    config: &'a WordCountConfig,
+    url_counts: &'a mut BTreeMap<String, usize>,
+    email_counts: &'a mut BTreeMap<String, usize>,
+}
+
+impl<'a> ExtractedTokens<'a> {
+    /// Count the URLs into `url_counts` and the email addresses into
+    /// `email_counts`
+    pub fn new(
+        config: &'a WordCountConfig,
+        url_counts: &'a mut BTreeMap<String, usize>,
+        email_counts: &'a mut BTreeMap<String, usize>,
+    ) -> Self {
+        Self {
+            config,
+            url_counts,
+            email_counts,
+        }
+    }
+}
+
+impl ChunkObserver for ExtractedTokens<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        // the same part of the line `for_each_token` removes them from
+        let line = match self.config.column {
+            Some(column) => match extract_column(line, column, self.config.column_separator) {
+                Some(field) => field,
+                None => return,
+            },
+            None => line,
+        };
+        for part in line.split_whitespace() {
+            let (counts, token) = match extracted(part, self.config) {
+                Some((Extracted::Url, token)) => (&mut *self.url_counts, token),
+                Some((Extracted::Email, token)) => (&mut *self.email_counts, token),
+                None => continue,
+            };
+            *counts.entry(token.to_string()).or_insert(0) += 1;
+        }
+    }
+}
+
+/// The number of new words of every line, as collected by `NewWordsPerLine`
+///
+/// Either the number of every line is kept, or only how many lines had
+/// every number, in a fixed number of buckets for large inputs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewWordLines {
+    /// The new words of every line in input order, unless bucketed
+    pub lines: Vec<usize>,
+    /// The number of lines with 0, 1, 2 and so on new words, the last
+    /// bucket also counting the lines with more; empty unless bucketed
+    pub buckets: Vec<usize>,
+    /// The new words of all lines together
+    pub total: usize,
+}
+
+impl NewWordLines {
+    /// Only keep the number of lines with up to `buckets - 1` new words,
+    /// counting the lines with more in the last bucket
+    pub fn bucketed(buckets: usize) -> Self {
+        Self {
+            buckets: vec![0; buckets.max(1)],
+            ..Default::default()
+        }
+    }
+
+    /// Add a line with `new` new words
+    pub fn add(&mut self, new: usize) {
+        self.total += new;
+        match self.buckets.len() {
+            0 => self.lines.push(new),
+            buckets => self.buckets[new.min(buckets - 1)] += 1,
+        }
+    }
+
+    /// The number of lines added
+    pub fn line_count(&self) -> usize {
+        if self.buckets.is_empty() {
+            self.lines.len()
+        } else {
+            self.buckets.iter().sum()
+        }
+    }
+
+    /// The mean number of new words per line, 0 without any lines
+    pub fn mean(&self) -> f64 {
+        match self.line_count() {
+            0 => 0.0,
+            lines => self.total as f64 / lines as f64,
+        }
+    }
+
+    /// The median number of new words per line, 0 without any lines
+    ///
+    /// The median of two middle lines is their mean. A median in the last
+    /// bucket is only known to be at least the lower bound of that bucket,
+    /// which is returned.
+    pub fn median(&self) -> f64 {
+        let lines = self.line_count();
+        if lines == 0 {
+            return 0.0;
+        }
+        let histogram: BTreeMap<usize, usize> = if self.buckets.is_empty() {
+            let mut histogram = BTreeMap::new();
+            for &new in &self.lines {
+                *histogram.entry(new).or_insert(0) += 1;
+            }
+            histogram
+        } else {
+            self.buckets.iter().copied().enumerate().collect()
+        };
+        histogram_median(&histogram, lines)
+    }
+}
+
+/// An observer that streams the counts of the words of every chunk as soon
+/// as it is done, as `{"word": ..., "count": ...}` lines
+///
+/// The counts are not final before the input is: a word is written again
+/// with its grown count in every later chunk it occurs in. Like `FirstSeen`
+/// the lines are tokenized a second time to find the words of a chunk, and
+/// every line is flushed right away so consumers see it without delay. The
+/// count filters of the output are not applied.
+pub struct NdjsonStream<'a> {
+    out: &'a mut dyn Write,
+    config: &'a WordCountConfig,
+    /// The counts of the inputs before the current one
+    before: &'a BTreeMap<String, usize>,
+    /// The n-gram window of the current input
+    window: VecDeque<String>,
+    /// The words of the current chunk
+    touched: BTreeSet<String>,
+}
+
+impl<'a> NdjsonStream<'a> {
+    /// Stream the counts of an input to `out`, added to the counts `before` it
+    pub fn new(
+        out: &'a mut dyn Write,
+        config: &'a WordCountConfig,
+        before: &'a BTreeMap<String, usize>,
+    ) -> Self {
+        Self {
+            out,
+            config,
+            before,
+            window: VecDeque::new(),
+            touched: BTreeSet::new(),
+        }
+    }
+}
+
+impl ChunkObserver for NdjsonStream<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        for word in mem::take(&mut self.touched) {
+            // a word dropped because of `max_unique_words` was not counted
+            let Some(&count) = word_counts.get(&word) else {
+                continue;
+            };
+            let count = self.before.get(&word).copied().unwrap_or(0) + count;
+            serde_json::to_writer(&mut *self.out, &WordEntry::plain(&word, count))?;
+            writeln!(self.out)?;
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        let touched = &mut self.touched;
+        for_each_unit(line, self.config, &mut self.window, |unit| {
+            touched.insert(unit);
+        });
+    }
+}
+
+/// The saved state of an interrupted run, written after every chunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The version of the format, see `CHECKPOINT_VERSION`
+    pub version: u32,
+    /// The input the checkpoint was taken from
+    pub source: PathBuf,
+    /// The totals of the input up to the checkpoint; `lines_read` is the line
+    /// to resume at
+    pub totals: Totals,
+    /// The word counts up to the checkpoint
+    pub word_counts: BTreeMap<String, usize>,
+}
+
+/// The borrowed counterpart of `Checkpoint`, so writing needs no copy of the map
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    version: u32,
+    source: &'a Path,
+    totals: &'a Totals,
+    word_counts: &'a BTreeMap<String, usize>,
+}
+
+/// An observer that writes a checkpoint of the input after every chunk
+///
+/// The totals of the chunk loop only cover the lines read in this run, so
+/// the totals resumed from are added to every checkpoint.
+#[derive(Debug, Clone)]
+pub struct CheckpointWriter {
+    /// The file the checkpoint is written to
+    path: PathBuf,
+    /// The input the checkpoint is taken from
+    source: PathBuf,
+    /// The totals resumed from
+    base: Totals,
+}
+
+impl CheckpointWriter {
+    /// Create a writer checkpointing `source` to `path`, starting from `base`
+    pub fn new(path: &Path, source: &Path, base: Totals) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            source: source.to_path_buf(),
+            base,
+        }
+    }
+}
+
+impl ChunkObserver for CheckpointWriter {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        let mut totals = *totals;
+        totals += self.base;
+        write_checkpoint(&self.path, &self.source, &totals, word_counts)
+    }
+}
+
+/// A single printed word and its count, with the optional columns switched
+/// on in the output config
+#[derive(Debug, Serialize)]
+struct WordEntry<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rank: Option<usize>,
+    word: &'a str,
+    count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    freq_pmw: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_count: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cumulative_pct: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_line: Option<usize>,
+}
+
+impl<'a> WordEntry<'a> {
+    /// An entry with only the word and its count
+    fn plain(word: &'a str, count: usize) -> Self {
+        Self {
+            rank: None,
+            word,
+            count,
+            freq_pmw: None,
+            log_count: None,
+            cumulative_pct: None,
+            first_line: None,
+        }
+    }
+}
+
+/// The cells of a line of the text format
+#[derive(Debug)]
+struct TextRow<'a> {
+    rank: Option<String>,
+    word: Cow<'a, str>,
+    count: Option<String>,
+    cumulative: Option<String>,
+    /// Whether this is the first row reaching the coverage threshold
+    reaches_threshold: bool,
+    first_line: Option<usize>,
+}
+
+/// The counts of an input at one point in time, as printed by `--stream`
+#[derive(Debug, Serialize)]
+struct Snapshot<'a> {
+    /// The milliseconds since the Unix epoch
+    timestamp_ms: u64,
+    counts: Vec<WordEntry<'a>>,
+}
+
+/// The difference between the word counts of two inputs
+///
+/// Every list is ordered by the magnitude of the count change, largest
+/// first, with ties ordered alphabetically.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CountDiff<'a> {
+    /// The words only counted in the first input, with their counts
+    pub only_left: Vec<(&'a str, usize)>,
+    /// The words only counted in the second input, with their counts
+    pub only_right: Vec<(&'a str, usize)>,
+    /// The words counted in both inputs a different number of times, with
+    /// the change from the first to the second input
+    pub changed: Vec<(&'a str, i64)>,
+}
+
+/// A word of either of two compared corpora, see `compare_corpora`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CorpusEntry<'a> {
+    pub word: &'a str,
+    /// The count in the first corpus
+    pub first: usize,
+    /// The count in the second corpus
+    pub second: usize,
+    /// `first / second`, `None` for a word of one corpus only
+    pub ratio: Option<f64>,
+    /// The log-likelihood G² of the counts, if computed
+    pub log_likelihood: Option<f64>,
+}
+
+/// The words of two corpora, split by whether they occur in both
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CorpusComparison<'a> {
+    pub both: Vec<CorpusEntry<'a>>,
+    pub only_first: Vec<CorpusEntry<'a>>,
+    pub only_second: Vec<CorpusEntry<'a>>,
+}
+
+/// A word of the input or of a reference frequency list, with its count in
+/// either, see `compare_to_reference`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceEntry<'a> {
+    pub word: &'a str,
+    /// The count in the input, `None` for a word missing from it
+    pub count: Option<usize>,
+    /// The count in the reference, `None` for a word new to it
+    pub reference: Option<usize>,
+}
+
+impl ReferenceEntry<'_> {
+    /// How much more often the word was counted in the input than in the
+    /// reference, counting a missing word as 0; `None` for a new word
+    pub fn delta(&self) -> Option<i64> {
+        let reference = self.reference?;
+        Some(self.count.unwrap_or(0) as i64 - reference as i64)
+    }
+}
+
+/// A word of the input with how much more frequent it is than in a
+/// baseline corpus, see `relative_frequencies`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeEntry<'a> {
+    pub word: &'a str,
+    /// The count in the input
+    pub count: usize,
+    /// The frequency of the word in the input divided by its frequency in
+    /// the baseline, `None` for a word the baseline lacks unless smoothed
+    pub ratio: Option<f64>,
+    /// Whether the baseline lacks the word
+    pub new: bool,
+}
+
+/// A word of a document with its TF-IDF score, see `tfidf`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TfidfEntry<'a> {
+    pub word: &'a str,
+    /// The term frequency of the word in the document
+    pub tf: f64,
+    /// The inverse document frequency of the word in the corpus
+    pub idf: f64,
+    /// The product of `tf` and `idf`
+    pub tfidf: f64,
+}
+
+/// A bound on the count of a word, checked by `failed_assertions`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CountAssertion {
+    /// The word must be counted at most this often
+    AtMost(String, usize),
+    /// The word must be counted at least this often, at least once for 1
+    AtLeast(String, usize),
+    /// The word must not be counted at all
+    Absent(String),
+}
+
+/// How `tfidf` weighs the count of a word in a document
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TfidfVariant {
+    /// The count divided by the number of tokens of the document
+    #[default]
+    Raw,
+    /// `1 + ln(count)`, so a word counted ten times does not weigh ten
+    /// times as much
+    Sublinear,
+    /// The saturating term frequency of Okapi BM25 with `k1 = 1.2` and `b =
+    /// 0.75`, normalised by the length of the document against the average
+    Bm25,
+}
+
+/// The format in which the comparison against a reference frequency list is
+/// printed, see `write_reference_diff`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReferenceDiffFormat {
+    /// One `word: count (reference: count, delta: change)` line per word
+    #[default]
+    Text,
+    /// A JSON array of `{"word", "count", "reference", "delta", "status"}`
+    /// objects
+    Json,
+}
+
+/// The base of the logarithm `OutputConfig::log_scale` prints the counts as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogBase {
+    /// The common logarithm, log₁₀
+    Ten,
+    /// The natural logarithm, ln
+    E,
+}
+
+/// Options controlling how the counted words are printed
+#[derive(Debug, Default, Clone)]
+pub struct OutputConfig {
+    /// The order of the printed entries
+    pub sort_order: SortOrder,
+    /// The maximum number of entries to print
+    pub top: Option<usize>,
+    /// Only print this many of the least frequent entries, least frequent
+    /// first whatever the `sort_order`
+    pub bottom: Option<usize>,
+    /// Also print the entries counted as often as the last of the `bottom`
+    /// ones, which are cut off at exactly `bottom` entries otherwise
+    pub bottom_ties: bool,
+    /// Leave out words counted fewer times than this
+    pub min_count: Option<usize>,
+    /// Leave out words counted more times than this
+    pub max_count: Option<usize>,
+    /// Leave out words not counted exactly this many times
+    pub exactly: Option<usize>,
+    /// The format of the printed entries
+    pub format: OutputFormat,
+    /// Leave out the header row of tabular formats
+    pub no_header: bool,
+    /// Start the tsv format with a header row, which it leaves out otherwise
+    pub tsv_header: bool,
+    /// Put every element of the xml format on a line of its own, indented
+    /// by two spaces per level
+    pub xml_indent: bool,
+    /// Only print the words counted exactly this many times, without their
+    /// counts in the text format, e.g. 1 for the hapax legomena
+    pub legomena: Option<usize>,
+    /// Print the frequency per million words (PMW) of every word, relative to
+    /// this total number of words, instead of its raw count
+    pub normalize_by: Option<usize>,
+    /// Print the logarithm of every count to this base instead of the count
+    pub log_scale: Option<LogBase>,
+    /// Prefix every entry with its dense frequency rank, see `dense_ranks`
+    pub show_rank: bool,
+    /// Color the entries of the text format, see `format_entry`
+    pub color: bool,
+    /// Print the text format as a table, the words padded to the longest one
+    /// and the counts aligned to the right
+    pub table: bool,
+    /// Start the table with a `WORD  COUNT` header and a separator line
+    pub table_header: bool,
+    /// Print the text format as `word\0count\0` records, see
+    /// `write_null_separated`
+    pub null_separated: bool,
+    /// End the stats with the average number of words per sentence, which
+    /// needs the sentences to be counted
+    pub words_per_sentence: bool,
+    /// Print the percentage of this total number of words covered by every
+    /// entry together with the entries before it, see `cumulative_pct`
+    pub cumulative_of: Option<usize>,
+    /// Mark the first entry whose cumulative percentage reaches this one
+    pub coverage_threshold: Option<f64>,
+    /// Only print the first entries that together cover this percentage of
+    /// this total number of words, the last of them reaching it, which are
+    /// the most frequent words with `SortOrder::CountDesc`
+    pub top_percent: Option<(f64, usize)>,
+    /// Annotate every entry with the line it was first seen on, as recorded
+    /// by `FirstSeen`
+    pub first_lines: Option<BTreeMap<String, usize>>,
+    /// Order `SortOrder::FirstSeen` by the position every entry was first
+    /// seen at instead of its first line, as recorded by `FirstOccurrences`
+    pub first_positions: Option<BTreeMap<String, usize>>,
+    /// End the stats with the mean, median and mode word length, see
+    /// `word_lengths`
+    pub word_lengths: bool,
+    /// Append the minimum, maximum, mean and median tokens per line of this
+    /// histogram to the stats, as collected by `WordsPerLine`
+    pub words_per_line: Option<BTreeMap<usize, usize>>,
+    /// Append the mean and median of these new words per line to the stats,
+    /// as collected by `NewWordsPerLine`
+    pub new_words_per_line: Option<NewWordLines>,
+    /// End the stats with the number, average and maximum of these tokens
+    /// per paragraph, as recorded by `Paragraphs`
+    pub paragraphs: Option<Vec<usize>>,
+    /// End the stats with the Shannon entropy and the normalised entropy of
+    /// the word distribution, see `entropy`
+    pub entropy: bool,
+    /// End the stats with the perplexity of the word distribution
+    pub perplexity: bool,
+    /// Pad or cut every word of the text and TSV output to exactly this many
+    /// chars, see `fit_width`
+    pub word_width: Option<usize>,
+    /// Ends the words cut down to `word_width`
+    pub truncation_marker: String,
+    /// Right-align the counts of the text and TSV output to at least this
+    /// many chars
+    pub count_width: Option<usize>,
+    /// Print every entry of the text output as this template instead, see
+    /// `fill_template`
+    pub template: Option<String>,
+}
+
+impl OutputConfig {
+    /// The frequency per million words of a word counted `count` times,
+    /// rounded to two decimal places, if `normalize_by` is set
+    pub fn freq_pmw(&self, count: usize) -> Option<f64> {
+        let total = self.normalize_by?;
+        if total == 0 {
+            return Some(0.0);
+        }
+        let pmw = count as f64 * 1_000_000.0 / total as f64;
+        Some((pmw * 100.0).round() / 100.0)
+    }
+
+    /// The logarithm of `count` to the `log_scale` base, rounded to four
+    /// decimal places, if `log_scale` is set
+    pub fn log_count(&self, count: usize) -> Option<f64> {
+        let log = match self.log_scale? {
+            LogBase::Ten => (count as f64).log10(),
+            LogBase::E => (count as f64).ln(),
+        };
+        Some((log * 10_000.0).round() / 10_000.0)
+    }
+
+    /// The percentage of the words counted `covered` times altogether, rounded
+    /// to two decimal places, if `cumulative_of` is set
+    pub fn cumulative_pct(&self, covered: usize) -> Option<f64> {
+        let total = self.cumulative_of?;
+        if total == 0 {
+            return Some(0.0);
+        }
+        let pct = covered as f64 * 100.0 / total as f64;
+        Some((pct * 100.0).round() / 100.0)
+    }
+
+    /// `word` fitted to the `word_width`, if set
+    fn fit_word<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        match self.word_width {
+            Some(width) => Cow::Owned(fit_width(word, width, &self.truncation_marker)),
+            None => Cow::Borrowed(word),
+        }
+    }
+
+    /// `count` right-aligned to the `count_width`, if set
+    fn fit_count(&self, count: String) -> String {
+        match self.count_width {
+            Some(width) => format!("{:>width$}", count),
+            None => count,
+        }
+    }
+
+    /// Whether a word counted `count` times passes the `min_count`,
+    /// `max_count` and `exactly` filters
+    pub fn keeps_count(&self, count: usize) -> bool {
+        self.min_count.is_none_or(|min| count >= min)
+            && self.max_count.is_none_or(|max| count <= max)
+            && self.exactly.is_none_or(|exactly| count == exactly)
+    }
+}
+
+/// A builder that collects the counting options and then drives the pipeline
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use word_count::WordCounter;
+///
+/// let mut counter = WordCounter::new();
+/// counter.strip_punct(true).sleep(None);
+/// let word_counts = counter.count_reader(Cursor::new("hello, world. hello")).unwrap();
+/// assert_eq!(word_counts.get("hello"), Some(&2));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct WordCounter {
+    config: WordCountConfig,
+    /// Leave out words counted fewer times than this from the result
+    min_count: Option<usize>,
+    /// Leave out words counted more times than this from the result
+    max_count: Option<usize>,
+}
+
+impl WordCounter {
+    /// Create a counter with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat words that differ only in case as the same word
+    pub fn ignore_case(&mut self, ignore_case: bool) -> &mut Self {
+        self.config.ignore_case = ignore_case;
+        self
+    }
+
+    /// Strip leading and trailing punctuation from each word
+    pub fn strip_punct(&mut self, strip_punct: bool) -> &mut Self {
+        self.config.strip_punct = strip_punct;
+        self
+    }
+
+    /// Split camelCase and PascalCase words into the words they are made of
+    pub fn split_camel_case(&mut self, split_camel_case: bool) -> &mut Self {
+        self.config.split_camel_case = split_camel_case;
+        self
+    }
+
+    /// Split snake_case words at their underscores
+    pub fn split_underscore(&mut self, split_underscore: bool) -> &mut Self {
+        self.config.split_underscore = split_underscore;
+        self
+    }
+
+    /// Split hyphenated compounds like `well-known` at their hyphens,
+    /// keeping those with a digit whole if `keep_numbers` is set
+    pub fn split_hyphenated(&mut self, split_hyphenated: bool, keep_numbers: bool) -> &mut Self {
+        self.config.split_hyphenated = split_hyphenated;
+        self.config.keep_hyphenated_numbers = keep_numbers;
+        self
+    }
+
+    /// Tokenize the lines as source code of `lang`
+    pub fn code_lang(&mut self, lang: CodeLang) -> &mut Self {
+        self.config.code_lang = Some(lang);
+        self
+    }
+
+    /// Set the number of lines processed between two sleeps
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.config.chunk_size = chunk_size;
+        self
+    }
+
+    /// Set the capacity in bytes of the buffer every input is read through
+    pub fn buffer_size(&mut self, buffer_size: usize) -> &mut Self {
+        self.config.buffer_size = buffer_size;
+        self
+    }
+
+    /// Set the pause after every chunk in milliseconds
+    pub fn sleep_ms(&mut self, sleep_ms: u64) -> &mut Self {
+        self.config.sleep = Some(Duration::from_millis(sleep_ms));
+        self
+    }
+
+    /// Set the pause after every chunk, `None` disables sleeping entirely
+    pub fn sleep(&mut self, sleep: Option<Duration>) -> &mut Self {
+        self.config.sleep = sleep;
+        self
+    }
+
+    /// Process several chunks at once on the rayon thread pool
+    pub fn parallel(&mut self, parallel: bool) -> &mut Self {
+        self.config.parallel = parallel;
+        self
+    }
+
+    /// Map input files into memory instead of reading them through a buffer
+    pub fn mmap(&mut self, mmap: bool) -> &mut Self {
+        self.config.mmap = mmap;
+        self
+    }
+
+    /// Only count words with at least `min_length` chars
+    pub fn min_length(&mut self, min_length: usize) -> &mut Self {
+        self.config.min_length = Some(min_length);
+        self
+    }
+
+    /// Only count words with at most `max_length` chars
+    pub fn max_length(&mut self, max_length: usize) -> &mut Self {
+        self.config.max_length = Some(max_length);
+        self
+    }
+
+    /// Cut tokens longer than `length` chars down to `length`, followed by
+    /// `marker`
+    pub fn truncate_tokens(&mut self, length: usize, marker: &str) -> &mut Self {
+        self.config.truncate_length = Some(length);
+        self.config.truncate_marker = marker.to_string();
+        self
+    }
+
+    /// Normalize every word into the Unicode normalization form `form`
+    pub fn unicode_form(&mut self, form: UnicodeForm) -> &mut Self {
+        self.config.unicode_form = Some(form);
+        self
+    }
+
+    /// Skip every word contained in `stopwords`
+    ///
+    /// The entries are compared against the normalised words, so they are
+    /// expected to be lowercase when `ignore_case` is set.
+    pub fn stopwords(&mut self, stopwords: HashSet<String>) -> &mut Self {
+        self.config.stopwords = stopwords;
+        self
+    }
+
+    /// Count the stems of the tokens in `lang` instead of the tokens
+    pub fn stem(&mut self, lang: StemLang) -> &mut Self {
+        self.config.stem = Some(lang);
+        self
+    }
+
+    /// Remove these prefixes and suffixes from the tokens, see
+    /// `WordCountConfig::strip_prefixes`
+    pub fn strip_affixes(&mut self, prefixes: Vec<String>, suffixes: Vec<String>) -> &mut Self {
+        self.config.strip_prefixes = prefixes;
+        self.config.strip_suffixes = suffixes;
+        self
+    }
+
+    /// Transliterate the tokens into Latin letters with `scheme`
+    #[cfg(feature = "transliterate")]
+    pub fn transliterate(&mut self, scheme: TranslitScheme) -> &mut Self {
+        self.config.transliterate = Some(scheme);
+        self
+    }
+
+    /// Skip the built-in stopwords of `lang` as well, see `builtin_stopwords`
+    pub fn stopwords_lang(&mut self, lang: StopwordLang) -> &mut Self {
+        let stopwords = builtin_stopwords(lang, self.config.ignore_case);
+        self.config.stopwords.extend(stopwords);
+        self
+    }
+
+    /// Only count the words matching `pattern`
+    pub fn include_pattern(&mut self, pattern: Regex) -> &mut Self {
+        self.config.include_pattern = Some(pattern);
+        self
+    }
+
+    /// Skip every word matching `pattern`
+    pub fn exclude_pattern(&mut self, pattern: Regex) -> &mut Self {
+        self.config.exclude_pattern = Some(pattern);
+        self
+    }
+
+    /// Only count the words contained in `vocab`
+    ///
+    /// Like the stopwords, the entries are compared against the normalised
+    /// words, and a word that is also a stopword is still skipped.
+    pub fn vocab(&mut self, vocab: HashSet<String>) -> &mut Self {
+        self.config.vocab = Some(vocab);
+        self
+    }
+
+    /// Decode the inputs from `encoding` instead of UTF-8
+    pub fn encoding(&mut self, encoding: Encoding) -> &mut Self {
+        self.config.encoding = encoding;
+        self
+    }
+
+    /// Replace invalid UTF-8 with U+FFFD instead of failing on it
+    pub fn force_utf8(&mut self, force_utf8: bool) -> &mut Self {
+        self.config.force_utf8 = force_utf8;
+        self
+    }
+
+    /// Only count the lines within `range`
+    pub fn line_range(&mut self, range: LineRange) -> &mut Self {
+        self.config.line_range = Some(range);
+        self
+    }
+
+    /// Skip the lines longer than `max` chars
+    pub fn skip_long_lines(&mut self, max: usize) -> &mut Self {
+        self.config.skip_long_lines = Some(max);
+        self
+    }
+
+    /// Seed the hasher of the `HashMap`s with `seed` instead of randomly
+    pub fn hash_seed(&mut self, seed: u64) -> &mut Self {
+        self.config.hash_seed = Some(seed);
+        self
+    }
+
+    /// Hash the words of the `HashMap`s with `algorithm` instead of foldhash
+    pub fn hash_algorithm(&mut self, algorithm: HashAlgorithm) -> &mut Self {
+        self.config.hash_algorithm = algorithm;
+        self
+    }
+
+    /// Stop adding new words once `max` unique words are counted
+    pub fn max_unique_words(&mut self, max: usize) -> &mut Self {
+        self.config.max_unique_words = Some(max);
+        self
+    }
+
+    /// Count every number as `placeholder`, see
+    /// `WordCountConfig::replace_numbers`
+    pub fn replace_numbers(&mut self, placeholder: &str) -> &mut Self {
+        self.config.replace_numbers = Some(placeholder.to_string());
+        self
+    }
+
+    /// Recognise the numbers to replace by `regex` instead of as digits only
+    pub fn number_regex(&mut self, regex: Regex) -> &mut Self {
+        self.config.number_regex = Some(regex);
+        self
+    }
+
+    /// Count every HTTP and HTTPS URL as `placeholder`
+    pub fn replace_urls(&mut self, placeholder: &str) -> &mut Self {
+        self.config.replace_urls = Some(placeholder.to_string());
+        self
+    }
+
+    /// Remove the HTTP and HTTPS URLs from the lines before tokenizing them
+    pub fn extract_urls(&mut self) -> &mut Self {
+        self.config.extract_urls = true;
+        self
+    }
+
+    /// Remove the email addresses from the lines before tokenizing them
+    pub fn extract_emails(&mut self) -> &mut Self {
+        self.config.extract_emails = true;
+        self
+    }
+
+    /// Count the grapheme clusters of the input as well
+    pub fn count_graphemes(&mut self) -> &mut Self {
+        self.config.count_graphemes = true;
+        self
+    }
+
+    /// Stop adding new words once the counts take up about `bytes` bytes
+    pub fn max_memory(&mut self, bytes: usize) -> &mut Self {
+        self.config.max_memory = Some(bytes);
+        self
+    }
+
+    /// Stop reading an input once `deadline` has passed
+    pub fn deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Count every line only once per input, recognising the lines seen
+    /// before as set by `dedup`
+    pub fn dedup_lines(&mut self, dedup: LineDedup) -> &mut Self {
+        self.config.dedup_lines = Some(dedup);
+        self
+    }
+
+    /// Only count the lines `regex` matches, or with `invert` the others
+    pub fn line_regex(&mut self, regex: Regex, invert: bool) -> &mut Self {
+        self.config.line_regex = Some(regex);
+        self.config.line_regex_invert = invert;
+        self
+    }
+
+    /// Skip the comments of source code in `language`
+    pub fn ignore_comments(&mut self, language: CommentLanguage) -> &mut Self {
+        self.config.comment_language = Some(language);
+        self
+    }
+
+    /// Only count a random sample of the lines, each kept with probability
+    /// `rate`
+    pub fn sample_rate(&mut self, rate: f64) -> &mut Self {
+        self.config.sample_rate = Some(rate);
+        self
+    }
+
+    /// Split lines on every occurrence of `separator` instead of on whitespace
+    pub fn field_separator(&mut self, separator: &str) -> &mut Self {
+        self.config.field_separator = Some(separator.to_string());
+        self
+    }
+
+    /// Split lines on the matches of `regex` instead of on whitespace
+    pub fn split_regex(&mut self, regex: Regex) -> &mut Self {
+        self.config.split_regex = Some(regex);
+        self
+    }
+
+    /// Accumulate the tokens in a `HashMap` and sort only once per input
+    pub fn use_hashmap(&mut self, use_hashmap: bool) -> &mut Self {
+        self.config.use_hashmap = use_hashmap;
+        self
+    }
+
+    /// Count sequences of `ngrams` consecutive tokens instead of single tokens
+    pub fn ngrams(&mut self, ngrams: usize) -> &mut Self {
+        self.config.ngrams = ngrams;
+        self
+    }
+
+    /// Count single characters instead of words
+    pub fn chars(&mut self, chars: bool) -> &mut Self {
+        self.config.chars = chars;
+        self
+    }
+
+    /// Leave out whitespace when counting characters
+    pub fn skip_whitespace_chars(&mut self, skip_whitespace_chars: bool) -> &mut Self {
+        self.config.skip_whitespace_chars = skip_whitespace_chars;
+        self
+    }
+
+    /// Count the n-grams of `n` chars of every word instead of the words
+    pub fn char_ngrams(&mut self, n: usize) -> &mut Self {
+        self.config.char_ngrams = Some(n);
+        self
+    }
+
+    /// Pad every word with `^` and `$` before taking its char n-grams
+    pub fn char_ngram_boundaries(&mut self, char_ngram_boundaries: bool) -> &mut Self {
+        self.config.char_ngram_boundaries = char_ngram_boundaries;
+        self
+    }
+
+    /// Leave out the words counted fewer than `min_count` times
+    pub fn min_count(&mut self, min_count: usize) -> &mut Self {
+        self.min_count = Some(min_count);
+        self
+    }
+
+    /// Leave out the words counted more than `max_count` times
+    pub fn max_count(&mut self, max_count: usize) -> &mut Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// The options collected so far
+    pub fn config(&self) -> &WordCountConfig {
+        &self.config
+    }
+
+    /// Count the words of any buffered reader
+    pub fn count_reader<R: BufRead>(&self, reader: R) -> io::Result<BTreeMap<String, usize>> {
+        let mut word_counts = BTreeMap::new();
+        count_reader(reader, &self.config, &mut word_counts)?;
+        Ok(self.filtered(word_counts))
+    }
+
+    /// Count the words of a single file
+    pub fn count_file(&self, path: &Path) -> io::Result<BTreeMap<String, usize>> {
+        let mut word_counts = BTreeMap::new();
+        count_file(path, &self.config, &mut word_counts)?;
+        Ok(self.filtered(word_counts))
+    }
+
+    /// Iterate lazily over the word counts of a buffered reader, chunk by chunk
+    ///
+    /// The count filters only apply to the finished counts, so they are not
+    /// applied to the iterator.
+    pub fn iter_reader<R: BufRead>(&self, reader: R) -> WordCountIter<R> {
+        WordCountIter::new(reader, self.config.clone())
+    }
+
+    /// Iterate lazily over the word counts of a single file, chunk by chunk
+    ///
+    /// Like `iter_reader`, the count filters are not applied.
+    pub fn iter_file(&self, path: &Path) -> io::Result<WordCountIter<BufReader<Box<dyn Read>>>> {
+        let mut file = File::open(path)?;
+        let compression = detect_compression(path, &mut file)?;
+        let reader =
+            BufReader::with_capacity(self.config.buffer_size, decompress(file, compression)?);
+        Ok(self.iter_reader(reader))
+    }
+
+    /// Apply the count filters, which only work on the finished counts
+    fn filtered(&self, mut word_counts: BTreeMap<String, usize>) -> BTreeMap<String, usize> {
+        word_counts.retain(|_, &mut count| {
+            self.min_count.is_none_or(|min| count >= min)
+                && self.max_count.is_none_or(|max| count <= max)
+        });
+        word_counts
+    }
+}
+
+impl From<WordCountConfig> for WordCounter {
+    fn from(config: WordCountConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+}
+
+/// Atomically replace the checkpoint at `path`
+///
+/// The checkpoint is written to a temporary file next to `path` first and
+/// then renamed, so an interruption never leaves a torn checkpoint behind.
+///
+/// # Arguments
+///
+/// * `path` - The file to write the checkpoint to
+/// * `source` - The input the checkpoint is taken from
+/// * `totals` - The totals up to the checkpoint
+/// * `word_counts` - The word counts up to the checkpoint
+pub fn write_checkpoint(
+    path: &Path,
+    source: &Path,
+    totals: &Totals,
+    word_counts: &BTreeMap<String, usize>,
+) -> io::Result<()> {
+    let checkpoint = CheckpointRef {
+        version: CHECKPOINT_VERSION,
+        source,
+        totals,
+        word_counts,
+    };
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    serde_json::to_writer(&mut writer, &checkpoint)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&tmp_path, path)
+}
+
+/// Read a checkpoint written by `write_checkpoint`
+///
+/// Checkpoints of another format version are rejected as invalid data.
+///
+/// # Arguments
+///
+/// * `path` - The file to read the checkpoint from
+pub fn read_checkpoint(path: &Path) -> io::Result<Checkpoint> {
+    let reader = BufReader::new(File::open(path)?);
+    let checkpoint: Checkpoint = serde_json::from_reader(reader)?;
+    if checkpoint.version != CHECKPOINT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "unsupported checkpoint version {} (expected {})",
+                checkpoint.version, CHECKPOINT_VERSION
+            ),
+        ));
+    }
+    Ok(checkpoint)
+}
+
+/// Count the words of a file or of stdin if `path` is `-`
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+pub fn count_path(
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+) -> io::Result<Totals> {
+    count_path_observed(path, config, word_counts, &mut ())
+}
+
+/// Count the words of a file or of stdin, notifying `observer` after every chunk
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read, `-` for stdin
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+/// * `observer` - Gets notified after every round of chunks
+pub fn count_path_observed(
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    if path == Path::new(STDIN_PATH) {
+        let mut stdin = io::stdin().lock();
+        if config.encoding != Encoding::Utf8 {
+            let text = decode_input(&mut stdin, config.encoding)?;
+            return count_reader_observed(io::Cursor::new(text), config, word_counts, observer);
+        }
+        let reader = BufReader::with_capacity(config.buffer_size, stdin);
+        count_reader_observed(reader, config, word_counts, observer)
+    } else {
+        count_file_observed(path, config, word_counts, observer)
+    }
+}
+
+/// Count the words of a single file, adding them to `word_counts`
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+pub fn count_file(
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+) -> io::Result<Totals> {
+    count_file_observed(path, config, word_counts, &mut ())
+}
+
+/// Count the words of a single file, notifying `observer` after every chunk
+fn count_file_observed(
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let mut file = File::open(path)?;
+    let compression = detect_compression(path, &mut file)?;
+    if config.encoding != Encoding::Utf8 {
+        let text = decode_input(&mut decompress(file, compression)?, config.encoding)?;
+        return count_reader_observed(io::Cursor::new(text), config, word_counts, observer);
+    }
+    if compression != Compression::None {
+        let reader = BufReader::with_capacity(config.buffer_size, decompress(file, compression)?);
+        return count_reader_observed(reader, config, word_counts, observer);
+    }
+    if config.mmap {
+        return count_mmap(&file, config, word_counts, observer);
+    }
+
+    // use larger buffer ti reduce I/O-operations
+    let reader = BufReader::with_capacity(config.buffer_size, file);
+    count_reader_observed(reader, config, word_counts, observer)
+}
+
+/// The compression of an input file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+}
+
+/// Detect the compression of `file` from the extension of `path`, falling
+/// back to the gzip magic bytes
+///
+/// The file is rewound after peeking at its first bytes.
+fn detect_compression(path: &Path, file: &mut File) -> io::Result<Compression> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("gz") => return Ok(Compression::Gzip),
+        Some("bz2") => return Ok(Compression::Bzip2),
+        _ => {}
+    }
+    // 0x1f 0x8b is never valid UTF-8, so plain text cannot be mistaken for gzip
+    let mut magic = Vec::with_capacity(2);
+    Read::take(&mut *file, 2).read_to_end(&mut magic)?;
+    file.rewind()?;
+    if magic == [0x1f, 0x8b] {
+        Ok(Compression::Gzip)
+    } else {
+        Ok(Compression::None)
+    }
+}
+
+/// Wrap `file` in a decoder for `compression`
+///
+/// Fails with `Unsupported` if the crate was built without the feature
+/// for `compression`.
+fn decompress(file: File, compression: Compression) -> io::Result<Box<dyn Read>> {
+    match compression {
+        Compression::None => Ok(Box::new(file)),
+        // a multi member decoder also reads files that were concatenated
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(file))),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(bzip2::read::MultiBzDecoder::new(file))),
+        #[cfg(not(feature = "gzip"))]
+        Compression::Gzip => Err(missing_feature("gzip")),
+        #[cfg(not(feature = "bzip2"))]
+        Compression::Bzip2 => Err(missing_feature("bzip2")),
+    }
+}
+
+/// The error for a compressed input whose decoder was not compiled in
+#[cfg(not(all(feature = "gzip", feature = "bzip2")))]
+fn missing_feature(feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} input is not supported, build with the `{}` feature",
+            feature, feature
+        ),
+    )
+}
+
+/// Read all of `input` and decode it from `encoding`, see `decode`
+fn decode_input(input: &mut dyn Read, encoding: Encoding) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    decode(&bytes, encoding)
+}
+
+/// Decode `bytes` as UTF-8 like `String::from_utf8_lossy`, replacing every
+/// invalid sequence with U+FFFD, and count the sequences replaced
+///
+/// ```
+/// use word_count::decode_utf8_lossy;
+///
+/// let (text, invalid) = decode_utf8_lossy(b"caf\xe9 \xff\xfe");
+/// assert_eq!(text, "caf\u{fffd} \u{fffd}\u{fffd}");
+/// assert_eq!(invalid, 3);
+/// ```
+pub fn decode_utf8_lossy(bytes: &[u8]) -> (Cow<'_, str>, usize) {
+    let invalid = bytes
+        .utf8_chunks()
+        .filter(|chunk| !chunk.invalid().is_empty())
+        .count();
+    (String::from_utf8_lossy(bytes), invalid)
+}
+
+/// Decode the raw bytes of an input from `encoding`
+///
+/// A UTF-16 byte order mark is stripped. Fails with `InvalidData` if the
+/// bytes are not valid in the encoding.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> io::Result<String> {
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+    let (bytes, from_bytes): (_, fn([u8; 2]) -> u16) = match encoding {
+        Encoding::Utf8 => {
+            return String::from_utf8(bytes.to_vec()).map_err(|err| invalid(err.to_string()))
+        }
+        Encoding::Latin1 => return Ok(bytes.iter().map(|&byte| char::from(byte)).collect()),
+        Encoding::Utf16le => (
+            bytes.strip_prefix(&[0xff, 0xfe]).unwrap_or(bytes),
+            u16::from_le_bytes,
+        ),
+        Encoding::Utf16be => (
+            bytes.strip_prefix(&[0xfe, 0xff]).unwrap_or(bytes),
+            u16::from_be_bytes,
+        ),
+    };
+    if bytes.len() % 2 != 0 {
+        return Err(invalid(String::from(
+            "UTF-16 input has an odd number of bytes",
+        )));
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|err| invalid(err.to_string()))
+}
+
+/// An encoding guessed by `detect_encoding`, with how sure the guess is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectedEncoding {
+    pub encoding: Encoding,
+    /// From 0 for a blind guess to 1 for a certain one, like after a
+    /// byte order mark
+    pub confidence: f64,
+}
+
+/// Guess which of the supported encodings the bytes at the start of an input
+/// are in
+///
+/// This is a heuristic rather than a statistical detector: a byte order
+/// mark decides, UTF-16 is told by the null bytes of its ASCII chars and
+/// valid UTF-8, including plain ASCII, is taken as UTF-8. Anything else
+/// is taken as Latin-1, the surer the fewer of its bytes are C1 control
+/// chars, which are rare in text. Null bytes outside of UTF-16 lower the
+/// confidence, as they suggest a binary input. A sample cut off within a
+/// UTF-8 char is still taken as UTF-8 if it has other non-ASCII chars.
+///
+/// ```
+/// use word_count::{detect_encoding, Encoding};
+///
+/// let detected = detect_encoding(b"caf\xe9 na\xefve");
+/// assert_eq!(detected.encoding, Encoding::Latin1);
+/// assert_eq!(detected.confidence, 1.0);
+/// ```
+pub fn detect_encoding(sample: &[u8]) -> DetectedEncoding {
+    let detected = |encoding, confidence| DetectedEncoding {
+        encoding,
+        confidence,
+    };
+    if sample.starts_with(&[0xef, 0xbb, 0xbf]) {
+        return detected(Encoding::Utf8, 1.0);
+    }
+    if sample.starts_with(&[0xff, 0xfe]) {
+        return detected(Encoding::Utf16le, 1.0);
+    }
+    if sample.starts_with(&[0xfe, 0xff]) {
+        return detected(Encoding::Utf16be, 1.0);
+    }
+    if sample.is_empty() {
+        return detected(Encoding::Utf8, 1.0);
+    }
+
+    // the other byte of an ASCII char in UTF-16 is null
+    let pairs = (sample.len() / 2).max(1) as f64;
+    let nulls_at = |start| {
+        sample[start..]
+            .iter()
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count() as f64
+    };
+    let (even_nulls, odd_nulls) = (nulls_at(0), nulls_at(1));
+    if odd_nulls > pairs / 4.0 && odd_nulls > even_nulls * 4.0 {
+        return detected(Encoding::Utf16le, (odd_nulls - even_nulls) / pairs);
+    }
+    if even_nulls > pairs / 4.0 && even_nulls > odd_nulls * 4.0 {
+        return detected(Encoding::Utf16be, (even_nulls - odd_nulls) / pairs);
+    }
+
+    let text_like = 1.0 - (even_nulls + odd_nulls) / sample.len() as f64;
+    match std::str::from_utf8(sample) {
+        Ok(_) => detected(Encoding::Utf8, text_like),
+        // a sample cut off within a char, unless nothing before it is UTF-8
+        // only, like a single Latin-1 byte at the end
+        Err(err) if err.error_len().is_none() && !sample[..err.valid_up_to()].is_ascii() => {
+            detected(Encoding::Utf8, text_like)
+        }
+        Err(_) => {
+            let high = sample.iter().filter(|&&byte| byte >= 0x80).count() as f64;
+            let printable = sample.iter().filter(|&&byte| byte >= 0xa0).count() as f64;
+            detected(Encoding::Latin1, printable / high * text_like)
+        }
+    }
+}
+
+/// The byte order mark, size and UTF-8 validity of an input, see
+/// `encoding_report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodingReport {
+    /// The encoding of the byte order mark the input starts with, if any
+    pub bom: Option<Encoding>,
+    /// The size of the input including the byte order mark
+    pub bytes: usize,
+    /// The number of chars after the byte order mark that are valid UTF-8
+    pub valid_sequences: usize,
+    /// The number of byte sequences after the byte order mark that are not
+    /// valid UTF-8, each as long as `str::from_utf8` reports
+    pub invalid_sequences: usize,
+}
+
+/// Report the byte order mark of the raw bytes of an input, their number
+/// and how many of the sequences after the mark are valid UTF-8
+///
+/// Without a byte order mark UTF-8 is assumed. The UTF-8 sequences are
+/// counted even after a UTF-16 mark, where they say little.
+///
+/// ```
+/// use word_count::{encoding_report, Encoding};
+///
+/// let report = encoding_report(b"\xef\xbb\xbfcaf\xe9");
+/// assert_eq!(report.bom, Some(Encoding::Utf8));
+/// assert_eq!((report.valid_sequences, report.invalid_sequences), (3, 1));
+/// ```
+pub fn encoding_report(bytes: &[u8]) -> EncodingReport {
+    let boms: [(&[u8], _); 3] = [
+        (&[0xef, 0xbb, 0xbf], Encoding::Utf8),
+        (&[0xff, 0xfe], Encoding::Utf16le),
+        (&[0xfe, 0xff], Encoding::Utf16be),
+    ];
+    let (bom, mut rest) = boms
+        .iter()
+        .find_map(|(bom, encoding)| Some((Some(*encoding), bytes.strip_prefix(*bom)?)))
+        .unwrap_or((None, bytes));
+    let mut report = EncodingReport {
+        bom,
+        bytes: bytes.len(),
+        valid_sequences: 0,
+        invalid_sequences: 0,
+    };
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(text) => {
+                report.valid_sequences += text.chars().count();
+                return report;
+            }
+            Err(err) => {
+                let valid = std::str::from_utf8(&rest[..err.valid_up_to()])
+                    .expect("the bytes before the error are valid");
+                report.valid_sequences += valid.chars().count();
+                report.invalid_sequences += 1;
+                match err.error_len() {
+                    Some(len) => rest = &rest[err.valid_up_to() + len..],
+                    // cut off within a char at the end
+                    None => return report,
+                }
+            }
+        }
+    }
+}
+
+/// Start timing a part of a round for `ChunkObserver::round_timed`, `None`
+/// on WebAssembly, where `Instant::now` panics
+pub(crate) fn start_timer() -> Option<Instant> {
+    (!cfg!(target_arch = "wasm32")).then(Instant::now)
+}
+
+/// The time since `timer` was started, zero without a timer
+pub(crate) fn elapsed(timer: Option<Instant>) -> Duration {
+    timer.map_or(Duration::ZERO, |timer| timer.elapsed())
+}
+
+/// Count the words of a file by mapping it into memory
+///
+/// The lines are borrowed straight from the mapped bytes, so no `String`
+/// has to be allocated per line.
+///
+/// # Arguments
+///
+/// * `file` - The opened file to map
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+/// * `observer` - Gets notified after every round of chunks
+fn count_mmap(
+    file: &File,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    // SAFETY: the mapping is only sound as long as no other process truncates
+    // or modifies the file while it is mapped, which would be undefined
+    // behaviour for the `&[u8]` view. We assume input corpora are not written
+    // to while they are being counted, just like `BufReader` would otherwise
+    // read a torn file.
+    let mmap = unsafe { Mmap::map(file)? };
+    let (text, invalid_utf8) = if config.force_utf8 {
+        decode_utf8_lossy(&mmap)
+    } else {
+        let text = std::str::from_utf8(&mmap)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        (Cow::Borrowed(text), 0)
+    };
+    let lines = text.split_inclusive('\n').map(|line| {
+        let (line, ending) = split_line_ending(line);
+        Ok((Cow::Borrowed(line), ending))
+    });
+    let mut totals = count_line_iter(lines, config, word_counts, observer)?;
+    totals.invalid_utf8 += invalid_utf8;
+    Ok(totals)
+}
+
+/// Count the words of any buffered reader, adding them to `word_counts`
+///
+/// # Arguments
+///
+/// * `reader` - The reader to consume line by line
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+pub fn count_reader<R: BufRead>(
+    reader: R,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+) -> io::Result<Totals> {
+    count_reader_observed(reader, config, word_counts, &mut ())
+}
+
+/// Count the words of any buffered reader, notifying `observer` after every chunk
+///
+/// # Arguments
+///
+/// * `reader` - The reader to consume line by line
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+/// * `observer` - Gets notified after every round of chunks
+pub fn count_reader_observed<R: BufRead>(
+    reader: R,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let mut lines = TerminatedLines::new(reader, config.force_utf8);
+    let mut totals = count_line_iter(&mut lines, config, word_counts, observer)?;
+    totals.invalid_utf8 += lines.invalid_utf8;
+    Ok(totals)
+}
+
+/// Count the words of a sequence of lines in chunks, sleeping after each round
+///
+/// # Arguments
+///
+/// * `lines` - The lines to process along with the byte length of the line
+///   terminator that was stripped from each of them
+/// * `config` - The options controlling how each token is normalised
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+/// * `observer` - Gets notified after every round of chunks
+fn count_line_iter<S, I>(
+    mut lines: I,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals>
+where
+    S: AsRef<str> + From<String> + Sync,
+    I: Iterator<Item = io::Result<(S, usize)>>,
+{
+    let mut totals = Totals::default();
+    // in parallel mode every worker gets a chunk of its own per round
+    let chunks_per_round = if config.parallel {
+        rayon::current_num_threads()
+    } else {
+        1
+    };
+    // with `use_hashmap` the tokens are only merged into the sorted map once
+    // at the end, which costs one insertion per unique word instead of one
+    // per token
+    let mut hashed = hashed_counts(config);
+    // n-grams continue across line and chunk boundaries, but not across inputs
+    let mut window = VecDeque::new();
+    // once the counts outgrow `max_memory` this copy caps the unique words
+    let mut capped: Option<WordCountConfig> = None;
+    let mut seen = config.dedup_lines.map(SeenLines::new);
+    let mut sample = config
+        .sample_rate
+        .map(|rate| LineSample::new(rate, config.hash_seed));
+    let mut nth_lines = config.every_nth_line.map(NthLines::new);
+    // block comments continue across line and chunk boundaries
+    let mut in_comment = false;
+    // like the n-grams, `every_nth_word` counts on across chunks
+    let mut position = 0;
+
+    // lines counted by an earlier run are neither counted nor totalled again
+    for _ in 0..config.skip_lines {
+        match lines.next() {
+            Some(line) => drop(line?),
+            None => break,
+        }
+    }
+
+    // Process in larger chunks to allow for more idle time between processing
+    loop {
+        let config = capped.as_ref().unwrap_or(config);
+        let mut chunks = Vec::with_capacity(chunks_per_round);
+        let mut reading = Duration::ZERO;
+        let collecting = start_timer();
+        for _ in 0..chunks_per_round {
+            let read = start_timer();
+            let mut chunk = read_chunk(&mut lines, config, &mut totals)?;
+            reading += elapsed(read);
+            if chunk.is_empty() {
+                break;
+            }
+            strip_chunk_comments(&mut chunk, config, &mut in_comment);
+            retain_matching_lines(&mut chunk, config);
+            if let Some(seen) = &mut seen {
+                totals.duplicate_lines += seen.remove_seen(&mut chunk);
+            }
+            if let Some(sample) = &mut sample {
+                sample.retain_sampled(&mut chunk);
+            }
+            if let Some(nth_lines) = &mut nth_lines {
+                nth_lines.retain_nth(&mut chunk);
+            }
+            totals.lines += observe_lines(&mut chunk, config, observer);
+            chunks.push(chunk);
+        }
+        if chunks.is_empty() {
+            break;
+        }
+
+        for chunk in &chunks {
+            if config.sentences {
+                totals.sentences += chunk_sentences(chunk, config);
+            }
+        }
+        observer.round_read(reading, elapsed(collecting).saturating_sub(reading));
+
+        // high cpu activity: DVFS might increase cpu frequency
+        let counting = start_timer();
+        if config.parallel {
+            // the shared map cannot be updated by several workers at once, so
+            // each worker counts into a local map and the results are summed
+            // every worker starts with the tokens preceding its chunk
+            let windows: Vec<_> = chunks
+                .iter()
+                .map(|chunk| {
+                    let preceding = window.clone();
+                    advance_window(&mut window, chunk, config);
+                    preceding
+                })
+                .collect();
+            let counts = chunks
+                .par_iter()
+                .zip(windows)
+                .map(|(chunk, mut window)| {
+                    let mut local = hashed_counts(config);
+                    // `every_nth_word` is not allowed in parallel mode
+                    let (_, dropped) = count_chunk(chunk, config, &mut window, &mut 0, &mut local);
+                    (local, dropped)
+                })
+                .reduce(
+                    || (hashed_counts(config), 0),
+                    |(mut acc, mut dropped), (counts, more)| {
+                        for (word, count) in counts {
+                            if !acc.add(word, count, config.max_unique_words) {
+                                dropped += count;
+                            }
+                        }
+                        (acc, dropped + more)
+                    },
+                );
+            let (counts, dropped) = counts;
+            let counted = counts.values().sum::<usize>();
+            let merge_dropped = if config.use_hashmap {
+                merge_counts_capped(&mut hashed, counts, config.max_unique_words)
+            } else {
+                merge_counts_capped(word_counts, counts, config.max_unique_words)
+            };
+            totals.words += counted - merge_dropped;
+            totals.words_dropped += dropped + merge_dropped;
+        } else if config.use_hashmap {
+            for chunk in &chunks {
+                let (counted, dropped) =
+                    count_chunk(chunk, config, &mut window, &mut position, &mut hashed);
+                totals.words += counted;
+                totals.words_dropped += dropped;
+            }
+        } else {
+            for chunk in &chunks {
+                let (counted, dropped) =
+                    count_chunk(chunk, config, &mut window, &mut position, word_counts);
+                totals.words += counted;
+                totals.words_dropped += dropped;
+            }
+        }
+
+        let counting = elapsed(counting);
+        observer.chunk_done(&totals, word_counts)?;
+
+        // Potential place for a short sleep to allow cpu to enter a lower power state
+        let sleeping = start_timer();
+        if let Some(sleep) = config.sleep {
+            std::thread::sleep(sleep);
+        }
+        observer.round_timed(counting, elapsed(sleeping))?;
+        if config
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            totals.timed_out = true;
+            break;
+        }
+
+        if let Some(max) = config.max_memory {
+            let bytes = estimate_map_bytes(word_counts) + estimate_entries_bytes(hashed.keys());
+            if bytes > max {
+                let unique = if config.use_hashmap {
+                    hashed.len()
+                } else {
+                    word_counts.len()
+                };
+                capped = Some(WordCountConfig {
+                    max_unique_words: Some(
+                        config.max_unique_words.map_or(unique, |m| m.min(unique)),
+                    ),
+                    max_memory: None,
+                    ..config.clone()
+                });
+            }
+        }
+    }
+    let config = capped.as_ref().unwrap_or(config);
+
+    // the words of an earlier input may fill the counts first
+    let dropped = merge_counts_capped(word_counts, hashed, config.max_unique_words);
+    totals.words -= dropped;
+    totals.words_dropped += dropped;
+    Ok(totals)
+}
+
+/// Read up to `chunk_size` lines, returning an empty chunk at the end of the input
+///
+/// The chars and bytes of every line, including its terminator, are added
+/// to `totals` so they match what `wc` reports for the raw input. Lines
+/// outside the `line_range` are only added to `lines_read`, so a chunk is
+/// filled with lines within the range and ends early at the end of it.
+fn read_chunk<S, I>(
+    lines: &mut I,
+    config: &WordCountConfig,
+    totals: &mut Totals,
+) -> io::Result<Vec<S>>
+where
+    S: AsRef<str>,
+    I: Iterator<Item = io::Result<(S, usize)>>,
+{
+    let mut chunk = Vec::with_capacity(config.chunk_size);
+    while chunk.len() < config.chunk_size {
+        // `lines_read` only covers the lines after the skipped ones
+        let number = config.skip_lines + totals.lines_read + 1;
+        let range = config.line_range;
+        if range.is_some_and(|range| range.is_past(number)) {
+            break;
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let (line, ending) = line?;
+        if range.is_some_and(|range| !range.contains(number)) {
+            totals.lines_read += 1;
+            continue;
+        }
+        // line terminators are ASCII, so their byte and char lengths agree
+        totals.bytes += line.as_ref().len() + ending;
+        totals.chars += line.as_ref().chars().count() + ending;
+        if config.count_graphemes {
+            totals.graphemes += count_graphemes(line.as_ref()) + usize::from(ending > 0);
+        }
+        totals.lines_read += 1;
+        chunk.push(line);
+    }
+    Ok(chunk)
+}
+
+/// Strip the comments of `WordCountConfig::comment_language` from the lines
+/// of `chunk`, `in_block` telling whether a block comment is still open from
+/// the chunk before
+pub(crate) fn strip_chunk_comments<S: AsRef<str> + From<String>>(
+    chunk: &mut [S],
+    config: &WordCountConfig,
+    in_block: &mut bool,
+) {
+    let Some(language) = config.comment_language else {
+        return;
+    };
+    for line in chunk {
+        if let Cow::Owned(stripped) = strip_comments(line.as_ref(), language, in_block) {
+            *line = S::from(stripped);
+        }
+    }
+}
+
+/// Remove the lines of `chunk` that `WordCountConfig::line_regex` excludes
+pub(crate) fn retain_matching_lines<S: AsRef<str>>(chunk: &mut Vec<S>, config: &WordCountConfig) {
+    if let Some(regex) = &config.line_regex {
+        chunk.retain(|line| regex.is_match(line.as_ref()) != config.line_regex_invert);
+    }
+}
+
+/// Pass every line of `chunk` in order to `observer` and remove those that
+/// `WordCountConfig::skip_long_lines` skips, returning how many lines
+/// `count_lines_in_chunk` counts among all of them
+pub(crate) fn observe_lines<S: AsRef<str>>(
+    chunk: &mut Vec<S>,
+    config: &WordCountConfig,
+    observer: &mut dyn ChunkObserver,
+) -> usize {
+    let lines = count_lines_in_chunk(chunk);
+    chunk.retain(|line| {
+        let line = line.as_ref();
+        if config
+            .skip_long_lines
+            .is_some_and(|max| is_longer_than(line, max))
+        {
+            observer.line_skipped(line);
+            false
+        } else {
+            observer.line_read(line);
+            true
+        }
+    });
+    lines
+}
+
+/// The lines of a reader with the length of the terminator of each line
+///
+/// Unlike `BufRead::lines` this keeps track of how many bytes were stripped,
+/// so the raw size of the input can be reconstructed.
+struct TerminatedLines<R> {
+    reader: R,
+    /// Whether to replace invalid UTF-8 instead of failing on it
+    lossy: bool,
+    /// The number of invalid sequences replaced so far
+    invalid_utf8: usize,
+}
+
+impl<R> TerminatedLines<R> {
+    fn new(reader: R, lossy: bool) -> Self {
+        Self {
+            reader,
+            lossy,
+            invalid_utf8: 0,
+        }
+    }
+}
+
+impl<R: BufRead> TerminatedLines<R> {
+    /// Read the next line into `line` like `BufRead::read_line`
+    fn read_line(&mut self, line: &mut String) -> io::Result<usize> {
+        if !self.lossy {
+            return self.reader.read_line(line);
+        }
+        let mut bytes = Vec::new();
+        let read = self.reader.read_until(b'\n', &mut bytes)?;
+        let (text, invalid_utf8) = decode_utf8_lossy(&bytes);
+        line.push_str(&text);
+        self.invalid_utf8 += invalid_utf8;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> Iterator for TerminatedLines<R> {
+    type Item = io::Result<(String, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                let length = split_line_ending(&line).0.len();
+                let ending = line.len() - length;
+                line.truncate(length);
+                Some(Ok((line, ending)))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Split a `\n` or `\r\n` terminator off a line, returning the line and its length
+fn split_line_ending(line: &str) -> (&str, usize) {
+    if let Some(line) = line.strip_suffix("\r\n") {
+        (line, 2)
+    } else if let Some(line) = line.strip_suffix('\n') {
+        (line, 1)
+    } else {
+        (line, 0)
+    }
+}
+
+/// Add all counts of `other` to `word_counts`
+pub fn merge_counts(
+    word_counts: &mut BTreeMap<String, usize>,
+    other: impl IntoIterator<Item = (String, usize)>,
+) {
+    for (word, count) in other {
+        *word_counts.entry(word).or_insert(0) += count;
+    }
+}
+
+/// Scale the counts of a `WordCountConfig::sample_rate` sample of the lines
+/// up to an estimate of the counts of all lines, dividing them by `rate`
+/// and rounding to the nearest count
+///
+/// The estimate is unbiased, but the rarer a word the less exact: words
+/// left out of the sample are missing from it altogether.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::scale_counts;
+///
+/// let mut word_counts = BTreeMap::from([(String::from("the"), 3)]);
+/// scale_counts(&mut word_counts, 0.01);
+/// assert_eq!(word_counts["the"], 300);
+/// ```
+pub fn scale_counts(word_counts: &mut BTreeMap<String, usize>, rate: f64) {
+    for count in word_counts.values_mut() {
+        *count = (*count as f64 / rate).round() as usize;
+    }
+}
+
+/// Add all counts of `other` to `word_counts` like `merge_counts`, but skip
+/// the words not in `word_counts` yet once it holds `max_unique_words`
+///
+/// Returns the sum of the skipped counts.
+pub fn merge_counts_capped(
+    word_counts: &mut impl CountMap,
+    other: impl IntoIterator<Item = (String, usize)>,
+    max_unique_words: Option<usize>,
+) -> usize {
+    let mut dropped = 0;
+    for (word, count) in other {
+        if !word_counts.add(word, count, max_unique_words) {
+            dropped += count;
+        }
+    }
+    dropped
+}
+
+/// A map from words to their counts that the counts of a chunk can be added to
+pub trait CountMap {
+    /// Add `count` to the count of `word`, unless `word` is new and the map
+    /// holds `max_unique_words` words already; returns whether it was added
+    fn add(&mut self, word: String, count: usize, max_unique_words: Option<usize>) -> bool;
+}
+
+impl CountMap for BTreeMap<String, usize> {
+    fn add(&mut self, word: String, count: usize, max_unique_words: Option<usize>) -> bool {
+        if let Some(total) = self.get_mut(&word) {
+            *total += count;
+        } else if max_unique_words.is_some_and(|max| self.len() >= max) {
+            return false;
+        } else {
+            self.insert(word, count);
+        }
+        true
+    }
+}
+
+impl<H: BuildHasher> CountMap for HashMap<String, usize, H> {
+    fn add(&mut self, word: String, count: usize, max_unique_words: Option<usize>) -> bool {
+        if let Some(total) = self.get_mut(&word) {
+            *total += count;
+        } else if max_unique_words.is_some_and(|max| self.len() >= max) {
+            return false;
+        } else {
+            self.insert(word, count);
+        }
+        true
+    }
+}
+
+/// Collect the entries of `word_counts` in the given order
+///
+/// Entries with equal counts are always ordered alphabetically so the
+/// output is deterministic.
+pub fn sorted_entries(
+    word_counts: &BTreeMap<String, usize>,
+    order: SortOrder,
+) -> Vec<(&str, usize)> {
+    sorted_entries_seen(word_counts, order, None)
+}
+
+/// Collect the entries of `word_counts` in the given order, ordering
+/// `SortOrder::FirstSeen` by `first_lines`
+fn sorted_entries_seen<'a>(
+    word_counts: &'a BTreeMap<String, usize>,
+    order: SortOrder,
+    first_lines: Option<&BTreeMap<String, usize>>,
+) -> Vec<(&'a str, usize)> {
+    // the map already yields its entries alphabetically
+    let mut entries: Vec<(&str, usize)> = word_counts
+        .iter()
+        .map(|(word, &count)| (word.as_str(), count))
+        .collect();
+    if order != SortOrder::Word {
+        entries.sort_by(|a, b| compare_entries(order, first_lines, a, b));
+    }
+    entries
+}
+
+/// Compare two `(word, count)` entries in the given order, ordering ties
+/// alphabetically
+///
+/// `first_lines` maps the words to the lines they were first seen on for
+/// `SortOrder::FirstSeen`, as recorded by `FirstSeen`; words without a line
+/// come last.
+///
+/// ```
+/// use std::cmp::Ordering;
+///
+/// use word_count::{compare_entries, SortOrder};
+///
+/// let order = SortOrder::LengthDesc;
+/// assert_eq!(compare_entries(order, None, &("bb", 1), &("a", 5)), Ordering::Less);
+/// assert_eq!(compare_entries(order, None, &("b", 1), &("a", 5)), Ordering::Greater);
+/// ```
+pub fn compare_entries(
+    order: SortOrder,
+    first_lines: Option<&BTreeMap<String, usize>>,
+    a: &(&str, usize),
+    b: &(&str, usize),
+) -> Ordering {
+    let length = |entry: &(&str, usize)| entry.0.chars().count();
+    let first_line = |entry: &(&str, usize)| {
+        first_lines
+            .and_then(|first_lines| first_lines.get(entry.0))
+            .copied()
+            .unwrap_or(usize::MAX)
+    };
+    let ordering = match order {
+        SortOrder::Word => Ordering::Equal,
+        SortOrder::CountDesc => b.1.cmp(&a.1),
+        SortOrder::CountAsc => a.1.cmp(&b.1),
+        SortOrder::LengthDesc => length(b).cmp(&length(a)),
+        SortOrder::LengthAsc => length(a).cmp(&length(b)),
+        SortOrder::FirstSeen => first_line(a).cmp(&first_line(b)),
+    };
+    ordering.then_with(|| a.0.cmp(b.0))
+}
+
+/// Compare the word counts of two inputs
+///
+/// Words counted equally often in both inputs are left out.
+///
+/// # Arguments
+///
+/// * `left` - The word counts of the first input
+/// * `right` - The word counts of the second input
+pub fn diff_counts<'a>(
+    left: &'a BTreeMap<String, usize>,
+    right: &'a BTreeMap<String, usize>,
+) -> CountDiff<'a> {
+    let mut diff = CountDiff::default();
+    for (word, &count) in left {
+        match right.get(word) {
+            None => diff.only_left.push((word.as_str(), count)),
+            Some(&other) if other != count => diff
+                .changed
+                .push((word.as_str(), other as i64 - count as i64)),
+            Some(_) => {}
+        }
+    }
+    diff.only_right = right
+        .iter()
+        .filter(|(word, _)| !left.contains_key(*word))
+        .map(|(word, &count)| (word.as_str(), count))
+        .collect();
+
+    // the sort is stable, so ties keep their alphabetical order
+    diff.only_left.sort_by_key(|&(_, count)| Reverse(count));
+    diff.only_right.sort_by_key(|&(_, count)| Reverse(count));
+    diff.changed
+        .sort_by_key(|&(_, change)| Reverse(change.unsigned_abs()));
+    diff
+}
+
+/// Compare the word counts of an input against a reference frequency list
+///
+/// The entries are ordered alphabetically. The words of the reference that
+/// were not counted in the input are only included with `show_missing`.
+///
+/// # Arguments
+///
+/// * `word_counts` - The word counts of the input
+/// * `reference` - The counts of the reference list
+/// * `show_missing` - Whether to include the words missing from the input
+pub fn compare_to_reference<'a>(
+    word_counts: &'a BTreeMap<String, usize>,
+    reference: &'a BTreeMap<String, usize>,
+    show_missing: bool,
+) -> Vec<ReferenceEntry<'a>> {
+    let mut entries: Vec<_> = word_counts
+        .iter()
+        .map(|(word, &count)| ReferenceEntry {
+            word,
+            count: Some(count),
+            reference: reference.get(word).copied(),
+        })
+        .collect();
+    if show_missing {
+        entries.extend(
+            reference
+                .iter()
+                .filter(|(word, _)| !word_counts.contains_key(*word))
+                .map(|(word, &count)| ReferenceEntry {
+                    word,
+                    count: None,
+                    reference: Some(count),
+                }),
+        );
+        entries.sort_by_key(|entry| entry.word);
+    }
+    entries
+}
+
+/// Write the comparison of an input against a reference frequency list
+///
+/// In the text format a known word is printed as `word: 3 (reference: 5,
+/// delta: -2)`, a new word as `word: 3 [new]` and a missing one as `word:
+/// [missing] (reference: 5)`.
+pub fn write_reference_diff<W: Write>(
+    out: &mut W,
+    entries: &[ReferenceEntry],
+    format: ReferenceDiffFormat,
+) -> io::Result<()> {
+    match format {
+        ReferenceDiffFormat::Text => {
+            for entry in entries {
+                match (entry.count, entry.reference) {
+                    (Some(count), Some(reference)) => writeln!(
+                        out,
+                        "{}: {} (reference: {}, delta: {:+})",
+                        entry.word,
+                        count,
+                        reference,
+                        count as i64 - reference as i64
+                    )?,
+                    (Some(count), None) => writeln!(out, "{}: {} [new]", entry.word, count)?,
+                    (None, reference) => writeln!(
+                        out,
+                        "{}: [missing] (reference: {})",
+                        entry.word,
+                        reference.unwrap_or(0)
+                    )?,
+                }
+            }
+        }
+        ReferenceDiffFormat::Json => {
+            let values: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    let status = match (entry.count, entry.reference) {
+                        (Some(_), Some(_)) => "known",
+                        (Some(_), None) => "new",
+                        (None, _) => "missing",
+                    };
+                    serde_json::json!({
+                        "word": entry.word,
+                        "count": entry.count.unwrap_or(0),
+                        "reference": entry.reference,
+                        "delta": entry.delta(),
+                        "status": status,
+                    })
+                })
+                .collect();
+            serde_json::to_writer(&mut *out, &values)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Compare the frequencies of the words of an input against a baseline
+/// corpus
+///
+/// The frequency of a word is its count divided by the number of tokens of
+/// its corpus. With `laplace` every count is increased by one and every
+/// number of tokens by the size of the vocabulary of both corpora, so the
+/// words the baseline lacks get a ratio too.
+///
+/// The entries are ordered alphabetically, or by their ratio, largest first,
+/// with `sort_by_ratio`; words without a ratio come first then and ties are
+/// ordered alphabetically.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::relative_frequencies;
+///
+/// let target = BTreeMap::from([(String::from("cat"), 4), (String::from("dog"), 1)]);
+/// let baseline = BTreeMap::from([(String::from("cat"), 2), (String::from("eel"), 3)]);
+/// let entries = relative_frequencies(&target, &baseline, false, false);
+/// assert_eq!(entries[0].ratio, Some(2.0));
+/// assert!(entries[1].new);
+/// ```
+///
+/// # Arguments
+///
+/// * `word_counts` - The word counts of the input
+/// * `baseline` - The word counts of the baseline corpus
+/// * `laplace` - Whether to apply add-one smoothing
+/// * `sort_by_ratio` - Whether to order the entries by their ratio
+pub fn relative_frequencies<'a>(
+    word_counts: &'a BTreeMap<String, usize>,
+    baseline: &BTreeMap<String, usize>,
+    laplace: bool,
+    sort_by_ratio: bool,
+) -> Vec<RelativeEntry<'a>> {
+    let (smoothing, vocabulary) = if laplace {
+        let only_baseline = baseline
+            .keys()
+            .filter(|word| !word_counts.contains_key(*word))
+            .count();
+        (1, word_counts.len() + only_baseline)
+    } else {
+        (0, 0)
+    };
+    let total = (word_counts.values().sum::<usize>() + vocabulary) as f64;
+    let baseline_total = (baseline.values().sum::<usize>() + vocabulary) as f64;
+    let mut entries: Vec<_> = word_counts
+        .iter()
+        .map(|(word, &count)| {
+            let reference = baseline.get(word).copied().unwrap_or(0) + smoothing;
+            RelativeEntry {
+                word,
+                count,
+                ratio: (reference > 0).then(|| {
+                    (count + smoothing) as f64 / total / (reference as f64 / baseline_total)
+                }),
+                new: !baseline.contains_key(word),
+            }
+        })
+        .collect();
+    if sort_by_ratio {
+        // the sort is stable, so ties keep their alphabetical order
+        let ratio = |entry: &RelativeEntry| entry.ratio.unwrap_or(f64::INFINITY);
+        entries.sort_by(|a, b| ratio(b).total_cmp(&ratio(a)));
+    }
+    entries
+}
+
+/// Write the comparison of an input against a baseline corpus
+///
+/// A word is printed as `word: 4 (×2.00 vs baseline)`, or as `word: 1 [new]`
+/// if the baseline lacks it. A smoothed word the baseline lacks is printed
+/// with both, like `word: 1 (×3.00 vs baseline) [new]`.
+pub fn write_relative_frequencies<W: Write>(
+    out: &mut W,
+    entries: &[RelativeEntry],
+) -> io::Result<()> {
+    for entry in entries {
+        write!(out, "{}: {}", entry.word, entry.count)?;
+        if let Some(ratio) = entry.ratio {
+            write!(out, " (\u{d7}{:.2} vs baseline)", ratio)?;
+        }
+        if entry.new {
+            write!(out, " [new]")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// The Okapi BM25 parameter limiting how much a repeated word weighs
+const BM25_K1: f64 = 1.2;
+/// The Okapi BM25 parameter controlling the normalisation by document length
+const BM25_B: f64 = 0.75;
+
+/// Score every word of every document of a corpus by TF-IDF
+///
+/// The inverse document frequency of a word is `ln(N / df)` of the `N`
+/// documents and the `df` of them containing it, so a word of every
+/// document scores 0. The entries of a document are ordered by their score,
+/// highest first, with ties ordered alphabetically.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::{tfidf, TfidfVariant};
+///
+/// let documents = [
+///     BTreeMap::from([(String::from("cat"), 1), (String::from("the"), 1)]),
+///     BTreeMap::from([(String::from("the"), 2)]),
+/// ];
+/// let scores = tfidf(&documents, TfidfVariant::Raw);
+/// assert_eq!(scores[0][0].word, "cat");
+/// assert_eq!(scores[0][0].tfidf, 0.5 * 2f64.ln());
+/// assert_eq!(scores[1][0].tfidf, 0.0);
+/// ```
+pub fn tfidf(
+    documents: &[BTreeMap<String, usize>],
+    variant: TfidfVariant,
+) -> Vec<Vec<TfidfEntry<'_>>> {
+    let mut document_frequencies: HashMap<&str, usize> = HashMap::new();
+    for word_counts in documents {
+        for word in word_counts.keys() {
+            *document_frequencies.entry(word).or_insert(0) += 1;
+        }
+    }
+    let lengths: Vec<usize> = documents
+        .iter()
+        .map(|word_counts| word_counts.values().sum())
+        .collect();
+    let average_length = lengths.iter().sum::<usize>() as f64 / documents.len().max(1) as f64;
+    documents
+        .iter()
+        .zip(&lengths)
+        .map(|(word_counts, &length)| {
+            let mut entries: Vec<_> = word_counts
+                .iter()
+                .map(|(word, &count)| {
+                    let count = count as f64;
+                    let tf = match variant {
+                        TfidfVariant::Raw => count / length as f64,
+                        TfidfVariant::Sublinear => 1.0 + count.ln(),
+                        TfidfVariant::Bm25 => {
+                            let norm = 1.0 - BM25_B + BM25_B * length as f64 / average_length;
+                            count * (BM25_K1 + 1.0) / (count + BM25_K1 * norm)
+                        }
+                    };
+                    let df = document_frequencies[word.as_str()];
+                    let idf = (documents.len() as f64 / df as f64).ln();
+                    TfidfEntry {
+                        word,
+                        tf,
+                        idf,
+                        tfidf: tf * idf,
+                    }
+                })
+                .collect();
+            // the sort is stable, so ties keep their alphabetical order
+            entries.sort_by(|a, b| b.tfidf.total_cmp(&a.tfidf));
+            entries
+        })
+        .collect()
+}
+
+/// Write the TF-IDF scores of a document as a `word\ttf\tidf\ttfidf` header
+/// and a tab-separated row per word, the scores to six decimal places
+pub fn write_tfidf<W: Write>(out: &mut W, entries: &[TfidfEntry]) -> io::Result<()> {
+    writeln!(out, "word\ttf\tidf\ttfidf")?;
+    for entry in entries {
+        writeln!(
+            out,
+            "{}\t{:.6}\t{:.6}\t{:.6}",
+            entry.word, entry.tf, entry.idf, entry.tfidf
+        )?;
+    }
+    Ok(())
+}
+
+/// Check every assertion against the word counts, returning a message for
+/// every failed one in the order of `assertions`
+///
+/// A word that was not counted has a count of 0.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::{failed_assertions, CountAssertion};
+///
+/// let word_counts = BTreeMap::from([(String::from("todo"), 3)]);
+/// let assertions = [
+///     CountAssertion::AtMost(String::from("todo"), 2),
+///     CountAssertion::AtLeast(String::from("license"), 1),
+/// ];
+/// assert_eq!(
+///     failed_assertions(&word_counts, &assertions),
+///     [
+///         "\"todo\" is counted 3 times, at most 2 allowed",
+///         "\"license\" is counted 0 times, at least 1 required",
+///     ]
+/// );
+/// ```
+pub fn failed_assertions(
+    word_counts: &BTreeMap<String, usize>,
+    assertions: &[CountAssertion],
+) -> Vec<String> {
+    let count_of = |word: &str| word_counts.get(word).copied().unwrap_or(0);
+    assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            CountAssertion::AtMost(word, max) if count_of(word) > *max => Some(format!(
+                "{:?} is counted {} times, at most {} allowed",
+                word,
+                count_of(word),
+                max
+            )),
+            CountAssertion::AtLeast(word, min) if count_of(word) < *min => Some(format!(
+                "{:?} is counted {} times, at least {} required",
+                word,
+                count_of(word),
+                min
+            )),
+            CountAssertion::Absent(word) if count_of(word) > 0 => Some(format!(
+                "{:?} is counted {} times, but must not appear",
+                word,
+                count_of(word)
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Compare the vocabularies of two corpora
+///
+/// Every list is ordered by the counts in both corpora together, largest
+/// first, or by the log-likelihood if `log_likelihood` is set, with ties
+/// ordered alphabetically.
+///
+/// # Arguments
+///
+/// * `first` - The word counts of the first corpus
+/// * `second` - The word counts of the second corpus
+/// * `log_likelihood` - Whether to compute the G² of every word, see
+///   `log_likelihood`
+pub fn compare_corpora<'a>(
+    first: &'a BTreeMap<String, usize>,
+    second: &'a BTreeMap<String, usize>,
+    log_likelihood: bool,
+) -> CorpusComparison<'a> {
+    let first_total = first.values().sum();
+    let second_total = second.values().sum();
+    let entry = |word: &'a str, a: usize, b: usize| CorpusEntry {
+        word,
+        first: a,
+        second: b,
+        ratio: (a > 0 && b > 0).then(|| a as f64 / b as f64),
+        log_likelihood: log_likelihood
+            .then(|| self::log_likelihood(a, b, first_total, second_total)),
+    };
+    let mut comparison = CorpusComparison::default();
+    for (word, &count) in first {
+        match second.get(word) {
+            Some(&other) => comparison.both.push(entry(word, count, other)),
+            None => comparison.only_first.push(entry(word, count, 0)),
+        }
+    }
+    comparison.only_second = second
+        .iter()
+        .filter(|(word, _)| !first.contains_key(*word))
+        .map(|(word, &count)| entry(word, 0, count))
+        .collect();
+
+    // the sort is stable, so ties keep their alphabetical order
+    for entries in [
+        &mut comparison.both,
+        &mut comparison.only_first,
+        &mut comparison.only_second,
+    ] {
+        if log_likelihood {
+            let g2 = |entry: &CorpusEntry| entry.log_likelihood.unwrap_or(0.0);
+            entries.sort_by(|a, b| g2(b).total_cmp(&g2(a)));
+        } else {
+            entries.sort_by_key(|entry| Reverse(entry.first + entry.second));
+        }
+    }
+    comparison
+}
+
+/// The log-likelihood G² of a word counted `a` times in a corpus of
+/// `first_total` tokens and `b` times in one of `second_total` tokens
+///
+/// This is `2 * (a * ln(a / E1) + b * ln(b / E2))` with the counts `E1` and
+/// `E2` expected if the word were as frequent in both corpora. The larger
+/// it is, the more significantly over- or under-represented the word is in
+/// either corpus; a term of a count of 0 is 0.
+///
+/// ```
+/// use word_count::log_likelihood;
+///
+/// assert_eq!(log_likelihood(5, 5, 100, 100), 0.0);
+/// assert!(log_likelihood(20, 5, 100, 100) > 3.84);
+/// ```
+pub fn log_likelihood(a: usize, b: usize, first_total: usize, second_total: usize) -> f64 {
+    let total = (first_total + second_total) as f64;
+    if total == 0.0 {
+        return 0.0;
+    }
+    let observed = (a + b) as f64;
+    let term = |count: usize, corpus: usize| {
+        let expected = corpus as f64 * observed / total;
+        if count == 0 {
+            0.0
+        } else {
+            count as f64 * (count as f64 / expected).ln()
+        }
+    };
+    2.0 * (term(a, first_total) + term(b, second_total))
+}
+
+/// Write the comparison of two corpora named `first` and `second`
+///
+/// The text format prints three sections like `write_diff`, a word of both
+/// as `word: 3 / 1 (ratio 3.00)`, and follows every count with its `G²` if
+/// computed. The csv format prints a `word,first,second,ratio,log_likelihood`
+/// row per word of all three lists, the JSON format an object of the lists
+/// `both`, `only_first` and `only_second`. Other formats fail with
+/// `Unsupported`.
+pub fn write_corpus_comparison<W: Write>(
+    out: &mut W,
+    comparison: &CorpusComparison,
+    format: OutputFormat,
+    first: &str,
+    second: &str,
+) -> io::Result<()> {
+    let sections = [
+        (String::from("in both"), &comparison.both),
+        (format!("only in {}", first), &comparison.only_first),
+        (format!("only in {}", second), &comparison.only_second),
+    ];
+    match format {
+        OutputFormat::Text => {
+            for (title, entries) in sections {
+                writeln!(out, "=== {} ===", title)?;
+                for entry in entries {
+                    let mut notes = Vec::new();
+                    if let Some(ratio) = entry.ratio {
+                        notes.push(format!("ratio {:.2}", ratio));
+                    }
+                    if let Some(g2) = entry.log_likelihood {
+                        notes.push(format!("G\u{b2} {:.2}", g2));
+                    }
+                    let counts = match (entry.first, entry.second) {
+                        (count, 0) | (0, count) => count.to_string(),
+                        (a, b) => format!("{} / {}", a, b),
+                    };
+                    if notes.is_empty() {
+                        writeln!(out, "{}: {}", entry.word, counts)?;
+                    } else {
+                        writeln!(out, "{}: {} ({})", entry.word, counts, notes.join(", "))?;
+                    }
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let value = serde_json::json!({
+                "both": comparison.both,
+                "only_first": comparison.only_first,
+                "only_second": comparison.only_second,
+            });
+            serde_json::to_writer(&mut *out, &value)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut *out);
+            for (_, entries) in sections {
+                for entry in entries {
+                    writer.serialize(entry)?;
+                }
+            }
+            writer.flush()?;
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only the text, json and csv formats can print a comparison",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Write the three sections of a diff between the inputs `left` and `right`
+pub fn write_diff<W: Write>(
+    out: &mut W,
+    diff: &CountDiff,
+    left: &str,
+    right: &str,
+) -> io::Result<()> {
+    writeln!(out, "=== only in {} ===", left)?;
+    for (word, count) in &diff.only_left {
+        writeln!(out, "{}: {}", word, count)?;
+    }
+    writeln!(out, "=== only in {} ===", right)?;
+    for (word, count) in &diff.only_right {
+        writeln!(out, "{}: {}", word, count)?;
+    }
+    writeln!(out, "=== changed ===")?;
+    for (word, change) in &diff.changed {
+        writeln!(out, "{}: {:+}", word, change)?;
+    }
+    Ok(())
+}
+
+/// Write the number of tokens of every paragraph as a `paragraph N: count`
+/// line, numbered from 1
+pub fn write_paragraphs<W: Write>(out: &mut W, paragraphs: &[usize]) -> io::Result<()> {
+    for (index, words) in paragraphs.iter().enumerate() {
+        writeln!(out, "paragraph {}: {}", index + 1, words)?;
+    }
+    Ok(())
+}
+
+/// Write the counts of every window as a section titled like `Window 1
+/// (lines 1-100)`, numbered from 1, see `write_section`
+pub fn write_windows<W: Write>(
+    out: &mut W,
+    windows: &[Window],
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    for (index, window) in windows.iter().enumerate() {
+        let title = format!(
+            "Window {} (lines {}-{})",
+            index + 1,
+            window.first_line,
+            window.last_line
+        );
+        write_section(out, &title, &window.word_counts, output_config)?;
+    }
+    Ok(())
+}
+
+/// Write the word counts as a unigram language model in the ARPA format
+/// read by tools like KenLM or Kaldi
+///
+/// Every word gets the probability `(count + smoothing) / (total +
+/// smoothing * words)`, add-k smoothing with k = `smoothing`, as its
+/// `log10`. `<unk>` and the sentence end `</s>` both get the probability
+/// of `unk_log_prob`, likewise a `log10`, which the words give up in
+/// proportion; the sentence start `<s>` is never predicted and gets -99.
+///
+/// # Arguments
+///
+/// * `out` - The destination of the model
+/// * `word_counts` - The counts to estimate the probabilities from
+/// * `smoothing` - The count added to every word, 0 for none
+/// * `unk_log_prob` - The `log10` probability of `<unk>` and `</s>`, below
+///   `log10(0.5)` so the words keep some probability
+pub fn write_arpa<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    smoothing: f64,
+    unk_log_prob: f64,
+) -> io::Result<()> {
+    let total = word_counts.values().sum::<usize>() as f64 + smoothing * word_counts.len() as f64;
+    // the words share what `<unk>` and `</s>` leave
+    let mass = 1.0 - 2.0 * 10f64.powf(unk_log_prob);
+    writeln!(out, "\\data\\")?;
+    writeln!(out, "ngram 1={}", word_counts.len() + 3)?;
+    writeln!(out)?;
+    writeln!(out, "\\1-grams:")?;
+    writeln!(out, "{:.6}\t<s>", -99.0)?;
+    writeln!(out, "{:.6}\t</s>", unk_log_prob)?;
+    writeln!(out, "{:.6}\t<unk>", unk_log_prob)?;
+    for (word, &count) in word_counts {
+        let probability = (count as f64 + smoothing) / total * mass;
+        writeln!(out, "{:.6}\t{}", probability.log10(), word)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "\\end\\")
+}
+
+/// How `write_word_cloud` scales the counts to the weights of the words
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CloudScale {
+    /// The weights grow with the counts
+    Linear,
+    /// The weights grow with the logarithm of the counts, which keeps a few
+    /// very frequent words from dwarfing all others
+    Log,
+}
+
+/// A word of a word cloud with its weight, see `write_word_cloud`
+#[derive(Debug, Serialize)]
+struct CloudWord<'a> {
+    text: &'a str,
+    weight: u32,
+}
+
+/// Write every word of `word_counts` on a line of its own, without its
+/// count, in the given order
+///
+/// `first_lines` orders `SortOrder::FirstSeen` like for `compare_entries`.
+pub fn write_vocabulary<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    order: SortOrder,
+    first_lines: Option<&BTreeMap<String, usize>>,
+) -> io::Result<()> {
+    for (word, _) in sorted_entries_seen(word_counts, order, first_lines) {
+        writeln!(out, "{}", word)?;
+    }
+    Ok(())
+}
+
+/// Write the `top` most frequent words as the JSON array of `{"text": ...,
+/// "weight": ...}` objects that word cloud libraries like d3-cloud take
+///
+/// The weights go from 1 for the least to 100 for the most frequent of the
+/// words, rounded to whole numbers and scaled as set by `scale`; if all of
+/// them are counted the same, they all weigh 100. Ties are broken
+/// alphabetically.
+pub fn write_word_cloud<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    top: usize,
+    scale: CloudScale,
+) -> io::Result<()> {
+    let mut entries: Vec<(&str, usize)> = word_counts
+        .iter()
+        .map(|(word, &count)| (word.as_str(), count))
+        .collect();
+    // the sort is stable, so ties stay in alphabetical order
+    entries.sort_by_key(|&(_, count)| Reverse(count));
+    entries.truncate(top);
+    let scaled = |count: usize| match scale {
+        CloudScale::Linear => count as f64,
+        CloudScale::Log => (count as f64).ln(),
+    };
+    let max = entries.first().map_or(0.0, |&(_, count)| scaled(count));
+    let min = entries.last().map_or(0.0, |&(_, count)| scaled(count));
+    let words: Vec<CloudWord> = entries
+        .into_iter()
+        .map(|(text, count)| {
+            let weight = if max > min {
+                1.0 + 99.0 * (scaled(count) - min) / (max - min)
+            } else {
+                100.0
+            };
+            CloudWord {
+                text,
+                weight: weight.round() as u32,
+            }
+        })
+        .collect();
+    serde_json::to_writer(&mut *out, &words)?;
+    writeln!(out)
+}
+
+/// Write every pair of `pairs` as a `word1\tword2\tcount` line, in the order
+/// of the pairs
+pub fn write_cooccurrences<W: Write>(
+    out: &mut W,
+    pairs: &BTreeMap<(String, String), usize>,
+) -> io::Result<()> {
+    for ((first, second), count) in pairs {
+        writeln!(
+            out,
+            "{}\t{}\t{}",
+            escape_tsv(first),
+            escape_tsv(second),
+            count
+        )?;
+    }
+    Ok(())
+}
+
+/// Map every count in `word_counts` to its dense frequency rank
+///
+/// The most frequent words have rank 1, words with equal counts share a rank
+/// and the next lower count gets the next rank, so no ranks are skipped.
+pub fn dense_ranks(word_counts: &BTreeMap<String, usize>) -> HashMap<usize, usize> {
+    let mut counts: Vec<usize> = word_counts.values().copied().collect();
+    counts.sort_unstable_by_key(|&count| Reverse(count));
+    counts.dedup();
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(index, count)| (count, index + 1))
+        .collect()
+}
+
+/// The placeholders of an output template, see `fill_template`
+pub const TEMPLATE_PLACEHOLDERS: [&str; 5] = ["word", "count", "rank", "freq_pmw", "length"];
+
+/// Fill the placeholders of `template` with the values of `entry`: `{word}`,
+/// `{count}`, its dense `{rank}`, its `{freq_pmw}` frequency per million
+/// words and the `{length}` of the word in chars
+///
+/// The placeholders are replaced one after the other, the word last so that
+/// braces in it are kept as they are. Other placeholders are kept as well,
+/// see `unknown_placeholders`.
+fn fill_template(template: &str, entry: &WordEntry, freq_pmw: f64) -> String {
+    let rank = entry.rank.map(|rank| rank.to_string()).unwrap_or_default();
+    template
+        .replace("{count}", &entry.count.to_string())
+        .replace("{rank}", &rank)
+        .replace("{freq_pmw}", &format!("{:.2}", freq_pmw))
+        .replace("{length}", &entry.word.chars().count().to_string())
+        .replace("{word}", entry.word)
+}
+
+/// The names of the placeholders of `template` that are none of the
+/// `TEMPLATE_PLACEHOLDERS`, in order, e.g. `["wrod"]` for `{wrod}: {count}`
+///
+/// ```
+/// use word_count::unknown_placeholders;
+///
+/// assert_eq!(unknown_placeholders("{rank}. {wrod} ({count})"), ["wrod"]);
+/// ```
+pub fn unknown_placeholders(template: &str) -> Vec<&str> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let name = &rest[..end];
+        // a nested brace starts the next placeholder instead
+        if name.contains('{') {
+            continue;
+        }
+        if !TEMPLATE_PLACEHOLDERS.contains(&name) {
+            unknown.push(name);
+        }
+        rest = &rest[end + 1..];
+    }
+    unknown
+}
+
+/// Pad `word` with spaces to exactly `width` terminal columns, or cut it
+/// down to them ending with `marker` if it is wider
+///
+/// The columns are measured with `display_width`, so a wide CJK char or
+/// emoji takes up two. A wide char that does not fit in the last column is
+/// left out for a space. A `marker` wider than `width` is cut down itself.
+///
+/// ```
+/// use word_count::fit_width;
+///
+/// assert_eq!(fit_width("über", 6, "…"), "über  ");
+/// assert_eq!(fit_width("straße", 4, "…"), "str…");
+/// assert_eq!(fit_width("日本語", 4, "…"), "日… ");
+/// ```
+pub fn fit_width(word: &str, width: usize, marker: &str) -> String {
+    let mut fitted = if display_width(word) <= width {
+        word.to_string()
+    } else {
+        let marker = take_columns(marker, width);
+        let kept = take_columns(word, width - display_width(marker));
+        format!("{}{}", kept, marker)
+    };
+    let padding = width - display_width(&fitted);
+    fitted.extend(std::iter::repeat_n(' ', padding));
+    fitted
+}
+
+/// Format a text entry, the word in cyan and the count in yellow if `color`
+/// is set, the count in bold as well if `bold` is set
+///
+/// Without `count` only the word is formatted.
+pub fn format_entry(word: &str, count: Option<&str>, color: bool, bold: bool) -> String {
+    let word = paint_word(word, color);
+    match count {
+        Some(count) => format!("{}: {}", word, paint_count(count, color, bold)),
+        None => word,
+    }
+}
+
+/// The word column of a text entry, cyan if `color` is set
+fn paint_word(word: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[36m{}\x1b[0m", word)
+    } else {
+        word.to_string()
+    }
+}
+
+/// The count column of a text entry, yellow if `color` is set and bold as well
+/// if `bold` is set
+fn paint_count(count: &str, color: bool, bold: bool) -> String {
+    match (color, bold) {
+        (false, _) => count.to_string(),
+        (true, false) => format!("\x1b[33m{}\x1b[0m", count),
+        (true, true) => format!("\x1b[1;33m{}\x1b[0m", count),
+    }
+}
+
+/// Collect the entries to print, sorted and limited as configured
+pub fn select_entries<'a>(
+    word_counts: &'a BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+) -> Vec<(&'a str, usize)> {
+    let first_seen = output_config
+        .first_positions
+        .as_ref()
+        .or(output_config.first_lines.as_ref());
+    let mut entries = sorted_entries_seen(word_counts, output_config.sort_order, first_seen);
+    entries.retain(|&(_, count)| {
+        output_config.keeps_count(count) && output_config.legomena.is_none_or(|n| count == n)
+    });
+    if let Some(top) = output_config.top {
+        // a limit above the number of unique words simply keeps them all
+        entries.truncate(top);
+    }
+    if let Some((pct, total)) = output_config.top_percent {
+        let needed = pct * total as f64 / 100.0;
+        let mut covered = 0;
+        let end = entries
+            .iter()
+            .position(|&(_, count)| {
+                covered += count;
+                covered as f64 >= needed
+            })
+            .map_or(entries.len(), |index| index + 1);
+        entries.truncate(end);
+    }
+    if let Some(bottom) = output_config.bottom {
+        // the sort is stable, so ties stay in alphabetical order
+        entries.sort_by_key(|&(_, count)| count);
+        let mut end = bottom.min(entries.len());
+        if output_config.bottom_ties && end > 0 {
+            while entries
+                .get(end)
+                .is_some_and(|&(_, count)| count == entries[end - 1].1)
+            {
+                end += 1;
+            }
+        }
+        entries.truncate(end);
+    }
+    entries
+}
+
+/// Write the selected entries in the configured format
+pub fn write_counts<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let selected = select_entries(word_counts, output_config);
+    write_selected_counts(out, word_counts, selected, output_config)
+}
+
+/// Write the entries `selected` from `word_counts` by `select_entries` in the
+/// configured format, for timing the selection apart from the writing
+pub fn write_selected_counts<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    selected: Vec<(&str, usize)>,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    // ranks always follow the counts, whatever order the entries are printed in
+    let ranks = (output_config.show_rank
+        || output_config.format == OutputFormat::Xml
+        || output_config.format == OutputFormat::Text && output_config.template.is_some())
+    .then(|| dense_ranks(word_counts));
+    // the coverage only adds up the printed entries, in their printed order
+    let mut covered = 0;
+    let entries: Vec<WordEntry> = selected
+        .into_iter()
+        .map(|(word, count)| {
+            covered += count;
+            WordEntry {
+                rank: ranks.as_ref().map(|ranks| ranks[&count]),
+                freq_pmw: output_config.freq_pmw(count),
+                log_count: output_config.log_count(count),
+                cumulative_pct: output_config.cumulative_pct(covered),
+                first_line: output_config
+                    .first_lines
+                    .as_ref()
+                    .and_then(|first_lines| first_lines.get(word).copied()),
+                ..WordEntry::plain(word, count)
+            }
+        })
+        .collect();
+    match output_config.format {
+        OutputFormat::Text if output_config.template.is_some() => {
+            let template = output_config.template.as_deref().unwrap_or_default();
+            // the frequencies are relative to all counted words by default
+            let total = output_config
+                .normalize_by
+                .unwrap_or_else(|| word_counts.values().sum());
+            let frequencies = OutputConfig {
+                normalize_by: Some(total),
+                ..Default::default()
+            };
+            for entry in &entries {
+                let freq_pmw = frequencies.freq_pmw(entry.count).unwrap_or_default();
+                writeln!(out, "{}", fill_template(template, entry, freq_pmw))?;
+            }
+        }
+        OutputFormat::Text if output_config.null_separated => {
+            write_null_separated(out, &entries, output_config)?
+        }
+        OutputFormat::Text => write_text(out, &entries, output_config)?,
+        OutputFormat::Json
+            if output_config.show_rank
+                || output_config.normalize_by.is_some()
+                || output_config.log_scale.is_some()
+                || output_config.cumulative_of.is_some()
+                || output_config.first_lines.is_some() =>
+        {
+            // a single number per word has no room for the extra columns
+            serde_json::to_writer(&mut *out, &entries)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Json => {
+            // serialise straight from the entries so the object keeps their order
+            let mut serializer = serde_json::Serializer::new(&mut *out);
+            serializer.collect_map(entries.iter().map(|entry| (entry.word, entry.count)))?;
+            writeln!(out)?;
+        }
+        OutputFormat::JsonArray => {
+            serde_json::to_writer(&mut *out, &entries)?;
+            writeln!(out)?;
+        }
+        OutputFormat::JsonLines => {
+            for entry in entries {
+                serde_json::to_writer(&mut *out, &entry)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Html => write_html(out, entries)?,
+        OutputFormat::Ndjson => {
+            // the entries themselves were streamed while counting, so the
+            // summary covers all counts whatever entries are selected
+            let summary = serde_json::json!({
+                "_summary": {
+                    "total_words": word_counts.values().sum::<usize>(),
+                    "unique_words": word_counts.len(),
+                }
+            });
+            serde_json::to_writer(&mut *out, &summary)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Tsv => write_tsv(out, &entries, output_config)?,
+        OutputFormat::Xml => write_xml(out, &entries, word_counts, output_config)?,
+        OutputFormat::Csv => {
+            // the csv writer takes care of quoting words with commas or quotes
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(!output_config.no_header)
+                .from_writer(&mut *out);
+            for entry in entries {
+                writer.serialize(entry)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the weights of `PositionWeights` in place of the counts, rounded to
+/// four decimal places in the text format
+///
+/// The `sort_order` and `top` apply as to the counts, the count orders
+/// ordering by weight. Only the text, json, csv and tsv formats are
+/// supported; the others fail with `Unsupported`.
+pub fn write_weighted_counts<W: Write>(
+    out: &mut W,
+    weights: &BTreeMap<String, f64>,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let mut entries: Vec<(&str, f64)> = weights
+        .iter()
+        .map(|(word, &weight)| (word.as_str(), weight))
+        .collect();
+    // the sorts are stable, so ties stay in alphabetical order
+    match output_config.sort_order {
+        SortOrder::CountDesc => entries.sort_by(|a, b| b.1.total_cmp(&a.1)),
+        SortOrder::CountAsc => entries.sort_by(|a, b| a.1.total_cmp(&b.1)),
+        SortOrder::LengthDesc => entries.sort_by_key(|&(word, _)| Reverse(word.chars().count())),
+        SortOrder::LengthAsc => entries.sort_by_key(|&(word, _)| word.chars().count()),
+        SortOrder::Word | SortOrder::FirstSeen => {}
+    }
+    if let Some(top) = output_config.top {
+        entries.truncate(top);
+    }
+    match output_config.format {
+        OutputFormat::Text => {
+            for (word, weight) in entries {
+                writeln!(out, "{}: {:.4}", word, weight)?;
+            }
+        }
+        OutputFormat::Json => {
+            let mut serializer = serde_json::Serializer::new(&mut *out);
+            serializer.collect_map(entries)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(&mut *out);
+            if !output_config.no_header {
+                writer.write_record(["word", "weight"])?;
+            }
+            for entry in entries {
+                writer.serialize(entry)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Tsv => {
+            if output_config.tsv_header {
+                writeln!(out, "word\tweight")?;
+            }
+            for (word, weight) in entries {
+                writeln!(out, "{}\t{}", escape_tsv(word), weight)?;
+            }
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only the text, json, csv and tsv formats can print weights",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Write every entry as its word and count, each followed by a NUL, like the
+/// paths of `find -print0`
+///
+/// The NUL terminates every field instead of separating two of them, so the
+/// last one does not make consumers like `xargs -0` see an empty record.
+/// Words of the legomena are written without their count.
+fn write_null_separated<W: Write>(
+    out: &mut W,
+    entries: &[WordEntry],
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    for entry in entries {
+        write!(out, "{}\0", entry.word)?;
+        if output_config.legomena.is_none() {
+            write!(out, "{}\0", entry.count)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the entries as tab-separated rows, with the optional columns
+/// around the word and count like in the csv format
+///
+/// Nothing is quoted; tabs, line breaks and backslashes in words are escaped
+/// as `\t`, `\n`, `\r` and `\\` instead.
+fn write_tsv<W: Write>(
+    out: &mut W,
+    entries: &[WordEntry],
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let columns = [
+        ("rank", output_config.show_rank),
+        ("word", true),
+        ("count", true),
+        ("freq_pmw", output_config.normalize_by.is_some()),
+        ("log_count", output_config.log_scale.is_some()),
+        ("cumulative_pct", output_config.cumulative_of.is_some()),
+        ("first_line", output_config.first_lines.is_some()),
+    ];
+    if output_config.tsv_header {
+        let names: Vec<&str> = columns
+            .iter()
+            .filter(|(_, shown)| *shown)
+            .map(|(name, _)| *name)
+            .collect();
+        writeln!(out, "{}", names.join("\t"))?;
+    }
+    for entry in entries {
+        let mut cells = Vec::with_capacity(columns.len());
+        cells.extend(entry.rank.map(|rank| rank.to_string()));
+        cells.push(escape_tsv(&output_config.fit_word(entry.word)));
+        cells.push(output_config.fit_count(entry.count.to_string()));
+        cells.extend(entry.freq_pmw.map(|freq_pmw| freq_pmw.to_string()));
+        cells.extend(entry.log_count.map(|log| log.to_string()));
+        cells.extend(entry.cumulative_pct.map(|pct| pct.to_string()));
+        cells.extend(entry.first_line.map(|line| line.to_string()));
+        writeln!(out, "{}", cells.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// Write the entries as an XML document, a `<word_counts>` root with the
+/// `total_words` and `unique_words` of all counts and a `<word>` element per
+/// entry in the printed order
+///
+/// Every `<word>` has the `value`, `count` and dense `rank` of its entry,
+/// followed by the `freq_pmw`, `log_count`, `cumulative_pct` and
+/// `first_line` attributes
+/// when they are printed. The words are escaped by `escape_xml`.
+fn write_xml<W: Write>(
+    out: &mut W,
+    entries: &[WordEntry],
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let (indent, newline) = if output_config.xml_indent {
+        ("  ", "\n")
+    } else {
+        ("", "")
+    };
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    write!(
+        out,
+        "<word_counts total_words=\"{}\" unique_words=\"{}\">{}",
+        word_counts.values().sum::<usize>(),
+        word_counts.len(),
+        newline
+    )?;
+    for entry in entries {
+        write!(
+            out,
+            "{}<word value=\"{}\" count=\"{}\"",
+            indent,
+            escape_xml(entry.word),
+            entry.count
+        )?;
+        if let Some(rank) = entry.rank {
+            write!(out, " rank=\"{}\"", rank)?;
+        }
+        if let Some(freq_pmw) = entry.freq_pmw {
+            write!(out, " freq_pmw=\"{}\"", freq_pmw)?;
+        }
+        if let Some(log) = entry.log_count {
+            write!(out, " log_count=\"{}\"", log)?;
+        }
+        if let Some(pct) = entry.cumulative_pct {
+            write!(out, " cumulative_pct=\"{}\"", pct)?;
+        }
+        if let Some(line) = entry.first_line {
+            write!(out, " first_line=\"{}\"", line)?;
+        }
+        write!(out, "/>{}", newline)?;
+    }
+    writeln!(out, "</word_counts>")
+}
+
+/// Escape `text` for an XML attribute value in double quotes
+///
+/// Besides the five predefined entities, tabs and line breaks become char
+/// references so parsers do not normalise them to spaces. The other
+/// control chars cannot appear in XML 1.0 at all and become U+FFFD.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            c if c < ' ' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape the chars of a tsv cell that would break its row or column
+fn escape_tsv(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write the `HTML_TOP` most frequent entries as a word cloud page
+fn write_html<W: Write>(out: &mut W, mut entries: Vec<WordEntry>) -> io::Result<()> {
+    // the sort is stable, so ties keep their configured order
+    entries.sort_by_key(|entry| Reverse(entry.count));
+    entries.truncate(HTML_TOP);
+    // `<` only occurs inside JSON strings, where the escape keeps a word like
+    // "</script>" from ending the script
+    let data = serde_json::to_string(&entries)?.replace('<', "\\u003c");
+    out.write_all(HTML_TEMPLATE.replace("{WORD_DATA_JSON}", &data).as_bytes())
+}
+
+/// Write the entries in the text format, as `word: count` lines or, with
+/// `table` set, as aligned columns
+fn write_text<W: Write>(
+    out: &mut W,
+    entries: &[WordEntry],
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let color = output_config.color;
+    let mut reached = false;
+    let rows: Vec<TextRow> = entries
+        .iter()
+        .map(|entry| TextRow {
+            first_line: entry.first_line,
+            cumulative: entry.cumulative_pct.map(|pct| format!("{:.2}%", pct)),
+            reaches_threshold: match (entry.cumulative_pct, output_config.coverage_threshold) {
+                (Some(pct), Some(threshold)) if !reached && pct >= threshold => {
+                    reached = true;
+                    true
+                }
+                _ => false,
+            },
+            rank: entry.rank.map(|rank| rank.to_string()),
+            word: output_config.fit_word(entry.word),
+            count: match entry.freq_pmw {
+                // the count is the same for every word, so it is left out
+                _ if output_config.legomena.is_some() => None,
+                Some(freq_pmw) => Some(format!("{:.2}", freq_pmw)),
+                None => match entry.log_count {
+                    Some(log) => Some(format!("{:.4}", log)),
+                    None => Some(entry.count.to_string()),
+                },
+            }
+            .map(|count| output_config.fit_count(count)),
+        })
+        .collect();
+
+    // the columns are as wide as their longest cell, the header included
+    let width = |header: &str, cell: fn(&TextRow) -> usize| {
+        let header = if output_config.table_header {
+            header.len()
+        } else {
+            0
+        };
+        rows.iter().map(cell).max().unwrap_or(0).max(header)
+    };
+    let rank_width = width("RANK", |row| row.rank.as_deref().map_or(0, str::len));
+    let word_width = width("WORD", |row| display_width(&row.word));
+    let count_width = width("COUNT", |row| row.count.as_deref().map_or(0, str::len));
+    let cumulative_width = width("CUM%", |row| row.cumulative.as_deref().map_or(0, str::len));
+    let show_rank = output_config.show_rank;
+    let show_count = output_config.legomena.is_none();
+
+    if output_config.table && output_config.table_header {
+        let mut header = Vec::new();
+        let mut separator = Vec::new();
+        if show_rank {
+            header.push(format!("{:>rank_width$}", "RANK"));
+            separator.push("-".repeat(rank_width));
+        }
+        header.push(format!("{:<word_width$}", "WORD"));
+        separator.push("-".repeat(word_width));
+        if show_count {
+            header.push(format!("{:>count_width$}", "COUNT"));
+            separator.push("-".repeat(count_width));
+        }
+        if output_config.cumulative_of.is_some() {
+            header.push(format!("{:>cumulative_width$}", "CUM%"));
+            separator.push("-".repeat(cumulative_width));
+        }
+        writeln!(out, "{}", header.join("  ").trim_end())?;
+        writeln!(out, "{}", separator.join("  "))?;
+    }
+
+    // the most frequent words only stand out when they come first
+    let bold_top = if output_config.sort_order == SortOrder::CountDesc {
+        BOLD_TOP
+    } else {
+        0
+    };
+    let marker = output_config
+        .coverage_threshold
+        .map(|threshold| format!("  <- {}% coverage", threshold))
+        .unwrap_or_default();
+    for (index, row) in rows.iter().enumerate() {
+        let bold = index < bold_top;
+        let word = &*row.word;
+        let marker = if row.reaches_threshold { &marker } else { "" };
+        let first_seen = row
+            .first_line
+            .map(|line| format!(" (first seen: line {})", line))
+            .unwrap_or_default();
+        if !output_config.table {
+            if let Some(rank) = &row.rank {
+                write!(out, "{} ", rank)?;
+            }
+            write!(
+                out,
+                "{}",
+                format_entry(word, row.count.as_deref(), color, bold)
+            )?;
+            if let Some(cumulative) = &row.cumulative {
+                write!(out, " {}", cumulative)?;
+            }
+            writeln!(out, "{}{}", first_seen, marker)?;
+            continue;
+        }
+        let mut cells = Vec::new();
+        if let Some(rank) = &row.rank {
+            cells.push(format!("{:>rank_width$}", rank));
+        }
+        match &row.count {
+            Some(count) => {
+                let padding = " ".repeat(word_width - display_width(word));
+                cells.push(paint_word(&format!("{}{}", word, padding), color));
+                cells.push(paint_count(
+                    &format!("{:>count_width$}", count),
+                    color,
+                    bold,
+                ));
+            }
+            // nothing follows the word, so it needs no padding
+            None => cells.push(paint_word(word, color)),
+        }
+        if let Some(cumulative) = &row.cumulative {
+            cells.push(format!("{:>cumulative_width$}", cumulative));
+        }
+        writeln!(out, "{}{}{}", cells.join("  "), first_seen, marker)?;
+    }
+    Ok(())
+}
+
+/// Write the selected entries as a single line JSON snapshot and flush it
+///
+/// The snapshot is an object with the `timestamp_ms` since the Unix epoch and
+/// the `counts` as an array of `{"word": ..., "count": ...}` objects.
+pub fn write_snapshot<W: Write>(
+    out: &mut W,
+    timestamp: Duration,
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let snapshot = Snapshot {
+        timestamp_ms: timestamp.as_millis() as u64,
+        counts: select_entries(word_counts, output_config)
+            .into_iter()
+            .map(|(word, count)| WordEntry::plain(word, count))
+            .collect(),
+    };
+    serde_json::to_writer(&mut *out, &snapshot)?;
+    writeln!(out)?;
+    // a consumer watching the stream should see every snapshot right away
+    out.flush()
+}
+
+/// Write a count table preceded by a `=== title ===` header line
+pub fn write_section<W: Write>(
+    out: &mut W,
+    title: &str,
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    writeln!(out, "=== {} ===", title)?;
+    write_counts(out, word_counts, output_config)
+}
+
+/// Estimate the bytes of memory taken up by `word_counts`
+///
+/// The bytes of all words are added to the count and the
+/// `BTREEMAP_NODE_OVERHEAD` of every entry, so this is an approximation.
+pub fn estimate_map_bytes(word_counts: &BTreeMap<String, usize>) -> usize {
+    estimate_entries_bytes(word_counts.keys())
+}
+
+/// Estimate the bytes of the entries of a map of `words` to their counts, see
+/// `estimate_map_bytes`
+fn estimate_entries_bytes<'a>(words: impl ExactSizeIterator<Item = &'a String>) -> usize {
+    let entries = words.len();
+    let bytes: usize = words.map(String::len).sum();
+    bytes + entries * (mem::size_of::<usize>() + BTREEMAP_NODE_OVERHEAD)
+}
+
+/// Collect the words counted exactly `n` times in alphabetical order
+///
+/// With `n` = 1 these are the hapax legomena, with `n` = 2 the dis legomena.
+pub fn legomena(word_counts: &BTreeMap<String, usize>, n: usize) -> Vec<&str> {
+    word_counts
+        .iter()
+        .filter(|&(_, &count)| count == n)
+        .map(|(word, _)| word.as_str())
+        .collect()
+}
+
+/// The mean, median and mode of the lengths of all counted tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WordLengths {
+    /// The mean number of chars per token
+    pub mean: f64,
+    /// The median number of chars, the mean of the two middle tokens for an
+    /// even number of tokens
+    pub median: f64,
+    /// The most frequent number of chars, the shortest one on a tie
+    pub mode: usize,
+}
+
+/// Compute the lengths of every occurrence of the words of `word_counts`,
+/// all zero without any words
+///
+/// A word counted `n` times is weighed `n` times, the lengths being grouped
+/// into a map from a number of chars to its tokens, which stays small.
+pub fn word_lengths(word_counts: &BTreeMap<String, usize>) -> WordLengths {
+    let lengths = word_length_distribution(word_counts);
+    let tokens: usize = lengths.values().sum();
+    if tokens == 0 {
+        return WordLengths {
+            mean: 0.0,
+            median: 0.0,
+            mode: 0,
+        };
+    }
+    let chars: usize = lengths.iter().map(|(length, count)| length * count).sum();
+    let median = histogram_median(&lengths, tokens);
+    let mode = lengths
+        .iter()
+        .max_by_key(|&(&length, &count)| (count, Reverse(length)))
+        .map(|(&length, _)| length)
+        .unwrap_or_default();
+    WordLengths {
+        mean: chars as f64 / tokens as f64,
+        median,
+        mode,
+    }
+}
+
+/// Count the occurrences of the words of every length in chars, for every
+/// length from 1 to the longest word, empty without any words
+///
+/// Like for `word_lengths`, a word counted `n` times is `n` occurrences.
+pub fn word_length_distribution(word_counts: &BTreeMap<String, usize>) -> BTreeMap<usize, usize> {
+    let mut lengths: BTreeMap<usize, usize> = BTreeMap::new();
+    for (word, &count) in word_counts {
+        *lengths.entry(word.chars().count()).or_insert(0) += count;
+    }
+    if let Some(&max) = lengths.keys().next_back() {
+        for length in 1..max {
+            lengths.entry(length).or_insert(0);
+        }
+    }
+    lengths
+}
+
+/// Write a `word_length_distribution` as `length N: K occurrences (P.PP%)`
+/// lines, or with `json` as a `{"N": K, ...}` object
+pub fn write_word_length_distribution<W: Write>(
+    out: &mut W,
+    lengths: &BTreeMap<usize, usize>,
+    json: bool,
+) -> io::Result<()> {
+    if json {
+        serde_json::to_writer(&mut *out, lengths)?;
+        return writeln!(out);
+    }
+    let tokens: usize = lengths.values().sum();
+    for (length, &count) in lengths {
+        let share = count as f64 * 100.0 / tokens as f64;
+        writeln!(
+            out,
+            "length {}: {} occurrences ({:.2}%)",
+            length, count, share
+        )?;
+    }
+    Ok(())
+}
+
+/// The Shannon entropy of a word distribution and the measures derived from
+/// it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entropy {
+    /// `H = -Σ p(w) log2 p(w)`, in bits, `p(w)` being the share of the tokens
+    /// that are the word `w`
+    pub bits: f64,
+    /// `H / log2(V)` for a vocabulary of `V` words, from 0 to 1 for an even
+    /// distribution
+    pub normalized: f64,
+    /// `2^H`, the number of equally frequent words with the same entropy
+    pub perplexity: f64,
+}
+
+/// Compute the entropy of the distribution of the tokens over the words of
+/// `word_counts`, all zero without any words
+///
+/// Like the type-token ratio, the entropy grows with the size of the
+/// vocabulary, so only texts of a similar length compare well. The
+/// normalised entropy is 0 for a vocabulary of one word.
+pub fn entropy(word_counts: &BTreeMap<String, usize>) -> Entropy {
+    let tokens: usize = word_counts.values().sum();
+    if tokens == 0 {
+        return Entropy {
+            bits: 0.0,
+            normalized: 0.0,
+            perplexity: 0.0,
+        };
+    }
+    let bits: f64 = word_counts
+        .values()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / tokens as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum();
+    let max_bits = (word_counts.len() as f64).log2();
+    Entropy {
+        bits,
+        normalized: if max_bits > 0.0 { bits / max_bits } else { 0.0 },
+        perplexity: bits.exp2(),
+    }
+}
+
+/// The median of the values of a histogram mapping every value to how often
+/// it occurs, the mean of the two middle ones for an even number of values
+///
+/// `total` is the number of values, which must not be 0.
+fn histogram_median(histogram: &BTreeMap<usize, usize>, total: usize) -> f64 {
+    // the values at these 0-based positions, in sorted order
+    let nth = |position: usize| {
+        let mut seen = 0;
+        for (&value, &count) in histogram {
+            seen += count;
+            if seen > position {
+                return value;
+            }
+        }
+        unreachable!("the position is below the number of values")
+    };
+    (nth((total - 1) / 2) + nth(total / 2)) as f64 / 2.0
+}
+
+/// The minimum, maximum, mean and median number of tokens per line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineWords {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    /// The mean of the two middle lines for an even number of lines
+    pub median: f64,
+}
+
+/// Compute the statistics of a histogram mapping a number of tokens to the
+/// lines with that many, as collected by `WordsPerLine`; all zero without
+/// any lines
+pub fn line_words(histogram: &BTreeMap<usize, usize>) -> LineWords {
+    let lines: usize = histogram.values().sum();
+    if lines == 0 {
+        return LineWords {
+            min: 0,
+            max: 0,
+            mean: 0.0,
+            median: 0.0,
+        };
+    }
+    let words: usize = histogram.iter().map(|(words, count)| words * count).sum();
+    LineWords {
+        min: *histogram.keys().next().expect("there are lines"),
+        max: *histogram.keys().next_back().expect("there are lines"),
+        mean: words as f64 / lines as f64,
+        median: histogram_median(histogram, lines),
+    }
+}
+
+/// The words of a range of counts, see `frequency_bands`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrequencyBand {
+    /// The lowest count of the band
+    pub low: usize,
+    /// The highest count of the band, `None` for the last one
+    pub high: Option<usize>,
+    /// The number of words counted within the band
+    pub words: usize,
+}
+
+/// Group the words of `word_counts` into bands of counts up to each of the
+/// increasing `bounds`, followed by a band of all higher counts
+///
+/// Every band is returned, even without any words, so the bands of two
+/// inputs line up.
+pub fn frequency_bands(
+    word_counts: &BTreeMap<String, usize>,
+    bounds: &[usize],
+) -> Vec<FrequencyBand> {
+    let mut bands: Vec<FrequencyBand> = Vec::with_capacity(bounds.len() + 1);
+    let mut low = 1;
+    for &high in bounds {
+        bands.push(FrequencyBand {
+            low,
+            high: Some(high),
+            words: 0,
+        });
+        low = high + 1;
+    }
+    bands.push(FrequencyBand {
+        low,
+        high: None,
+        words: 0,
+    });
+    for &count in word_counts.values() {
+        // the first band reaching the count holds it
+        let index = bounds.partition_point(|&high| high < count);
+        bands[index].words += 1;
+    }
+    bands
+}
+
+/// Write every band as a `[low-high]: N words` line, `[low]` for a band of
+/// a single count and `[low+]` for the last one
+pub fn write_frequency_bands<W: Write>(out: &mut W, bands: &[FrequencyBand]) -> io::Result<()> {
+    for band in bands {
+        let range = match band.high {
+            Some(high) if high == band.low => format!("{}", high),
+            Some(high) => format!("{}-{}", band.low, high),
+            None => format!("{}+", band.low),
+        };
+        let noun = if band.words == 1 { "word" } else { "words" };
+        writeln!(out, "[{}]: {} {}", range, band.words, noun)?;
+    }
+    Ok(())
+}
+
+/// Group the words of `word_counts` by their lowercase form, mapping every
+/// form to the words written like it in any case and their counts
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::case_variants;
+///
+/// let word_counts = BTreeMap::from([(String::from("The"), 2), (String::from("the"), 5)]);
+/// let variants = case_variants(&word_counts);
+/// assert_eq!(variants["the"].len(), 2);
+/// ```
+pub fn case_variants(
+    word_counts: &BTreeMap<String, usize>,
+) -> BTreeMap<String, BTreeMap<String, usize>> {
+    let mut variants: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for (word, &count) in word_counts {
+        variants
+            .entry(word.to_lowercase())
+            .or_default()
+            .insert(word.clone(), count);
+    }
+    variants
+}
+
+/// Write every lowercase form of `case_variants` as a `the: the=4231
+/// The=512 THE=3` line, its most frequent variant first
+pub fn write_case_variants<W: Write>(
+    out: &mut W,
+    variants: &BTreeMap<String, BTreeMap<String, usize>>,
+) -> io::Result<()> {
+    for (lowercase, words) in variants {
+        write!(out, "{}:", lowercase)?;
+        for (word, count) in sorted_entries(words, SortOrder::CountDesc) {
+            write!(out, " {}={}", word, count)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// The words of `word_counts` sharing a first char or a script, see
+/// `group_by_first_char` and `group_by_script`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordGroup {
+    /// The first char or the name of the script
+    pub key: String,
+    /// The number of distinct words of the group
+    pub words: usize,
+    /// The counts of the words of the group added up
+    pub occurrences: usize,
+}
+
+/// Group the words of `word_counts` by their first char, the most frequent
+/// group first
+///
+/// Groups with the same number of occurrences are ordered by their char.
+pub fn group_by_first_char(word_counts: &BTreeMap<String, usize>) -> Vec<WordGroup> {
+    group_words(word_counts, |word| {
+        word.chars().next().map_or_else(String::new, String::from)
+    })
+}
+
+/// Group the words of `word_counts` by their script, like `Latin` or
+/// `Cyrillic`, the most frequent group first
+///
+/// The script of a word is that of its first letter, told by the Unicode
+/// block it is in, for the most common scripts only: letters of the others
+/// are `Other`. Words without any letter, like numbers, are `Common`.
+pub fn group_by_script(word_counts: &BTreeMap<String, usize>) -> Vec<WordGroup> {
+    group_words(word_counts, |word| {
+        word.chars()
+            .find(|c| c.is_alphabetic())
+            .map_or("Common", char_script)
+            .to_string()
+    })
+}
+
+/// Group the words of `word_counts` by `key`, ordered like the groups of
+/// `group_by_first_char`
+fn group_words(
+    word_counts: &BTreeMap<String, usize>,
+    key: impl Fn(&str) -> String,
+) -> Vec<WordGroup> {
+    let mut groups: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for (word, &count) in word_counts {
+        let group = groups.entry(key(word)).or_default();
+        group.0 += 1;
+        group.1 += count;
+    }
+    let mut groups: Vec<WordGroup> = groups
+        .into_iter()
+        .map(|(key, (words, occurrences))| WordGroup {
+            key,
+            words,
+            occurrences,
+        })
+        .collect();
+    // the sort is stable, so ties stay in the order of their keys
+    groups.sort_by_key(|group| Reverse(group.occurrences));
+    groups
+}
+
+/// The script of the letter `c`, by the Unicode blocks of the most common
+/// scripts
+fn char_script(c: char) -> &'static str {
+    const BLOCKS: [(u32, u32, &str); 30] = [
+        (0x0041, 0x024F, "Latin"),
+        (0x0250, 0x02AF, "Latin"),
+        (0x0370, 0x03FF, "Greek"),
+        (0x0400, 0x052F, "Cyrillic"),
+        (0x0530, 0x058F, "Armenian"),
+        (0x0590, 0x05FF, "Hebrew"),
+        (0x0600, 0x06FF, "Arabic"),
+        (0x0750, 0x077F, "Arabic"),
+        (0x08A0, 0x08FF, "Arabic"),
+        (0x0900, 0x097F, "Devanagari"),
+        (0x0980, 0x09FF, "Bengali"),
+        (0x0E00, 0x0E7F, "Thai"),
+        (0x10A0, 0x10FF, "Georgian"),
+        (0x1100, 0x11FF, "Hangul"),
+        (0x1C80, 0x1C8F, "Cyrillic"),
+        (0x1E00, 0x1EFF, "Latin"),
+        (0x1F00, 0x1FFF, "Greek"),
+        (0x2C60, 0x2C7F, "Latin"),
+        (0x2DE0, 0x2DFF, "Cyrillic"),
+        (0x3040, 0x309F, "Hiragana"),
+        (0x30A0, 0x30FF, "Katakana"),
+        (0x3130, 0x318F, "Hangul"),
+        (0x3400, 0x4DBF, "Han"),
+        (0x4E00, 0x9FFF, "Han"),
+        (0xA640, 0xA69F, "Cyrillic"),
+        (0xA720, 0xA7FF, "Latin"),
+        (0xAC00, 0xD7AF, "Hangul"),
+        (0xF900, 0xFAFF, "Han"),
+        (0xFB50, 0xFEFF, "Arabic"),
+        (0x20000, 0x2FFFF, "Han"),
+    ];
+    let c = u32::from(c);
+    BLOCKS
+        .iter()
+        .find(|&&(first, last, _)| (first..=last).contains(&c))
+        .map_or("Other", |&(_, _, script)| script)
+}
+
+/// Write every group as a `key: N words, N total occurrences` line, with
+/// the code point after the key if `code_points` is set, like `a (U+0061)`
+pub fn write_word_groups<W: Write>(
+    out: &mut W,
+    groups: &[WordGroup],
+    code_points: bool,
+) -> io::Result<()> {
+    for group in groups {
+        let noun = if group.words == 1 { "word" } else { "words" };
+        write!(out, "{}", group.key)?;
+        if code_points {
+            if let Some(c) = group.key.chars().next() {
+                write!(out, " (U+{:04X})", u32::from(c))?;
+            }
+        }
+        writeln!(
+            out,
+            ": {} {}, {} total occurrences",
+            group.words, noun, group.occurrences
+        )?;
+    }
+    Ok(())
+}
+
+/// A word with its length in chars and its count, see `longest_words`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordLength<'a> {
+    pub word: &'a str,
+    /// The number of Unicode scalar values of the word
+    pub length: usize,
+    pub count: usize,
+}
+
+/// The `n` longest words of `word_counts`, the longest first and words of
+/// the same length in alphabetical order
+///
+/// Only the `n` longest words seen so far are kept while going through the
+/// words, so the whole vocabulary is never sorted.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::longest_words;
+///
+/// let word_counts = BTreeMap::from([
+///     (String::from("a"), 4),
+///     (String::from("naïve"), 1),
+///     (String::from("tree"), 2),
+/// ]);
+/// let longest = longest_words(&word_counts, 2);
+/// assert_eq!((longest[0].word, longest[0].length), ("naïve", 5));
+/// assert_eq!(longest[1].word, "tree");
+/// ```
+pub fn longest_words(word_counts: &BTreeMap<String, usize>, n: usize) -> Vec<WordLength<'_>> {
+    extreme_words(word_counts, n, |length| length)
+}
+
+/// The `n` shortest words of `word_counts`, the shortest first and words of
+/// the same length in alphabetical order, see `longest_words`
+pub fn shortest_words(word_counts: &BTreeMap<String, usize>, n: usize) -> Vec<WordLength<'_>> {
+    extreme_words(word_counts, n, Reverse)
+}
+
+/// The `n` words with the greatest `key` of their length, in the order of
+/// `longest_words`
+fn extreme_words<K: Ord>(
+    word_counts: &BTreeMap<String, usize>,
+    n: usize,
+    key: impl Fn(usize) -> K,
+) -> Vec<WordLength<'_>> {
+    // a min-heap of the best words so far, the worst of them on top
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    for (word, &count) in word_counts {
+        let length = word.chars().count();
+        heap.push(Reverse((
+            key(length),
+            Reverse(word.as_str()),
+            length,
+            count,
+        )));
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((_, Reverse(word), length, count))| WordLength {
+            word,
+            length,
+            count,
+        })
+        .collect()
+}
+
+/// The `n` most frequent words of every length in chars within `lengths`,
+/// by length
+///
+/// Words counted equally often are ordered alphabetically. A length without
+/// any word has no entry.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use word_count::top_per_length;
+///
+/// let word_counts = BTreeMap::from([
+///     (String::from("and"), 3),
+///     (String::from("the"), 5),
+///     (String::from("word"), 1),
+/// ]);
+/// let top = top_per_length(&word_counts, 1, 1..=3);
+/// assert_eq!(top, BTreeMap::from([(3, vec![("the", 5)])]));
+/// ```
+pub fn top_per_length(
+    word_counts: &BTreeMap<String, usize>,
+    n: usize,
+    lengths: RangeInclusive<usize>,
+) -> BTreeMap<usize, Vec<(&str, usize)>> {
+    let mut groups: BTreeMap<usize, Vec<(&str, usize)>> = BTreeMap::new();
+    for (word, &count) in word_counts {
+        let length = word.chars().count();
+        if lengths.contains(&length) {
+            groups.entry(length).or_default().push((word, count));
+        }
+    }
+    for words in groups.values_mut() {
+        // the sort is stable, so ties keep their alphabetical order
+        words.sort_by_key(|&(_, count)| Reverse(count));
+        words.truncate(n);
+    }
+    groups
+}
+
+/// Write every group of `top_per_length` as a `length 3: [the: 5, and: 3]`
+/// line
+pub fn write_top_per_length<W: Write>(
+    out: &mut W,
+    groups: &BTreeMap<usize, Vec<(&str, usize)>>,
+) -> io::Result<()> {
+    for (length, words) in groups {
+        let words: Vec<_> = words
+            .iter()
+            .map(|(word, count)| format!("{}: {}", word, count))
+            .collect();
+        writeln!(out, "length {}: [{}]", length, words.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Write the number of distinct contexts of `word` next to its occurrences,
+/// as recorded by `ContextDiversity`, e.g.
+/// `cat: 3 unique contexts in 4 occurrences (0.75)`
+pub fn write_context_diversity<W: Write>(
+    out: &mut W,
+    word: &str,
+    contexts: &WordContexts,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}: {} unique contexts in {} occurrences ({:.2})",
+        word,
+        contexts.contexts.len(),
+        contexts.occurrences,
+        contexts.diversity()
+    )
+}
+
+/// Write a concordance sorted by its left context, the nearest field first
+/// like in a standard KWIC sort, one `left  keyword  right` line each
+///
+/// The left contexts are aligned to the right so the keywords line up. Ties
+/// keep the order of the input. The width of a context is its number of
+/// chars, which is off for wide chars like CJK.
+pub fn write_concordance<W: Write>(out: &mut W, lines: &[KwicLine]) -> io::Result<()> {
+    let mut lines: Vec<&KwicLine> = lines.iter().collect();
+    lines.sort_by(|a, b| a.left.iter().rev().cmp(b.left.iter().rev()));
+    let join = |fields: &[String]| -> String {
+        let fields: Vec<&str> = fields
+            .iter()
+            .map(String::as_str)
+            .filter(|field| !field.is_empty())
+            .collect();
+        fields.join(" ")
+    };
+    let lefts: Vec<String> = lines.iter().map(|line| join(&line.left)).collect();
+    let width = lefts
+        .iter()
+        .map(|left| left.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (line, left) in lines.iter().zip(&lefts) {
+        let padding = width - left.chars().count();
+        let right = join(&line.right);
+        let text = format!(
+            "{}{}  {}  {}",
+            " ".repeat(padding),
+            left,
+            line.keyword,
+            right
+        );
+        writeln!(out, "{}", text.trim_end())?;
+    }
+    Ok(())
+}
+
+/// The parameters of Heaps' law `V = K * N^β`, relating the number of unique
+/// words `V` to the number of words `N`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapsFit {
+    pub k: f64,
+    pub beta: f64,
+}
+
+/// Fit Heaps' law to the `(words, unique_words)` points of a
+/// `VocabularyGrowth` by least squares on `log V = log K + β log N`
+///
+/// Points without any words are left out. `None` unless at least two points
+/// with different numbers of words are left.
+///
+/// ```
+/// use word_count::heaps_fit;
+///
+/// let fit = heaps_fit(&[(100, 20), (400, 40), (1600, 80)]).unwrap();
+/// assert!((fit.k - 2.0).abs() < 1e-9);
+/// assert!((fit.beta - 0.5).abs() < 1e-9);
+/// ```
+pub fn heaps_fit(points: &[(usize, usize)]) -> Option<HeapsFit> {
+    let logs: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|&&(words, unique)| words > 0 && unique > 0)
+        .map(|&(words, unique)| ((words as f64).ln(), (unique as f64).ln()))
+        .collect();
+    let n = logs.len() as f64;
+    let mean_x = logs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = logs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = logs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = logs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    // also without any points, as the means are NaN then
+    if sxx.is_nan() || sxx == 0.0 {
+        return None;
+    }
+    let beta = sxy / sxx;
+    Some(HeapsFit {
+        k: (mean_y - beta * mean_x).exp(),
+        beta,
+    })
+}
+
+/// Write the `(words, unique_words)` points of a `VocabularyGrowth` as
+/// tab-separated rows
+pub fn write_vocabulary_growth<W: Write>(out: &mut W, points: &[(usize, usize)]) -> io::Result<()> {
+    for (words, unique) in points {
+        writeln!(out, "{}\t{}", words, unique)?;
+    }
+    Ok(())
+}
+
+/// Write the words under a `=== title ===` header as `word: N chars (count:
+/// N)` lines
+pub fn write_word_lengths<W: Write>(
+    out: &mut W,
+    title: &str,
+    words: &[WordLength],
+) -> io::Result<()> {
+    writeln!(out, "=== {} ===", title)?;
+    for word in words {
+        writeln!(
+            out,
+            "{}: {} chars (count: {})",
+            word.word, word.length, word.count
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the entries as the bars of a histogram, one `word  |███| count` line
+/// each
+///
+/// The words are padded to the widest one and the bars scaled so that the
+/// longest one fills what is left of `width` columns, at least 1. The width of
+/// a word is its `display_width`, so wide chars like CJK take up two columns.
+///
+/// # Arguments
+///
+/// * `out` - The destination of the histogram
+/// * `entries` - The words and counts to show, in order
+/// * `width` - The number of columns a line may take up
+/// * `bar` - The char the bars are drawn with
+pub fn write_histogram<W: Write>(
+    out: &mut W,
+    entries: &[(&str, usize)],
+    width: usize,
+    bar: char,
+) -> io::Result<()> {
+    let word_width = entries
+        .iter()
+        .map(|(word, _)| display_width(word))
+        .max()
+        .unwrap_or(0);
+    let count_width = entries
+        .iter()
+        .map(|(_, count)| count.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let max = entries.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    // two spaces, the two `|` and the space before the count
+    let available = width.saturating_sub(word_width + count_width + 5).max(1);
+    for &(word, count) in entries {
+        let length = if max == 0 {
+            0
+        } else {
+            (count as f64 * available as f64 / max as f64).round() as usize
+        };
+        let padding = word_width - display_width(word);
+        let bar: String = std::iter::repeat_n(bar, length).collect();
+        writeln!(out, "{}{}  |{}| {}", word, " ".repeat(padding), bar, count)?;
+    }
+    Ok(())
+}
+
+/// Write a `total words: N  unique words: M` summary line
+///
+/// The line ends with the type-token ratio, the unique words divided by the
+/// total words, where higher values indicate a greater lexical variety, and
+/// the moving average type-token ratio if `mattr` is given.
+///
+/// When a count filter is active the number of unique words left after
+/// filtering is appended, so the number of filtered words can be seen. When
+/// only legomena are printed their number is appended along with their share
+/// of the unique words, e.g. the hapax ratio.
+pub fn write_stats<W: Write>(
+    out: &mut W,
+    totals: &Totals,
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+    mattr: Option<f64>,
+) -> io::Result<()> {
+    write!(
+        out,
+        "total words: {}  unique words: {}",
+        totals.words,
+        word_counts.len()
+    )?;
+    if output_config.min_count.is_some()
+        || output_config.max_count.is_some()
+        || output_config.exactly.is_some()
+    {
+        let kept = word_counts
+            .values()
+            .filter(|&&count| output_config.keeps_count(count))
+            .count();
+        write!(out, "  unique words after filtering: {}", kept)?;
+    }
+    if let Some(n) = output_config.legomena {
+        let legomena = legomena(word_counts, n).len();
+        let ratio = if word_counts.is_empty() {
+            0.0
+        } else {
+            legomena as f64 / word_counts.len() as f64
+        };
+        let name = match n {
+            1 => String::from("hapax legomena"),
+            2 => String::from("dis legomena"),
+            n => format!("words counted {} times", n),
+        };
+        write!(out, "  {}: {} (ratio {:.4})", name, legomena, ratio)?;
+    }
+    let ttr = if totals.words == 0 {
+        0.0
+    } else {
+        word_counts.len() as f64 / totals.words as f64
+    };
+    write!(out, "  TTR: {:.4}", ttr)?;
+    if let Some(mattr) = mattr {
+        write!(out, "  MATTR: {:.4}", mattr)?;
+    }
+    if output_config.words_per_sentence {
+        let average = if totals.sentences == 0 {
+            0.0
+        } else {
+            totals.words as f64 / totals.sentences as f64
+        };
+        write!(out, "  avg words/sentence: {:.2}", average)?;
+    }
+    if output_config.word_lengths {
+        let lengths = word_lengths(word_counts);
+        write!(
+            out,
+            "  avg word length: {:.2}  median: {}  mode: {}",
+            lengths.mean, lengths.median, lengths.mode
+        )?;
+    }
+    if let Some(histogram) = &output_config.words_per_line {
+        let stats = line_words(histogram);
+        write!(
+            out,
+            "  words/line min: {}  max: {}  mean: {:.2}  median: {}",
+            stats.min, stats.max, stats.mean, stats.median
+        )?;
+    }
+    if let Some(lines) = &output_config.new_words_per_line {
+        write!(
+            out,
+            "  new words/line mean: {:.2}  median: {}",
+            lines.mean(),
+            lines.median()
+        )?;
+    }
+    if let Some(paragraphs) = &output_config.paragraphs {
+        let average = if paragraphs.is_empty() {
+            0.0
+        } else {
+            paragraphs.iter().sum::<usize>() as f64 / paragraphs.len() as f64
+        };
+        write!(
+            out,
+            "  paragraphs: {}  avg words/paragraph: {:.2}  longest paragraph: {}",
+            paragraphs.len(),
+            average,
+            paragraphs.iter().max().unwrap_or(&0)
+        )?;
+    }
+    if output_config.entropy || output_config.perplexity {
+        let entropy = entropy(word_counts);
+        if output_config.entropy {
+            write!(
+                out,
+                "  entropy: {:.4} bits  normalized entropy: {:.4}",
+                entropy.bits, entropy.normalized
+            )?;
+        }
+        if output_config.perplexity {
+            write!(out, "  perplexity: {:.2}", entropy.perplexity)?;
+        }
+    }
+    writeln!(out)
+}
+
+/// The statistics of `write_stats` as the fields of a JSON object
+///
+/// The fields are named after the parts of the summary line, like
+/// `total_words`, `ttr` and `avg_word_length`; a part that would not be
+/// written is left out, the groups of the word lengths, words per line and
+/// paragraphs becoming nested objects.
+pub fn stats_json(
+    totals: &Totals,
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+    mattr: Option<f64>,
+) -> serde_json::Map<String, serde_json::Value> {
+    use serde_json::json;
+
+    let mut stats = serde_json::Map::new();
+    stats.insert(String::from("total_words"), json!(totals.words));
+    stats.insert(String::from("unique_words"), json!(word_counts.len()));
+    if output_config.min_count.is_some()
+        || output_config.max_count.is_some()
+        || output_config.exactly.is_some()
+    {
+        let kept = word_counts
+            .values()
+            .filter(|&&count| output_config.keeps_count(count))
+            .count();
+        stats.insert(String::from("unique_words_after_filtering"), json!(kept));
+    }
+    if let Some(n) = output_config.legomena {
+        let legomena = legomena(word_counts, n).len();
+        let ratio = if word_counts.is_empty() {
+            0.0
+        } else {
+            legomena as f64 / word_counts.len() as f64
+        };
+        stats.insert(
+            String::from("legomena"),
+            json!({"times": n, "words": legomena, "ratio": ratio}),
+        );
+    }
+    let ttr = if totals.words == 0 {
+        0.0
+    } else {
+        word_counts.len() as f64 / totals.words as f64
+    };
+    stats.insert(String::from("ttr"), json!(ttr));
+    if let Some(mattr) = mattr {
+        stats.insert(String::from("mattr"), json!(mattr));
+    }
+    if output_config.words_per_sentence {
+        let average = if totals.sentences == 0 {
+            0.0
+        } else {
+            totals.words as f64 / totals.sentences as f64
+        };
+        stats.insert(String::from("avg_words_per_sentence"), json!(average));
+    }
+    if output_config.word_lengths {
+        let lengths = word_lengths(word_counts);
+        stats.insert(
+            String::from("avg_word_length"),
+            json!({"mean": lengths.mean, "median": lengths.median, "mode": lengths.mode}),
+        );
+    }
+    if let Some(histogram) = &output_config.words_per_line {
+        let line = line_words(histogram);
+        stats.insert(
+            String::from("words_per_line"),
+            json!({"min": line.min, "max": line.max, "mean": line.mean, "median": line.median}),
+        );
+    }
+    if let Some(lines) = &output_config.new_words_per_line {
+        stats.insert(
+            String::from("new_words_per_line"),
+            json!({"mean": lines.mean(), "median": lines.median()}),
+        );
+    }
+    if let Some(paragraphs) = &output_config.paragraphs {
+        let average = if paragraphs.is_empty() {
+            0.0
+        } else {
+            paragraphs.iter().sum::<usize>() as f64 / paragraphs.len() as f64
+        };
+        stats.insert(
+            String::from("paragraphs"),
+            json!({
+                "count": paragraphs.len(),
+                "avg_words": average,
+                "longest": paragraphs.iter().max().unwrap_or(&0),
+            }),
+        );
+    }
+    if output_config.entropy || output_config.perplexity {
+        let entropy = entropy(word_counts);
+        if output_config.entropy {
+            stats.insert(
+                String::from("entropy"),
+                json!({"bits": entropy.bits, "normalized": entropy.normalized}),
+            );
+        }
+        if output_config.perplexity {
+            stats.insert(String::from("perplexity"), json!(entropy.perplexity));
+        }
+    }
+    stats
+}
+
+/// Process a chunk of lines, updating the word counts
+///
+/// Returns the number of tokens, or n-grams, that were counted. Tokens
+/// dropped because of `max_unique_words` are not included. An
+/// `every_nth_word` sample starts over with the first token of every chunk.
+///
+/// # Arguments
+///
+/// * `chunk` - A slice of strings representing the lines to process
+/// * `config` - The options controlling how each token is normalised
+/// * `window` - The last `ngrams - 1` tokens of the previous chunk, which is
+///   updated with the tail of this chunk
+/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::{BTreeMap, VecDeque};
+///
+/// use word_count::{process_chunk, WordCountConfig};
+///
+/// let mut word_counts = BTreeMap::new();
+/// let mut window = VecDeque::new();
+/// process_chunk(&["a b", "b"], &WordCountConfig::default(), &mut window, &mut word_counts);
+/// assert_eq!(word_counts.get("b"), Some(&2));
+/// ```
+pub fn process_chunk<S: AsRef<str>>(
+    chunk: &[S],
+    config: &WordCountConfig,
+    window: &mut VecDeque<String>,
+    word_counts: &mut BTreeMap<String, usize>,
+) -> usize {
+    count_chunk(chunk, config, window, &mut 0, word_counts).0
+}
+
+/// Whether `line` holds more than `max` Unicode scalar values
+///
+/// Only the first `max + 1` chars are looked at, so a huge line costs no
+/// more than a short one.
+pub fn is_longer_than(line: &str, max: usize) -> bool {
+    // a char takes at least one byte
+    line.len() > max && line.chars().nth(max).is_some()
+}
+
+/// Count the lines of a chunk that contain anything but whitespace
+///
+/// # Arguments
+///
+/// * `chunk` - A slice of strings representing the lines to process
+pub fn count_lines_in_chunk<S: AsRef<str>>(chunk: &[S]) -> usize {
+    chunk
+        .iter()
+        .filter(|line| !line.as_ref().trim().is_empty())
+        .count()
+}
+
+/// The abbreviations whose trailing `.` does not end a sentence
+const ABBREVIATIONS: [&str; 10] = [
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "e.g", "i.e",
+];
+
+/// Count the sentences ending in a line
+///
+/// This is a naive heuristic: a sentence ends at a `.`, `!` or `?` followed by
+/// whitespace or the end of the line. A run of them, like an ellipsis or
+/// `?!`, ends a single sentence, and the `.` of a few common abbreviations
+/// like "Dr." ends none.
+///
+/// # Arguments
+///
+/// * `line` - The line to count the sentences of
+pub fn count_sentences(line: &str) -> usize {
+    let is_terminator = |c: char| matches!(c, '.' | '!' | '?');
+    let mut sentences = 0;
+    for (index, c) in line.char_indices() {
+        if !is_terminator(c) {
+            continue;
+        }
+        let rest = &line[index + c.len_utf8()..];
+        if rest
+            .chars()
+            .next()
+            .is_some_and(|next| !next.is_whitespace())
+        {
+            // only the last terminator of a run, or one inside a word like
+            // "3.14", is followed by something else
+            continue;
+        }
+        let before = line[..index].trim_end_matches(is_terminator);
+        let word = before
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches(|c: char| !c.is_alphanumeric());
+        let single_dot = c == '.' && before.len() == index;
+        if word.is_empty()
+            || single_dot
+                && ABBREVIATIONS
+                    .iter()
+                    .any(|abbreviation| word.eq_ignore_ascii_case(abbreviation))
+        {
+            continue;
+        }
+        sentences += 1;
+    }
+    sentences
+}
+
+/// Count the sentences of a chunk, treating a token that ends with one of
+/// `endings` as the end of a sentence
+///
+/// Unlike `count_sentences` no abbreviations are recognised. A token made of
+/// nothing but endings right after the end of a sentence, like the `!` of
+/// `wait. !`, continues that sentence, just like a run of endings at the end
+/// of a token.
+///
+/// # Arguments
+///
+/// * `chunk` - A slice of strings representing the lines to process
+/// * `endings` - The chars that end a sentence, e.g. `.`, `!` and `?`
+pub fn count_sentences_in_chunk<S: AsRef<str>>(chunk: &[S], endings: &[char]) -> usize {
+    let mut sentences = 0;
+    // whether the previous token ended a sentence
+    let mut ended = false;
+    for token in chunk
+        .iter()
+        .flat_map(|line| line.as_ref().split_whitespace())
+    {
+        if !token.ends_with(endings) {
+            ended = false;
+        } else if !(ended && token.chars().all(|c| endings.contains(&c))) {
+            sentences += 1;
+            ended = true;
+        }
+    }
+    sentences
+}
+
+/// Count the sentences of a chunk as configured in `config`
+fn chunk_sentences<S: AsRef<str>>(chunk: &[S], config: &WordCountConfig) -> usize {
+    if config.sentence_per_line {
+        return chunk
+            .iter()
+            .filter(|line| !line.as_ref().trim().is_empty())
+            .count();
+    }
+    match &config.sentence_endings {
+        Some(endings) => count_sentences_in_chunk(chunk, endings),
+        None => chunk
+            .iter()
+            .map(|line| count_sentences(line.as_ref()))
+            .sum(),
+    }
+}
+
+/// An empty `HashMap` to count into, hashing with the `hash_algorithm`
+///
+/// The counts end up in a sorted map, so the iteration order of the
+/// `HashMap` only shows where it decides which words `max_unique_words`
+/// drops. With a `hash_seed` that order is the same on every run: the seed
+/// goes into the hasher itself, e.g. as the per-hasher seed of foldhash
+/// 0.2's `fast::FoldHasher` combined with its fixed shared seed, and does
+/// not seed any random number generator.
+fn hashed_counts(config: &WordCountConfig) -> HashMap<String, usize, CountHashState> {
+    HashMap::with_hasher(CountHashState::new(config.hash_algorithm, config.hash_seed))
+}
+
+/// Count a chunk of lines into any map, returning the number of tokens
+/// counted and dropped because of `max_unique_words`
+///
+/// # Arguments
+///
+/// * `chunk` - A slice of strings representing the lines to process
+/// * `config` - The options controlling how each token is normalised
+/// * `window` - The last `ngrams - 1` tokens of the previous chunk
+/// * `position` - The number of units seen before, for `every_nth_word`
+/// * `word_counts` - A mutable reference to the map to store the word counts
+fn count_chunk<S: AsRef<str>>(
+    chunk: &[S],
+    config: &WordCountConfig,
+    window: &mut VecDeque<String>,
+    position: &mut usize,
+    word_counts: &mut impl CountMap,
+) -> (usize, usize) {
+    let mut counted = 0;
+    let mut dropped = 0;
+    for line in chunk {
+        let line = line.as_ref();
+        for_each_unit(line, config, window, |word| {
+            let skipped = config
+                .every_nth_word
+                .is_some_and(|every| !position.is_multiple_of(every));
+            *position += 1;
+            if skipped {
+                return;
+            }
+            if word_counts.add(word, 1, config.max_unique_words) {
+                counted += 1;
+            } else {
+                dropped += 1;
+            }
+        });
+    }
+    (counted, dropped)
+}
+
+/// Call `f` with every counted unit of a line, either a token or an n-gram
+///
+/// An n-gram is emitted for every token once `window` holds the `ngrams - 1`
+/// tokens before it, so n-grams span line boundaries.
+fn for_each_unit(
+    line: &str,
+    config: &WordCountConfig,
+    window: &mut VecDeque<String>,
+    mut f: impl FnMut(String),
+) {
+    if let Some(n) = config.char_ngrams {
+        return for_each_token(line, config, |word| {
+            for_each_char_ngram(&word, n, config.char_ngram_boundaries, &mut f)
+        });
+    }
+    if config.ngrams <= 1 {
+        return for_each_token(line, config, f);
+    }
+    for_each_token(line, config, |word| {
+        window.push_back(word);
+        if window.len() == config.ngrams {
+            f(config.ngram_join.join(window));
+            window.pop_front();
+        }
+    });
+}
+
+/// Call `f` with every sequence of `n` consecutive chars of `word`
+///
+/// A word shorter than `n` has none, unless it is padded with the
+/// `boundaries` markers `^` and `$` first.
+fn for_each_char_ngram(word: &str, n: usize, boundaries: bool, mut f: impl FnMut(String)) {
+    let n = n.max(1);
+    let padding = if boundaries { n - 1 } else { 0 };
+    let chars: Vec<char> = std::iter::repeat_n('^', padding)
+        .chain(word.chars())
+        .chain(std::iter::repeat_n('$', padding))
+        .collect();
+    for ngram in chars.windows(n) {
+        f(ngram.iter().collect());
+    }
+}
+
+/// Move `window` past `chunk` without counting, keeping its last `ngrams - 1`
+/// tokens
+///
+/// Only the lines at the end of the chunk that are needed are tokenized.
+fn advance_window<S: AsRef<str>>(
+    window: &mut VecDeque<String>,
+    chunk: &[S],
+    config: &WordCountConfig,
+) {
+    let keep = config.ngrams.saturating_sub(1);
+    if keep == 0 {
+        return;
+    }
+    let mut tail = Vec::new();
+    for line in chunk.iter().rev() {
+        let mut tokens = Vec::new();
+        for_each_token(line.as_ref(), config, |word| tokens.push(word));
+        tail.splice(0..0, tokens);
+        if tail.len() >= keep {
+            break;
+        }
+    }
+    window.extend(tail);
+    while window.len() > keep {
+        window.pop_front();
+    }
+}
+
+/// Extract the `column`th field of a line, 1-based, or `None` if the line has
+/// fewer fields
+///
+/// Without a `separator` the fields are separated by runs of whitespace,
+/// otherwise by every occurrence of the separator, so fields can be empty.
+///
+/// # Arguments
+///
+/// * `line` - The line to extract the field from
+/// * `column` - The number of the field, starting at 1
+/// * `separator` - The char between two fields, `None` for whitespace
+pub fn extract_column(line: &str, column: usize, separator: Option<char>) -> Option<&str> {
+    let index = column.checked_sub(1)?;
+    match separator {
+        Some(separator) => line.split(separator).nth(index),
+        None => line.split_whitespace().nth(index),
+    }
+}
+
+/// Split a line into tokens and call `f` with each of them once normalised
+pub fn for_each_token(line: &str, config: &WordCountConfig, mut f: impl FnMut(String)) {
+    let line = match config.column {
+        Some(column) => match extract_column(line, column, config.column_separator) {
+            Some(field) => field,
+            None => return,
+        },
+        None => line,
+    };
+    let kept;
+    let line = if config.extract_urls || config.extract_emails {
+        kept = without_extracted(line, config);
+        kept.as_ref()
+    } else {
+        line
+    };
+    let mut emit_part = |word: &str| {
+        if config.split_camel_case || config.split_underscore {
+            for part in split_identifier(word, config.split_camel_case, config.split_underscore) {
+                if let Some(part) = normalize_token(part, config) {
+                    f(part);
+                }
+            }
+        } else if let Some(word) = normalize_token(word, config) {
+            f(word);
+        }
+    };
+    let mut emit = |word: &str| {
+        if config.split_hyphenated {
+            split_hyphenated(word, config.keep_hyphenated_numbers)
+                .into_iter()
+                .for_each(&mut emit_part);
+        } else {
+            emit_part(word);
+        }
+    };
+    if config.chars {
+        let mut buf = [0; 4];
+        for c in line.chars() {
+            if !(config.skip_whitespace_chars && c.is_whitespace()) {
+                emit(c.encode_utf8(&mut buf));
+            }
+        }
+        return;
+    }
+    if let Some(lang) = config.code_lang {
+        return line
+            .split(|c| !is_code_identifier_char(c, lang))
+            .filter(|word| !word.is_empty())
+            .for_each(&mut emit);
+    }
+    match (&config.split_regex, config.field_separator.as_deref()) {
+        // splitting can leave empty tokens around leading or repeated separators
+        (Some(regex), _) => regex
+            .split(line)
+            .filter(|word| !word.is_empty())
+            .for_each(&mut emit),
+        // a whitespace separator collapses its runs like the default split
+        (None, Some(separator)) if !separator.chars().all(char::is_whitespace) => line
+            .split(separator)
+            .filter(|word| !word.is_empty())
+            .for_each(&mut emit),
+        _ => line.split_whitespace().for_each(&mut emit),
+    }
+}
+
+/// `line` without the URLs and emails that `extracted` takes out of it
+///
+/// Only their own bytes are cut, so the whitespace around them is kept for
+/// the split of the rest of the line.
+fn without_extracted<'a>(line: &'a str, config: &WordCountConfig) -> Cow<'a, str> {
+    let mut kept = String::new();
+    let mut cut = 0;
+    let mut start = 0;
+    // every piece holds one part and the whitespace char after it
+    for piece in line.split_inclusive(char::is_whitespace) {
+        let part = piece.trim_end_matches(char::is_whitespace);
+        if !part.is_empty() && extracted(part, config).is_some() {
+            kept.push_str(&line[cut..start]);
+            cut = start + part.len();
+        }
+        start += piece.len();
+    }
+    if cut == 0 {
+        return Cow::Borrowed(line);
+    }
+    kept.push_str(&line[cut..]);
+    Cow::Owned(kept)
+}
+
+/// Whether `c` can be part of an identifier, keyword or number in `lang`
+///
+/// Source code is split at every other char, which covers comment markers
+/// like `//` and `#`, string delimiters and punctuation like `(`, `;` or
+/// `.` alike. This is no parser: the words of comments and string literals
+/// are counted like identifiers, and keywords like `fn` or `def` like any
+/// other word.
+///
+/// ```
+/// use word_count::{is_code_identifier_char, CodeLang};
+///
+/// assert!(is_code_identifier_char('_', CodeLang::Rust));
+/// assert!(is_code_identifier_char('$', CodeLang::Js));
+/// assert!(!is_code_identifier_char('$', CodeLang::Python));
+/// assert!(!is_code_identifier_char(':', CodeLang::Rust));
+/// ```
+pub fn is_code_identifier_char(c: char, lang: CodeLang) -> bool {
+    c.is_alphanumeric() || c == '_' || lang == CodeLang::Js && c == '$'
+}
+
+/// Split an identifier into the words it is made of
+///
+/// With `camel_case` it is split between every lowercase letter and a
+/// following uppercase one, so runs of capitals like the `HTTP` of
+/// `HTTPServer` stay together. With `underscore` it is split at its
+/// underscores, leaving out empty parts.
+///
+/// ```
+/// use word_count::split_identifier;
+///
+/// assert_eq!(split_identifier("BufReader", true, false), ["Buf", "Reader"]);
+/// assert_eq!(split_identifier("max_line_len", false, true), ["max", "line", "len"]);
+/// ```
+pub fn split_identifier(word: &str, camel_case: bool, underscore: bool) -> Vec<&str> {
+    let parts: Vec<&str> = if underscore {
+        word.split('_').filter(|part| !part.is_empty()).collect()
+    } else {
+        vec![word]
+    };
+    if !camel_case {
+        return parts;
+    }
+    let mut words = Vec::new();
+    for part in parts {
+        let mut start = 0;
+        let mut previous_lowercase = false;
+        for (index, c) in part.char_indices() {
+            if previous_lowercase && c.is_uppercase() {
+                words.push(&part[start..index]);
+                start = index;
+            }
+            previous_lowercase = c.is_lowercase();
+        }
+        words.push(&part[start..]);
+    }
+    words
+}
+
+/// Split a hyphenated compound into the words it is made of, leaving out
+/// the empty parts around leading, trailing or repeated hyphens
+///
+/// Both the ASCII hyphen-minus and the Unicode hyphen `‐` split. With
+/// `keep_numbers` a word with a digit is kept whole, like a date or `3-D`.
+/// The parts keep any punctuation around the word, which `strip_punct`
+/// strips from them as from any token.
+///
+/// ```
+/// use word_count::split_hyphenated;
+///
+/// assert_eq!(split_hyphenated("state-of-the-art", false), ["state", "of", "the", "art"]);
+/// assert_eq!(split_hyphenated("2023-01-01", true), ["2023-01-01"]);
+/// ```
+pub fn split_hyphenated(word: &str, keep_numbers: bool) -> Vec<&str> {
+    if keep_numbers && word.chars().any(|c| c.is_ascii_digit()) {
+        return vec![word];
+    }
+    word.split(['-', '‐'])
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Normalise a single token according to the config
+///
+/// Returns `None` if nothing is left of the token after normalisation, in
+/// which case it must not be counted.
+///
+/// # Arguments
+///
+/// * `word` - The raw token as produced by the tokenizer
+/// * `config` - The options controlling how the token is normalised
+fn normalize_token(word: &str, config: &WordCountConfig) -> Option<String> {
+    let normalized;
+    let word = match config.unicode_form {
+        Some(form) if !word.is_ascii() => {
+            normalized = normalize_unicode(word, form);
+            normalized.as_str()
+        }
+        _ => word,
+    };
+    let word = if config.strip_punct {
+        word.trim_matches(|c: char| !c.is_alphanumeric())
+    } else {
+        word
+    };
+    if word.is_empty() {
+        return None;
+    }
+    if let Some(placeholder) = placeholder_for(word, config) {
+        return Some(placeholder.to_string());
+    }
+
+    // checked before any allocation so filtered words cost nothing
+    if config.min_length.is_some() || config.max_length.is_some() {
+        let length = word.chars().count();
+        if config.min_length.is_some_and(|min| length < min)
+            || config.max_length.is_some_and(|max| length > max)
+        {
+            return None;
+        }
+    }
+
+    if config.remove_numbers && is_number_like(word)
+        || config.remove_integers && is_integer(word)
+        || config.remove_floats && is_float(word)
+        || config.only_alpha && !word.chars().all(char::is_alphabetic)
+        || config.only_alphanumeric && !word.chars().all(char::is_alphanumeric)
+    {
+        return None;
+    }
+
+    let truncated;
+    let word = match config
+        .truncate_length
+        .and_then(|length| word.char_indices().nth(length))
+    {
+        Some((end, _)) => {
+            truncated = format!("{}{}", &word[..end], config.truncate_marker);
+            truncated.as_str()
+        }
+        None => word,
+    };
+    let word = if config.ignore_case {
+        // `to_lowercase` works on the full Unicode mapping, so letters
+        // that expand to several chars (e.g. 'İ') are handled correctly
+        word.to_lowercase()
+    } else {
+        word.to_string()
+    };
+    #[cfg(feature = "transliterate")]
+    let word = match config.transliterate {
+        Some(TranslitScheme::Gost) if !word.is_ascii() => transliterate_gost(&word),
+        _ => word,
+    };
+    let word = strip_affixes(word, config);
+    if word.is_empty() {
+        return None;
+    }
+    let word = match config.stem {
+        Some(StemLang::En) => porter_stem(&word),
+        None => word,
+    };
+
+    if config.stopwords.contains(&word) {
+        return None;
+    }
+    if config
+        .vocab
+        .as_ref()
+        .is_some_and(|vocab| !vocab.contains(&word))
+    {
+        return None;
+    }
+    if config
+        .include_pattern
+        .as_ref()
+        .is_some_and(|pattern| !pattern.is_match(&word))
+        || config
+            .exclude_pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(&word))
+    {
+        return None;
+    }
+    Some(word)
+}
+
+/// `word` normalised like the tokens counted with `config`, so it can be
+/// looked up in their counts
+///
+/// A word that is not counted at all, like a stopword, is returned as it is.
+///
+/// ```
+/// use word_count::{counted_form, WordCountConfig};
+///
+/// let config = WordCountConfig {
+///     ignore_case: true,
+///     strip_punct: true,
+///     ..Default::default()
+/// };
+/// assert_eq!(counted_form("Hello!", &config), "hello");
+/// ```
+pub fn counted_form(word: &str, config: &WordCountConfig) -> String {
+    normalize_token(word, config).unwrap_or_else(|| word.to_string())
+}
+
+/// `word` without the `strip_prefixes` it starts with and the
+/// `strip_suffixes` it then ends with, each removed once and in order
+fn strip_affixes(mut word: String, config: &WordCountConfig) -> String {
+    for prefix in &config.strip_prefixes {
+        if word.starts_with(prefix.as_str()) {
+            word.drain(..prefix.len());
+        }
+    }
+    for suffix in &config.strip_suffixes {
+        if word.ends_with(suffix.as_str()) {
+            word.truncate(word.len() - suffix.len());
+        }
+    }
+    word
+}
+
+/// The placeholder of `replace_urls` or `replace_numbers` that `token` is
+/// counted as, if any
+fn placeholder_for<'a>(token: &str, config: &'a WordCountConfig) -> Option<&'a str> {
+    if let Some(placeholder) = &config.replace_urls {
+        if is_url(token) {
+            return Some(placeholder);
+        }
+    }
+    let placeholder = config.replace_numbers.as_deref()?;
+    let number = match &config.number_regex {
+        Some(regex) => regex.is_match(token),
+        None => token.bytes().all(|b| b.is_ascii_digit()),
+    };
+    number.then_some(placeholder)
+}
+
+/// Whether `token` starts with `http://` or `https://`, in any case
+fn is_url(token: &str) -> bool {
+    let lowercase = token.get(..8).unwrap_or(token).to_ascii_lowercase();
+    lowercase.starts_with("http://") || lowercase.starts_with("https://")
+}
+
+/// Whether `token` looks like an email address: a local part of letters,
+/// digits and `._%+-`, an `@` and a domain of at least two dot-separated
+/// labels of letters, digits and `-`
+fn is_email(token: &str) -> bool {
+    let Some((local, domain)) = token.split_once('@') else {
+        return false;
+    };
+    let labels: Vec<&str> = domain.split('.').collect();
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || "._%+-".contains(c))
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+}
+
+/// What `extracted` found a part of a line to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Extracted {
+    Url,
+    Email,
+}
+
+/// The URL or email address a whitespace-separated `part` of a line is,
+/// without the punctuation around it, if `config` extracts it
+fn extracted<'a>(part: &'a str, config: &WordCountConfig) -> Option<(Extracted, &'a str)> {
+    let token = part.trim_matches(|c| "()<>[]{}\"',.;:!?".contains(c));
+    if config.extract_urls && is_url(token) {
+        Some((Extracted::Url, token))
+    } else if config.extract_emails && is_email(token) {
+        Some((Extracted::Email, token))
+    } else {
+        None
+    }
+}
+
+/// Whether `token` is made of digits, `.` and `-` only, with at least one digit
+fn is_number_like(token: &str) -> bool {
+    token.chars().any(|c| c.is_ascii_digit())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == '-')
+}
+
+/// Whether `token` is a decimal integer with an optional sign
+fn is_integer(token: &str) -> bool {
+    let digits = token.strip_prefix(['+', '-']).unwrap_or(token);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Whether `token` is a floating-point literal with an optional sign, i.e.
+/// digits with a fraction, an exponent or both
+fn is_float(token: &str) -> bool {
+    let unsigned = token.strip_prefix(['+', '-']).unwrap_or(token);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let mantissa_ok = is_digits(whole)
+        && fraction.is_none_or(is_digits)
+        && whole.len() + fraction.map_or(0, str::len) > 0;
+    let exponent_ok = exponent.is_none_or(is_integer);
+    // a plain integer has neither a fraction nor an exponent
+    mantissa_ok && exponent_ok && (fraction.is_some() || exponent.is_some())
+}
+
+/// Whether the file at `path` looks binary, i.e. has a null byte in its first 8 KB
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to check
+pub fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(8192);
+    File::open(path)?.take(8192).read_to_end(&mut head)?;
+    Ok(head.contains(&0))
+}
+
+/// Extract the text of a line of JSON: its string values joined by spaces
+///
+/// Numbers, booleans and nulls are no text and left out. The strings are
+/// collected depth first, the fields of an object ordered by their key.
+/// With a `path`, like `["message"]` for `message`, only the strings below
+/// the field at the path are collected; an array on the way stands for each
+/// of its elements. This is a dotted field path, not full JMESPath.
+///
+/// ```
+/// use word_count::json_text;
+///
+/// let line = r#"{"level": "info", "message": "disk full", "code": 28}"#;
+/// assert_eq!(json_text(line, &[]).unwrap(), "info disk full");
+/// assert_eq!(json_text(line, &["message".into()]).unwrap(), "disk full");
+/// ```
+pub fn json_text(line: &str, path: &[String]) -> serde_json::Result<String> {
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    let mut strings = Vec::new();
+    collect_json_strings(&value, path, &mut strings);
+    Ok(strings.join(" "))
+}
+
+/// Collect the strings of `value` below `path` into `strings`
+fn collect_json_strings<'a>(
+    value: &'a serde_json::Value,
+    path: &[String],
+    strings: &mut Vec<&'a str>,
+) {
+    use serde_json::Value;
+
+    match (value, path.split_first()) {
+        (Value::Array(items), _) => {
+            for item in items {
+                collect_json_strings(item, path, strings);
+            }
+        }
+        (Value::Object(fields), None) => {
+            for field in fields.values() {
+                collect_json_strings(field, path, strings);
+            }
+        }
+        (Value::Object(fields), Some((key, rest))) => {
+            if let Some(field) = fields.get(key) {
+                collect_json_strings(field, rest, strings);
+            }
+        }
+        (Value::String(string), None) => strings.push(string),
+        _ => {}
+    }
+}
+
+/// Read a list of words, one per line, ignoring blank lines
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read
+pub fn read_word_list(path: &Path) -> io::Result<HashSet<String>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut words = HashSet::new();
+    for line in reader.lines() {
+        let line = line?;
+        let word = line.trim();
+        if !word.is_empty() {
+            words.insert(word.to_string());
+        }
+    }
+    Ok(words)
+}
+
+/// Read the word counts printed by an earlier run, so new counts can be
+/// merged into them
+///
+/// Only the plain text (`word: count` lines) and JSON object formats can be
+/// read back, other formats are rejected.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read
+/// * `format` - The format the file was printed in
+pub fn read_counts(path: &Path, format: OutputFormat) -> io::Result<BTreeMap<String, usize>> {
+    let reader = BufReader::new(File::open(path)?);
+    match format {
+        OutputFormat::Text => {
+            let mut word_counts = BTreeMap::new();
+            for (index, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // words may contain ": " themselves, the count never does
+                let entry = line
+                    .rsplit_once(": ")
+                    .and_then(|(word, count)| Some((word, count.parse::<usize>().ok()?)));
+                let Some((word, count)) = entry else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {} is not a `word: count` line", index + 1),
+                    ));
+                };
+                *word_counts.entry(word.to_string()).or_insert(0) += count;
+            }
+            Ok(word_counts)
+        }
+        OutputFormat::Json => Ok(serde_json::from_reader(reader)?),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "only the text and json formats can be read back",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::*;
+
+    #[test]
+    fn test_process_chunk() {
+        // Arrange
+        let chunk = vec![
+            String::from("The quick brown fox jumps over the lazy dog"),
+            String::from("The dog barks"),
+        ];
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &chunk,
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        assert_eq!(word_counts.get("The"), Some(&2));
+        assert_eq!(word_counts.get("the"), Some(&1));
+        assert_eq!(word_counts.get("quick"), Some(&1));
+        assert_eq!(word_counts.get("brown"), Some(&1));
+        assert_eq!(word_counts.get("fox"), Some(&1));
+        assert_eq!(word_counts.get("jumps"), Some(&1));
+        assert_eq!(word_counts.get("over"), Some(&1));
+        assert_eq!(word_counts.get("lazy"), Some(&1));
+        assert_eq!(word_counts.get("dog"), Some(&2));
+        assert_eq!(word_counts.get("barks"), Some(&1));
+        assert_eq!(word_counts.len(), 10); // Total unique words
+    }
+
+    #[test]
+    fn test_process_chunk_empty() {
+        // Arrange
+        let chunk: Vec<String> = Vec::new();
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &chunk,
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        assert!(word_counts.is_empty());
+    }
+
+    #[test]
+    fn test_process_chunk_case_insensitive() {
+        // Arrange
+        let chunk = vec![String::from("The THE the"), String::from("Dog dog DOG")];
+        let config = WordCountConfig {
+            ignore_case: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("the"), Some(&3));
+        assert_eq!(word_counts.get("dog"), Some(&3));
+        assert_eq!(word_counts.len(), 2);
+    }
+
+    #[test]
+    fn test_process_chunk_case_insensitive_unicode() {
+        // Arrange
+        let chunk = vec![String::from("ÜBER Über über İSTANBUL İstanbul")];
+        let config = WordCountConfig {
+            ignore_case: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("über"), Some(&3));
+        // 'İ' lowercases to 'i' followed by a combining dot above
+        assert_eq!(word_counts.get("i\u{307}stanbul"), Some(&2));
+        assert_eq!(word_counts.len(), 2);
+    }
+
+    #[test]
+    fn test_process_chunk_strip_punct() {
+        // Arrange
+        let chunk = vec![String::from("hello, (world) the end. --- hello")];
+        let config = WordCountConfig {
+            strip_punct: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("hello"), Some(&2));
+        assert_eq!(word_counts.get("world"), Some(&1));
+        assert_eq!(word_counts.get("end"), Some(&1));
+        assert_eq!(word_counts.get("---"), None);
+        assert_eq!(word_counts.len(), 4); // hello, world, the, end
+    }
+
+    #[test]
+    fn test_process_chunk_keeps_punct_by_default() {
+        // Arrange
+        let chunk = vec![String::from("hello, hello")];
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &chunk,
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        assert_eq!(word_counts.get("hello,"), Some(&1));
+        assert_eq!(word_counts.get("hello"), Some(&1));
+    }
+
+    #[test]
+    fn test_count_file_aggregates_two_files() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        std::fs::write(&first, "apple banana\napple").unwrap();
+        std::fs::write(&second, "banana cherry").unwrap();
+        let config = WordCountConfig::default();
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        for path in [&first, &second] {
+            let mut file_counts = BTreeMap::new();
+            count_file(path, &config, &mut file_counts).unwrap();
+            merge_counts(&mut word_counts, file_counts);
+        }
+
+        // Assert
+        assert_eq!(word_counts.get("apple"), Some(&2));
+        assert_eq!(word_counts.get("banana"), Some(&2));
+        assert_eq!(word_counts.get("cherry"), Some(&1));
+        assert_eq!(word_counts.len(), 3);
+    }
+
+    #[test]
+    fn test_count_file_missing_file() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let result = count_file(
+            &dir.path().join("missing.txt"),
+            &WordCountConfig::default(),
+            &mut word_counts,
+        );
+
+        // Assert
+        assert!(result.is_err());
+        assert!(word_counts.is_empty());
+    }
+
+    #[test]
+    fn test_count_reader() {
+        // Arrange
+        let reader = io::Cursor::new("one two\ntwo");
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        count_reader(reader, &WordCountConfig::default(), &mut word_counts).unwrap();
+
+        // Assert
+        assert_eq!(word_counts.get("one"), Some(&1));
+        assert_eq!(word_counts.get("two"), Some(&2));
+    }
+
+    #[test]
+    fn test_count_reader_small_chunks() {
+        // Arrange
+        let reader = io::Cursor::new("a b\nb c\nc d\n");
+        let config = WordCountConfig {
+            chunk_size: 2,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        count_reader(reader, &config, &mut word_counts).unwrap();
+
+        // Assert
+        assert_eq!(word_counts.get("a"), Some(&1));
+        assert_eq!(word_counts.get("b"), Some(&2));
+        assert_eq!(word_counts.get("c"), Some(&2));
+        assert_eq!(word_counts.get("d"), Some(&1));
+    }
+
+    #[test]
+    fn test_count_reader_line_range() {
+        // Arrange
+        let text = "a\nb\nc\nd\ne\n";
+        let count = |start, end| {
+            let config = WordCountConfig {
+                chunk_size: 2,
+                sleep: None,
+                line_range: Some(LineRange { start, end }),
+                ..Default::default()
+            };
+            let mut word_counts = BTreeMap::new();
+            let totals = count_reader(io::Cursor::new(text), &config, &mut word_counts).unwrap();
+            let words: Vec<String> = word_counts.into_keys().collect();
+            (words.join(" "), totals.lines)
+        };
+
+        // Act
+        let from_start = count(1, Some(2));
+        let mid_file = count(2, Some(4));
+        // the range ends within the second chunk of the lines in range
+        let mid_chunk = count(3, Some(5));
+        let to_end = count(4, None);
+
+        // Assert
+        assert_eq!(from_start, (String::from("a b"), 2));
+        assert_eq!(mid_file, (String::from("b c d"), 3));
+        assert_eq!(mid_chunk, (String::from("c d e"), 3));
+        assert_eq!(to_end, (String::from("d e"), 2));
+    }
+
+    #[test]
+    fn test_count_reader_line_range_stops_reading() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 3,
+            sleep: None,
+            line_range: Some(LineRange {
+                start: 2,
+                end: Some(3),
+            }),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let totals = count_reader(
+            io::Cursor::new("a\nb\nc\nd\ne\n"),
+            &config,
+            &mut word_counts,
+        )
+        .unwrap();
+
+        // Assert
+        // line 4 was never read
+        assert_eq!(totals.lines_read, 3);
+        assert_eq!(totals.bytes, 4);
+    }
+
+    #[test]
+    fn test_count_file_utf16le() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("utf16le.txt");
+        // a byte order mark, then "héllo wörld\r\nhéllo\n"
+        let mut bytes = vec![0xff, 0xfe];
+        for unit in "héllo wörld\r\nhéllo\n".encode_utf16() {
+            bytes.extend(unit.to_le_bytes());
+        }
+        std::fs::write(&path, bytes).unwrap();
+        let config = WordCountConfig {
+            encoding: Encoding::Utf16le,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        count_file(&path, &config, &mut word_counts).unwrap();
+
+        // Assert
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([(String::from("héllo"), 2), (String::from("wörld"), 1)])
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        // Act
+        let be = decode(&[0xfe, 0xff, 0x00, 0x61, 0x00, 0xe9], Encoding::Utf16be);
+        let no_bom = decode(&[0x61, 0x00], Encoding::Utf16le);
+        let latin1 = decode(&[0x63, 0x61, 0x66, 0xe9], Encoding::Latin1);
+        let odd = decode(&[0x61, 0x00, 0x62], Encoding::Utf16le);
+        let lone_surrogate = decode(&[0x00, 0xd8], Encoding::Utf16le);
+
+        // Assert
+        assert_eq!(be.unwrap(), "aé");
+        assert_eq!(no_bom.unwrap(), "a");
+        assert_eq!(latin1.unwrap(), "café");
+        assert_eq!(odd.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            lone_surrogate.unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_count_reader_parallel_matches_sequential() {
+        // Arrange
+        let text: String = (0..1000)
+            .map(|i| format!("word{} shared common{}\n", i % 37, i % 5))
+            .collect();
+        let sequential = WordCountConfig {
+            chunk_size: 7,
+            sleep: None,
+            ..Default::default()
+        };
+        let parallel = WordCountConfig {
+            parallel: true,
+            ..sequential.clone()
+        };
+        let mut sequential_counts = BTreeMap::new();
+        let mut parallel_counts = BTreeMap::new();
+
+        // Act
+        count_reader(io::Cursor::new(&text), &sequential, &mut sequential_counts).unwrap();
+        count_reader(io::Cursor::new(&text), &parallel, &mut parallel_counts).unwrap();
+
+        // Assert
+        assert_eq!(parallel_counts, sequential_counts);
+        assert_eq!(parallel_counts.get("shared"), Some(&1000));
+    }
+
+    #[test]
+    fn test_count_file_mmap_matches_buffered() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt");
+        std::fs::write(&path, "one two\r\ntwo three\n\nthree three").unwrap();
+        let buffered = WordCountConfig {
+            chunk_size: 2,
+            sleep: None,
+            ..Default::default()
+        };
+        let mapped = WordCountConfig {
+            mmap: true,
+            ..buffered.clone()
+        };
+        let mut buffered_counts = BTreeMap::new();
+        let mut mapped_counts = BTreeMap::new();
+
+        // Act
+        count_file(&path, &buffered, &mut buffered_counts).unwrap();
+        count_file(&path, &mapped, &mut mapped_counts).unwrap();
+
+        // Assert
+        assert_eq!(mapped_counts, buffered_counts);
+        assert_eq!(mapped_counts.get("three"), Some(&3));
+    }
+
+    #[test]
+    fn test_count_file_mmap_empty_file() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.txt");
+        std::fs::write(&path, "").unwrap();
+        let config = WordCountConfig {
+            mmap: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        count_file(&path, &config, &mut word_counts).unwrap();
+
+        // Assert
+        assert!(word_counts.is_empty());
+    }
+
+    #[test]
+    fn test_write_snapshot() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 3),
+            (String::from("c"), 2),
+        ]);
+        let output_config = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            top: Some(2),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_snapshot(
+            &mut out,
+            Duration::from_millis(1500),
+            &word_counts,
+            &output_config,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"timestamp_ms\":1500,\"counts\":[{\"word\":\"b\",\"count\":3},{\"word\":\"c\",\"count\":2}]}\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_counts() {
+        // Arrange
+        let left = BTreeMap::from([
+            (String::from("gone"), 1),
+            (String::from("same"), 2),
+            (String::from("fewer"), 5),
+            (String::from("more"), 1),
+        ]);
+        let right = BTreeMap::from([
+            (String::from("new"), 3),
+            (String::from("same"), 2),
+            (String::from("fewer"), 2),
+            (String::from("more"), 2),
+        ]);
+
+        // Act
+        let diff = diff_counts(&left, &right);
+
+        // Assert
+        assert_eq!(diff.only_left, vec![("gone", 1)]);
+        assert_eq!(diff.only_right, vec![("new", 3)]);
+        assert_eq!(diff.changed, vec![("fewer", -3), ("more", 1)]);
+    }
+
+    #[test]
+    fn test_write_diff() {
+        // Arrange
+        let diff = CountDiff {
+            only_left: vec![("gone", 1)],
+            only_right: vec![],
+            changed: vec![("fewer", -3), ("more", 1)],
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_diff(&mut out, &diff, "a.txt", "b.txt").unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "=== only in a.txt ===\ngone: 1\n=== only in b.txt ===\n=== changed ===\nfewer: -3\nmore: +1\n"
+        );
+    }
+
+    #[test]
+    fn test_compare_corpora() {
+        // Arrange
+        let first = BTreeMap::from([
+            (String::from("cat"), 6),
+            (String::from("dog"), 2),
+            (String::from("owl"), 2),
+        ]);
+        let second = BTreeMap::from([(String::from("cat"), 2), (String::from("dog"), 8)]);
+        let mut text = Vec::new();
+
+        // Act
+        let plain = compare_corpora(&first, &second, false);
+        let scored = compare_corpora(&first, &second, true);
+        write_corpus_comparison(&mut text, &plain, OutputFormat::Text, "a", "b").unwrap();
+
+        // Assert
+        let words = |entries: &[CorpusEntry]| {
+            entries
+                .iter()
+                .map(|entry| entry.word.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(&plain.both), ["dog", "cat"]);
+        assert_eq!(words(&plain.only_first), ["owl"]);
+        assert!(plain.only_second.is_empty());
+        assert_eq!(plain.both[1].ratio, Some(3.0));
+        assert_eq!(plain.only_first[0].ratio, None);
+        // both corpora have 10 tokens, so "cat" and "dog" differ most
+        assert_eq!(words(&scored.both), ["dog", "cat"]);
+        let expected = log_likelihood(6, 2, 10, 10);
+        assert_eq!(scored.both[1].log_likelihood, Some(expected));
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "=== in both ===\ndog: 2 / 8 (ratio 0.25)\ncat: 6 / 2 (ratio 3.00)\n\
+             === only in a ===\nowl: 2\n=== only in b ===\n"
+        );
+    }
+
+    #[test]
+    fn test_log_likelihood() {
+        // Act
+        let even = log_likelihood(10, 10, 1000, 1000);
+        let skewed = log_likelihood(30, 10, 1000, 1000);
+        let absent = log_likelihood(0, 12, 500, 1500);
+
+        // Assert
+        assert_eq!(even, 0.0);
+        // 2 * (30 ln(30/20) + 10 ln(10/20))
+        assert!((skewed - 10.465).abs() < 0.001, "{}", skewed);
+        // 2 * 12 ln(12/9)
+        assert!((absent - 6.904).abs() < 0.001, "{}", absent);
+    }
+
+    #[test]
+    fn test_compare_to_reference() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("cat"), 3), (String::from("zebu"), 1)]);
+        let reference = BTreeMap::from([(String::from("cat"), 5), (String::from("dog"), 4)]);
+        let mut text = Vec::new();
+        let mut json = Vec::new();
+
+        // Act
+        let entries = compare_to_reference(&word_counts, &reference, true);
+        write_reference_diff(&mut text, &entries, ReferenceDiffFormat::Text).unwrap();
+        write_reference_diff(&mut json, &entries[1..], ReferenceDiffFormat::Json).unwrap();
+
+        // Assert
+        assert_eq!(
+            compare_to_reference(&word_counts, &reference, false).len(),
+            2
+        );
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "cat: 3 (reference: 5, delta: -2)\ndog: [missing] (reference: 4)\nzebu: 1 [new]\n"
+        );
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[{\"count\":0,\"delta\":-4,\"reference\":4,\"status\":\"missing\",\"word\":\"dog\"},\
+             {\"count\":1,\"delta\":null,\"reference\":null,\"status\":\"new\",\"word\":\"zebu\"}]\n"
+        );
+    }
+
+    #[test]
+    fn test_tfidf_variants() {
+        // Arrange
+        let documents = [
+            BTreeMap::from([(String::from("a"), 2), (String::from("b"), 1)]),
+            BTreeMap::from([(String::from("a"), 1), (String::from("c"), 1)]),
+        ];
+        let mut raw = Vec::new();
+
+        // Act
+        let scores = tfidf(&documents, TfidfVariant::Raw);
+        write_tfidf(&mut raw, &scores[0]).unwrap();
+        let sublinear = tfidf(&documents, TfidfVariant::Sublinear);
+        let bm25 = tfidf(&documents, TfidfVariant::Bm25);
+
+        // Assert
+        // idf(a) = ln(2/2) = 0 and idf(b) = idf(c) = ln(2/1)
+        assert_eq!(
+            String::from_utf8(raw).unwrap(),
+            "word\ttf\tidf\ttfidf\nb\t0.333333\t0.693147\t0.231049\n\
+             a\t0.666667\t0.000000\t0.000000\n"
+        );
+        assert_eq!(scores[1][0].word, "c");
+        assert!((scores[1][0].tfidf - 0.346574).abs() < 1e-6);
+        // 1 + ln(2) for the word counted twice
+        assert!((sublinear[0][1].tf - 1.693147).abs() < 1e-6);
+        assert!((sublinear[0][0].tfidf - std::f64::consts::LN_2).abs() < 1e-12);
+        // an average length of 2.5, so 1 - 0.75 + 0.75 * 3 / 2.5 = 1.15 for
+        // the first document and 2 * 2.2 / (2 + 1.2 * 1.15) for its "a"
+        assert!((bm25[0][1].tf - 1.301775).abs() < 1e-6);
+        assert!((bm25[0][0].tfidf - 0.640724).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_relative_frequencies() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 4),
+            (String::from("b"), 2),
+            (String::from("c"), 2),
+        ]);
+        let baseline = BTreeMap::from([
+            (String::from("a"), 2),
+            (String::from("b"), 4),
+            (String::from("d"), 2),
+        ]);
+        let mut text = Vec::new();
+        let mut smoothed = Vec::new();
+
+        // Act
+        let entries = relative_frequencies(&word_counts, &baseline, false, true);
+        write_relative_frequencies(&mut text, &entries).unwrap();
+        let entries = relative_frequencies(&word_counts, &baseline, true, false);
+        write_relative_frequencies(&mut smoothed, &entries).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "c: 2 [new]\na: 4 (\u{d7}2.00 vs baseline)\nb: 2 (\u{d7}0.50 vs baseline)\n"
+        );
+        // 5/12 against 3/12, 3/12 against 5/12 and 3/12 against 1/12
+        assert_eq!(
+            String::from_utf8(smoothed).unwrap(),
+            "a: 4 (\u{d7}1.67 vs baseline)\nb: 2 (\u{d7}0.60 vs baseline)\n\
+             c: 2 (\u{d7}3.00 vs baseline) [new]\n"
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_count_gzip_file() {
+        // Arrange
+        use flate2::write::GzEncoder;
+        let dir = tempfile::tempdir().unwrap();
+        // no extension, so the magic bytes have to give the compression away
+        let path = dir.path().join("corpus");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(b"the cat\nthe dog\n").unwrap();
+        encoder.finish().unwrap();
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let totals = count_file(&path, &WordCountConfig::default(), &mut word_counts).unwrap();
+
+        // Assert
+        assert_eq!(word_counts.get("the"), Some(&2));
+        assert_eq!(word_counts.get("dog"), Some(&1));
+        assert_eq!(totals.bytes, 16);
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_count_bzip2_file() {
+        // Arrange
+        use bzip2::write::BzEncoder;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt.bz2");
+        let mut encoder = BzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(b"a b a\n").unwrap();
+        encoder.finish().unwrap();
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        count_file(&path, &WordCountConfig::default(), &mut word_counts).unwrap();
+
+        // Assert
+        assert_eq!(word_counts.get("a"), Some(&2));
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_count_gzip_file_without_feature() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.gz");
+        fs::write(&path, b"\x1f\x8b").unwrap();
+
+        // Act
+        let result = count_file(&path, &WordCountConfig::default(), &mut BTreeMap::new());
+
+        // Assert
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("text.txt");
+        let binary = dir.path().join("image.bin");
+        fs::write(&text, "plain words\n").unwrap();
+        fs::write(&binary, b"\x89PNG\0\0\0").unwrap();
+
+        // Act & Assert
+        assert!(!is_binary(&text).unwrap());
+        assert!(is_binary(&binary).unwrap());
+    }
+
+    #[test]
+    fn test_legomena() {
+        // Arrange
+        let mut word_counts = BTreeMap::new();
+        process_chunk(
+            &["the cat and the dog", "and the bird"],
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Act
+        let hapax = legomena(&word_counts, 1);
+        let dis = legomena(&word_counts, 2);
+
+        // Assert
+        assert_eq!(hapax, vec!["bird", "cat", "dog"]);
+        assert_eq!(dis, vec!["and"]);
+    }
+
+    #[test]
+    fn test_write_counts_legomena() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("b"), 1),
+            (String::from("a"), 1),
+            (String::from("c"), 2),
+        ]);
+        let output_config = OutputConfig {
+            legomena: Some(1),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+        write_stats(
+            &mut out,
+            &Totals::default(),
+            &word_counts,
+            &output_config,
+            None,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a\nb\ntotal words: 0  unique words: 3  hapax legomena: 2 (ratio 0.6667)  TTR: 0.0000\n"
+        );
+    }
+
+    #[test]
+    fn test_dense_ranks_ties() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("the"), 5),
+            (String::from("a"), 5),
+            (String::from("cat"), 3),
+            (String::from("dog"), 1),
+            (String::from("bird"), 1),
+        ]);
+
+        // Act
+        let ranks = dense_ranks(&word_counts);
+
+        // Assert
+        assert_eq!(ranks, HashMap::from([(5, 1), (3, 2), (1, 3)]));
+    }
+
+    #[test]
+    fn test_write_counts_show_rank() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("the"), 2),
+            (String::from("a"), 2),
+            (String::from("cat"), 1),
+        ]);
+        let text = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            show_rank: true,
+            ..Default::default()
+        };
+        let json = OutputConfig {
+            format: OutputFormat::Json,
+            sort_order: SortOrder::Word,
+            ..text.clone()
+        };
+        let mut text_out = Vec::new();
+        let mut json_out = Vec::new();
+
+        // Act
+        write_counts(&mut text_out, &word_counts, &text).unwrap();
+        write_counts(&mut json_out, &word_counts, &json).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text_out).unwrap(),
+            "1 a: 2\n1 the: 2\n2 cat: 1\n"
+        );
+        assert_eq!(
+            String::from_utf8(json_out).unwrap(),
+            "[{\"rank\":1,\"word\":\"a\",\"count\":2},{\"rank\":2,\"word\":\"cat\",\"count\":1},{\"rank\":1,\"word\":\"the\",\"count\":2}]\n"
+        );
+    }
+
+    #[test]
+    fn test_format_entry() {
+        // Act
+        let plain = format_entry("cat", Some("3"), false, true);
+        let colored = format_entry("cat", Some("3"), true, false);
+        let bold = format_entry("cat", Some("3"), true, true);
+        let word_only = format_entry("cat", None, true, true);
+
+        // Assert
+        assert_eq!(plain, "cat: 3");
+        assert_eq!(colored, "\x1b[36mcat\x1b[0m: \x1b[33m3\x1b[0m");
+        assert_eq!(bold, "\x1b[36mcat\x1b[0m: \x1b[1;33m3\x1b[0m");
+        assert_eq!(word_only, "\x1b[36mcat\x1b[0m");
+    }
+
+    #[test]
+    fn test_write_counts_table() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 12345),
+            (String::from("incomprehension"), 7),
+        ]);
+        let output_config = OutputConfig {
+            table: true,
+            table_header: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "WORD             COUNT\n\
+             ---------------  -----\n\
+             a                12345\n\
+             incomprehension      7\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_table_wide_words() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("cat"), 12), (String::from("日本語"), 3)]);
+        let output_config = OutputConfig {
+            table: true,
+            table_header: true,
+            ..Default::default()
+        };
+        let fitted = OutputConfig {
+            word_width: Some(4),
+            ..output_config.clone()
+        };
+        let mut out = Vec::new();
+        let mut fitted_out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+        write_counts(&mut fitted_out, &word_counts, &fitted).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(fitted_out).unwrap(),
+            "WORD  COUNT\n\
+             ----  -----\n\
+             cat      12\n\
+             日本      3\n"
+        );
+        // the CJK word takes up two columns per char
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "WORD    COUNT\n\
+             ------  -----\n\
+             cat        12\n\
+             日本語      3\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_cumulative() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("the"), 5),
+            (String::from("cat"), 3),
+            (String::from("sat"), 2),
+        ]);
+        let text = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            cumulative_of: Some(10),
+            coverage_threshold: Some(75.0),
+            ..Default::default()
+        };
+        let json = OutputConfig {
+            format: OutputFormat::JsonLines,
+            top: Some(1),
+            ..text.clone()
+        };
+        let mut text_out = Vec::new();
+        let mut json_out = Vec::new();
+
+        // Act
+        write_counts(&mut text_out, &word_counts, &text).unwrap();
+        write_counts(&mut json_out, &word_counts, &json).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text_out).unwrap(),
+            "the: 5 50.00%\ncat: 3 80.00%  <- 75% coverage\nsat: 2 100.00%\n"
+        );
+        assert_eq!(
+            String::from_utf8(json_out).unwrap(),
+            "{\"word\":\"the\",\"count\":5,\"cumulative_pct\":50.0}\n"
+        );
+    }
+
+    #[test]
+    fn test_first_occurrences() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut positions = BTreeMap::new();
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 2),
+            (String::from("dog"), 1),
+            (String::from("the"), 2),
+        ]);
+
+        // Act
+        let mut first_occurrences = FirstOccurrences::new(&config, &mut positions);
+        first_occurrences.line_read("the dog b");
+        first_occurrences.line_read("");
+        let mut first_occurrences = FirstOccurrences::new(&config, &mut positions);
+        first_occurrences.line_read("b a the");
+        let output_config = OutputConfig {
+            sort_order: SortOrder::FirstSeen,
+            first_positions: Some(positions.clone()),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        assert_eq!(
+            positions,
+            BTreeMap::from([
+                (String::from("a"), 3),
+                (String::from("b"), 2),
+                (String::from("dog"), 1),
+                (String::from("the"), 0),
+            ])
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "the: 2\ndog: 1\nb: 2\na: 1\n"
+        );
+    }
+
+    #[test]
+    fn test_first_seen() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut first_lines = BTreeMap::new();
+        let mut first_seen = FirstSeen::new(&config, 0, &mut first_lines);
+
+        // Act
+        first_seen.line_read("a b");
+        first_seen.line_read("b c");
+        let output_config = OutputConfig {
+            first_lines: Some(first_lines),
+            ..Default::default()
+        };
+        let json = OutputConfig {
+            format: OutputFormat::JsonLines,
+            top: Some(1),
+            ..output_config.clone()
+        };
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 2),
+            (String::from("c"), 1),
+        ]);
+        let mut text_out = Vec::new();
+        let mut json_out = Vec::new();
+        write_counts(&mut text_out, &word_counts, &output_config).unwrap();
+        write_counts(&mut json_out, &word_counts, &json).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text_out).unwrap(),
+            "a: 1 (first seen: line 1)\nb: 2 (first seen: line 1)\nc: 1 (first seen: line 2)\n"
+        );
+        assert_eq!(
+            String::from_utf8(json_out).unwrap(),
+            "{\"word\":\"a\",\"count\":1,\"first_line\":1}\n"
+        );
+    }
+
+    #[test]
+    fn test_position_weights() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut weights = BTreeMap::new();
+        let mut position_weights = PositionWeights::new(&config, 0, &mut weights);
+
+        // Act
+        position_weights.line_read("early both");
+        for _ in 2..1000 {
+            position_weights.line_read("");
+        }
+        position_weights.line_read("late both");
+
+        // Assert
+        assert_eq!(weights["early"], 1.0);
+        assert_eq!(weights["late"], 1.0 / (1.0 + 1000f64.ln()));
+        assert!(weights["early"] > weights["late"]);
+        assert_eq!(weights["both"], weights["early"] + weights["late"]);
+    }
+
+    #[test]
+    fn test_write_weighted_counts() {
+        // Arrange
+        let weights = BTreeMap::from([
+            (String::from("a"), 0.5),
+            (String::from("b"), 1.25),
+            (String::from("c"), 1.0 / 3.0),
+        ]);
+        let text = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            top: Some(2),
+            ..Default::default()
+        };
+        let csv = OutputConfig {
+            format: OutputFormat::Csv,
+            ..Default::default()
+        };
+        let html = OutputConfig {
+            format: OutputFormat::Html,
+            ..Default::default()
+        };
+        let mut text_out = Vec::new();
+        let mut csv_out = Vec::new();
+
+        // Act
+        write_weighted_counts(&mut text_out, &weights, &text).unwrap();
+        write_weighted_counts(&mut csv_out, &weights, &csv).unwrap();
+        let unsupported = write_weighted_counts(&mut Vec::new(), &weights, &html);
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text_out).unwrap(),
+            "b: 1.2500\na: 0.5000\n"
+        );
+        assert_eq!(
+            String::from_utf8(csv_out).unwrap(),
+            "word,weight\na,0.5\nb,1.25\nc,0.3333333333333333\n"
+        );
+        assert_eq!(unsupported.unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_concordance_pads_the_line_ends() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            strip_punct: true,
+            ..Default::default()
+        };
+        let mut lines = Vec::new();
+        let mut concordance = Concordance::new(&config, "cat", 2, &mut lines);
+
+        // Act
+        concordance.line_read("Cat, said the dog to the cat");
+        concordance.line_read("a cat");
+        concordance.line_read("no match here");
+
+        // Assert
+        let fields = |fields: [&str; 2]| fields.map(String::from).to_vec();
+        assert_eq!(
+            lines,
+            [
+                KwicLine {
+                    left: fields(["", ""]),
+                    keyword: String::from("Cat,"),
+                    right: fields(["said", "the"]),
+                },
+                KwicLine {
+                    left: fields(["to", "the"]),
+                    keyword: String::from("cat"),
+                    right: fields(["", ""]),
+                },
+                KwicLine {
+                    left: fields(["", "a"]),
+                    keyword: String::from("cat"),
+                    right: fields(["", ""]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_context_diversity_counts_repeated_contexts_once() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            ..Default::default()
+        };
+        let mut contexts = WordContexts::default();
+        let mut diversity = ContextDiversity::new(&config, "cat", 1, &mut contexts);
+
+        // Act
+        diversity.line_read("the cat sat");
+        diversity.line_read("a cat ran");
+        diversity.line_read("The Cat sat down");
+        diversity.line_read("cat food");
+        diversity.line_read("no match here");
+
+        // Assert
+        assert_eq!(contexts.occurrences, 4);
+        assert_eq!(contexts.contexts.len(), 3);
+        assert_eq!(contexts.diversity(), 0.75);
+    }
+
+    #[test]
+    fn test_write_concordance() {
+        // Arrange
+        let line = |left: [&str; 2], keyword: &str, right: [&str; 2]| KwicLine {
+            left: left.map(String::from).to_vec(),
+            keyword: keyword.to_string(),
+            right: right.map(String::from).to_vec(),
+        };
+        let lines = [
+            line(["to", "the"], "cat", ["", ""]),
+            line(["", ""], "Cat,", ["said", "the"]),
+            line(["", "a"], "cat", ["sat", ""]),
+        ];
+        let mut out = Vec::new();
+
+        // Act
+        write_concordance(&mut out, &lines).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "        Cat,  said the\n     a  cat  sat\nto the  cat\n"
+        );
+    }
+
+    #[test]
+    fn test_vocabulary_growth() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let counted = BTreeMap::from([(String::from("the"), 3)]);
+        let mut points = Vec::new();
+        let mut word_counts = BTreeMap::new();
+        let input = "the cat\n\nthe dog\nsaw a cat\nthe cat\n";
+
+        // Act
+        let mut growth = VocabularyGrowth::new(&counted, 3, &mut points);
+        count_reader_observed(
+            io::Cursor::new(input),
+            &config,
+            &mut word_counts,
+            &mut growth,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(points, [(5, 2), (7, 3), (10, 5), (12, 5)]);
+        assert!(points
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 <= pair[1].1));
+    }
+
+    #[test]
+    fn test_heaps_fit() {
+        // Act
+        let fit = heaps_fit(&[(0, 0), (1, 1), (100, 10)]).unwrap();
+
+        // Assert
+        assert!((fit.k - 1.0).abs() < 1e-9);
+        assert!((fit.beta - 0.5).abs() < 1e-9);
+        assert_eq!(heaps_fit(&[(10, 5), (10, 5)]), None);
+        assert_eq!(heaps_fit(&[]), None);
+    }
+
+    #[test]
+    fn test_cooccurrences() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut pairs = BTreeMap::new();
+        let mut all_pairs = BTreeMap::new();
+
+        // Act
+        Cooccurrences::new(&config, 2, &mut pairs).line_read("c b a");
+        Cooccurrences::new(&config, 5, &mut all_pairs).line_read("c b a");
+        let mut out = Vec::new();
+        write_cooccurrences(&mut out, &pairs).unwrap();
+
+        // Assert
+        let pair = |a: &str, b: &str| (String::from(a), String::from(b));
+        assert_eq!(
+            pairs,
+            BTreeMap::from([(pair("a", "b"), 1), (pair("b", "c"), 1)])
+        );
+        assert_eq!(
+            all_pairs,
+            BTreeMap::from([
+                (pair("a", "b"), 1),
+                (pair("a", "c"), 1),
+                (pair("b", "c"), 1)
+            ])
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), "a\tb\t1\nb\tc\t1\n");
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let lines = ["", "  ", "a b", "c", "", "\t", "", "d e f", ""];
+        let mut words = Vec::new();
+        let mut unterminated = Vec::new();
+
+        // Act
+        let mut paragraphs = Paragraphs::new(&config, &mut words);
+        for line in lines {
+            paragraphs.line_read(line);
+        }
+        paragraphs.finish();
+        let mut paragraphs = Paragraphs::new(&config, &mut unterminated);
+        for line in ["a", "", "b c"] {
+            paragraphs.line_read(line);
+        }
+        paragraphs.finish();
+
+        // Assert
+        assert_eq!(words, vec![3, 3]);
+        assert_eq!(unterminated, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_paragraphs_span_chunks() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let mut words = Vec::new();
+        let mut paragraphs = Paragraphs::new(&config, &mut words);
+
+        // Act
+        count_reader_observed(
+            io::Cursor::new("\n\na b\nc\n\n\n\nd\n\n"),
+            &config,
+            &mut BTreeMap::new(),
+            &mut paragraphs,
+        )
+        .unwrap();
+        paragraphs.finish();
+
+        // Assert
+        assert_eq!(words, vec![3, 1]);
+    }
+
+    #[test]
+    fn test_windows_split_at_boundary() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut windows = Vec::new();
+        let mut observer = Windows::new(&config, 2, 0, &mut windows);
+
+        // Act
+        for line in ["a b", "b", "c", "c d", "e"] {
+            observer.line_read(line);
+        }
+        observer.finish();
+
+        // Assert
+        let ranges: Vec<_> = windows
+            .iter()
+            .map(|w| (w.first_line, w.last_line))
+            .collect();
+        assert_eq!(ranges, vec![(1, 2), (3, 4), (5, 5)]);
+        assert_eq!(
+            windows[0].word_counts,
+            BTreeMap::from([(String::from("a"), 1), (String::from("b"), 2)])
+        );
+        assert_eq!(
+            windows[1].word_counts,
+            BTreeMap::from([(String::from("c"), 2), (String::from("d"), 1)])
+        );
+    }
+
+    #[test]
+    fn test_windows_overlap() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut windows = Vec::new();
+        let mut complete = Vec::new();
+        let lines = ["a", "b", "c", "d", "e"];
+
+        // Act
+        let mut observer = Windows::new(&config, 3, 1, &mut windows);
+        for line in lines {
+            observer.line_read(line);
+        }
+        observer.finish();
+        let mut observer = Windows::new(&config, 3, 2, &mut complete);
+        for line in lines {
+            observer.line_read(line);
+        }
+        observer.finish();
+
+        // Assert
+        let ranges: Vec<_> = windows
+            .iter()
+            .map(|w| (w.first_line, w.last_line))
+            .collect();
+        assert_eq!(ranges, vec![(1, 3), (3, 5)]);
+        assert_eq!(windows[1].word_counts.get("c"), Some(&1));
+        let ranges: Vec<_> = complete
+            .iter()
+            .map(|w| (w.first_line, w.last_line))
+            .collect();
+        assert_eq!(ranges, vec![(1, 3), (2, 4), (3, 5)]);
+    }
+
+    #[test]
+    fn test_porter_stem() {
+        // Arrange
+        let cases = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("cats", "cat"),
+            ("agreed", "agre"),
+            ("plastered", "plaster"),
+            ("conflated", "conflat"),
+            ("hopping", "hop"),
+            ("falling", "fall"),
+            ("filing", "file"),
+            ("happy", "happi"),
+            ("relational", "relat"),
+            ("generalizations", "gener"),
+            ("hopefulness", "hope"),
+            ("adjustment", "adjust"),
+            ("adoption", "adopt"),
+            ("controlling", "control"),
+            ("Running", "Running"),
+            ("as", "as"),
+        ];
+
+        // Act
+        let stems: Vec<String> = cases.iter().map(|(word, _)| porter_stem(word)).collect();
+
+        // Assert
+        let expected: Vec<&str> = cases.iter().map(|(_, stem)| *stem).collect();
+        assert_eq!(stems, expected);
+    }
+
+    #[cfg(feature = "transliterate")]
+    #[test]
+    fn test_transliterate_gost() {
+        // Arrange
+        let cases = [
+            ("слово", "slovo"),
+            ("Москва", "Moskva"),
+            ("щёлочь", "shcheloch"),
+            ("ЖУК", "ZhUK"),
+            ("объём", "obieem"),
+            ("word", "word"),
+            ("café", "café"),
+        ];
+
+        // Act
+        let transliterated: Vec<String> = cases
+            .iter()
+            .map(|(word, _)| transliterate_gost(word))
+            .collect();
+
+        // Assert
+        let expected: Vec<&str> = cases.iter().map(|(_, latin)| *latin).collect();
+        assert_eq!(transliterated, expected);
+    }
+
+    #[cfg(feature = "transliterate")]
+    #[test]
+    fn test_process_chunk_transliterate() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            strip_punct: true,
+            transliterate: Some(TranslitScheme::Gost),
+            stopwords: HashSet::from([String::from("i")]),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &["Слово и слово, word."],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([(String::from("slovo"), 2), (String::from("word"), 1)])
+        );
+    }
+
+    #[test]
+    fn test_process_chunk_stem() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            strip_punct: true,
+            stem: Some(StemLang::En),
+            stopwords: HashSet::from([String::from("runner")]),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &["Run, runs, running and runner, ran the runners."],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        // "runner" is not an inflection of "run", so Porter keeps it
+        assert_eq!(word_counts.get("run"), Some(&3));
+        assert_eq!(word_counts.get("runner"), None);
+        assert_eq!(word_counts.get("ran"), Some(&1));
+    }
+
+    #[test]
+    fn test_builtin_stopwords() {
+        // Act
+        let english = builtin_stopwords(StopwordLang::En, false);
+        let german = builtin_stopwords(StopwordLang::De, true);
+
+        // Assert
+        assert!(english.contains("the") && english.contains("The"));
+        assert!(german.contains("und") && !german.contains("Und"));
+        for lang in [StopwordLang::Fr, StopwordLang::Es] {
+            assert!(builtin_stopwords(lang, true).contains("de"));
+        }
+    }
+
+    #[test]
+    fn test_skip_long_lines() {
+        // Arrange
+        let long = "x".repeat(1_000_000);
+        let input = format!("a b\n{}\nb\n", long);
+        let config = WordCountConfig {
+            sleep: None,
+            skip_long_lines: Some(10_000),
+            ..Default::default()
+        };
+        struct Longest(usize);
+        impl ChunkObserver for Longest {
+            fn chunk_done(
+                &mut self,
+                _totals: &Totals,
+                _word_counts: &BTreeMap<String, usize>,
+            ) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn line_read(&mut self, line: &str) {
+                self.0 = self.0.max(line.len());
+            }
+        }
+        let mut word_counts = BTreeMap::new();
+        let mut longest = Longest(0);
+
+        // Act
+        let totals = count_reader_observed(
+            io::Cursor::new(&input),
+            &config,
+            &mut word_counts,
+            &mut longest,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([(String::from("a"), 1), (String::from("b"), 2)])
+        );
+        assert_eq!(totals.words, 3);
+        assert_eq!(totals.lines, 3);
+        assert_eq!(longest.0, 3);
+    }
+
+    #[test]
+    fn test_is_longer_than() {
+        assert!(is_longer_than("abcd", 3));
+        assert!(!is_longer_than("abc", 3));
+        // 3 chars in 6 bytes
+        assert!(!is_longer_than("äöü", 3));
+        assert!(is_longer_than("äöü", 2));
+    }
+
+    #[test]
+    fn test_word_lengths() {
+        // Arrange
+        let config = WordCountConfig {
+            strip_punct: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+        process_chunk(
+            &["The quick brown fox jumps over the lazy dog."],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Act
+        let lengths = word_lengths(&word_counts);
+        let empty = word_lengths(&BTreeMap::new());
+
+        // Assert
+        // 3 + 5 + 5 + 3 + 5 + 4 + 3 + 4 + 3 = 35 chars in 9 words
+        assert_eq!(lengths.mean, 35.0 / 9.0);
+        assert_eq!(lengths.median, 4.0);
+        assert_eq!(lengths.mode, 3);
+        assert_eq!(empty.mean, 0.0);
+    }
+
+    #[test]
+    fn test_normalize_unicode() {
+        // Arrange
+        let precomposed = "caf\u{E9}";
+        let decomposed = "cafe\u{301}";
+        let config = WordCountConfig {
+            unicode_form: Some(UnicodeForm::Nfc),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+        let mut unnormalized = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &[precomposed, decomposed],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+        process_chunk(
+            &[precomposed, decomposed],
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut unnormalized,
+        );
+
+        // Assert
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([(String::from(precomposed), 2)])
+        );
+        assert_eq!(unnormalized.len(), 2);
+        assert_eq!(normalize_unicode(precomposed, UnicodeForm::Nfd), decomposed);
+        // the dot below sorts before the circumflex, and both compose
+        assert_eq!(
+            normalize_unicode("a\u{302}\u{323}", UnicodeForm::Nfc),
+            "\u{1EAD}"
+        );
+        assert_eq!(
+            normalize_unicode("\u{1EAD}", UnicodeForm::Nfd),
+            "a\u{323}\u{302}"
+        );
+        assert_eq!(normalize_unicode("ǖ", UnicodeForm::Nfd), "u\u{308}\u{304}");
+        assert_eq!(
+            normalize_unicode("ﬁ½ＡＢ", UnicodeForm::Nfkc),
+            "fi1\u{2044}2AB"
+        );
+        assert_eq!(normalize_unicode("ﬁ", UnicodeForm::Nfc), "ﬁ");
+        assert_eq!(
+            normalize_unicode("한", UnicodeForm::Nfd),
+            "\u{1112}\u{1161}\u{11AB}"
+        );
+        assert_eq!(
+            normalize_unicode("\u{1112}\u{1161}\u{11AB}", UnicodeForm::Nfc),
+            "한"
+        );
+        assert_eq!(normalize_unicode("Ё", UnicodeForm::Nfkd), "Е\u{308}");
+    }
+
+    #[test]
+    fn test_normalize_unicode_angstrom_sign() {
+        // Act
+        let composed = normalize_unicode("\u{212B}", UnicodeForm::Nfc);
+        let decomposed = normalize_unicode("\u{212B}", UnicodeForm::Nfd);
+
+        // Assert
+        assert_eq!(composed, "\u{C5}");
+        assert_eq!(decomposed, "A\u{30A}");
+    }
+
+    #[test]
+    fn test_normalize_unicode_ohm_sign() {
+        // Act
+        let composed = normalize_unicode("\u{2126}", UnicodeForm::Nfc);
+        let decomposed = normalize_unicode("\u{2126}", UnicodeForm::Nfd);
+
+        // Assert
+        assert_eq!(composed, "\u{3A9}");
+        assert_eq!(decomposed, "\u{3A9}");
+    }
+
+    #[test]
+    fn test_normalize_unicode_kelvin_sign() {
+        // Act
+        let composed = normalize_unicode("\u{212A}", UnicodeForm::Nfc);
+        let decomposed = normalize_unicode("\u{212A}", UnicodeForm::Nfd);
+
+        // Assert
+        assert_eq!(composed, "K");
+        assert_eq!(decomposed, "K");
+    }
+
+    #[test]
+    fn test_count_graphemes() {
+        // Arrange
+        let texts = [
+            "",
+            "abc",
+            "e\u{301}",
+            "👨\u{200D}👩\u{200D}👧\u{200D}👦",
+            "👍🏽👍",
+            "🇩🇪🇫🇷🇮",
+            "1\u{FE0F}\u{20E3}",
+            "\r\n\n",
+            "한\u{1100}\u{1161}\u{11A8}",
+            "a\u{200D}b",
+            "👍\u{200D}a",
+            "❤\u{FE0F}\u{200D}🔥",
+        ];
+
+        // Act
+        let counts: Vec<usize> = texts.iter().map(|text| count_graphemes(text)).collect();
+
+        // Assert
+        assert_eq!(counts, [0, 3, 1, 1, 2, 3, 1, 2, 2, 2, 2, 1]);
+        assert_eq!(texts[3].chars().count(), 7);
+    }
+
+    #[test]
+    fn test_word_length_distribution() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 3),
+            (String::from("be"), 1),
+            (String::from("to"), 2),
+            (String::from("über"), 4),
+        ]);
+
+        // Act
+        let lengths = word_length_distribution(&word_counts);
+        let mut text = Vec::new();
+        write_word_length_distribution(&mut text, &lengths, false).unwrap();
+        let mut json = Vec::new();
+        write_word_length_distribution(&mut json, &lengths, true).unwrap();
+
+        // Assert
+        assert_eq!(lengths, BTreeMap::from([(1, 3), (2, 3), (3, 0), (4, 4)]));
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "length 1: 3 occurrences (30.00%)\nlength 2: 3 occurrences (30.00%)\n\
+             length 3: 0 occurrences (0.00%)\nlength 4: 4 occurrences (40.00%)\n"
+        );
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"1\":3,\"2\":3,\"3\":0,\"4\":4}\n"
+        );
+        assert!(word_length_distribution(&BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_words_per_line() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut histogram = BTreeMap::new();
+        let mut observer = WordsPerLine::new(&config, 1.0, &mut histogram);
+
+        // Act
+        for line in ["one two three", "", "one", "one two three four five", "a b"] {
+            observer.line_read(line);
+        }
+        let stats = line_words(&histogram);
+
+        // Assert
+        assert_eq!(
+            histogram,
+            BTreeMap::from([(0, 1), (1, 1), (2, 1), (3, 1), (5, 1)])
+        );
+        assert_eq!(stats.min, 0);
+        assert_eq!(stats.max, 5);
+        assert_eq!(stats.mean, 11.0 / 5.0);
+        assert_eq!(stats.median, 2.0);
+        assert_eq!(line_words(&BTreeMap::new()).mean, 0.0);
+    }
+
+    #[test]
+    fn test_line_reservoir() {
+        // Arrange
+        let lines: Vec<String> = (0..100).map(|i| format!("line {}", i)).collect();
+        let mut all = LineReservoir::new(100, None);
+        let mut sample = LineReservoir::new(10, Some(7));
+        let mut again = LineReservoir::new(10, Some(7));
+        // how often each of ten lines is sampled over many runs
+        let mut hits = [0; 10];
+
+        // Act
+        for line in &lines {
+            all.line_read(line);
+            sample.line_read(line);
+            again.line_read(line);
+        }
+        for seed in 0..2000 {
+            let mut reservoir = LineReservoir::new(2, Some(seed));
+            for i in 0..10 {
+                reservoir.line_read(&i.to_string());
+            }
+            for line in reservoir.into_lines() {
+                hits[line.parse::<usize>().unwrap()] += 1;
+            }
+        }
+
+        // Assert
+        assert_eq!(all.into_lines(), lines);
+        let sample = sample.into_lines();
+        assert_eq!(sample.len(), 10);
+        assert_eq!(sample, again.into_lines());
+        let positions: Vec<usize> = sample
+            .iter()
+            .map(|line| lines.iter().position(|l| l == line).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        // every line is expected in 400 of the 2000 samples of 2 out of 10
+        assert!(
+            hits.iter().all(|&hit| (300..500).contains(&hit)),
+            "{:?}",
+            hits
+        );
+    }
+
+    #[test]
+    fn test_new_words_per_line() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut lines = NewWordLines::default();
+        let mut bucketed = NewWordLines::bucketed(2);
+        let input = ["the cat sat", "the cat sat", "a cat a dog", ""];
+
+        // Act
+        let mut observer = NewWordsPerLine::new(&config, &mut lines);
+        for line in input {
+            observer.line_read(line);
+        }
+        let mut observer = NewWordsPerLine::new(&config, &mut bucketed);
+        for line in input {
+            observer.line_read(line);
+        }
+
+        // Assert
+        assert_eq!(lines.lines, [3, 0, 2, 0]);
+        assert_eq!(lines.mean(), 5.0 / 4.0);
+        assert_eq!(lines.median(), 1.0);
+        // the lines with more than one new word all end up in the last bucket
+        assert_eq!(bucketed.buckets, [2, 2]);
+        assert_eq!(bucketed.mean(), 5.0 / 4.0);
+        assert_eq!(bucketed.median(), 0.5);
+        assert_eq!(NewWordLines::default().median(), 0.0);
+    }
+
+    #[test]
+    fn test_extracted_tokens_keep_whitespace() {
+        // Arrange
+        let config = WordCountConfig {
+            extract_urls: true,
+            chars: true,
+            ..Default::default()
+        };
+        let mut chars = Vec::new();
+
+        // Act
+        for_each_token("a\t\thttp://x.io b", &config, |c| chars.push(c));
+
+        // Assert
+        assert_eq!(chars, ["a", "\t", "\t", " ", "b"]);
+    }
+
+    #[test]
+    fn test_extracted_tokens() {
+        // Arrange
+        let config = WordCountConfig {
+            extract_urls: true,
+            extract_emails: true,
+            sleep: None,
+            ..Default::default()
+        };
+        let text = "http://example.com hello\nmail (me@example.org), see HTTPS://x.io/a.\n\
+                    http://example.com @home a@b\n";
+        let mut word_counts = BTreeMap::new();
+        let mut url_counts = BTreeMap::new();
+        let mut email_counts = BTreeMap::new();
+
+        // Act
+        let mut observer = ExtractedTokens::new(&config, &mut url_counts, &mut email_counts);
+        count_reader_observed(
+            io::Cursor::new(text),
+            &config,
+            &mut word_counts,
+            &mut observer,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([
+                (String::from("@home"), 1),
+                (String::from("a@b"), 1),
+                (String::from("hello"), 1),
+                (String::from("mail"), 1),
+                (String::from("see"), 1),
+            ])
+        );
+        assert_eq!(
+            url_counts,
+            BTreeMap::from([
+                (String::from("HTTPS://x.io/a"), 1),
+                (String::from("http://example.com"), 2),
+            ])
+        );
+        assert_eq!(
+            email_counts,
+            BTreeMap::from([(String::from("me@example.org"), 1)])
+        );
+    }
+
+    #[test]
+    fn test_words_per_line_sample() {
+        // Arrange
+        let config = WordCountConfig {
+            hash_seed: Some(7),
+            ..Default::default()
+        };
+        let mut first = BTreeMap::new();
+        let mut second = BTreeMap::new();
+
+        // Act
+        for histogram in [&mut first, &mut second] {
+            let mut observer = WordsPerLine::new(&config, 0.25, histogram);
+            for _ in 0..1000 {
+                observer.line_read("a b");
+            }
+        }
+
+        // Assert
+        // the same seed samples the same lines, about a quarter of them
+        assert_eq!(first, second);
+        let sampled = first[&2];
+        assert!((150..350).contains(&sampled), "{} lines sampled", sampled);
+    }
+
+    #[test]
+    fn test_entropy_of_uniform_distribution() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 3),
+            (String::from("b"), 3),
+            (String::from("c"), 3),
+            (String::from("d"), 3),
+        ]);
+
+        // Act
+        let entropy = entropy(&word_counts);
+
+        // Assert
+        assert!((entropy.bits - 2.0).abs() < 1e-12);
+        assert!((entropy.normalized - 1.0).abs() < 1e-12);
+        assert!((entropy.perplexity - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_entropy_of_skewed_distribution() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a"), 3), (String::from("b"), 1)]);
+
+        // Act
+        let skewed = entropy(&word_counts);
+        let single = entropy(&BTreeMap::from([(String::from("a"), 5)]));
+
+        // Assert
+        // -(0.75 log2 0.75 + 0.25 log2 0.25)
+        assert!((skewed.bits - 0.811_278_124_459_132_8).abs() < 1e-12);
+        assert!(skewed.normalized < 1.0);
+        assert_eq!(single.bits, 0.0);
+        assert_eq!(single.normalized, 0.0);
+        assert_eq!(single.perplexity, 1.0);
+    }
+
+    #[test]
+    fn test_word_lengths_even_median_and_tied_mode() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("ab"), 2),
+            (String::from("abcd"), 2),
+            (String::from("é"), 1),
+            (String::from("abcdef"), 1),
+        ]);
+
+        // Act
+        let lengths = word_lengths(&word_counts);
+
+        // Assert
+        assert_eq!(lengths.median, 3.0);
+        assert_eq!(lengths.mode, 2);
+        assert_eq!(lengths.mean, 19.0 / 6.0);
+    }
+
+    #[test]
+    fn test_write_stats_paragraphs() {
+        // Arrange
+        let totals = Totals {
+            words: 6,
+            ..Default::default()
+        };
+        let output_config = OutputConfig {
+            paragraphs: Some(vec![3, 1, 2]),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_stats(&mut out, &totals, &BTreeMap::new(), &output_config, None).unwrap();
+
+        // Assert
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .ends_with("  paragraphs: 3  avg words/paragraph: 2.00  longest paragraph: 3\n"));
+    }
+
+    #[test]
+    fn test_write_counts_null_separated() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a b"), 2), (String::from("c"), 1)]);
+        let output_config = OutputConfig {
+            null_separated: true,
+            ..Default::default()
+        };
+        let hapax = OutputConfig {
+            legomena: Some(1),
+            ..output_config.clone()
+        };
+        let mut out = Vec::new();
+        let mut hapax_out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+        write_counts(&mut hapax_out, &word_counts, &hapax).unwrap();
+
+        // Assert
+        assert_eq!(out, b"a b\x002\x00c\x001\x00");
+        assert_eq!(hapax_out, b"c\x00");
+    }
+
+    #[test]
+    fn test_ndjson_stream() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let before = BTreeMap::from([(String::from("a"), 5)]);
+        let mut word_counts = BTreeMap::new();
+        let mut out = Vec::new();
+
+        // Act
+        let mut stream = NdjsonStream::new(&mut out, &config, &before);
+        count_reader_observed(
+            io::Cursor::new("a b\nb"),
+            &config,
+            &mut word_counts,
+            &mut stream,
+        )
+        .unwrap();
+        let output_config = OutputConfig {
+            format: OutputFormat::Ndjson,
+            ..Default::default()
+        };
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        // "b" is written again once its count grows
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"word\":\"a\",\"count\":6}\n\
+             {\"word\":\"b\",\"count\":1}\n\
+             {\"word\":\"b\",\"count\":2}\n\
+             {\"_summary\":{\"total_words\":3,\"unique_words\":2}}\n"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_stream_skips_dropped_words() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            max_unique_words: Some(1),
+            ..Default::default()
+        };
+        let before = BTreeMap::new();
+        let mut word_counts = BTreeMap::new();
+        let mut out = Vec::new();
+
+        // Act
+        let mut stream = NdjsonStream::new(&mut out, &config, &before);
+        count_reader_observed(
+            io::Cursor::new("a b\nb a"),
+            &config,
+            &mut word_counts,
+            &mut stream,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"word\":\"a\",\"count\":1}\n{\"word\":\"a\",\"count\":2}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_tsv() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a\tb"), 1),
+            (String::from("the"), 3),
+            (String::from("back\\slash"), 1),
+        ]);
+        let plain = OutputConfig {
+            format: OutputFormat::Tsv,
+            sort_order: SortOrder::CountDesc,
+            ..Default::default()
+        };
+        let ranked = OutputConfig {
+            show_rank: true,
+            tsv_header: true,
+            ..plain.clone()
+        };
+        let mut plain_out = Vec::new();
+        let mut ranked_out = Vec::new();
+
+        // Act
+        write_counts(&mut plain_out, &word_counts, &plain).unwrap();
+        write_counts(&mut ranked_out, &word_counts, &ranked).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(plain_out).unwrap(),
+            "the\t3\na\\tb\t1\nback\\\\slash\t1\n"
+        );
+        assert_eq!(
+            String::from_utf8(ranked_out).unwrap(),
+            "rank\tword\tcount\n1\tthe\t3\n2\ta\\tb\t1\n2\tback\\\\slash\t1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_fixed_width() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("straße"), 12),
+            (String::from("日本語"), 3),
+            (String::from("über"), 1),
+        ]);
+        let text = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            word_width: Some(5),
+            truncation_marker: String::from("…"),
+            count_width: Some(3),
+            ..Default::default()
+        };
+        let tsv = OutputConfig {
+            format: OutputFormat::Tsv,
+            truncation_marker: String::from(">"),
+            ..text.clone()
+        };
+        let mut text_out = Vec::new();
+        let mut tsv_out = Vec::new();
+
+        // Act
+        write_counts(&mut text_out, &word_counts, &text).unwrap();
+        write_counts(&mut tsv_out, &word_counts, &tsv).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text_out).unwrap(),
+            "stra…:  12\n日本…:   3\nüber :   1\n"
+        );
+        assert_eq!(
+            String::from_utf8(tsv_out).unwrap(),
+            "stra>\t 12\n日本>\t  3\nüber \t  1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_template_placeholders() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("the"), 3), (String::from("größe"), 1)]);
+        let templated = |template: &str| OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            template: Some(String::from(template)),
+            ..Default::default()
+        };
+        let templates = [
+            ("{word}", "the\ngröße\n"),
+            ("{count}", "3\n1\n"),
+            ("{rank}", "1\n2\n"),
+            ("{freq_pmw}", "750000.00\n250000.00\n"),
+            ("{length}", "3\n5\n"),
+            (
+                "{rank}. {word} ({count}, {length} chars, {freq_pmw} pmw) {other}",
+                "1. the (3, 3 chars, 750000.00 pmw) {other}\n\
+                 2. größe (1, 5 chars, 250000.00 pmw) {other}\n",
+            ),
+        ];
+
+        // Act
+        let written: Vec<String> = templates
+            .iter()
+            .map(|(template, _)| {
+                let mut out = Vec::new();
+                write_counts(&mut out, &word_counts, &templated(template)).unwrap();
+                String::from_utf8(out).unwrap()
+            })
+            .collect();
+
+        // Assert
+        let expected: Vec<&str> = templates.iter().map(|(_, expected)| *expected).collect();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_unknown_placeholders() {
+        // Act
+        let unknown = unknown_placeholders("{{word}} {Count} {} {rank} {freq_pmw");
+
+        // Assert
+        assert_eq!(unknown, ["Count", ""]);
+    }
+
+    #[test]
+    fn test_fit_width() {
+        // Arrange
+        let cases = [
+            ("ab", 4, "…", "ab  "),
+            ("abcd", 4, "…", "abcd"),
+            ("ñandú", 3, "…", "ña…"),
+            ("😀😀😀", 2, "", "😀"),
+            ("日本語", 4, "…", "日… "),
+            ("e\u{301}té", 3, "…", "e\u{301}té"),
+            ("abcdef", 2, "...", ".."),
+        ];
+
+        // Act
+        let fitted: Vec<String> = cases
+            .iter()
+            .map(|(word, width, marker, _)| fit_width(word, *width, marker))
+            .collect();
+
+        // Assert
+        let expected: Vec<&str> = cases.iter().map(|case| case.3).collect();
+        assert_eq!(fitted, expected);
+    }
+
+    #[test]
+    fn test_display_width() {
+        // Arrange
+        let texts = [
+            "abc",
+            "日本語",
+            "한국",
+            "ｆｕｌｌ",
+            "😀!",
+            "e\u{301}",
+            "a\u{200B}b",
+            "",
+        ];
+
+        // Act
+        let widths: Vec<usize> = texts.iter().map(|text| display_width(text)).collect();
+
+        // Assert
+        assert_eq!(widths, [3, 6, 4, 8, 3, 1, 2, 0]);
+    }
+
+    /// Undo `escape_xml`, the way an XML parser reads an attribute value
+    fn unescape_xml(text: &str) -> String {
+        let entities = Regex::new("&(amp|lt|gt|quot|apos|#(\\d+));").unwrap();
+        entities
+            .replace_all(text, |captures: &regex::Captures| {
+                match (&captures[1], captures.get(2)) {
+                    (_, Some(code)) => char::from_u32(code.as_str().parse().unwrap())
+                        .unwrap()
+                        .to_string(),
+                    ("amp", _) => String::from("&"),
+                    ("lt", _) => String::from("<"),
+                    ("gt", _) => String::from(">"),
+                    ("quot", _) => String::from("\""),
+                    _ => String::from("'"),
+                }
+            })
+            .into_owned()
+    }
+
+    #[test]
+    fn test_write_counts_xml() {
+        // Arrange
+        let words = ["a&b", "<tag>", "\"quoted\"", "it's", "tab\there", "plain"];
+        let word_counts: BTreeMap<String, usize> = words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| (word.to_string(), index + 1))
+            .collect();
+        let output_config = OutputConfig {
+            format: OutputFormat::Xml,
+            sort_order: SortOrder::CountDesc,
+            ..Default::default()
+        };
+        let indented = OutputConfig {
+            xml_indent: true,
+            ..output_config.clone()
+        };
+        let mut out = Vec::new();
+        let mut indented_out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+        write_counts(&mut indented_out, &word_counts, &indented).unwrap();
+
+        // Assert
+        let xml = String::from_utf8(out).unwrap();
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <word_counts total_words=\"21\" unique_words=\"6\"><word value=\"plain\" \
+             count=\"6\" rank=\"1\"/>"
+        ));
+        assert!(xml.ends_with("/></word_counts>\n"));
+        let element =
+            Regex::new(r#"<word value="([^"<>]*)" count="(\d+)" rank="(\d+)"/>"#).unwrap();
+        let parsed: Vec<(String, usize, usize)> = element
+            .captures_iter(&xml)
+            .map(|captures| {
+                (
+                    unescape_xml(&captures[1]),
+                    captures[2].parse().unwrap(),
+                    captures[3].parse().unwrap(),
+                )
+            })
+            .collect();
+        let expected: Vec<(String, usize, usize)> = words
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, word)| (word.to_string(), index + 1, words.len() - index))
+            .collect();
+        assert_eq!(parsed, expected);
+        let indented_xml = String::from_utf8(indented_out).unwrap();
+        assert!(indented_xml.contains("\">\n  <word value=\"plain\" count=\"6\" rank=\"1\"/>\n"));
+        assert!(indented_xml.ends_with("/>\n</word_counts>\n"));
+    }
+
+    #[test]
+    fn test_write_counts_html() {
+        // Arrange
+        let word_counts =
+            BTreeMap::from([(String::from("cloud"), 3), (String::from("</script>"), 1)]);
+        let output_config = OutputConfig {
+            format: OutputFormat::Html,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        let html = String::from_utf8(out).unwrap();
+        let data = html
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("const words = "))
+            .and_then(|data| data.strip_suffix(';'))
+            .unwrap();
+        let words: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(
+            words,
+            serde_json::json!([
+                {"word": "cloud", "count": 3},
+                {"word": "</script>", "count": 1},
+            ])
+        );
+        assert_eq!(html.matches("</script>").count(), 1);
+    }
+
+    #[test]
+    fn test_mattr() {
+        // Arrange
+        let config = WordCountConfig::default();
+        let mut mattr = Mattr::new(&config, 2);
+        let mut short = Mattr::new(&config, 10);
+
+        // Act
+        mattr.line_read("a a b");
+        mattr.line_read("a");
+        short.line_read("a a b");
+
+        // Assert
+        // the windows "a a", "a b" and "b a" have ratios 0.5, 1 and 1
+        assert!((mattr.value() - 2.5 / 3.0).abs() < 1e-9);
+        assert!((short.value() - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_write_counts_normalized() {
+        // Arrange
+        let word_counts =
+            BTreeMap::from([(String::from("common"), 999_999), (String::from("rare"), 1)]);
+        let text = OutputConfig {
+            normalize_by: Some(1_000_000),
+            ..Default::default()
+        };
+        let json = OutputConfig {
+            format: OutputFormat::Json,
+            ..text.clone()
+        };
+        let mut text_out = Vec::new();
+        let mut json_out = Vec::new();
+
+        // Act
+        write_counts(&mut text_out, &word_counts, &text).unwrap();
+        write_counts(&mut json_out, &word_counts, &json).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(text_out).unwrap(),
+            "common: 999999.00\nrare: 1.00\n"
+        );
+        assert_eq!(
+            String::from_utf8(json_out).unwrap(),
+            "[{\"word\":\"common\",\"count\":999999,\"freq_pmw\":999999.0},{\"word\":\"rare\",\"count\":1,\"freq_pmw\":1.0}]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_log_scale() {
+        // Arrange
+        let word_counts =
+            BTreeMap::from([(String::from("common"), 100), (String::from("rare"), 1)]);
+        let log10 = OutputConfig {
+            log_scale: Some(LogBase::Ten),
+            ..Default::default()
+        };
+        let ln = OutputConfig {
+            log_scale: Some(LogBase::E),
+            format: OutputFormat::Json,
+            ..Default::default()
+        };
+        let mut log10_out = Vec::new();
+        let mut ln_out = Vec::new();
+
+        // Act
+        write_counts(&mut log10_out, &word_counts, &log10).unwrap();
+        write_counts(&mut ln_out, &word_counts, &ln).unwrap();
+
+        // Assert
+        assert_eq!(log10.log_count(1), Some(0.0));
+        assert_eq!(log10.log_count(100), Some(2.0));
+        assert_eq!(
+            String::from_utf8(log10_out).unwrap(),
+            "common: 2.0000\nrare: 0.0000\n"
+        );
+        assert_eq!(
+            String::from_utf8(ln_out).unwrap(),
+            "[{\"word\":\"common\",\"count\":100,\"log_count\":4.6052},{\"word\":\"rare\",\"count\":1,\"log_count\":0.0}]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_histogram() {
+        // Arrange
+        let entries = [("the", 10), ("a", 5), ("cat", 1)];
+        let mut out = Vec::new();
+
+        // Act
+        // 3 columns of words, 2 of counts and 5 around the bar leave 10
+        write_histogram(&mut out, &entries, 20, '#').unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "the  |##########| 10\na    |#####| 5\ncat  |#| 1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_histogram_narrow() {
+        // Arrange
+        let entries = [("hello", 3), ("world", 2)];
+        let mut out = Vec::new();
+
+        // Act
+        write_histogram(&mut out, &entries, 5, '█').unwrap();
+
+        // Assert
+        // even without any room left the longest bar gets a single column
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "hello  |█| 3\nworld  |█| 2\n"
+        );
+    }
+
+    #[test]
+    fn test_write_histogram_wide_words() {
+        // Arrange
+        let entries = [("日本", 2), ("abc", 1)];
+        let mut out = Vec::new();
+
+        // Act
+        // 4 columns of words, 1 of counts and 5 around the bar leave 4
+        write_histogram(&mut out, &entries, 14, '#').unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "日本  |####| 2\nabc   |##| 1\n"
+        );
+    }
+
+    #[test]
+    fn test_estimate_map_bytes() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("ab"), 1), (String::from("cde"), 7)]);
+
+        // Act
+        let bytes = estimate_map_bytes(&word_counts);
+
+        // Assert
+        let entry = mem::size_of::<usize>() + BTREEMAP_NODE_OVERHEAD;
+        assert_eq!(bytes, 5 + 2 * entry);
+        assert_eq!(estimate_map_bytes(&BTreeMap::new()), 0);
+    }
+
+    #[test]
+    fn test_stats_json() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a"), 3), (String::from("b"), 1)]);
+        let totals = Totals {
+            words: 4,
+            sentences: 2,
+            ..Default::default()
+        };
+        let output_config = OutputConfig {
+            words_per_sentence: true,
+            word_lengths: true,
+            ..Default::default()
+        };
+
+        // Act
+        let stats = stats_json(&totals, &word_counts, &output_config, None);
+
+        // Assert
+        assert_eq!(
+            serde_json::Value::Object(stats).to_string(),
+            "{\"avg_word_length\":{\"mean\":1.0,\"median\":1.0,\"mode\":1},\
+             \"avg_words_per_sentence\":2.0,\"total_words\":4,\"ttr\":0.5,\"unique_words\":2}"
+        );
+    }
+
+    #[test]
+    fn test_write_stats() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 3),
+            (String::from("b"), 1),
+            (String::from("c"), 2),
+        ]);
+        let totals = Totals {
+            words: 6,
+            ..Default::default()
+        };
+        let filtered = OutputConfig {
+            min_count: Some(2),
+            ..Default::default()
+        };
+        let mut plain_out = Vec::new();
+        let mut filtered_out = Vec::new();
+
+        // Act
+        write_stats(
+            &mut plain_out,
+            &totals,
+            &word_counts,
+            &OutputConfig::default(),
+            None,
+        )
+        .unwrap();
+        write_stats(
+            &mut filtered_out,
+            &totals,
+            &word_counts,
+            &filtered,
+            Some(0.75),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(plain_out).unwrap(),
+            "total words: 6  unique words: 3  TTR: 0.5000\n"
+        );
+        assert_eq!(
+            String::from_utf8(filtered_out).unwrap(),
+            "total words: 6  unique words: 3  unique words after filtering: 2  TTR: 0.5000  MATTR: 0.7500\n"
+        );
+    }
+
+    #[test]
+    fn test_process_chunk_chars() {
+        // Arrange
+        let config = WordCountConfig {
+            chars: true,
+            ..Default::default()
+        };
+        let mut the_counts = BTreeMap::new();
+        let mut three_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&["the"], &config, &mut VecDeque::new(), &mut the_counts);
+        process_chunk(&["three"], &config, &mut VecDeque::new(), &mut three_counts);
+
+        // Assert
+        assert_eq!(the_counts.get("e"), Some(&1));
+        assert_eq!(three_counts.get("e"), Some(&2));
+    }
+
+    #[test]
+    fn test_process_chunk_chars_whitespace() {
+        // Arrange
+        let chunk = vec!["a b\tb"];
+        let with_whitespace = WordCountConfig {
+            chars: true,
+            ..Default::default()
+        };
+        let without_whitespace = WordCountConfig {
+            skip_whitespace_chars: true,
+            ..with_whitespace.clone()
+        };
+        let mut with_counts = BTreeMap::new();
+        let mut without_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &chunk,
+            &with_whitespace,
+            &mut VecDeque::new(),
+            &mut with_counts,
+        );
+        process_chunk(
+            &chunk,
+            &without_whitespace,
+            &mut VecDeque::new(),
+            &mut without_counts,
+        );
+
+        // Assert
+        assert_eq!(with_counts.get(" "), Some(&1));
+        assert_eq!(with_counts.get("\t"), Some(&1));
+        assert_eq!(with_counts.get("b"), Some(&2));
+        assert_eq!(without_counts.len(), 2);
+    }
+
+    #[test]
+    fn test_process_chunk_bigrams() {
+        // Arrange
+        let chunk = vec!["the cat sat", "on the mat"];
+        let config = WordCountConfig {
+            ngrams: 2,
+            ..Default::default()
+        };
+        let mut window = VecDeque::new();
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let counted = process_chunk(&chunk, &config, &mut window, &mut word_counts);
+
+        // Assert
+        assert_eq!(counted, 5);
+        assert_eq!(word_counts.get("the cat"), Some(&1));
+        // the bigram spanning the line boundary
+        assert_eq!(word_counts.get("sat on"), Some(&1));
+        assert_eq!(word_counts.get("the mat"), Some(&1));
+        assert_eq!(window, VecDeque::from([String::from("mat")]));
+    }
+
+    #[test]
+    fn test_process_chunk_bigrams_with_separator() {
+        // Arrange
+        let chunk = vec!["the cat sat"];
+        let separated = WordCountConfig {
+            ngrams: 2,
+            ngram_join: NgramJoin::Separator(String::from("|")),
+            ..Default::default()
+        };
+        let templated = WordCountConfig {
+            ngram_join: NgramJoin::template("{2}<-{1}").unwrap(),
+            ..separated.clone()
+        };
+        let unigrams = WordCountConfig {
+            ngram_join: NgramJoin::Separator(String::from("|")),
+            ..Default::default()
+        };
+        let mut separated_counts = BTreeMap::new();
+        let mut templated_counts = BTreeMap::new();
+        let mut unigram_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &chunk,
+            &separated,
+            &mut VecDeque::new(),
+            &mut separated_counts,
+        );
+        process_chunk(
+            &chunk,
+            &templated,
+            &mut VecDeque::new(),
+            &mut templated_counts,
+        );
+        process_chunk(&chunk, &unigrams, &mut VecDeque::new(), &mut unigram_counts);
+
+        // Assert
+        let counts = |words: [&str; 2]| -> BTreeMap<String, usize> {
+            words.iter().map(|word| (word.to_string(), 1)).collect()
+        };
+        assert_eq!(separated_counts, counts(["cat|sat", "the|cat"]));
+        assert_eq!(templated_counts, counts(["cat<-the", "sat<-cat"]));
+        assert_eq!(unigram_counts.len(), 3);
+    }
+
+    #[test]
+    fn test_ngram_template() {
+        // Act
+        let template = NgramJoin::template("{1}_{2}_{3}.");
+        let no_token = NgramJoin::template("a_b");
+        let unclosed = NgramJoin::template("{1}_{2");
+        let zero = NgramJoin::template("{0}_{1}");
+
+        // Assert
+        assert_eq!(
+            template,
+            Ok(NgramJoin::Template(vec![
+                TemplatePart::Token(1),
+                TemplatePart::Text(String::from("_")),
+                TemplatePart::Token(2),
+                TemplatePart::Text(String::from("_")),
+                TemplatePart::Token(3),
+                TemplatePart::Text(String::from(".")),
+            ]))
+        );
+        assert_eq!(template.unwrap().max_position(), 3);
+        assert!(no_token.is_err());
+        assert!(unclosed.is_err());
+        assert!(zero.is_err());
+    }
+
+    #[test]
+    fn test_ngrams_span_chunks() {
+        // Arrange
+        let input = "a b\nc\n\nd e\n";
+        let sequential = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            ngrams: 3,
+            ..Default::default()
+        };
+        let parallel = WordCountConfig {
+            parallel: true,
+            ..sequential.clone()
+        };
+        let mut sequential_counts = BTreeMap::new();
+        let mut parallel_counts = BTreeMap::new();
+
+        // Act
+        count_reader(io::Cursor::new(input), &sequential, &mut sequential_counts).unwrap();
+        count_reader(io::Cursor::new(input), &parallel, &mut parallel_counts).unwrap();
+
+        // Assert
+        let expected: BTreeMap<String, usize> = ["a b c", "b c d", "c d e"]
+            .into_iter()
+            .map(|ngram| (ngram.to_string(), 1))
+            .collect();
+        assert_eq!(sequential_counts, expected);
+        assert_eq!(parallel_counts, expected);
+    }
+
+    #[test]
+    fn test_max_unique_words_caps_the_counts() {
+        // Arrange
+        let input = "a b a\nc a d\nb e\n";
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            max_unique_words: Some(2),
+            ..Default::default()
+        };
+        let modes = [
+            config.clone(),
+            WordCountConfig {
+                use_hashmap: true,
+                ..config.clone()
+            },
+            WordCountConfig {
+                parallel: true,
+                ..config.clone()
+            },
+        ];
+        struct MaxLen(usize);
+        impl ChunkObserver for MaxLen {
+            fn chunk_done(
+                &mut self,
+                _totals: &Totals,
+                word_counts: &BTreeMap<String, usize>,
+            ) -> io::Result<()> {
+                self.0 = self.0.max(word_counts.len());
+                Ok(())
+            }
+        }
+        let mut word_counts = BTreeMap::new();
+        let mut max_len = MaxLen(0);
+
+        // Act
+        let totals = count_reader_observed(
+            io::Cursor::new(input),
+            &config,
+            &mut word_counts,
+            &mut max_len,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(max_len.0, 2);
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([(String::from("a"), 3), (String::from("b"), 2)])
+        );
+        assert_eq!(totals.words, 5);
+        assert_eq!(totals.words_dropped, 3);
+        for mode in &modes {
+            let mut counts = BTreeMap::new();
+            let totals = count_reader(io::Cursor::new(input), mode, &mut counts).unwrap();
+            assert!(counts.len() <= 2);
+            assert_eq!(totals.words, counts.values().sum::<usize>());
+            assert_eq!(totals.words + totals.words_dropped, 8);
+        }
+    }
+
+    #[test]
+    fn test_dedup_lines_counts_a_line_once() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 2,
+            sleep: None,
+            ..Default::default()
+        };
+        let exact = WordCountConfig {
+            dedup_lines: Some(LineDedup::Exact),
+            ..config.clone()
+        };
+        let approx = WordCountConfig {
+            dedup_lines: Some(LineDedup::Approx(0.01)),
+            ..config.clone()
+        };
+        let repeated = "the cat\n".repeat(5);
+        let mut once = BTreeMap::new();
+        let mut exact_counts = BTreeMap::new();
+        let mut approx_counts = BTreeMap::new();
+
+        // Act
+        count_reader(io::Cursor::new("the cat\n"), &config, &mut once).unwrap();
+        let totals = count_reader(io::Cursor::new(&repeated), &exact, &mut exact_counts).unwrap();
+        let approx_totals =
+            count_reader(io::Cursor::new(&repeated), &approx, &mut approx_counts).unwrap();
+
+        // Assert
+        assert_eq!(exact_counts, once);
+        assert_eq!(approx_counts, once);
+        assert_eq!(
+            (totals.lines, totals.lines_read, totals.duplicate_lines),
+            (1, 5, 4)
+        );
+        assert_eq!(approx_totals.duplicate_lines, 4);
+    }
+
+    #[test]
+    fn test_line_regex() {
+        // Arrange
+        let text = "INFO user logged in\nDEBUG cache miss\nINFO user logged out\n";
+        let config = WordCountConfig {
+            line_regex: Some(Regex::new("^INFO").unwrap()),
+            sleep: None,
+            ..Default::default()
+        };
+        let inverted = WordCountConfig {
+            line_regex_invert: true,
+            ..config.clone()
+        };
+        let mut matching = BTreeMap::new();
+        let mut others = BTreeMap::new();
+
+        // Act
+        let totals = count_reader(io::Cursor::new(text), &config, &mut matching).unwrap();
+        count_reader(io::Cursor::new(text), &inverted, &mut others).unwrap();
+
+        // Assert
+        assert_eq!(matching.get("user"), Some(&2));
+        assert_eq!(matching.get("cache"), None);
+        assert_eq!((totals.lines, totals.lines_read), (2, 3));
+        assert_eq!(
+            others,
+            BTreeMap::from([
+                (String::from("DEBUG"), 1),
+                (String::from("cache"), 1),
+                (String::from("miss"), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ignore_comments_of_rust_source() {
+        // Arrange
+        let source = "/// Doc comment\n\
+            fn main() { // start here\n\
+            let x = 1; /* inline */ let y = 2;\n\
+            /* a block\n\
+            still inside */ call(x);\n\
+            }\n";
+        let config = WordCountConfig {
+            comment_language: Some(CommentLanguage::Rust),
+            // the block comment spans two chunks
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let totals = count_reader(io::Cursor::new(source), &config, &mut word_counts).unwrap();
+
+        // Assert
+        let words: Vec<&str> = word_counts.keys().map(String::as_str).collect();
+        assert_eq!(
+            words,
+            ["1;", "2;", "=", "call(x);", "fn", "let", "main()", "x", "y", "{", "}"]
+        );
+        assert_eq!(word_counts.get("let"), Some(&2));
+        assert_eq!(totals.lines_read, 6);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        // Arrange
+        let lines = [
+            (CommentLanguage::Python, "x = 1  # one", "x = 1  "),
+            (CommentLanguage::Lua, "a --[[ b ]] c -- d", "a   c "),
+            (
+                CommentLanguage::Html,
+                "<p>Hi <!-- x --><b>you</b></p>",
+                " Hi   you  ",
+            ),
+            (CommentLanguage::Rust, "no comment", "no comment"),
+        ];
+
+        // Act
+        let stripped: Vec<String> = lines
+            .iter()
+            .map(|(language, line, _)| strip_comments(line, *language, &mut false).into_owned())
+            .collect();
+        let mut in_block = false;
+        let opened = strip_comments("<p>a <!-- b", CommentLanguage::Html, &mut in_block);
+        let was_open = in_block;
+        let closed = strip_comments("c --> d", CommentLanguage::Html, &mut in_block);
+
+        // Assert
+        let expected: Vec<&str> = lines.iter().map(|line| line.2).collect();
+        assert_eq!(stripped, expected);
+        assert_eq!((opened.as_ref(), was_open), (" a ", true));
+        assert_eq!((closed.as_ref(), in_block), ("  d", false));
+    }
+
+    #[test]
+    fn test_sample_rate_counts_a_share_of_the_lines() {
+        // Arrange
+        let text: String = (0..1000).map(|i| format!("line {}\n", i)).collect();
+        let config = WordCountConfig {
+            sleep: None,
+            hash_seed: Some(7),
+            ..Default::default()
+        };
+        let sampled = WordCountConfig {
+            sample_rate: Some(0.25),
+            ..config.clone()
+        };
+        let everything = WordCountConfig {
+            sample_rate: Some(1.0),
+            ..config.clone()
+        };
+        let mut expected = BTreeMap::new();
+        let mut all_counts = BTreeMap::new();
+        let mut first = BTreeMap::new();
+        let mut second = BTreeMap::new();
+
+        // Act
+        count_reader(io::Cursor::new(&text), &config, &mut expected).unwrap();
+        count_reader(io::Cursor::new(&text), &everything, &mut all_counts).unwrap();
+        let totals = count_reader(io::Cursor::new(&text), &sampled, &mut first).unwrap();
+        count_reader(io::Cursor::new(&text), &sampled, &mut second).unwrap();
+        let mut scaled = first.clone();
+        scale_counts(&mut scaled, 0.25);
+
+        // Assert
+        assert_eq!(all_counts, expected);
+        // the same seed samples the same lines, about a quarter of them
+        assert_eq!(first, second);
+        assert_eq!(totals.lines_read, 1000);
+        assert!(
+            (150..350).contains(&totals.lines),
+            "{} lines sampled",
+            totals.lines
+        );
+        assert_eq!(first["line"], totals.lines);
+        assert_eq!(scaled["line"], totals.lines * 4);
+    }
+
+    #[test]
+    fn test_every_nth_word_and_line() {
+        // Arrange
+        let text = "a b c d\ne f g h\ni j k l\n";
+        let config = WordCountConfig {
+            // the words are counted on across chunks
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let every_word = WordCountConfig {
+            every_nth_word: Some(1),
+            ..config.clone()
+        };
+        let every_third_word = WordCountConfig {
+            every_nth_word: Some(3),
+            ..config.clone()
+        };
+        let composed = WordCountConfig {
+            every_nth_line: Some(2),
+            every_nth_word: Some(3),
+            ..config.clone()
+        };
+        let mut expected = BTreeMap::new();
+        let mut all_words = BTreeMap::new();
+        let mut thirds = BTreeMap::new();
+        let mut both = BTreeMap::new();
+
+        // Act
+        count_reader(io::Cursor::new(text), &config, &mut expected).unwrap();
+        count_reader(io::Cursor::new(text), &every_word, &mut all_words).unwrap();
+        let totals = count_reader(io::Cursor::new(text), &every_third_word, &mut thirds).unwrap();
+        let both_totals = count_reader(io::Cursor::new(text), &composed, &mut both).unwrap();
+
+        // Assert
+        let words = |counts: &BTreeMap<String, usize>| counts.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(all_words, expected);
+        assert_eq!(words(&thirds), ["a", "d", "g", "j"]);
+        assert_eq!((totals.words, totals.lines), (4, 3));
+        assert_eq!(words(&both), ["a", "d", "k"]);
+        assert_eq!((both_totals.lines, both_totals.lines_read), (2, 3));
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        // Arrange
+        let mut filter = BloomFilter::new(1000, 0.01);
+
+        // Act
+        let inserted: Vec<_> = (0..1000).map(|i| filter.insert(&i.to_string())).collect();
+
+        // Assert
+        // with a 1% false positive rate hardly any new item is mistaken
+        assert!(inserted.iter().filter(|&&new| new).count() > 950);
+        assert!((0..1000).all(|i| !filter.insert(&i.to_string())));
+    }
+
+    #[test]
+    fn test_deadline_stops_reading() {
+        // Arrange
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let totals =
+            count_reader(io::Cursor::new("a b\nc\nd\n"), &config, &mut word_counts).unwrap();
+
+        // Assert
+        // the first round is always counted
+        assert!(totals.timed_out);
+        assert_eq!(totals.lines_read, 1);
+        assert_eq!(word_counts.len(), 2);
+    }
+
+    #[test]
+    fn test_max_memory_stops_adding_words() {
+        // Arrange
+        let input = "a b a\nc a d\nb e\n";
+        let config = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            max_memory: Some(1),
+            ..Default::default()
+        };
+        let hashed = WordCountConfig {
+            use_hashmap: true,
+            ..config.clone()
+        };
+        let mut word_counts = BTreeMap::new();
+        let mut hashed_counts = BTreeMap::new();
+
+        // Act
+        let totals = count_reader(io::Cursor::new(input), &config, &mut word_counts).unwrap();
+        let hashed_totals =
+            count_reader(io::Cursor::new(input), &hashed, &mut hashed_counts).unwrap();
+
+        // Assert
+        // the words of the first line exceed the limit, later ones are dropped
+        let expected = BTreeMap::from([(String::from("a"), 3), (String::from("b"), 2)]);
+        assert_eq!(word_counts, expected);
+        assert_eq!(hashed_counts, expected);
+        assert_eq!((totals.words, totals.words_dropped), (5, 3));
+        assert_eq!((hashed_totals.words, hashed_totals.words_dropped), (5, 3));
+        assert!(estimate_map_bytes(&word_counts) > 1);
+    }
+
+    #[test]
+    fn test_hash_seed_drops_the_same_words() {
+        // Arrange
+        let text: String = (0..200).map(|i| format!("w{}\n", i % 53)).collect();
+        let config = WordCountConfig {
+            sleep: None,
+            use_hashmap: true,
+            hash_seed: Some(7),
+            max_unique_words: Some(5),
+            ..Default::default()
+        };
+        let mut first = BTreeMap::new();
+        let mut second = BTreeMap::new();
+
+        // Act
+        count_reader(io::Cursor::new(&text), &config, &mut first).unwrap();
+        count_reader(io::Cursor::new(&text), &config, &mut second).unwrap();
+
+        // Assert
+        assert_eq!(first.len(), 5);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_use_hashmap_matches_sorted_map() {
+        // Arrange
+        let input = "the cat\nthe dog\n\na cat the\n";
+        let sorted = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let hashed = WordCountConfig {
+            use_hashmap: true,
+            ..sorted.clone()
+        };
+        let hashed_parallel = WordCountConfig {
+            parallel: true,
+            ..hashed.clone()
+        };
+        let mut expected = BTreeMap::new();
+        let mut hashed_counts = BTreeMap::new();
+        let mut parallel_counts = BTreeMap::new();
+
+        // Act
+        let expected_totals = count_reader(io::Cursor::new(input), &sorted, &mut expected).unwrap();
+        let hashed_totals =
+            count_reader(io::Cursor::new(input), &hashed, &mut hashed_counts).unwrap();
+        count_reader(
+            io::Cursor::new(input),
+            &hashed_parallel,
+            &mut parallel_counts,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(hashed_counts, expected);
+        assert_eq!(parallel_counts, expected);
+        assert_eq!(hashed_totals.words, expected_totals.words);
+    }
+
+    #[test]
+    fn test_hash_algorithms_count_the_same() {
+        // Arrange
+        let input = "the cat\nthe extraordinarily long dog\n\na cat the\nnaïve déjà vu\n";
+        let sorted = WordCountConfig {
+            chunk_size: 1,
+            sleep: None,
+            ..Default::default()
+        };
+        let mut expected = BTreeMap::new();
+        count_reader(io::Cursor::new(input), &sorted, &mut expected).unwrap();
+        let fnv_of_a = {
+            let mut hasher = CountHashState::new(HashAlgorithm::Fnv, None).build_hasher();
+            hasher.write(b"a");
+            hasher.finish()
+        };
+
+        // Act
+        let counts: Vec<_> = HashAlgorithm::value_variants()
+            .iter()
+            .flat_map(|&hash_algorithm| {
+                [None, Some(7)].map(|hash_seed| (hash_algorithm, hash_seed))
+            })
+            .map(|(hash_algorithm, hash_seed)| {
+                let config = WordCountConfig {
+                    use_hashmap: true,
+                    hash_algorithm,
+                    hash_seed,
+                    ..sorted.clone()
+                };
+                let mut word_counts = BTreeMap::new();
+                count_reader(io::Cursor::new(input), &config, &mut word_counts).unwrap();
+                word_counts
+            })
+            .collect();
+
+        // Assert
+        assert_eq!(counts.len(), 8);
+        assert!(counts.iter().all(|word_counts| *word_counts == expected));
+        // the published FNV-1a test vector
+        assert_eq!(fnv_of_a, 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        // Arrange
+        let corpus = "the cat\nsat on\n\nthe mat\nthe end\nof it\n";
+        let half = "the cat\nsat on\n\n";
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = dir.path().join("counts.checkpoint");
+        let source = Path::new("corpus.txt");
+        let config = WordCountConfig {
+            chunk_size: 2,
+            sleep: None,
+            ..Default::default()
+        };
+        let mut expected = BTreeMap::new();
+        let expected_totals =
+            count_reader(io::Cursor::new(corpus), &config, &mut expected).unwrap();
+
+        // Act
+        // the interrupted run only gets to see the first half of the corpus
+        let mut writer = CheckpointWriter::new(&checkpoint_path, source, Totals::default());
+        count_reader_observed(
+            io::Cursor::new(half),
+            &config,
+            &mut BTreeMap::new(),
+            &mut writer,
+        )
+        .unwrap();
+        let checkpoint = read_checkpoint(&checkpoint_path).unwrap();
+        let resumed_config = WordCountConfig {
+            skip_lines: checkpoint.totals.lines_read,
+            ..config.clone()
+        };
+        let mut resumed = checkpoint.word_counts;
+        let mut resumed_totals =
+            count_reader(io::Cursor::new(corpus), &resumed_config, &mut resumed).unwrap();
+        resumed_totals += checkpoint.totals;
+
+        // Assert
+        assert_eq!(checkpoint.source, source);
+        assert_eq!(checkpoint.totals.lines_read, 3);
+        assert_eq!(resumed, expected);
+        assert_eq!(resumed_totals, expected_totals);
+    }
+
+    #[test]
+    fn test_read_checkpoint_rejects_other_version() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counts.checkpoint");
+        fs::write(
+            &path,
+            r#"{"version":0,"source":"a","totals":{"lines":0,"words":0,"chars":0,"bytes":0,"lines_read":0},"word_counts":{}}"#,
+        )
+        .unwrap();
+
+        // Act
+        let result = read_checkpoint(&path);
+
+        // Assert
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_force_utf8_replaces_invalid_sequences() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scraped.txt");
+        fs::write(&path, b"caf\xe9 ok\n\xff\xfe ok\n").unwrap();
+        let lossy = WordCountConfig {
+            force_utf8: true,
+            ..Default::default()
+        };
+        let mapped = WordCountConfig {
+            mmap: true,
+            ..lossy.clone()
+        };
+        let mut strict_counts = BTreeMap::new();
+        let mut lossy_counts = BTreeMap::new();
+        let mut mapped_counts = BTreeMap::new();
+
+        // Act
+        let strict = count_file(&path, &WordCountConfig::default(), &mut strict_counts);
+        let totals = count_file(&path, &lossy, &mut lossy_counts).unwrap();
+        let mapped_totals = count_file(&path, &mapped, &mut mapped_counts).unwrap();
+
+        // Assert
+        assert_eq!(strict.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            lossy_counts,
+            BTreeMap::from([
+                (String::from("caf\u{fffd}"), 1),
+                (String::from("ok"), 2),
+                (String::from("\u{fffd}\u{fffd}"), 1),
+            ])
+        );
+        assert_eq!(totals.invalid_utf8, 3);
+        assert_eq!(mapped_counts, lossy_counts);
+        assert_eq!(mapped_totals.invalid_utf8, 3);
+    }
+
+    #[test]
+    fn test_count_reader_observed_after_every_chunk() {
+        // Arrange
+        struct Recorder(Vec<usize>);
+        impl ChunkObserver for Recorder {
+            fn chunk_done(
+                &mut self,
+                totals: &Totals,
+                _word_counts: &BTreeMap<String, usize>,
+            ) -> io::Result<()> {
+                self.0.push(totals.lines);
+                Ok(())
+            }
+        }
+        let config = WordCountConfig {
+            chunk_size: 2,
+            sleep: None,
+            ..Default::default()
+        };
+        let mut recorder = Recorder(Vec::new());
+
+        // Act
+        count_reader_observed(
+            io::Cursor::new("a\nb\nc\nd\ne\n"),
+            &config,
+            &mut BTreeMap::new(),
+            &mut recorder,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(recorder.0, vec![2, 4, 5]);
+    }
+
+    #[test]
+    fn test_word_counter_builder() {
+        // Arrange
+        let mut counter = WordCounter::new();
+        counter
+            .ignore_case(true)
+            .strip_punct(true)
+            .chunk_size(1)
+            .sleep(None);
+
+        // Act
+        let word_counts = counter
+            .count_reader(io::Cursor::new("Hello, world!\nhello"))
+            .unwrap();
+
+        // Assert
+        assert_eq!(word_counts.get("hello"), Some(&2));
+        assert_eq!(word_counts.get("world"), Some(&1));
+        assert_eq!(word_counts.len(), 2);
+    }
+
+    #[test]
+    fn test_word_counter_count_filters() {
+        // Arrange
+        let input = "a a a b b c";
+        let mut min = WordCounter::new();
+        min.sleep(None).min_count(2);
+        let mut range = WordCounter::new();
+        range.sleep(None).min_count(2).max_count(2);
+
+        // Act
+        let at_least_two = min.count_reader(io::Cursor::new(input)).unwrap();
+        let exactly_two = range.count_reader(io::Cursor::new(input)).unwrap();
+
+        // Assert
+        assert_eq!(
+            at_least_two,
+            BTreeMap::from([(String::from("a"), 3), (String::from("b"), 2)])
+        );
+        assert_eq!(exactly_two, BTreeMap::from([(String::from("b"), 2)]));
+    }
+
+    #[test]
+    fn test_word_counter_count_file() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input.txt");
+        std::fs::write(&path, "The quick fox.\nThe lazy dog, the end\n").unwrap();
+        let mut counter = WordCounter::new();
+        counter
+            .ignore_case(true)
+            .strip_punct(true)
+            .min_length(3)
+            .max_length(4)
+            .stopwords(HashSet::from([String::from("lazy")]))
+            .chunk_size(1)
+            .sleep_ms(0);
+
+        // Act
+        let word_counts = counter.count_file(&path).unwrap();
+
+        // Assert
+        assert_eq!(counter.config().sleep, Some(Duration::ZERO));
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([
+                (String::from("dog"), 1),
+                (String::from("end"), 1),
+                (String::from("fox"), 1),
+                (String::from("the"), 3),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_word_count_iter_yields_chunk_by_chunk() {
+        // Arrange
+        let mut counter = WordCounter::new();
+        counter.chunk_size(2).sleep(None);
+        let input = "b a\na\n\nc\nb";
+
+        // Act
+        let mut iter = counter.iter_reader(io::Cursor::new(input));
+        let first: Vec<_> = iter.by_ref().take(2).collect();
+        let lines_read = iter.totals().lines_read;
+        let rest: Vec<_> = iter.collect();
+
+        // Assert
+        assert_eq!(first, vec![(String::from("a"), 2), (String::from("b"), 1)]);
+        // the second chunk was not read before its entries were needed
+        assert_eq!(lines_read, 2);
+        assert_eq!(rest, vec![(String::from("c"), 1), (String::from("b"), 1)]);
+    }
+
+    #[test]
+    fn test_word_count_iter_collect_final() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input.txt");
+        std::fs::write(&path, "b a\na\n\nc\nb").unwrap();
+        let mut counter = WordCounter::new();
+        counter.chunk_size(2).sleep(None);
+
+        // Act
+        let mut iter = counter.iter_file(&path).unwrap();
+        iter.next();
+        let word_counts = iter.collect_final().unwrap();
+
+        // Assert
+        assert_eq!(word_counts, counter.count_file(&path).unwrap());
+    }
+
+    #[test]
+    fn test_write_section_format() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("dog"), 2), (String::from("cat"), 1)]);
+        let mut out = Vec::new();
+
+        // Act
+        write_section(&mut out, "a.txt", &word_counts, &OutputConfig::default()).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "=== a.txt ===\ncat: 1\ndog: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_sorted_entries_by_count_desc() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("b"), 2),
+            (String::from("a"), 2),
+            (String::from("c"), 5),
+            (String::from("d"), 1),
+        ]);
+
+        // Act
+        let entries = sorted_entries(&word_counts, SortOrder::CountDesc);
+
+        // Assert
+        assert_eq!(entries, vec![("c", 5), ("a", 2), ("b", 2), ("d", 1)]);
+    }
+
+    #[test]
+    fn test_sorted_entries_by_count_asc() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("b"), 2),
+            (String::from("a"), 2),
+            (String::from("c"), 5),
+            (String::from("d"), 1),
+        ]);
+
+        // Act
+        let entries = sorted_entries(&word_counts, SortOrder::CountAsc);
+
+        // Assert
+        assert_eq!(entries, vec![("d", 1), ("a", 2), ("b", 2), ("c", 5)]);
+    }
+
+    #[test]
+    fn test_sorted_entries_by_every_key() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("bb"), 2),
+            (String::from("a"), 3),
+            (String::from("dddd"), 1),
+            (String::from("ccc"), 2),
+        ]);
+        let first_lines = BTreeMap::from([
+            (String::from("ccc"), 1),
+            (String::from("a"), 2),
+            (String::from("dddd"), 2),
+        ]);
+        let first_and_last = |order| {
+            let entries = sorted_entries_seen(&word_counts, order, Some(&first_lines));
+            (entries[0].0, entries[entries.len() - 1].0)
+        };
+
+        // Act
+        let orders = [
+            SortOrder::Word,
+            SortOrder::CountDesc,
+            SortOrder::CountAsc,
+            SortOrder::LengthDesc,
+            SortOrder::LengthAsc,
+            SortOrder::FirstSeen,
+        ]
+        .map(first_and_last);
+
+        // Assert
+        assert_eq!(
+            orders,
+            [
+                ("a", "dddd"),
+                ("a", "dddd"),
+                ("dddd", "a"),
+                ("dddd", "a"),
+                ("a", "dddd"),
+                // "bb" has no first line, "a" and "dddd" tie alphabetically
+                ("ccc", "bb"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_entries_exactly() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("d"), 5),
+            (String::from("a"), 1),
+            (String::from("b"), 5),
+            (String::from("c"), 1),
+            (String::from("e"), 2),
+        ]);
+        let exactly = |n| OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            exactly: Some(n),
+            ..Default::default()
+        };
+
+        // Act
+        let once = select_entries(&word_counts, &exactly(1));
+        let five = select_entries(&word_counts, &exactly(5));
+        let none = select_entries(&word_counts, &exactly(6));
+
+        // Assert
+        assert_eq!(once, vec![("a", 1), ("c", 1)]);
+        assert_eq!(five, vec![("b", 5), ("d", 5)]);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_select_entries_top_one() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 3),
+            (String::from("c"), 2),
+        ]);
+        let output_config = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            top: Some(1),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        assert_eq!(entries, vec![("b", 3)]);
+    }
+
+    #[test]
+    fn test_select_entries_top_all_words() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 3),
+            (String::from("c"), 2),
+        ]);
+        let output_config = OutputConfig {
+            top: Some(3),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        assert_eq!(entries, vec![("a", 1), ("b", 3), ("c", 2)]);
+    }
+
+    #[test]
+    fn test_select_entries_top_more_than_words() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a"), 1), (String::from("b"), 3)]);
+        let output_config = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            top: Some(10),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        assert_eq!(entries, vec![("b", 3), ("a", 1)]);
+    }
+
+    #[test]
+    fn test_write_counts_json_round_trip() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("b\"q"), 2),
+            (String::from("a"), 1),
+            (String::from("c"), 3),
+        ]);
+        let output_config = OutputConfig {
+            format: OutputFormat::Json,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        let parsed: BTreeMap<String, usize> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed, word_counts);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"a\":1,\"b\\\"q\":2,\"c\":3}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_json_array() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a"), 1), (String::from("b"), 2)]);
+        let output_config = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            format: OutputFormat::JsonArray,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"word\":\"b\",\"count\":2},{\"word\":\"a\",\"count\":1}]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_csv_quotes_words() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a,b"), 1), (String::from("\"q\""), 2)]);
+        let output_config = OutputConfig {
+            format: OutputFormat::Csv,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "word,count\n\"\"\"q\"\"\",2\n\"a,b\",1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_counts_csv_no_header() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("a"), 1)]);
+        let output_config = OutputConfig {
+            format: OutputFormat::Csv,
+            no_header: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        // Act
+        write_counts(&mut out, &word_counts, &output_config).unwrap();
+
+        // Assert
+        assert_eq!(String::from_utf8(out).unwrap(), "a,1\n");
+    }
+
+    #[test]
+    fn test_select_entries_min_count_inclusive() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 2),
+            (String::from("c"), 3),
+        ]);
+        let output_config = OutputConfig {
+            min_count: Some(2),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        assert_eq!(entries, vec![("b", 2), ("c", 3)]);
+    }
+
+    #[test]
+    fn test_select_entries_max_count_inclusive() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 2),
+            (String::from("c"), 3),
+        ]);
+        let output_config = OutputConfig {
+            max_count: Some(2),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        assert_eq!(entries, vec![("a", 1), ("b", 2)]);
+    }
+
+    #[test]
+    fn test_select_entries_count_range_before_top() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 2),
+            (String::from("c"), 3),
+            (String::from("d"), 4),
+        ]);
+        let output_config = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            top: Some(1),
+            min_count: Some(2),
+            max_count: Some(3),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        assert_eq!(entries, vec![("c", 3)]);
+    }
+
+    #[test]
+    fn test_select_entries_bottom_ties() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 3),
+            (String::from("b"), 2),
+            (String::from("c"), 1),
+            (String::from("d"), 2),
+            (String::from("e"), 2),
+        ]);
+        let ties = OutputConfig {
+            bottom: Some(2),
+            bottom_ties: true,
+            ..Default::default()
+        };
+        let no_ties = OutputConfig {
+            bottom_ties: false,
+            ..ties.clone()
+        };
+
+        // Act
+        let with_ties = select_entries(&word_counts, &ties);
+        let without_ties = select_entries(&word_counts, &no_ties);
+
+        // Assert
+        assert_eq!(with_ties, vec![("c", 1), ("b", 2), ("d", 2), ("e", 2)]);
+        assert_eq!(without_ties, vec![("c", 1), ("b", 2)]);
+    }
+
+    #[test]
+    fn test_select_entries_bottom_after_min_count() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("b"), 2),
+            (String::from("c"), 2),
+            (String::from("d"), 3),
+        ]);
+        let output_config = OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            bottom: Some(1),
+            bottom_ties: true,
+            min_count: Some(2),
+            ..Default::default()
+        };
+
+        // Act
+        let entries = select_entries(&word_counts, &output_config);
+
+        // Assert
+        // the tie at the boundary is taken from the filtered words
+        assert_eq!(entries, vec![("b", 2), ("c", 2)]);
+    }
+
+    #[test]
+    fn test_select_entries_top_percent() {
+        // Arrange
+        // 10 tokens: "a" alone covers 50%, "a" and "b" 80%, all of them 100%
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 5),
+            (String::from("b"), 3),
+            (String::from("c"), 1),
+            (String::from("d"), 1),
+        ]);
+        let top_percent = |pct| OutputConfig {
+            sort_order: SortOrder::CountDesc,
+            top_percent: Some((pct, 10)),
+            ..Default::default()
+        };
+
+        // Act
+        let half = select_entries(&word_counts, &top_percent(50.0));
+        let above_half = select_entries(&word_counts, &top_percent(50.1));
+        let eighty = select_entries(&word_counts, &top_percent(80.0));
+        let all = select_entries(&word_counts, &top_percent(100.0));
+
+        // Assert
+        assert_eq!(half, vec![("a", 5)]);
+        // the word reaching the percentage is included
+        assert_eq!(above_half, vec![("a", 5), ("b", 3)]);
+        assert_eq!(eighty, vec![("a", 5), ("b", 3)]);
+        assert_eq!(all.len(), 4);
+    }
+
+    #[test]
+    fn test_process_chunk_length_filters() {
+        // Arrange
+        let chunk = vec![String::from("a to café naïveté über ß")];
+        let config = WordCountConfig {
+            min_length: Some(2),
+            max_length: Some(4),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        let keys: Vec<_> = word_counts.keys().collect();
+        assert_eq!(keys, vec!["café", "to", "über"]);
+    }
+
+    #[test]
+    fn test_process_chunk_length_after_strip_punct() {
+        // Arrange
+        let chunk = vec![String::from("(é) ab,")];
+        let config = WordCountConfig {
+            strip_punct: true,
+            max_length: Some(1),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("é"), Some(&1));
+        assert_eq!(word_counts.len(), 1);
+    }
+
+    #[test]
+    fn test_process_chunk_stopwords() {
+        // Arrange
+        let chunk = vec![String::from("the cat and The dog")];
+        let config = WordCountConfig {
+            stopwords: HashSet::from([String::from("the"), String::from("and")]),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        let keys: Vec<_> = word_counts.keys().collect();
+        assert_eq!(keys, vec!["The", "cat", "dog"]);
+    }
+
+    #[test]
+    fn test_process_chunk_stopwords_ignore_case() {
+        // Arrange
+        let chunk = vec![String::from("the cat and The dog")];
+        let config = WordCountConfig {
+            ignore_case: true,
+            stopwords: HashSet::from([String::from("the"), String::from("and")]),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        let keys: Vec<_> = word_counts.keys().collect();
+        assert_eq!(keys, vec!["cat", "dog"]);
+    }
+
+    #[test]
+    fn test_process_chunk_vocab_after_stopwords() {
+        // Arrange
+        let chunk = vec![String::from("the cat and the dog")];
+        let config = WordCountConfig {
+            stopwords: HashSet::from([String::from("the")]),
+            vocab: Some(HashSet::from([String::from("the"), String::from("cat")])),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts, BTreeMap::from([(String::from("cat"), 1)]));
+    }
+
+    #[test]
+    fn test_count_sentences() {
+        // Act
+        let plain = count_sentences("One. Two! Three? Four");
+        let end_of_line = count_sentences("It ends here.");
+        let abbreviations = count_sentences("Dr. Smith met Mrs. Jones, e.g. at St. Mary's.");
+        let ellipses = count_sentences("Wait... What?! No...");
+        let inside_words = count_sentences("Pi is 3.14 and the site is example.com");
+
+        // Assert
+        assert_eq!(plain, 3);
+        assert_eq!(end_of_line, 1);
+        assert_eq!(abbreviations, 1);
+        assert_eq!(ellipses, 3);
+        assert_eq!(inside_words, 0);
+    }
+
+    #[test]
+    fn test_process_chunk_field_separator() {
+        // Arrange
+        let tabs = vec![String::from("the cat\tsat\t\tthe cat")];
+        let commas = vec![String::from("a,b,,a")];
+        let tab_config = WordCountConfig {
+            field_separator: Some(String::from("\t")),
+            ..Default::default()
+        };
+        let comma_config = WordCountConfig {
+            field_separator: Some(String::from(",")),
+            ..Default::default()
+        };
+        let mut tab_counts = BTreeMap::new();
+        let mut comma_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&tabs, &tab_config, &mut VecDeque::new(), &mut tab_counts);
+        process_chunk(
+            &commas,
+            &comma_config,
+            &mut VecDeque::new(),
+            &mut comma_counts,
+        );
+
+        // Assert
+        // a tab is whitespace, so the line is split like without a separator
+        assert_eq!(tab_counts.get("the"), Some(&2));
+        assert_eq!(tab_counts.get("sat"), Some(&1));
+        assert_eq!(
+            comma_counts,
+            BTreeMap::from([(String::from("a"), 2), (String::from("b"), 1)])
+        );
+    }
+
+    #[test]
+    fn test_process_chunk_multi_char_field_separator() {
+        // Arrange
+        let chunk = vec![String::from("new york::los angeles::new york")];
+        let config = WordCountConfig {
+            field_separator: Some(String::from("::")),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("new york"), Some(&2));
+        assert_eq!(word_counts.get("los angeles"), Some(&1));
+    }
+
+    #[test]
+    fn test_process_chunk_char_ngrams() {
+        // Arrange
+        let chunk = vec![String::from("hello he")];
+        let config = WordCountConfig {
+            char_ngrams: Some(3),
+            ..Default::default()
+        };
+        let boundaries = WordCountConfig {
+            char_ngram_boundaries: true,
+            ..config.clone()
+        };
+        let mut word_counts = BTreeMap::new();
+        let mut padded_counts = BTreeMap::new();
+
+        // Act
+        let ngrams = process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+        process_chunk(
+            &chunk,
+            &boundaries,
+            &mut VecDeque::new(),
+            &mut padded_counts,
+        );
+
+        // Assert
+        // "he" is too short for a trigram of its own
+        assert_eq!(ngrams, 3);
+        let keys: Vec<&str> = word_counts.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["ell", "hel", "llo"]);
+        assert_eq!(padded_counts.get("^^h"), Some(&2));
+        assert_eq!(padded_counts.get("^he"), Some(&2));
+        assert_eq!(padded_counts.get("he$"), Some(&1));
+        assert_eq!(padded_counts.get("lo$"), Some(&1));
+        assert_eq!(padded_counts.get("o$$"), Some(&1));
+    }
+
+    #[test]
+    fn test_count_sentences_in_chunk() {
+        // Arrange
+        let endings = ['.', '!', '?'];
+
+        // Act
+        let plain = count_sentences_in_chunk(&["One. Two! Three? Four"], &endings);
+        let runs = count_sentences_in_chunk(&["Wait... What?! No !!", "Yes"], &endings);
+        let across_lines = count_sentences_in_chunk(&["It ends", "here.", "? Next."], &endings);
+        let only_enders = count_sentences_in_chunk(&["Hello ."], &endings);
+        let custom = count_sentences_in_chunk(&["One; two. Three;"], &[';']);
+
+        // Assert
+        assert_eq!(plain, 3);
+        assert_eq!(runs, 3);
+        assert_eq!(across_lines, 2);
+        assert_eq!(only_enders, 1);
+        assert_eq!(custom, 2);
+    }
+
+    #[test]
+    fn test_chunk_sentences_per_line() {
+        // Arrange
+        let config = WordCountConfig {
+            sentence_per_line: true,
+            ..Default::default()
+        };
+
+        // Act
+        let sentences =
+            chunk_sentences(&["Dr. Smith arrived", "", "He left . Then", " \n"], &config);
+
+        // Assert
+        assert_eq!(sentences, 2);
+    }
+
+    #[test]
+    fn test_extract_column() {
+        // Act
+        let more = extract_column("2024-01-01 ERROR disk full", 2, None);
+        let exact = extract_column("a  b", 2, None);
+        let fewer = extract_column("a b", 3, None);
+        let separated = extract_column("1|the quick fox|x", 2, Some('|'));
+        let empty = extract_column("1||x", 2, Some('|'));
+        let short = extract_column("1|2", 3, Some('|'));
+
+        // Assert
+        assert_eq!(more, Some("ERROR"));
+        assert_eq!(exact, Some("b"));
+        assert_eq!(fewer, None);
+        assert_eq!(separated, Some("the quick fox"));
+        assert_eq!(empty, Some(""));
+        assert_eq!(short, None);
+    }
+
+    #[test]
+    fn test_process_chunk_column_skips_short_lines() {
+        // Arrange
+        let chunk = vec![
+            String::from("1,red fox,x"),
+            String::from("2"),
+            String::from("3,red,y"),
+        ];
+        let config = WordCountConfig {
+            column: Some(2),
+            column_separator: Some(','),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let counted = process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(counted, 3);
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([(String::from("fox"), 1), (String::from("red"), 2)])
+        );
+    }
+
+    #[test]
+    fn test_read_counts() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("counts.txt");
+        let json = dir.path().join("counts.json");
+        let malformed = dir.path().join("malformed.txt");
+        std::fs::write(&text, "a: 2\nkey: value: 3\n\n").unwrap();
+        std::fs::write(&json, r#"{"a":2,"b":1}"#).unwrap();
+        std::fs::write(&malformed, "a: 2\nb 1\n").unwrap();
+
+        // Act
+        let from_text = read_counts(&text, OutputFormat::Text).unwrap();
+        let from_json = read_counts(&json, OutputFormat::Json).unwrap();
+        let err = read_counts(&malformed, OutputFormat::Text).unwrap_err();
+
+        // Assert
+        assert_eq!(
+            from_text,
+            BTreeMap::from([(String::from("a"), 2), (String::from("key: value"), 3)])
+        );
+        assert_eq!(
+            from_json,
+            BTreeMap::from([(String::from("a"), 2), (String::from("b"), 1)])
+        );
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 2 is not a `word: count` line");
+    }
+
+    /// Count `line` with the given include and exclude patterns
+    fn count_with_patterns(
+        line: &str,
+        include: Option<&str>,
+        exclude: Option<&str>,
+    ) -> Vec<String> {
+        let config = WordCountConfig {
+            include_pattern: include.map(|pattern| Regex::new(pattern).unwrap()),
+            exclude_pattern: exclude.map(|pattern| Regex::new(pattern).unwrap()),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+        process_chunk(&[line], &config, &mut VecDeque::new(), &mut word_counts);
+        word_counts.into_keys().collect()
+    }
+
+    #[test]
+    fn test_process_chunk_exclude_pattern() {
+        // Act
+        let numbers = count_with_patterns("42 apples 7b 3", None, Some(r"^\d+$"));
+        let unanchored = count_with_patterns("42 apples 7b 3", None, Some(r"\d"));
+        let empty = count_with_patterns("a b", None, Some(""));
+
+        // Assert
+        assert_eq!(numbers, vec!["7b", "apples"]);
+        assert_eq!(unanchored, vec!["apples"]);
+        // the empty pattern matches every token
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_process_chunk_include_pattern() {
+        // Act
+        let lowercase = count_with_patterns("the Cat sat42 on", Some("^[a-z]+$"), None);
+        let unanchored = count_with_patterns("the Cat sat42 on", Some("[a-z]"), None);
+        let empty = count_with_patterns("a b", Some(""), None);
+
+        // Assert
+        assert_eq!(lowercase, vec!["on", "the"]);
+        assert_eq!(unanchored, vec!["Cat", "on", "sat42", "the"]);
+        assert_eq!(empty, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_process_chunk_include_and_exclude_patterns() {
+        // Act
+        let words = count_with_patterns("the then other them", Some("^th"), Some("n$"));
+
+        // Assert
+        assert_eq!(words, vec!["the", "them"]);
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        // Arrange
+        let utf16le: Vec<u8> = "hello".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let utf16be: Vec<u8> = "hello".encode_utf16().flat_map(u16::to_be_bytes).collect();
+
+        // Act
+        let detected = [
+            detect_encoding(b"plain ascii"),
+            detect_encoding("caf\u{e9}".as_bytes()),
+            // cut off within the two bytes of the last char
+            detect_encoding(&"\u{e9} caf\u{e9}".as_bytes()[..7]),
+            detect_encoding(b"caf\xe9"),
+            detect_encoding(&utf16le),
+            detect_encoding(&utf16be),
+            detect_encoding(b"\xff\xfe"),
+        ];
+        let control_chars = detect_encoding(b"\x81\x82\x83\xe9");
+
+        // Assert
+        let encodings: Vec<_> = detected.iter().map(|detected| detected.encoding).collect();
+        assert_eq!(
+            encodings,
+            [
+                Encoding::Utf8,
+                Encoding::Utf8,
+                Encoding::Utf8,
+                Encoding::Latin1,
+                Encoding::Utf16le,
+                Encoding::Utf16be,
+                Encoding::Utf16le,
+            ]
+        );
+        assert!(detected.iter().all(|detected| detected.confidence == 1.0));
+        assert_eq!(control_chars.encoding, Encoding::Latin1);
+        assert_eq!(control_chars.confidence, 0.25);
+    }
+
+    #[test]
+    fn test_encoding_report() {
+        // Act
+        let plain = encoding_report("naïve".as_bytes());
+        let utf16 = encoding_report(b"\xfe\xff\x00a");
+        let broken = encoding_report(b"a\xff\xfeb\xc3");
+
+        // Assert
+        assert_eq!(
+            plain,
+            EncodingReport {
+                bom: None,
+                bytes: 6,
+                valid_sequences: 5,
+                invalid_sequences: 0,
+            }
+        );
+        assert_eq!(utf16.bom, Some(Encoding::Utf16be));
+        assert_eq!(utf16.bytes, 4);
+        assert_eq!((broken.valid_sequences, broken.invalid_sequences), (2, 3));
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        // Act
+        let camel = split_identifier("processChunk", true, false);
+        let pascal = split_identifier("HTTPServerError", true, false);
+        let screaming = split_identifier("SCREAMING_SNAKE_CASE", true, true);
+        let mixed = split_identifier("__init_wordCount_", true, true);
+        let unsplit = split_identifier("snake_case", true, false);
+
+        // Assert
+        assert_eq!(camel, ["process", "Chunk"]);
+        assert_eq!(pascal, ["HTTPServer", "Error"]);
+        assert_eq!(screaming, ["SCREAMING", "SNAKE", "CASE"]);
+        assert_eq!(mixed, ["init", "word", "Count"]);
+        assert_eq!(unsplit, ["snake_case"]);
+    }
+
+    #[test]
+    fn test_split_hyphenated() {
+        // Act
+        let compound = split_hyphenated("state-of-the-art", false);
+        let dangling = split_hyphenated("-pre-", false);
+        let date = split_hyphenated("2023-01-01", false);
+        let kept_date = split_hyphenated("2023-01-01", true);
+        let kept_3d = split_hyphenated("3-D", true);
+        let dashes = split_hyphenated("--", true);
+
+        // Assert
+        assert_eq!(compound, ["state", "of", "the", "art"]);
+        assert_eq!(dangling, ["pre"]);
+        assert_eq!(date, ["2023", "01", "01"]);
+        assert_eq!(kept_date, ["2023-01-01"]);
+        assert_eq!(kept_3d, ["3-D"]);
+        assert!(dashes.is_empty());
+    }
+
+    #[test]
+    fn test_split_hyphenated_tokens() {
+        // Arrange
+        let config = WordCountConfig {
+            strip_punct: true,
+            split_hyphenated: true,
+            keep_hyphenated_numbers: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &["a well-known, up-to-date (3-D) pre- view - on 2023-01-01."],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        let words: Vec<_> = word_counts.keys().map(String::as_str).collect();
+        assert_eq!(
+            words,
+            [
+                "2023-01-01",
+                "3-D",
+                "a",
+                "date",
+                "known",
+                "on",
+                "pre",
+                "to",
+                "up",
+                "view",
+                "well"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_camel_case_tokens() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            split_camel_case: true,
+            split_underscore: true,
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &["bufReader BufReader MAX_LINE_LEN process_chunk processChunk"],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        let words: Vec<_> = word_counts.keys().map(String::as_str).collect();
+        assert_eq!(
+            words,
+            ["buf", "chunk", "len", "line", "max", "process", "reader"]
+        );
+        assert_eq!(word_counts["reader"], 2);
+        assert_eq!(word_counts["process"], 2);
+    }
+
+    #[test]
+    fn test_tokenize_code() {
+        // Arrange
+        let config = WordCountConfig {
+            code_lang: Some(CodeLang::Rust),
+            split_camel_case: true,
+            split_underscore: true,
+            ..Default::default()
+        };
+        let code = [
+            "// Count the words of a line",
+            "fn count_words(line: &str) -> usize {",
+            "    let words = line.split(\"; \").count();",
+            "    words",
+            "}",
+        ];
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&code, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts["fn"], 1);
+        assert_eq!(word_counts["let"], 1);
+        assert_eq!(word_counts["count"], 2);
+        assert_eq!(word_counts["words"], 4);
+        assert_eq!(word_counts["line"], 3);
+        assert_eq!(word_counts["usize"], 1);
+        assert!(word_counts
+            .keys()
+            .all(|word| word.chars().all(char::is_alphanumeric)));
+    }
+
+    #[test]
+    fn test_validate_config() {
+        // Arrange
+        let valid = WordCountConfig::default();
+        let invalid = WordCountConfig {
+            chunk_size: 0,
+            min_length: Some(5),
+            max_length: Some(2),
+            line_range: Some(LineRange {
+                start: 10,
+                end: Some(3),
+            }),
+            ..Default::default()
+        };
+
+        // Act
+        let valid_errors = validate_config(&valid);
+        let invalid_errors = validate_config(&invalid);
+
+        // Assert
+        assert!(valid_errors.is_empty());
+        assert_eq!(
+            invalid_errors,
+            [
+                "chunk_size must be at least 1",
+                "min_length 5 is greater than max_length 2",
+                "line_range must not end before its start",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_text() {
+        // Arrange
+        let nested = r#"{"user": {"name": "ada", "bio": {"text": "hello world"}}, "id": 7}"#;
+        let array = r#"["one", ["two", "three"], {"four": "four"}]"#;
+        let mixed = r#"{"a": 1.5, "b": true, "c": null, "d": "text", "e": [2, "more"]}"#;
+        let logs = r#"[{"message": "first"}, {"level": "x"}, {"message": ["second", 3]}]"#;
+
+        // Act
+        let nested_text = json_text(nested, &[]).unwrap();
+        let nested_path = json_text(nested, &[String::from("user"), String::from("bio")]);
+        let array_text = json_text(array, &[]).unwrap();
+        let mixed_text = json_text(mixed, &[]).unwrap();
+        let logs_text = json_text(logs, &[String::from("message")]).unwrap();
+        let invalid = json_text("{\"unterminated\": ", &[]);
+
+        // Assert
+        assert_eq!(nested_text, "hello world ada");
+        assert_eq!(nested_path.unwrap(), "hello world");
+        assert_eq!(array_text, "one two three four");
+        assert_eq!(mixed_text, "text more");
+        assert_eq!(logs_text, "first second");
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_longest_and_shortest_words() {
+        // Arrange
+        let word_counts: BTreeMap<String, usize> = [
+            ("cat", 3),
+            ("dog", 1),
+            ("hippopotamus", 1),
+            ("élan", 2),
+            ("a", 5),
+        ]
+        .into_iter()
+        .map(|(word, count)| (word.to_string(), count))
+        .collect();
+        let mut out = Vec::new();
+
+        // Act
+        let longest = longest_words(&word_counts, 2);
+        let shortest = shortest_words(&word_counts, 3);
+        write_word_lengths(&mut out, "longest words", &longest).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "=== longest words ===\nhippopotamus: 12 chars (count: 1)\nélan: 4 chars (count: 2)\n"
+        );
+        let shortest: Vec<_> = shortest.iter().map(|word| word.word).collect();
+        assert_eq!(shortest, ["a", "cat", "dog"]);
+        assert!(longest_words(&word_counts, 0).is_empty());
+        assert_eq!(longest_words(&word_counts, 9).len(), 5);
+    }
+
+    #[test]
+    fn test_top_per_length() {
+        // Arrange
+        let word_counts: BTreeMap<String, usize> = [
+            ("the", 9),
+            ("and", 4),
+            ("cat", 4),
+            ("a", 3),
+            ("dogs", 2),
+            ("tree", 1),
+            ("élan", 2),
+        ]
+        .into_iter()
+        .map(|(word, count)| (word.to_string(), count))
+        .collect();
+        let mut out = Vec::new();
+
+        // Act
+        let top = top_per_length(&word_counts, 2, 1..=usize::MAX);
+        let threes = top_per_length(&word_counts, 1, 3..=3);
+        write_top_per_length(&mut out, &top).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "length 1: [a: 3]\nlength 3: [the: 9, and: 4]\nlength 4: [dogs: 2, élan: 2]\n"
+        );
+        assert_eq!(threes, BTreeMap::from([(3, vec![("the", 9)])]));
+    }
+
+    #[test]
+    fn test_write_arpa() {
+        // Arrange
+        let word_counts = BTreeMap::from([(String::from("cat"), 3), (String::from("the"), 1)]);
+        let mut plain = Vec::new();
+        let mut smoothed = Vec::new();
+
+        // Act
+        write_arpa(&mut plain, &word_counts, 0.0, -99.0).unwrap();
+        write_arpa(&mut smoothed, &word_counts, 1.0, -2.0).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(plain).unwrap(),
+            "\\data\\\nngram 1=5\n\n\\1-grams:\n-99.000000\t<s>\n-99.000000\t</s>\n\
+             -99.000000\t<unk>\n-0.124939\tcat\n-0.602060\tthe\n\n\\end\\\n"
+        );
+        let smoothed = String::from_utf8(smoothed).unwrap();
+        let sum: f64 = smoothed
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter(|(_, word)| *word != "<s>")
+            .map(|(log_prob, _)| 10f64.powf(log_prob.parse().unwrap()))
+            .sum();
+        assert!((sum - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_write_vocabulary() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("b"), 1),
+            (String::from("a"), 1),
+            (String::from("c"), 3),
+        ]);
+        let mut alphabetical = Vec::new();
+        let mut by_count = Vec::new();
+
+        // Act
+        write_vocabulary(&mut alphabetical, &word_counts, SortOrder::Word, None).unwrap();
+        write_vocabulary(&mut by_count, &word_counts, SortOrder::CountDesc, None).unwrap();
+
+        // Assert
+        assert_eq!(String::from_utf8(alphabetical).unwrap(), "a\nb\nc\n");
+        assert_eq!(String::from_utf8(by_count).unwrap(), "c\na\nb\n");
+    }
+
+    #[test]
+    fn test_write_word_cloud() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("a"), 1),
+            (String::from("cat"), 10),
+            (String::from("the"), 100),
+            (String::from("zebra"), 1),
+        ]);
+        let same = BTreeMap::from([(String::from("a"), 2), (String::from("b"), 2)]);
+        let mut linear = Vec::new();
+        let mut log = Vec::new();
+        let mut tied = Vec::new();
+
+        // Act
+        write_word_cloud(&mut linear, &word_counts, 3, CloudScale::Linear).unwrap();
+        write_word_cloud(&mut log, &word_counts, 3, CloudScale::Log).unwrap();
+        write_word_cloud(&mut tied, &same, 200, CloudScale::Linear).unwrap();
+
+        // Assert
+        assert_eq!(
+            String::from_utf8(linear).unwrap(),
+            "[{\"text\":\"the\",\"weight\":100},{\"text\":\"cat\",\"weight\":10},\
+             {\"text\":\"a\",\"weight\":1}]\n"
+        );
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "[{\"text\":\"the\",\"weight\":100},{\"text\":\"cat\",\"weight\":51},\
+             {\"text\":\"a\",\"weight\":1}]\n"
+        );
+        assert_eq!(
+            String::from_utf8(tied).unwrap(),
+            "[{\"text\":\"a\",\"weight\":100},{\"text\":\"b\",\"weight\":100}]\n"
+        );
+    }
+
+    #[test]
+    fn test_case_variants() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("THE"), 3),
+            (String::from("The"), 512),
+            (String::from("the"), 4231),
+            (String::from("Ärger"), 1),
+            (String::from("cat"), 2),
+        ]);
+
+        // Act
+        let variants = case_variants(&word_counts);
+        let mut out = Vec::new();
+        write_case_variants(&mut out, &variants).unwrap();
+
+        // Assert
+        assert_eq!(
+            variants["the"],
+            BTreeMap::from([
+                (String::from("THE"), 3),
+                (String::from("The"), 512),
+                (String::from("the"), 4231),
+            ])
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "cat: cat=2\nthe: the=4231 The=512 THE=3\närger: Ärger=1\n"
+        );
+    }
+
+    #[test]
+    fn test_group_by_first_char_and_script() {
+        // Arrange
+        let word_counts = BTreeMap::from([
+            (String::from("apple"), 3),
+            (String::from("art"), 1),
+            (String::from("book"), 2),
+            (String::from("дом"), 4),
+            (String::from("да"), 1),
+            (String::from("42"), 1),
+        ]);
+
+        // Act
+        let by_char = group_by_first_char(&word_counts);
+        let by_script = group_by_script(&word_counts);
+        let mut out = Vec::new();
+        write_word_groups(&mut out, &by_char, true).unwrap();
+        write_word_groups(&mut out, &by_script, false).unwrap();
+
+        // Assert
+        assert_eq!(
+            by_script,
+            [
+                WordGroup {
+                    key: String::from("Latin"),
+                    words: 3,
+                    occurrences: 6,
+                },
+                WordGroup {
+                    key: String::from("Cyrillic"),
+                    words: 2,
+                    occurrences: 5,
+                },
+                WordGroup {
+                    key: String::from("Common"),
+                    words: 1,
+                    occurrences: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "д (U+0434): 2 words, 5 total occurrences\n\
+             a (U+0061): 2 words, 4 total occurrences\n\
+             b (U+0062): 1 word, 2 total occurrences\n\
+             4 (U+0034): 1 word, 1 total occurrences\n\
+             Latin: 3 words, 6 total occurrences\n\
+             Cyrillic: 2 words, 5 total occurrences\n\
+             Common: 1 word, 1 total occurrences\n"
+        );
+        assert_eq!(char_script('ß'), "Latin");
+        assert_eq!(char_script('λ'), "Greek");
+        assert_eq!(char_script('字'), "Han");
+        assert_eq!(char_script('ᚠ'), "Other");
+    }
+
+    #[test]
+    fn test_frequency_bands() {
+        // Arrange
+        let word_counts: BTreeMap<String, usize> = [1, 1, 2, 5, 6, 10, 11, 100, 101, 1000, 1001]
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| (format!("w{}", i), count))
+            .collect();
+
+        // Act
+        let bands = frequency_bands(&word_counts, &[1, 5, 10, 100, 1000]);
+        let mut out = Vec::new();
+        write_frequency_bands(&mut out, &bands).unwrap();
+
+        // Assert
+        let words: Vec<usize> = bands.iter().map(|band| band.words).collect();
+        assert_eq!(words, vec![2, 2, 2, 2, 2, 1]);
+        assert_eq!(words.iter().sum::<usize>(), word_counts.len());
+        assert_eq!(
+            bands[1],
+            FrequencyBand {
+                low: 2,
+                high: Some(5),
+                words: 2
+            }
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[1]: 2 words\n[2-5]: 2 words\n[6-10]: 2 words\n[11-100]: 2 words\n\
+             [101-1000]: 2 words\n[1001+]: 1 word\n"
+        );
+    }
+
+    #[test]
+    fn test_process_chunk_only_alpha() {
+        // Arrange
+        let chunk = vec![String::from("hello123 Café naïve word2vec 42 (quoted) x-y")];
+        let count = |config: WordCountConfig| {
+            let mut word_counts = BTreeMap::new();
+            process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+            word_counts.into_keys().collect::<Vec<_>>()
+        };
+
+        // Act
+        let alpha = count(WordCountConfig {
+            only_alpha: true,
+            ignore_case: true,
+            ..Default::default()
+        });
+        let alpha_stripped = count(WordCountConfig {
+            only_alpha: true,
+            strip_punct: true,
+            ..Default::default()
+        });
+        let alphanumeric = count(WordCountConfig {
+            only_alphanumeric: true,
+            ..Default::default()
+        });
+
+        // Assert
+        assert_eq!(alpha, vec!["café", "naïve"]);
+        assert_eq!(alpha_stripped, vec!["Café", "naïve", "quoted"]);
+        assert_eq!(
+            alphanumeric,
+            vec!["42", "Café", "hello123", "naïve", "word2vec"]
+        );
+    }
+
+    #[test]
+    fn test_truncate_tokens() {
+        // Arrange
+        let long = "x".repeat(100);
+        let config = WordCountConfig {
+            truncate_length: Some(20),
+            ..Default::default()
+        };
+        let unmarked = WordCountConfig {
+            truncate_marker: String::new(),
+            ..config.clone()
+        };
+        let lines = [format!(
+            "{} https://example.com/a https://example.com/b naïveté",
+            long
+        )];
+        let short = WordCountConfig {
+            truncate_length: Some(5),
+            ..unmarked.clone()
+        };
+        let mut word_counts = BTreeMap::new();
+        let mut unmarked_counts = BTreeMap::new();
+        let mut short_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&lines, &config, &mut VecDeque::new(), &mut word_counts);
+        process_chunk(
+            &lines,
+            &unmarked,
+            &mut VecDeque::new(),
+            &mut unmarked_counts,
+        );
+        process_chunk(&lines, &short, &mut VecDeque::new(), &mut short_counts);
+
+        // Assert
+        let truncated = format!("{}…", "x".repeat(20));
+        assert_eq!(word_counts.get(&truncated), Some(&1));
+        assert_eq!(word_counts.get("naïveté"), Some(&1));
+        assert_eq!(unmarked_counts.keys().next().unwrap().chars().count(), 20);
+        assert_eq!(short_counts.get("https"), Some(&2));
+        assert_eq!(short_counts.get("naïve"), Some(&1));
+    }
+
+    #[test]
+    fn test_strip_affixes() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            strip_prefixes: vec![String::from("un"), String::from("re")],
+            strip_suffixes: vec![String::from("ing")],
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &["Unhappy happy running ing un unredoing sun"],
+            &config,
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        // "running" loses its "ing" only, which is intended
+        assert_eq!(
+            word_counts,
+            BTreeMap::from([
+                (String::from("do"), 1),
+                (String::from("happy"), 2),
+                (String::from("runn"), 1),
+                (String::from("sun"), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_replace_numbers_and_urls() {
+        // Arrange
+        let config = WordCountConfig {
+            ignore_case: true,
+            strip_punct: true,
+            replace_numbers: Some(String::from("<NUM>")),
+            replace_urls: Some(String::from("<URL>")),
+            ..Default::default()
+        };
+        let with_regex = WordCountConfig {
+            number_regex: Some(Regex::new(r"^-?\d+(\.\d+)?$").unwrap()),
+            ..config.clone()
+        };
+        let lines = vec!["123 and 456, see HTTPS://example.com/a.", "http://x 3.5 A1"];
+        let mut word_counts = BTreeMap::new();
+        let mut regex_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&lines, &config, &mut VecDeque::new(), &mut word_counts);
+        process_chunk(&lines, &with_regex, &mut VecDeque::new(), &mut regex_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("<NUM>"), Some(&2));
+        assert_eq!(word_counts.get("<URL>"), Some(&2));
+        assert_eq!(word_counts.get("3.5"), Some(&1));
+        assert_eq!(word_counts.get("a1"), Some(&1));
+        assert_eq!(regex_counts.get("<NUM>"), Some(&3));
+    }
+
+    #[test]
+    fn test_process_chunk_remove_numbers() {
+        // Arrange
+        let chunk = vec![String::from("123 3.14 2023-01-01 -7 1e-9 word123 v1.2 -")];
+        let count = |config: WordCountConfig| {
+            let mut word_counts = BTreeMap::new();
+            process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+            word_counts.into_keys().collect::<Vec<_>>()
+        };
+
+        // Act
+        let numbers = count(WordCountConfig {
+            remove_numbers: true,
+            ..Default::default()
+        });
+        let integers = count(WordCountConfig {
+            remove_integers: true,
+            ..Default::default()
+        });
+        let floats = count(WordCountConfig {
+            remove_floats: true,
+            ..Default::default()
+        });
+
+        // Assert
+        assert_eq!(numbers, vec!["-", "1e-9", "v1.2", "word123"]);
+        assert_eq!(
+            integers,
+            vec!["-", "1e-9", "2023-01-01", "3.14", "v1.2", "word123"]
+        );
+        assert_eq!(
+            floats,
+            vec!["-", "-7", "123", "2023-01-01", "v1.2", "word123"]
+        );
+    }
+
+    #[test]
+    fn test_read_word_list() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stopwords.txt");
+        std::fs::write(&path, "the\n  a \n\nis\n").unwrap();
+
+        // Act
+        let words = read_word_list(&path).unwrap();
+
+        // Assert
+        assert_eq!(
+            words,
+            HashSet::from([String::from("the"), String::from("a"), String::from("is")])
+        );
+    }
+
+    #[test]
+    fn test_process_chunk_returns_token_count() {
+        // Arrange
+        let chunk = vec![String::from("a b a"), String::from(""), String::from("c")];
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        let counted = process_chunk(
+            &chunk,
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        assert_eq!(counted, 4);
+    }
+
+    #[test]
+    fn test_count_lines_in_chunk_skips_blank_lines() {
+        // Arrange
+        let chunk = vec!["first", "", "  \t", "second"];
+
+        // Act
+        let lines = count_lines_in_chunk(&chunk);
+
+        // Assert
+        assert_eq!(lines, 2);
+    }
+
+    #[test]
+    fn test_count_reader_totals_without_trailing_newline() {
+        // Arrange
+        let reader = io::Cursor::new("one two\n\nthree");
+
+        // Act
+        let totals =
+            count_reader(reader, &WordCountConfig::default(), &mut BTreeMap::new()).unwrap();
+
+        // Assert
+        assert_eq!(totals.lines, 2);
+        assert_eq!(totals.words, 3);
+    }
+
+    #[test]
+    fn test_count_reader_chars_and_bytes() {
+        // Arrange
+        let text = "héllo\nwörld\r\n\nend";
+
+        // Act
+        let totals = count_reader(
+            io::Cursor::new(text),
+            &WordCountConfig::default(),
+            &mut BTreeMap::new(),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(totals.bytes, text.len());
+        assert_eq!(totals.bytes, 19);
+        assert_eq!(totals.chars, 17);
+    }
+
+    #[test]
+    fn test_count_file_mmap_chars_and_bytes() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt");
+        let text = "héllo\nwörld\r\n\nend";
+        std::fs::write(&path, text).unwrap();
+        let config = WordCountConfig {
+            mmap: true,
+            ..Default::default()
+        };
+
+        // Act
+        let totals = count_file(&path, &config, &mut BTreeMap::new()).unwrap();
+
+        // Assert
+        assert_eq!(totals.bytes, 19);
+        assert_eq!(totals.chars, 17);
+    }
+
+    #[test]
+    fn test_process_chunk_split_regex() {
+        // Arrange
+        let chunk = vec![String::from(",well-known,,state-of-the-art, well")];
+        let config = WordCountConfig {
+            split_regex: Some(Regex::new("[-,]").unwrap()),
+            ..Default::default()
+        };
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut word_counts);
+
+        // Assert
+        assert_eq!(word_counts.get("well"), Some(&1));
+        assert_eq!(word_counts.get(" well"), Some(&1));
+        assert_eq!(word_counts.get("state"), Some(&1));
+        assert_eq!(word_counts.get(""), None);
+        assert_eq!(word_counts.len(), 7);
+    }
+
+    #[test]
+    fn test_process_chunk_split_regex_whitespace_equivalent() {
+        // Arrange
+        let chunk = vec![String::from("  the quick\tbrown  fox ")];
+        let config = WordCountConfig {
+            split_regex: Some(Regex::new(r"\s+").unwrap()),
+            ..Default::default()
+        };
+        let mut regex_counts = BTreeMap::new();
+        let mut whitespace_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(&chunk, &config, &mut VecDeque::new(), &mut regex_counts);
+        process_chunk(
+            &chunk,
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut whitespace_counts,
+        );
+
+        // Assert
+        assert_eq!(regex_counts, whitespace_counts);
+    }
+
+    #[test]
+    fn test_process_chunk_sorted_keys() {
+        // Arrange
+        let chunk = vec![
+            String::from("zebra apple cat"),
+            String::from("dog elephant bear"),
+        ];
+        let mut word_counts = BTreeMap::new();
+
+        // Act
+        process_chunk(
+            &chunk,
+            &WordCountConfig::default(),
+            &mut VecDeque::new(),
+            &mut word_counts,
+        );
+
+        // Assert
+        let keys: Vec<_> = word_counts.keys().collect();
+        assert_eq!(
+            keys,
+            vec!["apple", "bear", "cat", "dog", "elephant", "zebra"]
+        );
+    }
+}
@@ -1,133 +1,5540 @@
-use std::collections::BTreeMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, IsTerminal, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{ArgAction, CommandFactory, Parser, ValueEnum};
+use glob::Pattern;
+use regex::Regex;
+use walkdir::WalkDir;
+#[cfg(feature = "tokio")]
+use word_count::count_path_async;
+#[cfg(feature = "sqlite")]
+use word_count::export_sqlite;
+#[cfg(feature = "plot")]
+use word_count::write_frequency_plot;
+#[cfg(feature = "transliterate")]
+use word_count::TranslitScheme;
+use word_count::{
+    builtin_stopwords, case_variants, compare_corpora, compare_to_reference, count_path_observed,
+    count_reader_observed, counted_form, detect_encoding, diff_counts, encoding_report,
+    estimate_map_bytes, extract_column, failed_assertions, frequency_bands, group_by_first_char,
+    group_by_script, heaps_fit, is_binary, is_longer_than, json_text, longest_words, merge_counts,
+    merge_counts_capped, read_checkpoint, read_counts, read_word_list, relative_frequencies,
+    scale_counts, select_entries, shortest_words, stats_json, tfidf, top_per_length,
+    unknown_placeholders, validate_config, word_length_distribution, write_arpa,
+    write_case_variants, write_concordance, write_context_diversity, write_cooccurrences,
+    write_corpus_comparison, write_counts, write_diff, write_frequency_bands, write_histogram,
+    write_paragraphs, write_reference_diff, write_relative_frequencies, write_section,
+    write_selected_counts, write_snapshot, write_stats, write_tfidf, write_top_per_length,
+    write_vocabulary, write_vocabulary_growth, write_weighted_counts, write_windows,
+    write_word_cloud, write_word_groups, write_word_length_distribution, write_word_lengths,
+    Checkpoint, CheckpointWriter, ChunkObserver, CloudScale, CodeLang, ColorChoice,
+    CommentLanguage, Concordance, ContextDiversity, Cooccurrences, CountAssertion, Encoding,
+    ExtractedTokens, FirstOccurrences, FirstSeen, HashAlgorithm, InputFormat, LineDedup, LineRange,
+    LineReservoir, LogBase, Mattr, NdjsonStream, NewWordLines, NewWordsPerLine, NgramJoin,
+    OutputConfig, OutputFormat, Paragraphs, PositionWeights, ReferenceDiffFormat, SortOrder,
+    StemLang, StopwordLang, TfidfVariant, Totals, UnicodeForm, VocabularyGrowth, Windows,
+    WordContexts, WordCountConfig, WordsPerLine, DEFAULT_CHUNK_SIZE, DEFAULT_SLEEP_MS, STDIN_PATH,
+};
+
+/// The name the text of `--process-text` is shown under, e.g. by `--per-file`
+const TEXT_INPUT: &str = "<text>";
+
+/// The width of a `--histogram` where the terminal does not tell its own
+const HISTOGRAM_WIDTH: usize = 80;
+
+/// The number of times `--benchmark` counts the inputs
+const BENCHMARK_RUNS: usize = 3;
+
+/// The largest thread count accepted by `--threads`
+const MAX_THREADS: usize = 512;
+
+/// The smallest buffer accepted by `--buffer-size`
+const MIN_BUFFER_SIZE: usize = 64;
+
+/// The largest buffer accepted by `--buffer-size`, 1 GiB
+const MAX_BUFFER_SIZE: usize = 1024 * 1024 * 1024;
 
 #[derive(Parser)]
 struct Cli {
-    /// The path to the file to read
-    path: std::path::PathBuf,
+    /// The paths of the files to read; reads from stdin if omitted or `-`
+    paths: Vec<std::path::PathBuf>,
+
+    /// Count the words of TEXT instead of reading any file
+    ///
+    /// The text is split into lines on `\n` and goes through the same
+    /// pipeline as a file would.
+    #[arg(long, value_name = "TEXT", conflicts_with_all = ["paths", "diff", "watch", "recursive"])]
+    process_text: Option<String>,
+
+    /// Split stdin into documents at every CHAR and count each document as
+    /// an input of its own, like a file, e.g. with a table each by --per-file
+    ///
+    /// CHAR is a single character or one of the escapes `\0`, `\t`, `\n` and
+    /// `\uXXXX`, e.g. `\u0000` for NUL. Stdin is read completely before the
+    /// first document is counted; a delimiter at the very end starts no empty
+    /// document.
+    #[arg(
+        long,
+        value_name = "CHAR",
+        value_parser = parse_stdin_delimiter,
+        conflicts_with_all = [
+            "paths", "input_glob", "process_text", "benchmark", "watch", "input_encoding_detect",
+        ]
+    )]
+    stdin_delimiter: Option<char>,
+
+    /// Print the number of lines containing anything but whitespace
+    #[arg(long)]
+    count_lines: bool,
+
+    /// Print the number of Unicode scalar values, including line terminators
+    #[arg(long)]
+    count_chars: bool,
+
+    /// Print the number of grapheme clusters, the chars as they are seen,
+    /// including line terminators
+    ///
+    /// An emoji like 👍🏽 or a letter with a combining accent is one grapheme
+    /// cluster of several Unicode scalar values.
+    #[arg(long)]
+    count_graphemes: bool,
+
+    /// Print the number of bytes, including line terminators
+    #[arg(long)]
+    count_bytes: bool,
+
+    /// Print the word counts; combined with any other --count-* flag the
+    /// totals are printed on one summary line instead
+    #[arg(long)]
+    count_words: bool,
+
+    /// Print the number of sentences, each ending at a token that ends with
+    /// one of the --sentence-end chars
+    ///
+    /// Combined with --count-words the average number of words per sentence
+    /// is printed as well.
+    #[arg(long)]
+    count_sentences: bool,
+
+    /// The chars that end a sentence for --count-sentences, e.g. `.!?;`
+    #[arg(
+        long,
+        value_name = "CHARS",
+        default_value = ".!?",
+        requires = "count_sentences",
+        value_parser = parse_sentence_end
+    )]
+    sentence_end: String,
+
+    /// Print only the summary instead of the word counts
+    ///
+    /// Without --show-stats or a --count-* flag the --show-stats line is the
+    /// summary, with a warning.
+    #[arg(short = 'q', long, conflicts_with_all = ["per_file", "stream", "diff"])]
+    quiet: bool,
+
+    /// Print the --show-stats line, and the totals of the --count-* flags,
+    /// but never the word counts
+    ///
+    /// With `--format json` the summary is a single JSON object instead.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "quiet", "total_only", "per_file", "no_aggregate", "stream", "diff", "histogram",
+            "frequency_bands", "window", "per_paragraph", "longest_words", "shortest_words",
+        ]
+    )]
+    summarize_only: bool,
+
+    /// Print nothing but the total number of counted words, e.g. for
+    /// `$(word_count --total-only file.txt)`
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "quiet",
+            "show_stats",
+            "words_per_sentence",
+            "avg_word_length",
+            "words_per_line",
+            "count_unique_per_line",
+            "per_file",
+            "count_lines",
+            "entropy",
+            "perplexity",
+            "count_chars",
+            "count_graphemes",
+            "count_bytes",
+            "count_words",
+            "count_sentences",
+            "diff",
+            "stream",
+        ]
+    )]
+    total_only: bool,
+
+    /// Show the progress of every file on stderr while it is counted
+    ///
+    /// Progress is only shown when stderr is a terminal.
+    #[arg(long)]
+    progress: bool,
+
+    /// Print the lines read, the unique words and the estimated memory of
+    /// the counts to stderr after every chunk, with the peak RSS where the
+    /// system reports it
+    ///
+    /// With --use-hashmap the counts only reach the sorted map once a file
+    /// is done, so the unique words stay 0 until then.
+    #[arg(short = 'v', long, conflicts_with = "progress")]
+    verbose: bool,
+
+    /// Print a `chunk_id,lines,unique_words_so_far,process_time_us,sleep_time_us`
+    /// CSV row to stderr after every round of chunks
+    ///
+    /// The process time covers counting the round, not reading it. The unique
+    /// words are those of the current input, and with --use-hashmap they stay
+    /// 0 until its end. With --parallel a round holds a chunk per thread.
+    #[arg(long)]
+    profile_chunks: bool,
+
+    /// Write the --profile-chunks CSV to FILE instead of stderr
+    #[arg(long, value_name = "FILE", requires = "profile_chunks")]
+    profile_output: Option<PathBuf>,
+
+    /// Print the time spent reading, collecting, counting, sorting and
+    /// writing all inputs to stderr at the end, a `stage: N us (P%)` line
+    /// each with its share of the total
+    ///
+    /// The time slept between rounds is printed apart and left out of the
+    /// total. With --async the reading overlaps the counting.
+    #[arg(long)]
+    benchmark_profile: bool,
+
+    /// Print a `chunk N: lines=M total_tokens=T unique_words=U ttr=X.XXXX
+    /// elapsed_ms=Y` line to stderr after every round of chunks
+    ///
+    /// Shows whether the word distribution settles as more of the input is
+    /// counted. Like with --profile-chunks, the chunks are numbered on over
+    /// all inputs but the tokens and unique words are those of the current
+    /// input, the unique words staying 0 until its end with --use-hashmap.
+    /// The elapsed time is since the start of counting.
+    #[arg(long)]
+    per_chunk_stats: bool,
+
+    /// Write the --per-chunk-stats lines to FILE instead of stderr
+    #[arg(long, value_name = "FILE", requires = "per_chunk_stats")]
+    chunk_stats_output: Option<PathBuf>,
+
+    /// Compare the word counts of exactly two files, printing the words only
+    /// in either of them and the words whose count changed
+    #[arg(long, conflicts_with = "per_file")]
+    diff: bool,
+
+    /// Compare the counts of the inputs against the reference frequency list
+    /// in FILE, printing every counted word with its count in both and the
+    /// change, or marked [new] if the reference lacks it
+    ///
+    /// The reference holds `word: count` lines like the text format prints.
+    /// Its words are lowercased with --ignore-case.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["diff", "per_file", "benchmark", "watch", "stream", "checkpoint"]
+    )]
+    word_list_diff: Option<PathBuf>,
+
+    /// Also print the words of the --word-list-diff reference that were not
+    /// counted, marked [missing]
+    #[arg(long, requires = "word_list_diff")]
+    show_missing: bool,
+
+    /// The format of the --word-list-diff comparison
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t = ReferenceDiffFormat::Text,
+        requires = "word_list_diff"
+    )]
+    word_list_diff_format: ReferenceDiffFormat,
+
+    /// Compare the counts of the inputs against those of the text in
+    /// BASELINE_FILE, printing every counted word with how many times more
+    /// frequent it is than in the baseline, or marked [new] if the baseline
+    /// lacks it
+    ///
+    /// The baseline is counted with the same options as the inputs.
+    #[arg(
+        long,
+        value_name = "BASELINE_FILE",
+        conflicts_with_all = [
+            "diff", "word_list_diff", "compare_dirs", "merge", "per_file", "benchmark", "watch",
+            "stream", "checkpoint", "append",
+        ]
+    )]
+    relative_to: Option<PathBuf>,
+
+    /// Add one to every count of --relative-to, so the words the baseline
+    /// lacks get a ratio too
+    #[arg(long, requires = "relative_to")]
+    laplace_smoothing: bool,
+
+    /// Order the words of --relative-to by their ratio, largest first,
+    /// instead of alphabetically
+    #[arg(long, requires = "relative_to")]
+    sort_by_ratio: bool,
+
+    /// Score the words of every input by TF-IDF against all inputs, printing
+    /// a `word<TAB>tf<TAB>idf<TAB>tfidf` table per input under a `=== path
+    /// ===` header, highest score first
+    ///
+    /// Every input is a document; the IDF of a word is ln(N / df). --top
+    /// limits the rows of every table.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "diff", "word_list_diff", "compare_dirs", "merge", "relative_to", "per_file",
+            "benchmark", "watch", "stream", "checkpoint", "append",
+        ]
+    )]
+    tfidf: bool,
+
+    /// How --tfidf weighs the count of a word in an input
+    #[arg(
+        long,
+        value_name = "VARIANT",
+        value_enum,
+        default_value_t = TfidfVariant::Raw,
+        requires = "tfidf"
+    )]
+    tfidf_variant: TfidfVariant,
+
+    /// Compare the vocabularies of the files below two directories, printing
+    /// the words of both with the ratio of their counts and the words only
+    /// in either of them
+    ///
+    /// Only the files whose name matches --glob are counted, and binary
+    /// files are skipped as with --recursive. Supports the text, json and
+    /// csv formats.
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["DIR1", "DIR2"],
+        conflicts_with_all = [
+            "paths", "diff", "word_list_diff", "per_file", "benchmark", "watch", "stream",
+            "checkpoint", "process_text",
+        ]
+    )]
+    compare_dirs: Option<Vec<PathBuf>>,
+
+    /// Sum up the counts of FILEs printed by earlier runs, e.g. on parts of
+    /// a corpus, and print them instead of counting any input
+    ///
+    /// The output options like --top, --output-sort-key and --output apply
+    /// to the sums as to new counts.
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 1..,
+        conflicts_with_all = [
+            "paths", "input_glob", "process_text", "stdin_delimiter", "diff", "compare_dirs",
+            "word_list_diff", "per_file", "benchmark", "watch", "stream", "checkpoint", "resume",
+            "append",
+        ]
+    )]
+    merge: Vec<PathBuf>,
+
+    /// The format of the --merge files, `text` or `json`; json if --format
+    /// is json and text otherwise
+    #[arg(long, value_name = "FORMAT", value_enum, requires = "merge")]
+    merge_format: Option<OutputFormat>,
+
+    /// Follow every count of --compare-dirs with the log-likelihood G² of the
+    /// word, ordering the words by it instead of by count
+    ///
+    /// The larger G² is, the more significantly the word is over- or
+    /// under-represented in either directory; above 3.84 is significant at
+    /// p < 0.05.
+    #[arg(long, requires = "compare_dirs")]
+    log_likelihood: bool,
+
+    /// Print the counts of the current input after every chunk
+    ///
+    /// On a terminal the previous counts are overwritten like a live
+    /// dashboard, otherwise a timestamped JSON snapshot is printed per chunk.
+    /// --top limits the snapshots and the sleep between chunks becomes the
+    /// refresh interval.
+    #[arg(long, conflicts_with_all = ["diff", "use_hashmap"])]
+    stream: bool,
+
+    /// Count the files again whenever one of them changes, replacing the
+    /// previous output
+    ///
+    /// Every change triggers a full re-read of all files rather than counting
+    /// only the appended lines, so truncated or rewritten files are counted
+    /// correctly too. A deleted file is waited for until it reappears.
+    #[arg(long, conflicts_with_all = ["stream", "diff", "checkpoint", "resume", "append"])]
+    watch: bool,
+
+    /// The number of milliseconds between two checks for changes in --watch mode
+    #[arg(long, value_name = "MS", default_value_t = 1000, requires = "watch")]
+    watch_interval: u64,
+
+    /// Count all files below every directory given as a path
+    #[arg(short = 'r', long)]
+    recursive: bool,
+
+    /// Count the files matching PATTERN as if they were given as paths, e.g.
+    /// `logs/2024-*.log`; may be given several times
+    ///
+    /// The matches follow the paths, in alphabetical order per pattern. A
+    /// pattern matching nothing is warned about but no error.
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = parse_input_glob,
+        conflicts_with = "process_text"
+    )]
+    input_glob: Vec<String>,
+
+    /// Only count the files found by --recursive whose name matches PATTERN
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        default_value = "*",
+        value_parser = Pattern::new,
+        requires = "recursive"
+    )]
+    glob: Pattern,
+
+    /// Follow symbolic links while walking directories
+    #[arg(long, requires = "recursive")]
+    follow_symlinks: bool,
+
+    /// Print a separate count table for every file before the aggregate
+    #[arg(long)]
+    per_file: bool,
+
+    /// Only print the tables of the single files in --per-file mode, leaving
+    /// out the combined table
+    #[arg(long, requires = "per_file")]
+    no_aggregate: bool,
+
+    /// Treat words that differ only in case as the same word
+    #[arg(short = 'i', long)]
+    ignore_case: bool,
+
+    /// Strip leading and trailing punctuation from each word before counting
+    #[arg(long)]
+    strip_punct: bool,
+
+    /// Normalize every word into the Unicode normalization FORM before
+    /// anything else, so that e.g. a precomposed "é" and an `e` followed by
+    /// a combining accent count as the same word; `nfkc` if not given
+    ///
+    /// The compatibility forms `nfkc` and `nfkd` also turn ligatures like
+    /// "ﬁ", super- and subscripts and fullwidth letters into plain ones.
+    /// Only the letters of the Latin, Greek, Cyrillic and Hangul scripts and
+    /// common punctuation are normalized.
+    #[arg(
+        long,
+        visible_alias = "normalize-unicode",
+        value_name = "FORM",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "nfkc"
+    )]
+    normalise_unicode: Option<UnicodeForm>,
+
+    /// Only count words with at least N characters
+    ///
+    /// The length is measured in Unicode scalar values, not bytes, so "café"
+    /// has a length of 4.
+    #[arg(long, value_name = "N")]
+    min_length: Option<usize>,
+
+    /// Only count words with at most N characters
+    ///
+    /// The length is measured in Unicode scalar values, not bytes, so "café"
+    /// has a length of 4.
+    #[arg(long, value_name = "N")]
+    max_length: Option<usize>,
+
+    /// Cut words with more than N characters down to N, followed by the
+    /// --truncate-marker, instead of skipping them like --max-length
+    ///
+    /// Long tokens with the same start, like URLs, are counted together.
+    #[arg(
+        long,
+        value_name = "N",
+        visible_alias = "max-token-length",
+        value_parser = parse_truncate_length
+    )]
+    truncate_token: Option<usize>,
+
+    /// Appended to every word cut by --truncate-token
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = "…",
+        requires = "truncate_token"
+    )]
+    truncate_marker: String,
+
+    /// Split lines on the matches of PATTERN instead of on whitespace
+    ///
+    /// The default split is equivalent to `\s+`. The empty tokens around
+    /// leading, repeated or zero-width separators are skipped, and e.g.
+    /// `[.,;!?]+ *` splits on runs of punctuation.
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        visible_alias = "word-separator-regex",
+        value_parser = Regex::new
+    )]
+    split_regex: Option<Regex>,
+
+    /// Split lines on every CHAR instead of on whitespace, e.g. `,` or `:`
+    ///
+    /// A whitespace CHAR like a tab still splits on runs of any whitespace.
+    /// This is a naive splitter: `,` does not know about the quoted fields of
+    /// CSV files.
+    #[arg(long, value_name = "CHAR", conflicts_with_all = ["split_regex", "chars"])]
+    field_separator: Option<char>,
+
+    /// Split lines on every occurrence of STR instead of on whitespace, like
+    /// --field-separator for separators of several chars
+    #[arg(
+        long,
+        value_name = "STR",
+        value_parser = parse_field_separator,
+        conflicts_with_all = ["field_separator", "split_regex", "chars"]
+    )]
+    field_separator_str: Option<String>,
+
+    /// Skip tokens made of digits, `.` and `-` only, like IP addresses, dates
+    /// and version numbers
+    #[arg(long)]
+    remove_numbers: bool,
+
+    /// Count every number as TOKEN instead, `<NUM>` if not given, so the
+    /// numbers are counted together whatever their value
+    ///
+    /// A number is a token of digits only, or one matching --number-regex.
+    /// The placeholder is neither lowercased nor filtered.
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        num_args = 0..=1,
+        default_missing_value = "<NUM>",
+        conflicts_with_all = ["remove_numbers", "remove_integers", "chars"]
+    )]
+    replace_numbers: Option<String>,
+
+    /// Recognise the numbers of --replace-numbers by REGEX, e.g.
+    /// `^-?\d+(\.\d+)?$` for decimals too; anchor it to match whole tokens
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new, requires = "replace_numbers")]
+    number_regex: Option<Regex>,
+
+    /// Count every token starting with `http://` or `https://` as TOKEN
+    /// instead, `<URL>` if not given
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        num_args = 0..=1,
+        default_missing_value = "<URL>",
+        conflicts_with = "chars"
+    )]
+    replace_urls: Option<String>,
+
+    /// Remove the tokens starting with `http://` or `https://` from every
+    /// line before it is tokenized, counting them apart
+    ///
+    /// The brackets, quotes and punctuation around a URL are not part of it.
+    /// Their counts are written to --url-output, if given.
+    #[arg(long, conflicts_with_all = ["chars", "replace_urls"])]
+    extract_urls: bool,
+
+    /// Write the counts of the URLs removed by --extract-urls to FILE, in the
+    /// format and order of the output
+    #[arg(long, value_name = "FILE", requires = "extract_urls")]
+    url_output: Option<PathBuf>,
+
+    /// Remove the email addresses from every line before it is tokenized,
+    /// counting them apart like --extract-urls
+    #[arg(long, conflicts_with = "chars")]
+    extract_emails: bool,
+
+    /// Write the counts of the email addresses removed by --extract-emails to
+    /// FILE, like --url-output
+    #[arg(long, value_name = "FILE", requires = "extract_emails")]
+    email_output: Option<PathBuf>,
+
+    /// Skip decimal integers like `42` or `-7`
+    #[arg(long)]
+    remove_integers: bool,
+
+    /// Skip floating-point literals like `3.14` or `1e-9`
+    #[arg(long)]
+    remove_floats: bool,
+
+    /// Only count the words made of letters only, like "café" but not
+    /// "word2vec"
+    ///
+    /// Checked after --strip-punct, so "(quoted)" counts as "quoted" with it.
+    #[arg(long, conflicts_with = "include_only_alphanumeric")]
+    include_only_alpha: bool,
+
+    /// Only count the words made of letters and digits only, like
+    /// "word2vec" but not "x-y"
+    #[arg(long)]
+    include_only_alphanumeric: bool,
+
+    /// Split every word between a lowercase letter and a following
+    /// uppercase one, so `BufReader` counts as `Buf` and `Reader`
+    ///
+    /// Runs of capitals stay together, like the `HTTP` of `HTTPServer`. The
+    /// parts are normalised like any other word, e.g. by --ignore-case.
+    #[arg(long, conflicts_with = "chars")]
+    split_on_camelcase: bool,
+
+    /// Split every word at its underscores, so `max_line_len` counts as
+    /// `max`, `line` and `len`
+    #[arg(long, conflicts_with = "chars")]
+    split_on_underscore: bool,
+
+    /// Split every word at its hyphens, so `state-of-the-art` counts as
+    /// `state`, `of`, `the` and `art`
+    ///
+    /// Hyphens at either end of a word and repeated ones leave no empty
+    /// words behind.
+    #[arg(long, conflicts_with = "chars")]
+    split_hyphenated: bool,
+
+    /// Keep the words with a digit whole with --split-hyphenated, like
+    /// `2023-01-01` or `3-D`
+    #[arg(long, requires = "split_hyphenated")]
+    no_split_hyphenated_numbers: bool,
+
+    /// Tokenize the input as source code of --code-lang, splitting at every
+    /// char that cannot be part of an identifier
+    ///
+    /// Comment markers, string delimiters and punctuation like `(` or `;`
+    /// are dropped, and identifiers are split like with --split-on-camelcase
+    /// and --split-on-underscore. It is no parser: the words of comments and
+    /// strings count like identifiers, and keywords like any other word.
+    #[arg(
+        long,
+        conflicts_with_all = ["chars", "split_regex", "field_separator", "field_separator_str"]
+    )]
+    tokenize_code: bool,
+
+    /// Read input that is tokenized already, like the output of spaCy or
+    /// Stanford CoreNLP, counting every whitespace-separated field as a token
+    ///
+    /// This is how lines are split anyway, so the counts stay the same, but
+    /// no option that splits tokens any further can be combined with it.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "chars", "char_ngrams", "split_regex", "field_separator", "field_separator_str",
+            "split_on_camelcase", "split_on_underscore", "split_hyphenated", "tokenize_code",
+        ]
+    )]
+    pretokenized: bool,
+
+    /// Read tokenized input with one sentence per line, like --pretokenized,
+    /// and count every line with a token as a sentence
+    ///
+    /// --words-per-sentence and --count-sentences then need no guess where
+    /// a sentence ends. Implies --pretokenized.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "chars", "char_ngrams", "split_regex", "field_separator", "field_separator_str",
+            "split_on_camelcase", "split_on_underscore", "split_hyphenated", "tokenize_code",
+            "sentence_end",
+        ]
+    )]
+    sentence_per_line: bool,
+
+    /// The language of --tokenize-code
+    #[arg(
+        long,
+        value_name = "LANG",
+        default_value = "rust",
+        requires = "tokenize_code"
+    )]
+    code_lang: CodeLang,
+
+    /// Only count the words matching PATTERN, e.g. `^[a-z]+$`
+    #[arg(long, value_name = "PATTERN", value_parser = Regex::new)]
+    include_pattern: Option<Regex>,
+
+    /// Skip every word matching PATTERN, e.g. `^\d+$` for plain numbers
+    ///
+    /// Combined with --include-pattern a word has to match that pattern and
+    /// must not match this one.
+    #[arg(long, value_name = "PATTERN", value_parser = Regex::new)]
+    exclude_pattern: Option<Regex>,
+
+    /// Count the frequency of every character instead of every word
+    ///
+    /// Whitespace other than line terminators is counted as well.
+    #[arg(long)]
+    chars: bool,
+
+    /// Do not count whitespace characters in --chars mode
+    #[arg(long, requires = "chars")]
+    no_whitespace_chars: bool,
+
+    /// Count sequences of N consecutive words, joined by --ngram-separator
+    ///
+    /// The sequences continue across line boundaries; 1 counts single words.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = parse_ngrams)]
+    ngrams: usize,
+
+    /// Join the words of --ngrams with STR, so that a bigram like `new_york`
+    /// cannot be mistaken for a single word with a space
+    #[arg(long, value_name = "STR", default_value = "_")]
+    ngram_separator: String,
+
+    /// Join the words of --ngrams after TEMPLATE instead, in which `{1}`,
+    /// `{2}` and so on stand for the first, second and so on word, e.g.
+    /// `{1}+{2}+{3}` for trigrams
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = NgramJoin::template,
+        conflicts_with = "ngram_separator"
+    )]
+    ngram_format: Option<NgramJoin>,
+
+    /// Count the sequences of N consecutive chars of every word instead of
+    /// the words, e.g. `hel`, `ell` and `llo` of "hello" for 3
+    ///
+    /// Words shorter than N are skipped unless padded by
+    /// --char-ngrams-word-boundaries.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = parse_char_ngrams,
+        conflicts_with_all = ["ngrams", "chars"]
+    )]
+    char_ngrams: Option<usize>,
+
+    /// Pad every word with N - 1 `^` before and `$` after it for
+    /// --char-ngrams, so the n-grams at its edges stand out
+    #[arg(long, requires = "char_ngrams")]
+    char_ngrams_word_boundaries: bool,
+
+    /// Skip every word listed in FILE, one word per line
+    #[arg(long, value_name = "FILE")]
+    stopwords: Option<PathBuf>,
+
+    /// Count the stems of the words instead of the words, so "runs" and
+    /// "running" both count as "run"
+    ///
+    /// Stemming is lossy: unrelated words can share a stem and a stem is not
+    /// always a word. It only applies to lowercase words, so it is best
+    /// combined with --ignore-case, and it happens before --stopwords, which
+    /// must then list stems.
+    #[arg(long)]
+    stem: bool,
+
+    /// The language of --stem
+    #[arg(long, value_name = "LANG", default_value = "en", requires = "stem")]
+    stem_lang: StemLang,
+
+    /// Remove PREFIX from every word starting with it, e.g. `un` to count
+    /// "unhappy" as "happy"; may be given several times, the prefixes being
+    /// removed in turn
+    ///
+    /// This happens after --ignore-case, which lowercases PREFIX as well, and
+    /// before --stem. A word left empty is not counted.
+    #[arg(long, value_name = "PREFIX", action = ArgAction::Append)]
+    prefix_strip: Vec<String>,
+
+    /// Remove SUFFIX from every word ending with it, after --prefix-strip;
+    /// may be given several times like it
+    ///
+    /// Unlike --stem this is no more than that, so `ing` turns "running"
+    /// into "runn".
+    #[arg(long, value_name = "SUFFIX", action = ArgAction::Append)]
+    suffix_strip: Vec<String>,
+
+    /// Transliterate the words into Latin letters with SCHEME, e.g. `gost`
+    /// for Russian
+    ///
+    /// This happens after --ignore-case and before --stem and --stopwords,
+    /// which must then list transliterated words. Chars the scheme has no
+    /// letters for are kept, so other words are counted unchanged.
+    #[cfg(feature = "transliterate")]
+    #[arg(long, value_enum, value_name = "SCHEME")]
+    transliterate: Option<TranslitScheme>,
+
+    /// Skip the stopwords built in for LANG, together with those of
+    /// --stopwords
+    #[arg(long, value_name = "LANG")]
+    stopwords_lang: Option<StopwordLang>,
+
+    /// Only count the words listed in FILE, one word per line
+    ///
+    /// Applied after --stopwords, so a word in both files is skipped.
+    #[arg(long, value_name = "FILE")]
+    vocab: Option<PathBuf>,
+
+    /// The encoding of the inputs
+    ///
+    /// Inputs in any encoding but UTF-8 are read into memory as a whole to be
+    /// decoded. A UTF-16 byte order mark is skipped.
+    #[arg(long, value_name = "ENC", value_enum, default_value_t = Encoding::Utf8)]
+    encoding: Encoding,
+
+    /// Replace invalid UTF-8 sequences with U+FFFD instead of skipping the
+    /// inputs containing them
+    ///
+    /// With --verbose or --show-stats the number of replaced sequences is
+    /// printed to stderr.
+    #[arg(long, conflicts_with_all = ["encoding", "input_encoding_detect"])]
+    force_utf8: bool,
+
+    /// Detect the encoding of every input file from its first 4 KiB instead
+    /// of taking --encoding, reporting it on stderr
+    ///
+    /// Only the encodings of --encoding are told apart, and text without any
+    /// non-ASCII char is read as UTF-8. The raw bytes of the file are looked
+    /// at, so compressed files cannot be detected.
+    #[arg(long, conflicts_with_all = ["encoding", "process_text"])]
+    input_encoding_detect: bool,
+
+    /// The confidence from 0 to 1 below which --input-encoding-detect gives
+    /// up on an input instead of counting it
+    #[arg(
+        long,
+        value_name = "F",
+        default_value_t = 0.75,
+        requires = "input_encoding_detect",
+        value_parser = parse_confidence
+    )]
+    encoding_confidence: f64,
+
+    /// Report the byte order mark and size of every input file on stderr,
+    /// with how many of its sequences are valid and invalid UTF-8
+    ///
+    /// Without a byte order mark UTF-8 is assumed, whatever --encoding says.
+    /// The raw bytes of the file are looked at, so compressed files are
+    /// reported as they are stored.
+    #[arg(long, conflicts_with_all = ["process_text", "stdin_delimiter"])]
+    report_encoding: bool,
+
+    /// The format of the inputs
+    ///
+    /// With `json` every line is parsed as a JSON value and its strings are
+    /// counted. Lines that are no valid JSON are skipped with a warning. With
+    /// `csv` the fields of every record are counted as a line, see
+    /// --csv-text-column, quoted fields spanning several lines included.
+    /// JSON and CSV inputs are neither decompressed nor decoded, and the chars
+    /// and bytes counted are those of the extracted text.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = InputFormat::Text)]
+    input_format: InputFormat,
+
+    /// Only count the strings below the field at PATH of every JSON line,
+    /// e.g. `message` or `request.user.name`
+    ///
+    /// The fields are separated by dots; an array on the way stands for each
+    /// of its elements. Requires --input-format json.
+    #[arg(long, value_name = "PATH")]
+    json_path: Option<String>,
+
+    /// Only count the field of every CSV record in the column N, starting at
+    /// 1, or in the column named NAME in the header; all fields without it
+    ///
+    /// Repeat it to count several columns, whose fields are joined by a
+    /// space. A name implies --csv-has-header. Requires --input-format csv.
+    #[arg(long, value_name = "N_OR_NAME")]
+    csv_text_column: Vec<String>,
+
+    /// Read the first CSV record as the names of the columns instead of
+    /// counting it
+    #[arg(long)]
+    csv_has_header: bool,
+
+    /// Only count the words of the Nth field of every line, starting at 1
+    ///
+    /// The fields are separated by whitespace unless --column-separator is
+    /// given. Lines with fewer fields are skipped.
+    #[arg(long, value_name = "N", value_parser = parse_column)]
+    column: Option<usize>,
+
+    /// Separate the fields of --column by CHAR, e.g. `|` or `,`, instead of by
+    /// whitespace
+    #[arg(long, value_name = "CHAR", requires = "column")]
+    column_separator: Option<char>,
+
+    /// Warn about every line skipped for having fewer fields than --column
+    #[arg(long, requires = "column")]
+    warn_short_lines: bool,
+
+    /// Only count the lines START to END, both included and starting at 1
+    ///
+    /// Either end can be left out, e.g. `10-` counts from line 10 to the end
+    /// of the input and `-20` the first 20 lines. The input is not read past
+    /// END.
+    #[arg(
+        long,
+        value_name = "START-END",
+        value_parser = parse_line_range,
+        allow_hyphen_values = true,
+        conflicts_with_all = ["first_seen_line", "warn_short_lines", "max_line_length"]
+    )]
+    lines: Option<LineRange>,
+
+    /// Warn on stderr about every line longer than N chars, with its number
+    /// and length
+    ///
+    /// Such lines, like base64 data or minified JavaScript, often mean the
+    /// input is not the text it was meant to be.
+    #[arg(long, value_name = "N")]
+    max_line_length: Option<usize>,
+
+    /// Skip the words of every line longer than N chars, measured like
+    /// --max-line-length
+    ///
+    /// The lines, chars and bytes of the skipped lines are still counted and
+    /// --max-line-length still warns of them, but no other option looking at
+    /// the lines themselves sees them.
+    #[arg(long, value_name = "N")]
+    skip_long_lines: Option<usize>,
+
+    /// Count every line only once per input, skipping the lines that were
+    /// read before, e.g. the repeated lines of generated text
+    ///
+    /// The skipped lines still count towards the chars, bytes and line
+    /// numbers of the input, but not towards its lines.
+    #[arg(long)]
+    deduplicate_lines: bool,
+
+    /// Print the number of duplicate lines skipped by --deduplicate-lines to
+    /// stderr
+    #[arg(long, requires = "deduplicate_lines")]
+    per_line_dedup_report: bool,
+
+    /// Only count the lines PATTERN matches anywhere in, e.g. `ERROR` for
+    /// the error lines of a log
+    ///
+    /// The other lines still count towards the chars, bytes and line numbers
+    /// of the input, but not towards its lines.
+    #[arg(long, value_name = "PATTERN", value_parser = Regex::new)]
+    line_regex: Option<Regex>,
+
+    /// Skip the lines --line-regex matches instead, like `grep -v`
+    #[arg(long, requires = "line_regex")]
+    line_regex_invert: bool,
+
+    /// Skip the comments of source code in LANG, so only the words of the
+    /// code itself are counted
+    ///
+    /// Block comments may span lines. With `html` every `<...>` tag is
+    /// skipped as well. Comment markers inside string literals are not told
+    /// apart.
+    #[arg(
+        long,
+        value_name = "LANG",
+        conflicts_with_all = ["stream", "checkpoint", "resume"]
+    )]
+    ignore_comments: Option<CommentLanguage>,
+
+    /// Recognise the duplicate lines with a Bloom filter instead of keeping
+    /// every line, taking a fixed amount of memory
+    ///
+    /// A new line may be mistaken for a duplicate and skipped, with the
+    /// probability of --dedup-fpr for up to a million distinct lines.
+    #[arg(long, requires = "deduplicate_lines")]
+    dedup_approx: bool,
+
+    /// The false positive rate of --dedup-approx
+    #[arg(
+        long,
+        value_name = "F",
+        default_value_t = 0.01,
+        value_parser = parse_false_positive_rate,
+        requires = "dedup_approx"
+    )]
+    dedup_fpr: f64,
+
+    /// The number of lines to process between two sleeps
+    ///
+    /// Smaller chunks reduce the peak memory usage at the cost of more
+    /// frequent sleeps and I/O overhead.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_CHUNK_SIZE,
+        value_parser = parse_chunk_size
+    )]
+    chunk_size: usize,
+
+    /// The number of milliseconds to sleep after every chunk
+    ///
+    /// The pause after each burst of work gives the cpu a chance to drop
+    /// into a lower power state instead of running at full frequency for
+    /// the whole file.
+    #[arg(long, value_name = "MS", default_value_t = DEFAULT_SLEEP_MS, conflicts_with = "no_sleep")]
+    sleep_ms: u64,
+
+    /// The bytes buffered per input, with an optional K, M or G suffix
+    ///
+    /// The suffixes are binary, so 4K are 4096 bytes. At least 64 bytes and
+    /// at most 1G.
+    #[arg(long, value_name = "BYTES", default_value = "1M", value_parser = parse_buffer_size)]
+    buffer_size: usize,
+
+    /// Never sleep between chunks, trading power efficiency for speed
+    #[arg(long)]
+    no_sleep: bool,
+
+    /// Count the inputs 3 times and print the median time and throughput to
+    /// stderr instead of the word counts
+    ///
+    /// Comparing a run with and without --no-sleep shows how much of the time
+    /// is spent sleeping.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "watch", "diff", "stream", "per_file", "checkpoint", "resume", "append", "output",
+            "cooccurrence",
+        ]
+    )]
+    benchmark: bool,
+
+    /// Stop counting once MS milliseconds have passed since the start,
+    /// printing the counts of what was read so far with a warning
+    ///
+    /// The time is checked after every chunk, so a smaller --chunk-size
+    /// keeps closer to the budget. The warning tells how much of the input
+    /// was counted.
+    #[arg(long, value_name = "MS", conflicts_with_all = ["watch", "benchmark"])]
+    time_budget: Option<u64>,
+
+    /// Check the arguments and print `OK: all arguments validated`, or every
+    /// problem found on stderr, without reading any input
+    ///
+    /// Besides the numbers, the word lists, the --word-list-diff reference
+    /// and the checkpoint to resume must be readable and the directory of
+    /// --output writable. An invalid regex or number fails to parse already,
+    /// with or without --dry-run.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Check the --assert-max-count, --assert-min-count and
+    /// --assert-word-absent assertions once the words are counted, exiting
+    /// with 1 if any of them fails
+    ///
+    /// The counts are printed as usual; every failed assertion is reported
+    /// on stderr before exiting.
+    #[arg(long, conflicts_with_all = ["watch", "stream", "dry_run"])]
+    ci_mode: bool,
+
+    /// Fail --ci-mode if WORD is counted more than N times; may be given
+    /// several times
+    ///
+    /// WORD is matched like a counted word, so in lowercase with
+    /// --ignore-case.
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["WORD", "N"],
+        action = ArgAction::Append,
+        requires = "ci_mode"
+    )]
+    assert_max_count: Vec<String>,
+
+    /// Fail --ci-mode if WORD is counted fewer than N times; may be given
+    /// several times
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["WORD", "N"],
+        action = ArgAction::Append,
+        requires = "ci_mode"
+    )]
+    assert_min_count: Vec<String>,
+
+    /// Fail --ci-mode if WORD is counted at all, e.g. `TODO` in release
+    /// documentation; may be given several times
+    #[arg(
+        long,
+        value_name = "WORD",
+        action = ArgAction::Append,
+        requires = "ci_mode"
+    )]
+    assert_word_absent: Vec<String>,
+
+    /// Process chunks concurrently on all cpu cores
+    #[arg(short = 'j', long)]
+    parallel: bool,
+
+    /// The number of worker threads of --parallel, at most 512, 0 for one per
+    /// cpu core
+    ///
+    /// This sizes the global thread pool, so it applies to any other parallel
+    /// work of the process as well.
+    #[arg(long, value_name = "N", requires = "parallel", value_parser = parse_threads)]
+    threads: Option<usize>,
+
+    /// Count into a hash map and sort only once per file instead of keeping
+    /// the words sorted on every insertion
+    ///
+    /// Faster for large vocabularies; the output is the same.
+    #[arg(long)]
+    use_hashmap: bool,
+
+    /// Seed the hasher of --use-hashmap and --parallel and the samples of
+    /// --random-sample and --wpl-sample-rate with N, so two runs with the
+    /// same seed and input print the same output
+    ///
+    /// N seeds the foldhash hasher, not a random number generator. The
+    /// output only depends on the hash order where --max-unique-words
+    /// decides which words to drop; by default the hasher is seeded
+    /// randomly. The samples are drawn from a pseudo-random sequence that
+    /// starts at N, offset by the path of every input for --random-sample,
+    /// or at a random state by default.
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// The hash function of the hash maps of --use-hashmap and --parallel
+    ///
+    /// The output is the same with any of them; only siphash resists
+    /// collisions crafted to slow counting down. fxhash and fnv are only
+    /// seeded by --seed. foldhash by default.
+    #[arg(long, value_name = "ALG", value_enum)]
+    hash_algorithm: Option<HashAlgorithm>,
+
+    /// Stop adding new words once N unique words are counted, bounding the
+    /// memory on adversarial input
+    ///
+    /// The words counted already keep being counted; the occurrences of
+    /// the others are dropped and reported on stderr at the end. With
+    /// --parallel or --use-hashmap, which words are dropped depends on the
+    /// order the chunks are merged in.
+    #[arg(long, value_name = "N", value_parser = parse_max_unique_words)]
+    max_unique_words: Option<usize>,
+
+    /// Stop adding new words once the counts take up about BYTES bytes, with
+    /// an optional K, M or G suffix
+    ///
+    /// Like --max-unique-words, the words counted already keep being
+    /// counted. The memory is estimated as by --verbose after every round of
+    /// chunks, so this is a soft cap that the last round may exceed; combine
+    /// it with --max-unique-words for a hard one. Not supported with --async.
+    #[arg(long, value_name = "BYTES", value_parser = parse_byte_size)]
+    max_memory: Option<usize>,
+
+    /// Read the inputs asynchronously while the previous chunk is counted
+    ///
+    /// Pays off on network filesystems and other slow storage.
+    #[cfg(feature = "tokio")]
+    #[arg(long = "async", conflicts_with_all = ["parallel", "mmap", "use_hashmap"])]
+    async_io: bool,
+
+    /// Map input files into memory instead of reading them line by line
+    ///
+    /// Avoids allocating a string per line, which pays off for large files
+    /// that are already in the page cache. Has no effect on stdin.
+    #[arg(long)]
+    mmap: bool,
+
+    /// Order the output by count, most frequent first (ties alphabetically)
+    ///
+    /// Deprecated, use --output-sort-key count-desc instead.
+    #[arg(short = 'c', long, conflicts_with = "sort_asc")]
+    sort_by_count: bool,
+
+    /// Order the output by count, least frequent first (ties alphabetically)
+    ///
+    /// Deprecated, use --output-sort-key count-asc instead.
+    #[arg(long)]
+    sort_asc: bool,
+
+    /// Order the output by KEY, ties alphabetically
+    ///
+    /// `rank` is the same as `count-desc`, and `first-seen` requires
+    /// --first-seen-line. The order of --cumulative, --top-percent and
+    /// --bottom is fixed.
+    #[arg(
+        long,
+        value_name = "KEY",
+        value_enum,
+        conflicts_with_all = ["sort_by_count", "sort_asc", "cumulative", "top_percent", "bottom"]
+    )]
+    output_sort_key: Option<SortOrder>,
+
+    /// Order the output by when every word was first seen, the first word
+    /// of the first line first
+    ///
+    /// Unlike `--output-sort-key first-seen` the words first seen on the
+    /// same line keep their order on it, and no --first-seen-line is needed.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output_sort_key", "sort_by_count", "sort_asc", "cumulative", "top_percent", "bottom",
+            "per_file", "stream",
+        ]
+    )]
+    sort_by_first_seen: bool,
+
+    /// Only print the first N entries of the sorted output
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Only print the N least frequent words, least frequent first
+    ///
+    /// Words counted as often as the Nth one are printed as well, unless
+    /// --bottom-ties is false.
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["top", "sort_by_count", "cumulative"]
+    )]
+    bottom: Option<usize>,
+
+    /// Whether --bottom also prints the words tied with the Nth one
+    #[arg(
+        long,
+        value_name = "BOOL",
+        default_value_t = true,
+        action = ArgAction::Set,
+        requires = "bottom"
+    )]
+    bottom_ties: bool,
+
+    /// Only print words counted at least N times
+    #[arg(long, value_name = "N")]
+    min_count: Option<usize>,
+
+    /// Only print words counted at most N times
+    #[arg(long, value_name = "N")]
+    max_count: Option<usize>,
+
+    /// Only print words found in at least N of the inputs, however often
+    /// each, e.g. 2 for the words shared by several files
+    ///
+    /// The words of an --append file were found in no input, so they cannot
+    /// be combined.
+    #[arg(long, value_name = "N", conflicts_with = "append")]
+    min_df: Option<usize>,
+
+    /// Only print words found in at most N of the inputs, e.g. to leave out
+    /// the function words found in nearly every file
+    #[arg(long, value_name = "N", conflicts_with = "append")]
+    max_df: Option<usize>,
+
+    /// Only print words counted exactly N times, e.g. 1 for the words seen
+    /// only once
+    #[arg(long, value_name = "N", conflicts_with_all = ["min_count", "max_count"])]
+    exactly: Option<usize>,
+
+    /// The format of the printed word counts
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Color the words and counts of the text format
+    ///
+    /// `auto` only colors when printing to a terminal rather than to a pipe
+    /// or an --output file. With --sort-by-count the 10 most frequent counts
+    /// are bold.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        default_value_t = ColorChoice::Auto,
+        default_missing_value = "always"
+    )]
+    color: ColorChoice,
+
+    /// Print the frequency per million words of every word instead of its count
+    ///
+    /// Makes the counts of inputs of different length comparable. The JSON
+    /// formats print `{"word": ..., "count": ..., "freq_pmw": ...}` objects.
+    #[arg(long)]
+    normalize: bool,
+
+    /// Print the common logarithm of every count, log₁₀(count), to four
+    /// decimal places instead of the count
+    ///
+    /// Spreads the counts of a large input, which span many orders of
+    /// magnitude, for Zipf analysis. The JSON formats print `{"word": ...,
+    /// "count": ..., "log_count": ...}` objects.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "normalize", "histogram", "top_per_length", "frequency_bands", "null_separated",
+            "stream", "append", "weight_by_position",
+        ]
+    )]
+    log_scale: bool,
+
+    /// Take the natural logarithm, ln(count), for --log-scale
+    #[arg(long, requires = "log_scale")]
+    natural_log: bool,
+
+    /// Prefix every word with its frequency rank, e.g. `1 the: 5432`
+    ///
+    /// Words with equal counts share a rank and the next count gets the next
+    /// rank. The JSON formats print objects with a `rank` field.
+    #[arg(long)]
+    show_rank: bool,
+
+    /// Follow every count with the line its word was first seen on, e.g.
+    /// `the: 5432 (first seen: line 1)`
+    ///
+    /// The lines of several inputs are numbered on from one input to the
+    /// next. The JSON formats print objects with a `first_line` field.
+    #[arg(long, conflicts_with_all = ["per_file", "stream"])]
+    first_seen_line: bool,
+
+    /// Print the words weighted by how close to the start of their input
+    /// they occur instead of their counts, e.g. for keyword extraction
+    ///
+    /// Every occurrence on line L adds 1 / (1 + ln(L)), so an occurrence on
+    /// the first line weighs 1 and one on line 1000 about 0.13. Every input
+    /// starts at line 1. Only the text, json, csv and tsv formats can print
+    /// weights.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "per_file", "per_paragraph", "no_aggregate", "stream", "window", "histogram",
+            "frequency_bands", "top_per_length", "first_seen_line", "show_rank", "bottom",
+            "top_percent", "diff",
+        ]
+    )]
+    weight_by_position: bool,
+
+    /// Sort by count and follow every count with the percentage of all words
+    /// covered by that word and the more frequent ones
+    ///
+    /// The JSON formats print objects with a `cumulative_pct` field.
+    #[arg(long, conflicts_with = "sort_asc")]
+    cumulative: bool,
+
+    /// Only print the most frequent words that together cover PCT% of all
+    /// counted words, followed by a `X words cover PCT% of the corpus` line
+    ///
+    /// The line goes to stderr with any format but text.
+    #[arg(
+        long,
+        value_name = "PCT",
+        value_parser = parse_top_percent,
+        conflicts_with_all = ["sort_asc", "top", "bottom", "per_file", "stream"]
+    )]
+    top_percent: Option<f64>,
+
+    /// Mark the first word at which the --cumulative percentage reaches PCT
+    #[arg(long, value_name = "PCT", requires = "cumulative", value_parser = parse_percentage)]
+    coverage_threshold: Option<f64>,
+
+    /// Print the text format as a table with aligned word and count columns
+    #[arg(long, conflicts_with = "format")]
+    table: bool,
+
+    /// Start the --table with a `WORD  COUNT` header and a separator line
+    #[arg(long, requires = "table")]
+    table_header: bool,
+
+    /// Pad or cut every word of the text and tsv output to exactly N
+    /// terminal columns, for tools that expect fixed-width columns
+    ///
+    /// The wide CJK chars and emoji take up two columns each. Ignored with
+    /// --format json or csv.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "append"
+    )]
+    output_width: Option<u64>,
+
+    /// Ends the words cut down by --output-width
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = "…",
+        requires = "output_width"
+    )]
+    truncation_marker: String,
+
+    /// Right-align the counts of the text and tsv output to at least N chars
+    ///
+    /// Ignored with --format json or csv.
+    #[arg(long, value_name = "N", conflicts_with = "append")]
+    count_width: Option<usize>,
+
+    /// Print every word of the text output as TEMPLATE, e.g.
+    /// `{rank}. {word} ({count})`
+    ///
+    /// The placeholders are `{word}`, `{count}`, its dense `{rank}`, its
+    /// `{freq_pmw}` frequency per million words and the `{length}` of the
+    /// word. Ignored with --format json, csv or any other format but text.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["table", "null_separated", "append"]
+    )]
+    output_format_template: Option<String>,
+
+    /// Print the N most frequent words as a bar chart, 20 if N is left out
+    ///
+    /// The longest bar fills the width of the terminal, or the `COLUMNS` of
+    /// the environment if stdout is no terminal, or 80 columns.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "20",
+        conflicts_with_all = [
+            "format", "table", "show_rank", "normalize", "cumulative", "first_seen_line",
+            "top_percent", "bottom", "per_file", "stream", "append", "null_separated", "diff",
+        ]
+    )]
+    histogram: Option<usize>,
+
+    /// Draw the --histogram bars with CHAR instead of `█`, or `#` where the
+    /// locale is not UTF-8
+    #[arg(long, value_name = "CHAR", requires = "histogram")]
+    histogram_char: Option<char>,
+
+    /// Print the N most frequent words of every length in chars instead of
+    /// every word, like `length 3: [the: 5432, and: 4901]`; 1 if N is not
+    /// given
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        conflicts_with_all = [
+            "format", "table", "show_rank", "normalize", "cumulative", "first_seen_line",
+            "top_percent", "bottom", "histogram", "frequency_bands", "per_file", "per_paragraph",
+            "window", "summarize_only", "stream", "append", "null_separated", "diff",
+        ]
+    )]
+    top_per_length: Option<usize>,
+
+    /// Only print the --top-per-length groups of the lengths within
+    /// MIN-MAX; either end may be left out, like `5-`
+    #[arg(
+        long,
+        value_name = "MIN-MAX",
+        value_parser = parse_length_range,
+        requires = "top_per_length"
+    )]
+    length_range: Option<RangeInclusive<usize>>,
+
+    /// Print how many words fall into every frequency band, like `[2-5]:
+    /// 2103 words`, instead of every word
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format", "table", "show_rank", "normalize", "cumulative", "first_seen_line",
+            "top_percent", "bottom", "histogram", "per_file", "per_paragraph", "stream",
+            "append", "null_separated", "diff",
+        ]
+    )]
+    frequency_bands: bool,
+
+    /// The upper bounds of the --frequency-bands, comma-separated and
+    /// increasing; a last band holds the words counted more often
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        default_values_t = [1, 5, 10, 100, 1000]
+    )]
+    band_boundaries: Vec<usize>,
+
+    /// Print how many words start with every char and how often they occur,
+    /// like `a (U+0061): 2103 words, 48211 total occurrences`, instead of
+    /// every word
+    ///
+    /// The most frequent chars come first, which makes stray encodings or
+    /// mixed languages stand out.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format", "table", "show_rank", "normalize", "cumulative", "first_seen_line",
+            "top_percent", "bottom", "histogram", "frequency_bands", "top_per_length", "per_file",
+            "per_paragraph", "window", "summarize_only", "stream", "append", "null_separated",
+            "diff", "log_scale", "weight_by_position",
+        ]
+    )]
+    group_by_first_char: bool,
+
+    /// Print how many words are written in every script, like `Latin` or
+    /// `Cyrillic`, and how often they occur, like --group-by-first-char
+    ///
+    /// The script of a word is that of its first letter; words without any
+    /// letter are `Common`, the letters of less common scripts `Other`.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format", "table", "show_rank", "normalize", "cumulative", "first_seen_line",
+            "top_percent", "bottom", "histogram", "frequency_bands", "top_per_length", "per_file",
+            "per_paragraph", "window", "summarize_only", "stream", "append", "null_separated",
+            "diff", "log_scale", "weight_by_position", "group_by_first_char",
+        ]
+    )]
+    group_by_script: bool,
+
+    /// Print every word in lowercase followed by every case it was written
+    /// in and its count, like `the: the=4231 The=512 THE=3`, instead of
+    /// every word
+    ///
+    /// This shows what --ignore-case would fold together, without folding
+    /// anything.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "ignore_case", "format", "table", "show_rank", "normalize", "cumulative",
+            "first_seen_line", "top_percent", "bottom", "histogram", "frequency_bands",
+            "top_per_length", "group_by_first_char", "group_by_script", "word_length_distribution",
+            "per_file", "per_paragraph", "window", "summarize_only", "stream", "append",
+            "null_separated", "diff", "log_scale", "weight_by_position",
+        ]
+    )]
+    case_variants: bool,
+
+    /// Print how often the words of every length occur, like `length 5:
+    /// 2103 occurrences (12.50%)`, from 1 char up to the longest word,
+    /// instead of every word
+    ///
+    /// With --format json the lengths are printed as a `{"N": K, ...}`
+    /// object instead.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "table", "histogram", "frequency_bands", "top_per_length", "group_by_first_char",
+            "group_by_script", "per_file", "per_paragraph", "window", "summarize_only", "stream",
+            "append", "null_separated", "diff", "weight_by_position",
+        ]
+    )]
+    word_length_distribution: bool,
+
+    /// Write the --word-length-distribution to FILE instead, printing the
+    /// words as usual
+    #[arg(long, value_name = "FILE", requires = "word_length_distribution")]
+    length_dist_output: Option<PathBuf>,
+
+    /// Print every word and count followed by a NUL instead of as `word:
+    /// count` lines, for `xargs -0` and the like
+    #[arg(
+        short = '0',
+        long,
+        conflicts_with_all = [
+            "format",
+            "table",
+            "show_rank",
+            "normalize",
+            "cumulative",
+            "first_seen_line",
+            "show_stats",
+            "words_per_sentence",
+            "avg_word_length",
+            "words_per_line",
+            "count_unique_per_line",
+            "top_percent",
+            "per_file",
+            "stream",
+            "append",
+            "diff",
+            "entropy",
+            "perplexity",
+        ]
+    )]
+    null_separated: bool,
+
+    /// Only print the words counted exactly once (hapax legomena)
+    ///
+    /// Combined with --show-stats their share of the unique words, the hapax
+    /// ratio, is printed as well.
+    #[arg(long, conflicts_with = "dis_legomena")]
+    hapax: bool,
+
+    /// Only print the words counted exactly twice (dis legomena)
+    #[arg(long)]
+    dis_legomena: bool,
+
+    /// Do not print the header row of the csv format
+    #[arg(long)]
+    no_header: bool,
+
+    /// Start the tsv format with a header row
+    #[arg(long)]
+    tsv_header: bool,
+
+    /// Pretty-print the xml format, every element on a line of its own and
+    /// indented by two spaces
+    #[arg(long)]
+    xml_indent: bool,
+
+    /// Append a `total words: N  unique words: M` line after the word counts
+    ///
+    /// The line ends with the type-token ratio (TTR) and the moving average
+    /// type-token ratio (MATTR); higher values indicate a greater lexical
+    /// variety. With any format but text the line goes to stderr, unless
+    /// --summarize-only prints nothing else.
+    #[arg(long)]
+    show_stats: bool,
+
+    /// Append the average number of words per sentence to the --show-stats line
+    ///
+    /// Sentences are found with a naive heuristic rather than a real parser:
+    /// one ends at a `.`, `!` or `?` followed by whitespace or the end of a
+    /// line, except after a few common abbreviations like "Dr.". Implies
+    /// --show-stats.
+    #[arg(long)]
+    words_per_sentence: bool,
+
+    /// Append the mean, median and mode number of chars per word to the
+    /// --show-stats line
+    ///
+    /// Every occurrence of a word counts, not just every unique word.
+    /// Implies --show-stats.
+    #[arg(long)]
+    avg_word_length: bool,
+
+    /// Append the Shannon entropy of the word distribution, in bits, and
+    /// the entropy normalised by that of a vocabulary this size to the
+    /// --show-stats line
+    ///
+    /// Like the TTR, the entropy grows with the vocabulary, so only compare
+    /// texts of a similar length. Implies --show-stats.
+    #[arg(long)]
+    entropy: bool,
+
+    /// Append the perplexity of the word distribution, 2 to the power of its
+    /// entropy, to the --show-stats line
+    ///
+    /// Implies --show-stats.
+    #[arg(long)]
+    perplexity: bool,
+
+    /// Append the minimum, maximum, mean and median words per line to the
+    /// --show-stats line
+    ///
+    /// A blank line has 0 words. Implies --show-stats.
+    #[arg(long)]
+    words_per_line: bool,
+
+    /// Append the mean and median number of new words per line, the words
+    /// no earlier line had, to the --show-stats line
+    ///
+    /// A word is new only once per line, however often it occurs on it.
+    /// Implies --show-stats.
+    #[arg(long)]
+    count_unique_per_line: bool,
+
+    /// Only keep how many lines had 0 to N - 1 new words for
+    /// --count-unique-per-line, counting the lines with more as N - 1,
+    /// instead of the number of every line
+    ///
+    /// A median in the last bucket is printed as N - 1.
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "count_unique_per_line",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    upl_histogram_buckets: Option<u64>,
+
+    /// Only count a random sample of the lines, each kept with probability
+    /// P from above 0 to 1, and scale the counts up by 1/P to estimate
+    /// those of all lines
+    ///
+    /// Faster on large inputs, as the lines left out are read but not
+    /// tokenized. The same lines are sampled again for the same --seed. The
+    /// line totals and the stats only cover the sampled lines.
+    #[arg(
+        long,
+        value_name = "P",
+        value_parser = parse_sample_rate,
+        conflicts_with_all = ["stream", "checkpoint", "resume"]
+    )]
+    random_sample: Option<f64>,
+
+    /// Only count the first of every N lines and scale the counts up by N,
+    /// a sample without gaps longer than N - 1 lines
+    ///
+    /// Combines with --random-sample and --every-nth-word, the scales
+    /// multiplying.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["stream", "checkpoint", "resume"]
+    )]
+    every_nth_line: Option<u64>,
+
+    /// Only count the first of every N words, whichever word they are, and
+    /// scale the counts up by N
+    ///
+    /// Finer-grained than sampling lines, but every line is still tokenized.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["stream", "checkpoint", "resume", "parallel"]
+    )]
+    every_nth_word: Option<u64>,
+
+    /// Print the counts of the --random-sample, --every-nth-line or
+    /// --every-nth-word sample as they are instead of scaling them up
+    #[arg(long)]
+    no_scale_estimates: bool,
+
+    /// Only track the words of this share of the lines for --words-per-line,
+    /// from above 0 to 1
+    ///
+    /// Every line is kept at random with this probability, the same lines
+    /// again for the same --seed.
+    #[arg(
+        long,
+        value_name = "R",
+        default_value_t = 1.0,
+        requires = "words_per_line",
+        value_parser = parse_sample_rate
+    )]
+    wpl_sample_rate: f64,
+
+    /// Print N lines picked at random from all inputs to stderr once they
+    /// are counted, for a look at the text, every line being as likely to
+    /// be picked
+    ///
+    /// The lines are kept while they are read, so the inputs are still read
+    /// only once, and printed in their order. The same --seed picks the
+    /// same lines again; without one, every run picks others.
+    #[arg(long, value_name = "N")]
+    sample_lines: Option<usize>,
+
+    /// Write the --sample-lines to FILE instead of stderr
+    #[arg(long, value_name = "FILE", requires = "sample_lines")]
+    sample_output: Option<PathBuf>,
+
+    /// Count the words of every paragraph, a run of lines ended by a blank
+    /// line, and append their number, average and maximum to --show-stats
+    ///
+    /// Lines that are empty or whitespace only are blank; a run of them
+    /// separates two paragraphs just like a single one.
+    #[arg(long)]
+    paragraph_mode: bool,
+
+    /// Print the number of words of every paragraph instead of the word
+    /// counts
+    #[arg(
+        long,
+        requires = "paragraph_mode",
+        conflicts_with_all = ["per_file", "stream", "histogram", "top_percent", "total_only"]
+    )]
+    per_paragraph: bool,
+
+    /// Print the top words of every window of N lines instead of the word
+    /// counts, e.g. to see the vocabulary change over a long text
+    ///
+    /// The windows run on over all inputs. --top limits the words of each,
+    /// with `--output-sort-key count-desc` to the most frequent ones.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = parse_window,
+        conflicts_with_all = [
+            "per_file", "per_paragraph", "no_aggregate", "stream", "histogram", "frequency_bands",
+            "top_percent", "quiet", "total_only", "diff", "append",
+        ]
+    )]
+    window: Option<usize>,
+
+    /// The number of lines every --window shares with the one before it
+    #[arg(long, value_name = "M", default_value_t = 0, requires = "window")]
+    window_overlap: usize,
+
+    /// Also print the N longest words with their length in chars and their
+    /// count, below the word counts
+    #[arg(long, value_name = "N", conflicts_with_all = ["total_only", "quiet"])]
+    longest_words: Option<usize>,
+
+    /// Also print the N shortest words, like --longest-words
+    #[arg(long, value_name = "N", conflicts_with_all = ["total_only", "quiet"])]
+    shortest_words: Option<usize>,
+
+    /// Write the --longest-words and --shortest-words to FILE instead of
+    /// below the word counts
+    #[arg(long, value_name = "FILE")]
+    longest_words_output: Option<PathBuf>,
+
+    /// Also write every counted word to FILE, one per line without its count,
+    /// as a vocabulary file for tokenizers or spell checkers
+    ///
+    /// The words are ordered like the output, see --output-sort-key.
+    #[arg(long, value_name = "FILE")]
+    write_vocab: Option<PathBuf>,
+
+    /// Order the words of --write-vocab by count, most frequent first, as
+    /// tools like SentencePiece expect
+    #[arg(long, requires = "write_vocab")]
+    vocab_sorted_by_count: bool,
+
+    /// Also write the --word-cloud-top most frequent words to FILE as a JSON
+    /// array of `{"text": ..., "weight": ...}` objects, for word cloud
+    /// libraries like d3-cloud or wordcloud2.js
+    ///
+    /// The weights go from 1 for the least to 100 for the most frequent of
+    /// the words.
+    #[arg(long, value_name = "FILE")]
+    word_cloud_json: Option<PathBuf>,
+
+    /// The number of words of --word-cloud-json
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 200,
+        requires = "word_cloud_json"
+    )]
+    word_cloud_top: usize,
+
+    /// Whether the --word-cloud-json weights grow with the counts or with
+    /// their logarithm
+    #[arg(
+        long,
+        value_name = "SCALE",
+        value_enum,
+        default_value_t = CloudScale::Linear,
+        requires = "word_cloud_json"
+    )]
+    word_cloud_json_scale: CloudScale,
+
+    /// Record the number of unique words after every chunk against the
+    /// number of words counted so far and print them as `words<TAB>unique`
+    /// rows to stderr at the end
+    ///
+    /// Shows how the vocabulary grows with the input, which Heaps' law
+    /// `V = K * N^β` models. The rows run on over all inputs.
+    #[arg(long)]
+    heaps_diagnostics: bool,
+
+    /// Write the --heaps-diagnostics rows to FILE instead of stderr
+    #[arg(long, value_name = "FILE", requires = "heaps_diagnostics")]
+    heaps_output: Option<PathBuf>,
+
+    /// Fit Heaps' law to the growth of the vocabulary by least squares and
+    /// print its K and β to stderr at the end
+    #[arg(long)]
+    heaps_fit: bool,
+
+    /// Also print every occurrence of WORD between the N words before and
+    /// after it on its line, a key word in context (KWIC) concordance sorted
+    /// by the words before it, below the word counts
+    ///
+    /// WORD is matched like a counted word, so regardless of case with
+    /// --ignore-case, but the context is printed as written.
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["WORD", "N"],
+        conflicts_with_all = ["total_only", "quiet"]
+    )]
+    show_context: Vec<String>,
+
+    /// Write the --show-context concordance to FILE instead of below the
+    /// word counts
+    #[arg(long, value_name = "FILE", requires = "show_context")]
+    context_output: Option<PathBuf>,
+
+    /// Also print how many distinct contexts of N words before and after it
+    /// WORD occurs in, next to how often it occurs, below the word counts
+    ///
+    /// A word in many different contexts is used broadly; one in few is
+    /// mostly part of a set phrase. WORD is matched like a counted word.
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["WORD", "N"],
+        conflicts_with_all = ["total_only", "quiet"]
+    )]
+    context_window_frequency: Vec<String>,
+
+    /// The number of tokens per window of the MATTR in --show-stats
+    #[arg(long, value_name = "N", default_value_t = 500, value_parser = parse_mattr_window)]
+    mattr_window: usize,
+
+    /// Also count the pairs of words at most N tokens apart on the same line
+    ///
+    /// Every word is paired with the N - 1 words after it; the pairs are
+    /// written to the --cooccurrence-output file.
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "cooccurrence_output",
+        value_parser = parse_cooccurrence_window
+    )]
+    cooccurrence: Option<usize>,
+
+    /// Write the --cooccurrence pairs to FILE as `word1<TAB>word2<TAB>count`
+    /// lines, the two words of a pair in sorted order
+    #[arg(long, value_name = "FILE", requires = "cooccurrence")]
+    cooccurrence_output: Option<PathBuf>,
+
+    /// Save the counts to FILE after every chunk, so an interrupted run can
+    /// be continued with --resume
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["diff", "use_hashmap"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue an interrupted run from the checkpoint in FILE, skipping the
+    /// lines that were already counted
+    #[arg(long, value_name = "FILE", conflicts_with = "diff")]
+    resume: Option<PathBuf>,
+
+    /// Replace the --output file with the counts of all inputs so far after
+    /// every N chunks, so they can be watched growing
+    ///
+    /// Every snapshot and the final output are written to a temporary file
+    /// first and renamed over the output, so a reader never sees a partial
+    /// file.
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "output",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = [
+            "diff", "use_hashmap", "stream", "watch", "per_file", "no_aggregate", "append",
+        ]
+    )]
+    output_every_n_chunks: Option<u64>,
+
+    /// The format of the --output-every-n-chunks snapshots; the --format of
+    /// the final output if not given
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        requires = "output_every_n_chunks"
+    )]
+    checkpoint_format: Option<OutputFormat>,
+
+    /// Also write all word counts into the `word_counts` table of the SQLite
+    /// database FILE, replacing the file
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE")]
+    export_sqlite: Option<PathBuf>,
+
+    /// Add to the counts of an existing --export-sqlite database instead of
+    /// replacing it
+    #[cfg(feature = "sqlite")]
+    #[arg(long, requires = "export_sqlite")]
+    append_sqlite: bool,
+
+    /// Also write the word counts to FILE as a unigram language model in the
+    /// ARPA format, for KenLM, Kaldi and the like
+    #[arg(long, value_name = "FILE")]
+    export_arpa: Option<PathBuf>,
+
+    /// Add K to the count of every word of --export-arpa, add-k smoothing
+    #[arg(
+        long,
+        value_name = "K",
+        default_value_t = 0.0,
+        value_parser = parse_arpa_smoothing,
+        requires = "export_arpa"
+    )]
+    arpa_smoothing: f64,
+
+    /// The log10 probability of `<unk>` and `</s>` in --export-arpa, below
+    /// -0.30 so the words keep some probability
+    #[arg(
+        long,
+        value_name = "LOG_PROB",
+        default_value_t = -99.0,
+        value_parser = parse_arpa_unk_prob,
+        allow_negative_numbers = true,
+        requires = "export_arpa"
+    )]
+    arpa_unk_prob: f64,
+
+    /// Also write a PNG scatter plot of log10(rank) against log10(frequency)
+    /// of the words to FILE, with a reference line for Zipf's law
+    #[cfg(feature = "plot")]
+    #[arg(long, value_name = "FILE")]
+    word_frequency_plot: Option<PathBuf>,
+
+    /// Add a Zipf reference line of exponent F to --word-frequency-plot,
+    /// next to the one of exponent 1
+    #[cfg(feature = "plot")]
+    #[arg(
+        long,
+        value_name = "F",
+        value_parser = parse_zipf_exponent,
+        requires = "word_frequency_plot"
+    )]
+    zipf_exponent: Option<f64>,
+
+    /// Write the output to FILE instead of stdout, truncating it if it exists
+    #[arg(short = 'o', long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Stop writing the output after BYTES bytes, with an optional K, M or G
+    /// suffix, and exit with an error
+    ///
+    /// The output is cut off at exactly BYTES bytes, even within a line.
+    #[arg(long, value_name = "BYTES", value_parser = parse_byte_size)]
+    output_limit: Option<usize>,
+
+    /// Merge the counts already in the --output file into the new ones and
+    /// rewrite it with the sums
+    ///
+    /// Only the plain text and json formats can be merged. The file always
+    /// gets all merged words as plain `word: count` lines or a json object,
+    /// whatever --top, --min-count or --color would print, so the next
+    /// --append can read it again; options adding more lines to the output
+    /// cannot be combined with it.
+    #[arg(
+        long,
+        requires = "output",
+        conflicts_with_all = [
+            "show_rank",
+            "normalize",
+            "cumulative",
+            "table",
+            "hapax",
+            "dis_legomena",
+            "show_stats",
+            "words_per_sentence",
+            "avg_word_length",
+            "words_per_line",
+            "count_unique_per_line",
+            "per_file",
+            "first_seen_line",
+            "entropy",
+            "perplexity",
+            "top_percent",
+            "longest_words",
+            "summarize_only",
+            "total_only",
+            "count_lines",
+            "count_graphemes",
+            "show_context",
+            "quiet",
+        ]
+    )]
+    append: bool,
+}
+
+fn main() {
+    let started = Instant::now();
+    let args = Cli::parse();
+    if args.diff && args.paths.len() != 2 {
+        Cli::command()
+            .error(
+                ErrorKind::WrongNumberOfValues,
+                "--diff requires exactly two files",
+            )
+            .exit();
+    }
+    if (args.checkpoint.is_some() || args.resume.is_some()) && args.paths.len() > 1 {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--checkpoint and --resume work on a single input",
+            )
+            .exit();
+    }
+    if args.append && !matches!(args.format, OutputFormat::Text | OutputFormat::Json) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--append can only merge the text and json formats",
+            )
+            .exit();
+    }
+    if args
+        .merge_format
+        .is_some_and(|format| !matches!(format, OutputFormat::Text | OutputFormat::Json))
+    {
+        Cli::command()
+            .error(
+                ErrorKind::InvalidValue,
+                "--merge-format can only read the text and json formats",
+            )
+            .exit();
+    }
+    if args.hash_algorithm.is_some() && !args.use_hashmap && !args.parallel {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--hash-algorithm requires --use-hashmap or --parallel",
+            )
+            .exit();
+    }
+    if args.no_scale_estimates
+        && args.random_sample.is_none()
+        && args.every_nth_line.is_none()
+        && args.every_nth_word.is_none()
+    {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                concat!(
+                    "--no-scale-estimates requires --random-sample, --every-nth-line or ",
+                    "--every-nth-word",
+                ),
+            )
+            .exit();
+    }
+    if let Some(template) = &args.output_format_template {
+        for name in unknown_placeholders(template) {
+            eprintln!(
+                "Warning: unknown placeholder {{{}}} in --output-format-template, printed as it is",
+                name
+            );
+        }
+    }
+    if args.json_path.is_some() && args.input_format != InputFormat::Json {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--json-path requires --input-format json",
+            )
+            .exit();
+    }
+    #[cfg(feature = "tokio")]
+    let async_io = args.async_io;
+    #[cfg(not(feature = "tokio"))]
+    let async_io = false;
+    if args.max_memory.is_some() && async_io {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--max-memory cannot be combined with --async",
+            )
+            .exit();
+    }
+    if (!args.csv_text_column.is_empty() || args.csv_has_header)
+        && args.input_format != InputFormat::Csv
+    {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--csv-text-column and --csv-has-header require --input-format csv",
+            )
+            .exit();
+    }
+    if args.csv_text_column.iter().any(|column| column == "0") {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the columns of --csv-text-column are numbered from 1",
+            )
+            .exit();
+    }
+    let input_format = args
+        .input_format
+        .to_possible_value()
+        .expect("no input format is skipped");
+    if args.input_format != InputFormat::Text && (args.mmap || async_io) {
+        // JSON lines and CSV records are parsed one by one from a plain reader
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--input-format {} cannot be combined with --mmap or --async",
+                    input_format.get_name()
+                ),
+            )
+            .exit();
+    }
+    if args.input_encoding_detect && args.input_format != InputFormat::Text {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--input-encoding-detect cannot be combined with --input-format {}",
+                    input_format.get_name()
+                ),
+            )
+            .exit();
+    }
+    if args.output_sort_key == Some(SortOrder::FirstSeen) && !args.first_seen_line {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--output-sort-key first-seen requires --first-seen-line",
+            )
+            .exit();
+    }
+    if args.sort_by_count {
+        eprintln!("Warning: --sort-by-count is deprecated, use --output-sort-key count-desc");
+    }
+    if args.sort_asc {
+        eprintln!("Warning: --sort-asc is deprecated, use --output-sort-key count-asc");
+    }
+    if args.compare_dirs.is_some()
+        && !matches!(
+            args.format,
+            OutputFormat::Text | OutputFormat::Json | OutputFormat::Csv
+        )
+    {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--compare-dirs only supports the text, json and csv formats",
+            )
+            .exit();
+    }
+    if args.weight_by_position
+        && !matches!(
+            args.format,
+            OutputFormat::Text | OutputFormat::Json | OutputFormat::Csv | OutputFormat::Tsv
+        )
+    {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--weight-by-position only supports the text, json, csv and tsv formats",
+            )
+            .exit();
+    }
+    if args.band_boundaries.is_empty()
+        || args.band_boundaries[0] == 0
+        || args
+            .band_boundaries
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+    {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "--band-boundaries must be increasing counts of at least 1",
+            )
+            .exit();
+    }
+    if args.summarize_only && !matches!(args.format, OutputFormat::Text | OutputFormat::Json) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--summarize-only is printed as text or with --format json only",
+            )
+            .exit();
+    }
+    if args.longest_words_output.is_some()
+        && args.longest_words.is_none()
+        && args.shortest_words.is_none()
+    {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--longest-words-output requires --longest-words or --shortest-words",
+            )
+            .exit();
+    }
+    if (args.longest_words.is_some() || args.shortest_words.is_some())
+        && args.longest_words_output.is_none()
+        && !matches!(args.format, OutputFormat::Text | OutputFormat::Tsv)
+    {
+        // the sections would break the structure of the output
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--longest-words and --shortest-words need --longest-words-output with this \
+                 --format",
+            )
+            .exit();
+    }
+    if let Some(format) = args
+        .ngram_format
+        .as_ref()
+        .filter(|format| format.max_position() > args.ngrams)
+    {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "--ngram-format refers to word {{{}}} of the --ngrams of {} words",
+                    format.max_position(),
+                    args.ngrams
+                ),
+            )
+            .exit();
+    }
+    if let Some(window) = args.window.filter(|&window| args.window_overlap >= window) {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "--window-overlap must be smaller than the --window of {} lines",
+                    window
+                ),
+            )
+            .exit();
+    }
+    if args.output_every_n_chunks.is_some()
+        && args.checkpoint_format.unwrap_or(args.format) == OutputFormat::Ndjson
+    {
+        // a snapshot would only hold the summary of the streamed lines
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--output-every-n-chunks cannot write snapshots in the ndjson format",
+            )
+            .exit();
+    }
+    if args.format == OutputFormat::Ndjson
+        && (args.stream
+            || args.per_file
+            || args.no_aggregate
+            || args.use_hashmap
+            || args.window.is_some())
+    {
+        // the lines are streamed per chunk of the whole run
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the ndjson format cannot be combined with --stream, --per-file, \
+                 --no-aggregate, --use-hashmap or --window",
+            )
+            .exit();
+    }
+    if args.quiet
+        && !(args.show_stats
+            || args.words_per_sentence
+            || args.avg_word_length
+            || args.words_per_line
+            || args.count_unique_per_line
+            || args.entropy
+            || args.perplexity
+            || args.count_lines
+            || args.count_chars
+            || args.count_graphemes
+            || args.count_bytes
+            || args.count_sentences)
+    {
+        eprintln!(
+            "Warning: --quiet without a summary flag like --show-stats prints the --show-stats \
+             line"
+        );
+    }
+    if let Some(threads) = args.threads {
+        // 0 leaves the choice to rayon, just like not building the pool
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            eprintln!("Error: cannot start {} threads: {}", threads, err);
+            process::exit(1);
+        }
+    }
+    let mut config = WordCountConfig {
+        ignore_case: args.ignore_case,
+        strip_punct: args.strip_punct,
+        chunk_size: args.chunk_size,
+        buffer_size: args.buffer_size,
+        sleep: (!args.no_sleep).then(|| Duration::from_millis(args.sleep_ms)),
+        parallel: args.parallel,
+        mmap: args.mmap,
+        min_length: args.min_length,
+        max_length: args.max_length,
+        truncate_length: args.truncate_token,
+        truncate_marker: args.truncate_marker.clone(),
+        split_regex: args.split_regex.clone(),
+        split_camel_case: args.split_on_camelcase || args.tokenize_code,
+        split_underscore: args.split_on_underscore || args.tokenize_code,
+        split_hyphenated: args.split_hyphenated,
+        keep_hyphenated_numbers: args.no_split_hyphenated_numbers,
+        code_lang: args.tokenize_code.then_some(args.code_lang),
+        field_separator: args
+            .field_separator
+            .map(String::from)
+            .or_else(|| args.field_separator_str.clone()),
+        remove_numbers: args.remove_numbers,
+        replace_numbers: args.replace_numbers.clone(),
+        number_regex: args.number_regex.clone(),
+        replace_urls: args.replace_urls.clone(),
+        extract_urls: args.extract_urls,
+        extract_emails: args.extract_emails,
+        remove_integers: args.remove_integers,
+        remove_floats: args.remove_floats,
+        only_alpha: args.include_only_alpha,
+        only_alphanumeric: args.include_only_alphanumeric,
+        include_pattern: args.include_pattern.clone(),
+        exclude_pattern: args.exclude_pattern.clone(),
+        stem: args.stem.then_some(args.stem_lang),
+        strip_prefixes: as_counted_affixes(&args.prefix_strip, args.ignore_case),
+        strip_suffixes: as_counted_affixes(&args.suffix_strip, args.ignore_case),
+        #[cfg(feature = "transliterate")]
+        transliterate: args.transliterate,
+        // the word lists are loaded below, once --dry-run checked the files
+        unicode_form: args.normalise_unicode,
+        stopwords: HashSet::new(),
+        vocab: None,
+        use_hashmap: args.use_hashmap,
+        ngrams: args.ngrams,
+        ngram_join: args
+            .ngram_format
+            .clone()
+            .unwrap_or_else(|| NgramJoin::Separator(args.ngram_separator.clone())),
+        chars: args.chars,
+        skip_whitespace_chars: args.no_whitespace_chars,
+        char_ngrams: args.char_ngrams,
+        char_ngram_boundaries: args.char_ngrams_word_boundaries,
+        sentences: args.words_per_sentence || args.count_sentences,
+        sentence_endings: args
+            .count_sentences
+            .then(|| args.sentence_end.chars().collect()),
+        count_graphemes: args.count_graphemes,
+        sentence_per_line: args.sentence_per_line,
+        column: args.column,
+        column_separator: args.column_separator,
+        encoding: args.encoding,
+        force_utf8: args.force_utf8,
+        line_range: args.lines,
+        skip_long_lines: args.skip_long_lines,
+        hash_seed: args.seed,
+        hash_algorithm: args.hash_algorithm.unwrap_or_default(),
+        max_unique_words: args.max_unique_words,
+        max_memory: args.max_memory,
+        deadline: args
+            .time_budget
+            .map(|budget| started + Duration::from_millis(budget)),
+        dedup_lines: args.deduplicate_lines.then_some(if args.dedup_approx {
+            LineDedup::Approx(args.dedup_fpr)
+        } else {
+            LineDedup::Exact
+        }),
+        line_regex: args.line_regex.clone(),
+        line_regex_invert: args.line_regex_invert,
+        comment_language: args.ignore_comments,
+        sample_rate: args.random_sample,
+        every_nth_line: args.every_nth_line.map(|every| every as usize),
+        every_nth_word: args.every_nth_word.map(|every| every as usize),
+        skip_lines: 0,
+    };
+    if args.dry_run {
+        dry_run(&args, &config);
+    }
+    let assertions = count_assertions_or_exit(&args, &config);
+    if let Some(path) = &args.stopwords {
+        config.stopwords = load_word_list(path, args.ignore_case, "stopwords");
+    }
+    if let Some(lang) = args.stopwords_lang {
+        config
+            .stopwords
+            .extend(builtin_stopwords(lang, args.ignore_case));
+    }
+    config.vocab = args
+        .vocab
+        .as_ref()
+        .map(|path| load_word_list(path, args.ignore_case, "vocab"));
+    let output_config = OutputConfig {
+        sort_order: if let Some(key) = args.output_sort_key {
+            key
+        } else if args.sort_by_first_seen {
+            SortOrder::FirstSeen
+        } else if args.sort_by_count || args.cumulative || args.top_percent.is_some() {
+            SortOrder::CountDesc
+        } else if args.sort_asc {
+            SortOrder::CountAsc
+        } else if args.format == OutputFormat::JsonArray {
+            // the array is meant for streaming consumers that want the most
+            // frequent words first
+            SortOrder::CountDesc
+        } else {
+            SortOrder::Word
+        },
+        top: args.top,
+        bottom: args.bottom,
+        bottom_ties: args.bottom_ties,
+        min_count: args.min_count,
+        max_count: args.max_count,
+        exactly: args.exactly,
+        format: args.format,
+        no_header: args.no_header,
+        tsv_header: args.tsv_header,
+        xml_indent: args.xml_indent,
+        normalize_by: None,
+        log_scale: args.log_scale.then_some(if args.natural_log {
+            LogBase::E
+        } else {
+            LogBase::Ten
+        }),
+        show_rank: args.show_rank,
+        table: args.table,
+        table_header: args.table_header,
+        null_separated: args.null_separated,
+        words_per_sentence: args.words_per_sentence,
+        word_lengths: args.avg_word_length,
+        entropy: args.entropy,
+        perplexity: args.perplexity,
+        word_width: args.output_width.map(|width| width as usize),
+        truncation_marker: args.truncation_marker.clone(),
+        count_width: args.count_width,
+        template: args.output_format_template.clone(),
+        cumulative_of: None,
+        coverage_threshold: args.coverage_threshold,
+        top_percent: None,
+        first_lines: None,
+        first_positions: None,
+        words_per_line: None,
+        new_words_per_line: None,
+        paragraphs: None,
+        color: match args.color {
+            ColorChoice::Auto => is_live_output(&args),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        },
+        legomena: if args.hapax {
+            Some(1)
+        } else if args.dis_legomena {
+            Some(2)
+        } else {
+            None
+        },
+    };
+
+    if args.diff {
+        let left = count_or_exit(&args.paths[0], &config);
+        let right = count_or_exit(&args.paths[1], &config);
+        let diff = diff_counts(&left, &right);
+        let mut out = open_output_or_exit(&args);
+        written(write_diff(
+            &mut out,
+            &diff,
+            &args.paths[0].display().to_string(),
+            &args.paths[1].display().to_string(),
+        ));
+        written(out.flush());
+        return;
+    }
+
+    if let Some(dirs) = &args.compare_dirs {
+        let counts: Vec<_> = dirs
+            .iter()
+            .map(|dir| {
+                if !dir.is_dir() {
+                    eprintln!("Error: {} is not a directory", dir.display());
+                    process::exit(1);
+                }
+                let files =
+                    expand_directories(std::slice::from_ref(dir), &args.glob, args.follow_symlinks);
+                count_all_or_exit(&args, Inputs::files(&files), &config)
+            })
+            .collect();
+        let comparison = compare_corpora(&counts[0], &counts[1], args.log_likelihood);
+        let mut out = open_output_or_exit(&args);
+        written(write_corpus_comparison(
+            &mut out,
+            &comparison,
+            args.format,
+            &dirs[0].display().to_string(),
+            &dirs[1].display().to_string(),
+        ));
+        written(out.flush());
+        return;
+    }
+
+    if !args.merge.is_empty() {
+        let format = args.merge_format.unwrap_or(match args.format {
+            OutputFormat::Json => OutputFormat::Json,
+            _ => OutputFormat::Text,
+        });
+        let mut word_counts = BTreeMap::new();
+        for path in &args.merge {
+            match read_counts(path, format) {
+                Ok(counts) => merge_counts(&mut word_counts, counts),
+                Err(err) => {
+                    eprintln!("Error: cannot merge {}: {}", path.display(), err);
+                    process::exit(1);
+                }
+            }
+        }
+        let totals = Totals {
+            words: word_counts.values().sum(),
+            ..Default::default()
+        };
+        let mut out = open_output_or_exit(&args);
+        written(write_counts(
+            &mut out,
+            &word_counts,
+            &with_totals(&output_config, &args, &totals),
+        ));
+        written(out.flush());
+        return;
+    }
+
+    let mut inputs = args.paths.clone();
+    for pattern in &args.input_glob {
+        expand_input_glob(pattern, &mut inputs);
+    }
+    let stdin_documents = match args.stdin_delimiter {
+        Some(delimiter) => read_stdin_documents_or_exit(delimiter),
+        None => Vec::new(),
+    };
+    let paths = if args.process_text.is_some() {
+        vec![PathBuf::from(TEXT_INPUT)]
+    } else if args.stdin_delimiter.is_some() {
+        stdin_documents
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    } else if args.paths.is_empty() && args.input_glob.is_empty() {
+        vec![PathBuf::from(STDIN_PATH)]
+    } else if args.recursive {
+        expand_directories(&inputs, &args.glob, args.follow_symlinks)
+    } else {
+        inputs
+    };
+
+    if args.input_encoding_detect && paths.iter().any(|path| path == Path::new(STDIN_PATH)) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--input-encoding-detect cannot detect the encoding of stdin",
+            )
+            .exit();
+    }
+    if args.report_encoding && paths.iter().any(|path| path == Path::new(STDIN_PATH)) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--report-encoding cannot report the encoding of stdin",
+            )
+            .exit();
+    }
+
+    let inputs = Inputs {
+        paths: &paths,
+        stdin_documents: &stdin_documents,
+    };
+
+    if let Some(reference_path) = &args.word_list_diff {
+        let reference = load_reference(reference_path, args.ignore_case);
+        let word_counts = count_all_or_exit(&args, inputs, &config);
+        let entries = compare_to_reference(&word_counts, &reference, args.show_missing);
+        let mut out = open_output_or_exit(&args);
+        written(write_reference_diff(
+            &mut out,
+            &entries,
+            args.word_list_diff_format,
+        ));
+        written(out.flush());
+        return;
+    }
+
+    if let Some(baseline_path) = &args.relative_to {
+        let baseline = count_or_exit(baseline_path, &config);
+        let word_counts = count_all_or_exit(&args, inputs, &config);
+        let entries = relative_frequencies(
+            &word_counts,
+            &baseline,
+            args.laplace_smoothing,
+            args.sort_by_ratio,
+        );
+        let mut out = open_output_or_exit(&args);
+        written(write_relative_frequencies(&mut out, &entries));
+        written(out.flush());
+        return;
+    }
+
+    if args.tfidf {
+        // the document frequencies need the counts of all inputs first
+        let documents: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                let document = Inputs {
+                    paths: std::slice::from_ref(path),
+                    ..inputs
+                };
+                count_all_or_exit(&args, document, &config)
+            })
+            .collect();
+        let scores = tfidf(&documents, args.tfidf_variant);
+        let mut out = open_output_or_exit(&args);
+        for (path, entries) in paths.iter().zip(&scores) {
+            let top = args.top.unwrap_or(entries.len()).min(entries.len());
+            written(writeln!(out, "=== {} ===", path.display()));
+            written(write_tfidf(&mut out, &entries[..top]));
+        }
+        written(out.flush());
+        return;
+    }
+
+    if args.benchmark {
+        if paths.iter().any(|path| path == Path::new(STDIN_PATH)) {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--benchmark cannot read stdin more than once",
+                )
+                .exit();
+        }
+        benchmark(&args, &config, &paths);
+        return;
+    }
+
+    if args.watch {
+        if paths.iter().any(|path| path == Path::new(STDIN_PATH)) {
+            Cli::command()
+                .error(ErrorKind::ArgumentConflict, "--watch cannot watch stdin")
+                .exit();
+        }
+        let interval = Duration::from_millis(args.watch_interval);
+        loop {
+            let stamps = modification_stamps(&paths);
+            let mut out = open_output_or_exit(&args);
+            if is_live_output(&args) {
+                // clear the screen so the new counts replace the old ones
+                written(write!(out, "\x1b[2J\x1b[H"));
+            }
+            run(
+                &args,
+                &config,
+                &output_config,
+                inputs,
+                None,
+                BTreeMap::new(),
+                &mut out,
+            );
+            drop(out);
+            wait_for_change(&paths, &stamps, interval);
+        }
+    }
+
+    let resumed = args
+        .resume
+        .as_deref()
+        .map(|path| load_checkpoint(path, &paths[0]));
+    if let Some(checkpoint) = &resumed {
+        config.skip_lines = checkpoint.totals.lines_read;
+    }
+    // read before opening the output, which truncates it
+    let existing = if args.append {
+        load_existing_counts(&args)
+    } else {
+        BTreeMap::new()
+    };
+    let mut out = open_output_or_exit(&args);
+    let word_counts = run(
+        &args,
+        &config,
+        &output_config,
+        inputs,
+        resumed,
+        existing,
+        &mut out,
+    );
+    let failures = failed_assertions(&word_counts, &assertions);
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("Assertion failed: {}", failure);
+        }
+        process::exit(1);
+    }
+}
+
+/// The `--assert-max-count`, `--assert-min-count` and `--assert-word-absent`
+/// assertions, exiting if a count is no number
+///
+/// The words are normalised like the tokens of `config`, so they match the
+/// counted words.
+fn count_assertions_or_exit(args: &Cli, config: &WordCountConfig) -> Vec<CountAssertion> {
+    if args.ci_mode
+        && args.assert_max_count.is_empty()
+        && args.assert_min_count.is_empty()
+        && args.assert_word_absent.is_empty()
+    {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--ci-mode requires at least one --assert-max-count, --assert-min-count or \
+                 --assert-word-absent",
+            )
+            .exit();
+    }
+    let parse = |values: &[String], flag: &str| -> Vec<(String, usize)> {
+        values
+            .chunks(2)
+            .map(|pair| {
+                let count = pair[1].parse().unwrap_or_else(|err| {
+                    Cli::command()
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!("invalid count '{}' for {}: {}", pair[1], flag, err),
+                        )
+                        .exit()
+                });
+                (counted_form(&pair[0], config), count)
+            })
+            .collect()
+    };
+    let at_most = parse(&args.assert_max_count, "--assert-max-count")
+        .into_iter()
+        .map(|(word, max)| CountAssertion::AtMost(word, max));
+    let at_least = parse(&args.assert_min_count, "--assert-min-count")
+        .into_iter()
+        .map(|(word, min)| CountAssertion::AtLeast(word, min));
+    let absent = args
+        .assert_word_absent
+        .iter()
+        .map(|word| CountAssertion::Absent(counted_form(word, config)));
+    at_most.chain(at_least).chain(absent).collect()
+}
+
+/// The affixes of `--prefix-strip` or `--suffix-strip`, lowercased when
+/// counting ignores case so they match the lowercased words
+fn as_counted_affixes(affixes: &[String], ignore_case: bool) -> Vec<String> {
+    affixes
+        .iter()
+        .map(|affix| {
+            if ignore_case {
+                affix.to_lowercase()
+            } else {
+                affix.clone()
+            }
+        })
+        .collect()
+}
+
+/// The share of the words counted by the samples of `--random-sample`,
+/// `--every-nth-line` and `--every-nth-word` together, 1 without any
+fn sampled_share(args: &Cli) -> f64 {
+    let every = args
+        .every_nth_line
+        .unwrap_or(1)
+        .saturating_mul(args.every_nth_word.unwrap_or(1));
+    args.random_sample.unwrap_or(1.0) / every as f64
+}
+
+/// The word of `--show-context` or `--context-window-frequency` given as
+/// `values`, normalised like the tokens of `config`, and its number of
+/// context words, if given
+fn context_word_or_exit(
+    config: &WordCountConfig,
+    values: &[String],
+    flag: &str,
+) -> Option<(String, usize)> {
+    let [word, context] = values else {
+        return None;
+    };
+    let context = context.parse().unwrap_or_else(|err| {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "invalid number of words '{}' for --{}: {}",
+                    context, flag, err
+                ),
+            )
+            .exit()
+    });
+    Some((counted_form(word, config), context))
+}
+
+/// The inputs to count, with the documents of stdin by the names they are
+/// counted under among the paths for --stdin-delimiter
+#[derive(Clone, Copy)]
+struct Inputs<'a> {
+    paths: &'a [PathBuf],
+    stdin_documents: &'a [(PathBuf, Vec<u8>)],
+}
+
+impl<'a> Inputs<'a> {
+    /// The files `paths`, none of them a document of stdin
+    fn files(paths: &'a [PathBuf]) -> Self {
+        Self {
+            paths,
+            stdin_documents: &[],
+        }
+    }
+}
+
+/// Count all `inputs` and print the results to `out`
+///
+/// # Arguments
+///
+/// * `args` - The parsed command line
+/// * `config` - The options controlling how the words are counted
+/// * `output_config` - The options controlling how the counts are printed
+/// * `inputs` - The inputs to count
+/// * `resumed` - The checkpoint the first input is resumed from
+/// * `existing` - The counts of an earlier run the new ones are added to
+/// * `out` - The destination of the output
+///
+/// Returns the word counts of all inputs together.
+fn run<W: Write>(
+    args: &Cli,
+    config: &WordCountConfig,
+    output_config: &OutputConfig,
+    inputs: Inputs,
+    mut resumed: Option<Checkpoint>,
+    existing: BTreeMap<String, usize>,
+    out: &mut W,
+) -> BTreeMap<String, usize> {
+    let mut word_counts = existing;
+    let mut totals = Totals::default();
+    let live_output = is_live_output(args);
+    // the MATTR runs over all inputs as one stream of tokens
+    let count_summary = args.count_lines
+        || args.count_chars
+        || args.count_graphemes
+        || args.count_bytes
+        || args.count_sentences;
+    let show_stats = args.show_stats
+        || args.words_per_sentence
+        || args.avg_word_length
+        || args.words_per_line
+        || args.count_unique_per_line
+        || args.entropy
+        || args.perplexity
+        || args.summarize_only
+        || args.quiet && !count_summary;
+    let json_summary = args.summarize_only && args.format == OutputFormat::Json;
+    let mut mattr = show_stats.then(|| Mattr::new(config, args.mattr_window));
+    // the lines are sampled from all inputs together
+    let mut reservoir = args
+        .sample_lines
+        .map(|size| LineReservoir::new(size, config.hash_seed));
+    // like the MATTR, the line numbers run on over all inputs
+    let mut first_lines = BTreeMap::new();
+    let mut first_positions = BTreeMap::new();
+    let mut lines_before = 0;
+    let mut pairs = BTreeMap::new();
+    // paragraphs end at the end of every input
+    let mut paragraph_words = Vec::new();
+    let mut paragraphs = args
+        .paragraph_mode
+        .then(|| Paragraphs::new(config, &mut paragraph_words));
+    let mut line_histogram = BTreeMap::new();
+    let mut words_per_line = args
+        .words_per_line
+        .then(|| WordsPerLine::new(config, args.wpl_sample_rate, &mut line_histogram));
+    let mut new_word_lines = match args.upl_histogram_buckets {
+        Some(buckets) => NewWordLines::bucketed(buckets as usize),
+        None => NewWordLines::default(),
+    };
+    let mut new_words_per_line = args
+        .count_unique_per_line
+        .then(|| NewWordsPerLine::new(config, &mut new_word_lines));
+    // --max-memory caps the merged counts as well as those of every input
+    let mut unique_cap = config.max_unique_words;
+    // like the MATTR, the windows run on over all inputs
+    let mut window_counts = Vec::new();
+    // like the word counts, the URLs and email addresses add up over all inputs
+    let mut url_counts = BTreeMap::new();
+    let mut email_counts = BTreeMap::new();
+    let mut windows = args
+        .window
+        .map(|size| Windows::new(config, size, args.window_overlap, &mut window_counts));
+    let mut weights = BTreeMap::new();
+    let context_word = context_word_or_exit(config, &args.show_context, "show-context");
+    let mut kwic_lines = Vec::new();
+    let diversity_word = context_word_or_exit(
+        config,
+        &args.context_window_frequency,
+        "context-window-frequency",
+    );
+    let mut word_contexts = WordContexts::default();
+    let mut heaps_points = Vec::new();
+    // the chunks are numbered on over all inputs
+    let mut profile = args.profile_chunks.then(|| open_profile_or_exit(args));
+    let mut benchmark = args.benchmark_profile.then(BenchmarkProfile::default);
+    let mut chunk_stats = args.per_chunk_stats.then(|| open_chunk_stats_or_exit(args));
+    let snapshot_config = OutputConfig {
+        format: args.checkpoint_format.unwrap_or(args.format),
+        ..output_config.clone()
+    };
+    let mut snapshot_rounds = 0;
+    // the number of inputs every word was found in, for --min-df and --max-df
+    let mut document_frequencies = BTreeMap::new();
+    let filter_df = args.min_df.is_some() || args.max_df.is_some();
+
+    for path in inputs.paths {
+        if args.report_encoding {
+            report_encoding(path);
+        }
+        let (mut file_counts, base) = match resumed.take() {
+            Some(checkpoint) => (checkpoint.word_counts, checkpoint.totals),
+            None => (BTreeMap::new(), Totals::default()),
+        };
+        let checkpoint = args
+            .checkpoint
+            .as_deref()
+            .map(|checkpoint| CheckpointWriter::new(checkpoint, path, base));
+        let stream = if args.stream {
+            Some(Streaming::Snapshots(Stream::new(
+                &mut *out,
+                output_config,
+                live_output,
+            )))
+        } else if args.format == OutputFormat::Ndjson {
+            Some(Streaming::Ndjson(NdjsonStream::new(
+                &mut *out,
+                config,
+                &word_counts,
+            )))
+        } else {
+            None
+        };
+        let short_lines = args
+            .column
+            .filter(|_| args.warn_short_lines)
+            .map(|column| ShortLines::new(path, column, args.column_separator, config.skip_lines));
+        let first_seen = args
+            .first_seen_line
+            .then(|| FirstSeen::new(config, lines_before + base.lines_read, &mut first_lines));
+        let first_occurrences = args
+            .sort_by_first_seen
+            .then(|| FirstOccurrences::new(config, &mut first_positions));
+        let cooccurrences = args
+            .cooccurrence
+            .map(|window| Cooccurrences::new(config, window, &mut pairs));
+        let verbose = args.verbose.then(|| Verbose::new(path));
+        let position_weights = args
+            .weight_by_position
+            .then(|| PositionWeights::new(config, base.lines_read, &mut weights));
+        let growth = (args.heaps_diagnostics || args.heaps_fit)
+            .then(|| VocabularyGrowth::new(&word_counts, totals.words, &mut heaps_points));
+        let concordance = context_word
+            .as_ref()
+            .map(|(word, context)| Concordance::new(config, word, *context, &mut kwic_lines));
+        let diversity = diversity_word
+            .as_ref()
+            .map(|(word, window)| ContextDiversity::new(config, word, *window, &mut word_contexts));
+        let snapshots = args.output_every_n_chunks.map(|every| OutputSnapshots {
+            path: args.output.as_deref().expect("requires --output"),
+            every,
+            output_config: &snapshot_config,
+            before: &word_counts,
+            rounds: &mut snapshot_rounds,
+        });
+        let extracted = (args.url_output.is_some() || args.email_output.is_some())
+            .then(|| ExtractedTokens::new(config, &mut url_counts, &mut email_counts));
+        let long_lines = args
+            .max_line_length
+            .map(|max| LongLines::new(path, max, config.skip_lines));
+        let mut observer = (
+            Progress::new(path, args.progress),
+            (
+                checkpoint,
+                (
+                    stream,
+                    (
+                        (mattr.as_mut(), reservoir.as_mut()),
+                        (
+                            short_lines,
+                            (
+                                (first_seen, first_occurrences),
+                                (
+                                    cooccurrences,
+                                    (
+                                        verbose,
+                                        (
+                                            paragraphs.as_mut(),
+                                            (
+                                                long_lines,
+                                                (
+                                                    (profile.as_mut(), chunk_stats.as_mut()),
+                                                    (
+                                                        (
+                                                            words_per_line.as_mut(),
+                                                            new_words_per_line.as_mut(),
+                                                        ),
+                                                        (
+                                                            windows.as_mut(),
+                                                            (
+                                                                position_weights,
+                                                                (
+                                                                    (concordance, diversity),
+                                                                    (
+                                                                        growth,
+                                                                        (
+                                                                            snapshots,
+                                                                            (
+                                                                                extracted,
+                                                                                benchmark.as_mut(),
+                                                                            ),
+                                                                        ),
+                                                                    ),
+                                                                ),
+                                                            ),
+                                                        ),
+                                                    ),
+                                                ),
+                                            ),
+                                        ),
+                                    ),
+                                ),
+                            ),
+                        ),
+                    ),
+                ),
+            ),
+        );
+        let result = count_input(
+            args,
+            inputs.stdin_documents,
+            path,
+            config,
+            &mut file_counts,
+            &mut observer,
+        );
+        observer.0.finish();
+        if let Some(stream) = &mut observer.1 .1 .0 {
+            written(stream.finish());
+        }
+        if let Some(paragraphs) = &mut paragraphs {
+            paragraphs.finish();
+        }
+        let mut file_totals = match result {
+            Ok(file_totals) => file_totals,
+            Err(err) => {
+                // the streamed output may hit the limit while counting
+                exit_if_truncated(&err);
+                // a single unreadable file should not abort the whole run
+                eprintln!("Warning: skipping {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        // the counts of the sample estimate those of all lines
+        let rate = sampled_share(args);
+        if rate < 1.0 && !args.no_scale_estimates {
+            scale_counts(&mut file_counts, rate);
+            file_totals.words = (file_totals.words as f64 / rate).round() as usize;
+        }
+        file_totals += base;
+        totals += file_totals;
+        lines_before += file_totals.lines_read;
+
+        if args.per_file {
+            written(write_section(
+                out,
+                &path.display().to_string(),
+                &file_counts,
+                &with_totals(output_config, args, &file_totals),
+            ));
+        }
+        if filter_df {
+            for word in file_counts.keys() {
+                *document_frequencies.entry(word.clone()).or_insert(0) += 1;
+            }
+        }
+        let dropped = merge_counts_capped(&mut word_counts, file_counts, unique_cap);
+        totals.words -= dropped;
+        totals.words_dropped += dropped;
+        if args
+            .max_memory
+            .is_some_and(|max| estimate_map_bytes(&word_counts) > max)
+        {
+            // the later inputs add no new words either
+            let unique = word_counts.len();
+            unique_cap = Some(unique_cap.map_or(unique, |cap| cap.min(unique)));
+        }
+        if file_totals.timed_out {
+            break;
+        }
+    }
+    if let Some(windows) = &mut windows {
+        windows.finish();
+    }
+    if filter_df {
+        word_counts.retain(|word, _| {
+            let df = document_frequencies.get(word).copied().unwrap_or(0);
+            args.min_df.is_none_or(|min| df >= min) && args.max_df.is_none_or(|max| df <= max)
+        });
+    }
+
+    for (path, counts) in [
+        (&args.url_output, &url_counts),
+        (&args.email_output, &email_counts),
+    ] {
+        let Some(path) = path else {
+            continue;
+        };
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_counts(&mut file, counts, output_config)?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+
+    if let Some(reservoir) = reservoir {
+        let lines = reservoir.into_lines();
+        match &args.sample_output {
+            Some(path) => {
+                let result = File::create(path).and_then(|file| {
+                    let mut file = BufWriter::new(file);
+                    for line in &lines {
+                        writeln!(file, "{}", line)?;
+                    }
+                    file.flush()
+                });
+                if let Err(err) = result {
+                    eprintln!("Error: cannot write {}: {}", path.display(), err);
+                    process::exit(1);
+                }
+            }
+            None => {
+                for line in &lines {
+                    eprintln!("{}", line);
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &args.length_dist_output {
+        let lengths = word_length_distribution(&word_counts);
+        let json = args.format == OutputFormat::Json;
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_word_length_distribution(&mut file, &lengths, json)?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.write_vocab {
+        let order = if args.vocab_sorted_by_count {
+            SortOrder::CountDesc
+        } else {
+            output_config.sort_order
+        };
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            let first_seen = if args.sort_by_first_seen {
+                &first_positions
+            } else {
+                &first_lines
+            };
+            write_vocabulary(&mut file, &word_counts, order, Some(first_seen))?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+
+    let output_started = Instant::now();
+    let mut sorting = Duration::ZERO;
+    if args.append {
+        // the display options would leave the next run nothing to merge
+        let merged = OutputConfig {
+            format: args.format,
+            ..Default::default()
+        };
+        written(write_counts(out, &word_counts, &merged));
+    } else if args.total_only {
+        written(writeln!(out, "{}", totals.words));
+    } else if count_summary && !json_summary {
+        let summary = [
+            ("lines", args.count_lines, totals.lines),
+            ("sentences", args.count_sentences, totals.sentences),
+            ("words", args.count_words, totals.words),
+            ("chars", args.count_chars, totals.chars),
+            ("graphemes", args.count_graphemes, totals.graphemes),
+            ("bytes", args.count_bytes, totals.bytes),
+        ];
+        let active: Vec<_> = summary.iter().filter(|(_, active, _)| *active).collect();
+        if let [(_, _, total)] = active[..] {
+            written(writeln!(out, "{}", total));
+        } else {
+            let mut fields: Vec<String> = active
+                .iter()
+                .map(|(name, _, total)| format!("{}: {}", name, total))
+                .collect();
+            if args.count_sentences && args.count_words {
+                let average = if totals.sentences == 0 {
+                    0.0
+                } else {
+                    totals.words as f64 / totals.sentences as f64
+                };
+                fields.push(format!("avg: {:.2} wps", average));
+            }
+            written(writeln!(out, "{}", fields.join("  ")));
+        }
+    } else if args.quiet || args.summarize_only || args.no_aggregate {
+        // either nothing but the summary is printed or every file already got
+        // its own table
+    } else if args.window.is_some() {
+        let output_config = with_totals(output_config, args, &totals);
+        written(write_windows(out, &window_counts, &output_config));
+    } else if args.per_paragraph {
+        written(write_paragraphs(out, &paragraph_words));
+    } else if args.per_file {
+        let output_config = with_totals(output_config, args, &totals);
+        written(write_section(out, "total", &word_counts, &output_config));
+    } else if args.weight_by_position {
+        written(write_weighted_counts(out, &weights, output_config));
+    } else {
+        let output_config = OutputConfig {
+            first_lines: args.first_seen_line.then_some(first_lines),
+            first_positions: args.sort_by_first_seen.then_some(first_positions),
+            ..with_totals(output_config, args, &totals)
+        };
+        match args.histogram {
+            Some(top) => write_histogram_or_exit(out, &word_counts, &output_config, args, top),
+            None if args.top_per_length.is_some() => {
+                let n = args.top_per_length.unwrap_or(1);
+                let lengths = args.length_range.clone().unwrap_or(1..=usize::MAX);
+                let groups = top_per_length(&word_counts, n, lengths);
+                written(write_top_per_length(out, &groups));
+            }
+            None if args.frequency_bands => {
+                let bands = frequency_bands(&word_counts, &args.band_boundaries);
+                written(write_frequency_bands(out, &bands));
+            }
+            None if args.group_by_first_char => {
+                let groups = group_by_first_char(&word_counts);
+                written(write_word_groups(out, &groups, true));
+            }
+            None if args.word_length_distribution && args.length_dist_output.is_none() => {
+                let lengths = word_length_distribution(&word_counts);
+                let json = args.format == OutputFormat::Json;
+                written(write_word_length_distribution(out, &lengths, json));
+            }
+            None if args.case_variants => {
+                written(write_case_variants(out, &case_variants(&word_counts)));
+            }
+            None if args.group_by_script => {
+                let groups = group_by_script(&word_counts);
+                written(write_word_groups(out, &groups, false));
+            }
+            None => {
+                let sorting_started = Instant::now();
+                let selected = select_entries(&word_counts, &output_config);
+                sorting = sorting_started.elapsed();
+                written(write_selected_counts(
+                    out,
+                    &word_counts,
+                    selected,
+                    &output_config,
+                ));
+            }
+        }
+        if let Some((pct, total)) = output_config.top_percent {
+            let words = select_entries(&word_counts, &output_config).len();
+            let summary = format!(
+                "{} words cover {}% of the corpus ({} total tokens)",
+                words, pct, total
+            );
+            // a line after the words would break the other formats
+            match args.format {
+                OutputFormat::Text => written(writeln!(out, "{}", summary)),
+                _ => eprintln!("{}", summary),
+            }
+        }
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &args.export_sqlite {
+        if let Err(err) = export_sqlite(path, &word_counts, args.append_sqlite) {
+            eprintln!("Error: cannot export to {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+    if let Some(path) = &args.export_arpa {
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_arpa(
+                &mut file,
+                &word_counts,
+                args.arpa_smoothing,
+                args.arpa_unk_prob,
+            )?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+    #[cfg(feature = "plot")]
+    if let Some(path) = &args.word_frequency_plot {
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_frequency_plot(&mut file, &word_counts, args.zipf_exponent)?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+    if let Some(path) = &args.cooccurrence_output {
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_cooccurrences(&mut file, &pairs)?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+    if args.longest_words.is_some() || args.shortest_words.is_some() {
+        match &args.longest_words_output {
+            Some(path) => {
+                let result = File::create(path).and_then(|file| {
+                    let mut file = BufWriter::new(file);
+                    write_extreme_words(&mut file, args, &word_counts)?;
+                    file.flush()
+                });
+                if let Err(err) = result {
+                    eprintln!("Error: cannot write {}: {}", path.display(), err);
+                    process::exit(1);
+                }
+            }
+            None => written(write_extreme_words(out, args, &word_counts)),
+        }
+    }
+    if let Some(path) = &args.word_cloud_json {
+        let result = File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_word_cloud(
+                &mut file,
+                &word_counts,
+                args.word_cloud_top,
+                args.word_cloud_json_scale,
+            )?;
+            file.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("Error: cannot write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+    if args.heaps_diagnostics {
+        write_heaps_diagnostics(args, &heaps_points);
+    }
+    if args.heaps_fit {
+        match heaps_fit(&heaps_points) {
+            Some(fit) => eprintln!("Heaps' law fit: K = {:.4}, β = {:.4}", fit.k, fit.beta),
+            None => eprintln!("Warning: too few chunks to fit Heaps' law"),
+        }
+    }
+    if context_word.is_some() {
+        match &args.context_output {
+            Some(path) => {
+                let result = File::create(path).and_then(|file| {
+                    let mut file = BufWriter::new(file);
+                    write_concordance(&mut file, &kwic_lines)?;
+                    file.flush()
+                });
+                if let Err(err) = result {
+                    eprintln!("Error: cannot write {}: {}", path.display(), err);
+                    process::exit(1);
+                }
+            }
+            None => written(write_concordance(out, &kwic_lines)),
+        }
+    }
+    if let Some((word, _)) = &diversity_word {
+        written(write_context_diversity(out, word, &word_contexts));
+    }
+    if show_stats {
+        let mattr = mattr.map(|mattr| mattr.value());
+        let output_config = OutputConfig {
+            words_per_line: args.words_per_line.then_some(line_histogram),
+            new_words_per_line: args.count_unique_per_line.then_some(new_word_lines),
+            paragraphs: args.paragraph_mode.then_some(paragraph_words),
+            ..output_config.clone()
+        };
+        if json_summary {
+            let mut stats = stats_json(&totals, &word_counts, &output_config, mattr);
+            let counted = [
+                ("lines", args.count_lines, totals.lines),
+                ("sentences", args.count_sentences, totals.sentences),
+                ("chars", args.count_chars, totals.chars),
+                ("graphemes", args.count_graphemes, totals.graphemes),
+                ("bytes", args.count_bytes, totals.bytes),
+            ];
+            for (name, _, total) in counted.into_iter().filter(|(_, active, _)| *active) {
+                stats.insert(String::from(name), total.into());
+            }
+            written(writeln!(out, "{}", serde_json::Value::Object(stats)));
+        } else if args.format == OutputFormat::Text || args.summarize_only {
+            written(write_stats(
+                out,
+                &totals,
+                &word_counts,
+                &output_config,
+                mattr,
+            ));
+        } else {
+            // a line after the words would break the other formats
+            written(write_stats(
+                &mut io::stderr(),
+                &totals,
+                &word_counts,
+                &output_config,
+                mattr,
+            ));
+        }
+    }
+
+    written(out.flush());
+    if let Some(benchmark) = &mut benchmark {
+        benchmark.sorting = sorting;
+        benchmark.output = output_started.elapsed().saturating_sub(sorting);
+        eprint!("{}", benchmark.summary());
+    }
+    if totals.words_dropped > 0 {
+        let limit = match (args.max_unique_words, args.max_memory) {
+            (Some(_), Some(_)) => "--max-unique-words and --max-memory limits",
+            (None, Some(_)) => "--max-memory limit",
+            _ => "--max-unique-words limit",
+        };
+        eprintln!(
+            "Warning: {} words dropped due to {}",
+            totals.words_dropped, limit
+        );
+    }
+    if totals.timed_out {
+        eprintln!(
+            "Warning: {}",
+            time_budget_message(&totals, config, inputs.paths)
+        );
+    }
+    if args.per_line_dedup_report {
+        eprintln!("{} duplicate lines skipped", totals.duplicate_lines);
+    }
+    if args.force_utf8 && (args.verbose || show_stats) {
+        eprintln!("{} invalid UTF-8 sequences replaced", totals.invalid_utf8);
+    }
+    word_counts
+}
+
+/// Replace every directory in `paths` by the files below it whose name
+/// matches `pattern`, skipping binary files
+///
+/// The files of a directory are sorted by name, so the order of the per-file
+/// output is the same on every run.
+fn expand_directories(paths: &[PathBuf], pattern: &Pattern, follow_symlinks: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let walker = WalkDir::new(path)
+            .follow_links(follow_symlinks)
+            .sort_by_file_name();
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    eprintln!("Warning: skipping {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file()
+                || !pattern.matches(&entry.file_name().to_string_lossy())
+            {
+                continue;
+            }
+            match is_binary(entry.path()) {
+                Ok(false) => files.push(entry.into_path()),
+                Ok(true) => eprintln!("Warning: skipping {}: binary file", entry.path().display()),
+                Err(err) => eprintln!("Warning: skipping {}: {}", entry.path().display(), err),
+            }
+        }
+    }
+    files
+}
+
+/// Add the paths matching the `--input-glob` `pattern` to `paths`, warning
+/// if there are none
+fn expand_input_glob(pattern: &str, paths: &mut Vec<PathBuf>) {
+    let matches = glob::glob(pattern).expect("the pattern was checked when parsing it");
+    let before = paths.len();
+    for entry in matches {
+        match entry {
+            Ok(path) => paths.push(path),
+            Err(err) => eprintln!(
+                "Warning: skipping {}: {}",
+                err.path().display(),
+                err.error()
+            ),
+        }
+    }
+    if paths.len() == before {
+        eprintln!("Warning: --input-glob {} matches no files", pattern);
+    }
+}
+
+/// The output config relating the counts to the words of `totals`, for
+/// `--normalize` and `--cumulative`
+fn with_totals(output_config: &OutputConfig, args: &Cli, totals: &Totals) -> OutputConfig {
+    OutputConfig {
+        normalize_by: args.normalize.then_some(totals.words),
+        cumulative_of: args.cumulative.then_some(totals.words),
+        top_percent: args.top_percent.map(|pct| (pct, totals.words)),
+        ..output_config.clone()
+    }
+}
+
+/// Count a single input on the configured backend
+#[cfg(feature = "tokio")]
+fn count_input(
+    args: &Cli,
+    stdin_documents: &[(PathBuf, Vec<u8>)],
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let offset = sampled_per_input(config, path);
+    let config = offset.as_ref().unwrap_or(config);
+    match args.input_format {
+        InputFormat::Json => {
+            return count_json_input(args, stdin_documents, path, config, word_counts, observer)
+        }
+        InputFormat::Csv => {
+            return count_csv_input(args, stdin_documents, path, config, word_counts, observer)
+        }
+        InputFormat::Text => {}
+    }
+    if let Some(text) = &args.process_text {
+        return count_reader_observed(Cursor::new(text), config, word_counts, observer);
+    }
+    if let Some(document) = stdin_document(stdin_documents, path) {
+        return count_reader_observed(Cursor::new(document), config, word_counts, observer);
+    }
+    let detected;
+    let config = if args.input_encoding_detect {
+        detected = WordCountConfig {
+            encoding: detect_encoding_or_exit(path, args.encoding_confidence),
+            ..config.clone()
+        };
+        &detected
+    } else {
+        config
+    };
+    if !args.async_io {
+        return count_path_observed(path, config, word_counts, observer);
+    }
+    tokio::runtime::Runtime::new()?.block_on(count_path_async(path, config, word_counts, observer))
+}
+
+/// Count a single input on the configured backend
+#[cfg(not(feature = "tokio"))]
+fn count_input(
+    args: &Cli,
+    stdin_documents: &[(PathBuf, Vec<u8>)],
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let offset = sampled_per_input(config, path);
+    let config = offset.as_ref().unwrap_or(config);
+    match args.input_format {
+        InputFormat::Json => {
+            return count_json_input(args, stdin_documents, path, config, word_counts, observer)
+        }
+        InputFormat::Csv => {
+            return count_csv_input(args, stdin_documents, path, config, word_counts, observer)
+        }
+        InputFormat::Text => {}
+    }
+    if let Some(text) = &args.process_text {
+        return count_reader_observed(Cursor::new(text), config, word_counts, observer);
+    }
+    if let Some(document) = stdin_document(stdin_documents, path) {
+        return count_reader_observed(Cursor::new(document), config, word_counts, observer);
+    }
+    let detected;
+    let config = if args.input_encoding_detect {
+        detected = WordCountConfig {
+            encoding: detect_encoding_or_exit(path, args.encoding_confidence),
+            ..config.clone()
+        };
+        &detected
+    } else {
+        config
+    };
+    count_path_observed(path, config, word_counts, observer)
+}
+
+/// `config` with its `hash_seed` offset by `path` when it seeds the sample of
+/// --random-sample, so the inputs are not all sampled alike
+fn sampled_per_input(config: &WordCountConfig, path: &Path) -> Option<WordCountConfig> {
+    let seed = config.hash_seed.filter(|_| config.sample_rate.is_some())?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    Some(WordCountConfig {
+        hash_seed: Some(seed ^ hasher.finish()),
+        ..config.clone()
+    })
+}
+
+/// The document of stdin counted as `path` with --stdin-delimiter
+fn stdin_document<'a>(stdin_documents: &'a [(PathBuf, Vec<u8>)], path: &Path) -> Option<&'a [u8]> {
+    stdin_documents
+        .iter()
+        .find(|(name, _)| name == path)
+        .map(|(_, document)| &document[..])
+}
+
+/// Read stdin completely and split it into the documents of
+/// --stdin-delimiter, named `<stdin 1>`, `<stdin 2>` and so on
+///
+/// A delimiter at the very end starts no empty document, but an empty stdin
+/// is one empty document.
+fn read_stdin_documents_or_exit(delimiter: char) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut input = Vec::new();
+    if let Err(err) = io::stdin().lock().read_to_end(&mut input) {
+        eprintln!("Error: cannot read stdin: {}", err);
+        process::exit(1);
+    }
+    let mut buf = [0; 4];
+    let delimiter = delimiter.encode_utf8(&mut buf).as_bytes();
+    let mut documents = Vec::new();
+    let mut rest = &input[..];
+    while let Some(end) = rest
+        .windows(delimiter.len())
+        .position(|window| window == delimiter)
+    {
+        documents.push(rest[..end].to_vec());
+        rest = &rest[end + delimiter.len()..];
+    }
+    if !rest.is_empty() || documents.is_empty() {
+        documents.push(rest.to_vec());
+    }
+    documents
+        .into_iter()
+        .enumerate()
+        .map(|(index, document)| (PathBuf::from(format!("<stdin {}>", index + 1)), document))
+        .collect()
+}
+
+/// Open the raw bytes of a JSON or CSV input, which are parsed before the
+/// text in them is counted
+fn open_structured_input<'a>(
+    args: &'a Cli,
+    stdin_documents: &'a [(PathBuf, Vec<u8>)],
+    path: &Path,
+) -> io::Result<Box<dyn BufRead + 'a>> {
+    Ok(if let Some(text) = &args.process_text {
+        Box::new(Cursor::new(text.as_bytes()))
+    } else if let Some(document) = stdin_document(stdin_documents, path) {
+        Box::new(Cursor::new(document))
+    } else if path == Path::new(STDIN_PATH) {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    })
+}
+
+/// Count the text of the JSON lines of a single input
+fn count_json_input(
+    args: &Cli,
+    stdin_documents: &[(PathBuf, Vec<u8>)],
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let reader = open_structured_input(args, stdin_documents, path)?;
+    let json_path = match &args.json_path {
+        Some(json_path) => json_path.split('.').map(String::from).collect(),
+        None => Vec::new(),
+    };
+    let lines = JsonLines::new(reader, path, json_path);
+    count_reader_observed(BufReader::new(lines), config, word_counts, observer)
+}
+
+/// Reads the text of the JSON lines of an input, one line of text per line
+/// of JSON, see `json_text`
+///
+/// A line that is no valid JSON is warned about on stderr and read as a
+/// blank line, so the numbers of the following lines stay the same.
+struct JsonLines<R> {
+    inner: R,
+    /// The path shown in front of every warning
+    path: String,
+    /// The field path the strings are collected below
+    json_path: Vec<String>,
+    /// The number of the last line read
+    line: usize,
+    /// The text of the last line read, with a line terminator
+    text: Vec<u8>,
+    /// The number of bytes of `text` read already
+    pos: usize,
+}
+
+impl<R: BufRead> JsonLines<R> {
+    fn new(inner: R, path: &Path, json_path: Vec<String>) -> Self {
+        Self {
+            inner,
+            path: path.display().to_string(),
+            json_path,
+            line: 0,
+            text: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for JsonLines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.text.len() {
+            let mut line = String::new();
+            if self.inner.read_line(&mut line)? == 0 {
+                return Ok(0);
+            }
+            self.line += 1;
+            self.text.clear();
+            self.pos = 0;
+            if !line.trim().is_empty() {
+                match json_text(&line, &self.json_path) {
+                    Ok(text) => self.text.extend_from_slice(text.as_bytes()),
+                    Err(err) => eprintln!(
+                        "Warning: {}:{} is not valid JSON: {}",
+                        self.path, self.line, err
+                    ),
+                }
+            }
+            self.text.push(b'\n');
+        }
+        let n = buf.len().min(self.text.len() - self.pos);
+        buf[..n].copy_from_slice(&self.text[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Count the text columns of the CSV records of a single input
+fn count_csv_input(
+    args: &Cli,
+    stdin_documents: &[(PathBuf, Vec<u8>)],
+    path: &Path,
+    config: &WordCountConfig,
+    word_counts: &mut BTreeMap<String, usize>,
+    observer: &mut dyn ChunkObserver,
+) -> io::Result<Totals> {
+    let reader = open_structured_input(args, stdin_documents, path)?;
+    let records = CsvLines::new(reader, args)?;
+    count_reader_observed(BufReader::new(records), config, word_counts, observer)
+}
+
+/// Reads the text columns of the CSV records of an input, one line of text
+/// per record
+///
+/// The line breaks within quoted fields become spaces, so the lines read are
+/// the records.
+struct CsvLines<R> {
+    records: csv::Reader<R>,
+    /// The indices of the columns to read, all of them if empty
+    columns: Vec<usize>,
+    record: csv::StringRecord,
+    /// The text of the last record read, with a line terminator
+    text: Vec<u8>,
+    /// The number of bytes of `text` read already
+    pos: usize,
+}
+
+impl<R: Read> CsvLines<R> {
+    /// Read the columns of `--csv-text-column` of `inner`, failing with
+    /// `InvalidData` if the header has no column of one of the names
+    fn new(inner: R, args: &Cli) -> io::Result<Self> {
+        let numbers: Vec<Option<usize>> = args
+            .csv_text_column
+            .iter()
+            .map(|column| column.parse::<usize>().ok().filter(|&n| n > 0))
+            .collect();
+        let has_header = args.csv_has_header || numbers.iter().any(Option::is_none);
+        // records with more or fewer fields than the first one are read too
+        let mut records = csv::ReaderBuilder::new()
+            .has_headers(has_header)
+            .flexible(true)
+            .from_reader(inner);
+        let mut columns = Vec::new();
+        for (column, number) in args.csv_text_column.iter().zip(numbers) {
+            let index = match number {
+                Some(number) => number - 1,
+                None => records
+                    .headers()?
+                    .iter()
+                    .position(|name| name == column)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("the CSV header has no column {:?}", column),
+                        )
+                    })?,
+            };
+            columns.push(index);
+        }
+        Ok(Self {
+            records,
+            columns,
+            record: csv::StringRecord::new(),
+            text: Vec::new(),
+            pos: 0,
+        })
+    }
+}
+
+impl<R: Read> Read for CsvLines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.text.len() {
+            if !self.records.read_record(&mut self.record)? {
+                return Ok(0);
+            }
+            let fields: Vec<&str> = if self.columns.is_empty() {
+                self.record.iter().collect()
+            } else {
+                // a record too short for a column has an empty field there
+                self.columns
+                    .iter()
+                    .map(|&index| self.record.get(index).unwrap_or(""))
+                    .collect()
+            };
+            self.text.clear();
+            self.pos = 0;
+            let line = fields.join(" ").replace(['\r', '\n'], " ");
+            self.text.extend_from_slice(line.as_bytes());
+            self.text.push(b'\n');
+        }
+        let n = buf.len().min(self.text.len() - self.pos);
+        buf[..n].copy_from_slice(&self.text[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Write the sections of `--longest-words` and `--shortest-words`
+fn write_extreme_words<W: Write>(
+    out: &mut W,
+    args: &Cli,
+    word_counts: &BTreeMap<String, usize>,
+) -> io::Result<()> {
+    if let Some(n) = args.longest_words {
+        write_word_lengths(out, "longest words", &longest_words(word_counts, n))?;
+    }
+    if let Some(n) = args.shortest_words {
+        write_word_lengths(out, "shortest words", &shortest_words(word_counts, n))?;
+    }
+    Ok(())
+}
+
+/// Write the `top` most frequent of the selected words as a `--histogram`
+fn write_histogram_or_exit<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    output_config: &OutputConfig,
+    args: &Cli,
+    top: usize,
+) {
+    let output_config = OutputConfig {
+        sort_order: SortOrder::CountDesc,
+        top: Some(top),
+        ..output_config.clone()
+    };
+    let entries = select_entries(word_counts, &output_config);
+    let width = terminal_columns()
+        .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(HISTOGRAM_WIDTH);
+    let bar = args
+        .histogram_char
+        .unwrap_or(if is_utf8_locale() { '█' } else { '#' });
+    written(write_histogram(out, &entries, width, bar));
+}
+
+/// The number of columns of the terminal stdout writes to, if it is one
+#[cfg(unix)]
+fn terminal_columns() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a `winsize` to the pointer it is given
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+}
+
+/// The number of columns of the terminal stdout writes to, unknown here
+#[cfg(not(unix))]
+fn terminal_columns() -> Option<usize> {
+    None
+}
+
+/// Whether the locale of the environment uses UTF-8, so the terminal can be
+/// expected to draw chars beyond ASCII
+///
+/// Like in the C library the first of `LC_ALL`, `LC_CTYPE` and `LANG` that is
+/// set decides.
+fn is_utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|value| {
+            let value = value.to_ascii_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+}
+
+/// Whether the output goes to a terminal, where it can be redrawn in place
+fn is_live_output(args: &Cli) -> bool {
+    args.output.is_none() && io::stdout().is_terminal()
+}
+
+/// The modification time and length of every path, `None` for missing ones
+fn modification_stamps(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+/// Poll `paths` every `interval` until one of them changed from `stamps`
+///
+/// A missing file is waited for until it reappears.
+fn wait_for_change(paths: &[PathBuf], stamps: &[Option<(SystemTime, u64)>], interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        let current = modification_stamps(paths);
+        if current.iter().all(Option::is_some) && current != stamps {
+            return;
+        }
+    }
+}
+
+/// Reports the progress of a single input on stderr
+struct Progress {
+    /// The path shown in front of the progress
+    path: String,
+    /// The size of the input, if it is known
+    size: Option<u64>,
+    /// Whether anything is shown at all
+    enabled: bool,
+}
+
+impl Progress {
+    /// Create the progress for `path`, which stays silent unless `requested`
+    /// and stderr is a terminal, so piped stderr is never cluttered
+    fn new(path: &Path, requested: bool) -> Self {
+        let size = fs::metadata(path).ok().map(|metadata| metadata.len());
+        Self {
+            path: path.display().to_string(),
+            size,
+            enabled: requested && io::stderr().is_terminal(),
+        }
+    }
+
+    /// Clear the progress line once the input is done
+    fn finish(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+impl ChunkObserver for Progress {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        if self.enabled {
+            let message = progress_message(totals, self.size);
+            eprint!("\r\x1b[K{}: {}", self.path, message);
+        }
+        Ok(())
+    }
+}
+
+/// Reports the lines read and the size of the counts of a single input on
+/// stderr after every chunk, for `--verbose`
+struct Verbose {
+    /// The path shown in front of every report
+    path: String,
+}
+
+impl Verbose {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.display().to_string(),
+        }
+    }
+}
+
+impl ChunkObserver for Verbose {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        let peak_rss = peak_rss_kb()
+            .map(|kb| format!("  peak RSS: {} kB", kb))
+            .unwrap_or_default();
+        eprintln!(
+            "{}: lines read: {}  unique words: {}  map memory: ~{} bytes{}",
+            self.path,
+            totals.lines_read,
+            word_counts.len(),
+            estimate_map_bytes(word_counts),
+            peak_rss
+        );
+        Ok(())
+    }
+}
+
+/// The time spent in every stage of counting all inputs, for
+/// `--benchmark-profile`
+#[derive(Debug, Default)]
+struct BenchmarkProfile {
+    /// Reading the lines of the inputs
+    reading: Duration,
+    /// Filtering the lines read and passing them to the observers
+    collecting: Duration,
+    /// Tokenizing the lines and counting the tokens
+    counting: Duration,
+    /// Selecting and sorting the printed entries
+    sorting: Duration,
+    /// Writing the output, the sorting left out
+    output: Duration,
+    /// The pauses between rounds, not part of the total
+    sleeping: Duration,
+}
+
+impl BenchmarkProfile {
+    /// A line per stage with its time and its share of the total, then the
+    /// total and the time slept
+    fn summary(&self) -> String {
+        let stages = [
+            ("file_io", self.reading),
+            ("line_collection", self.collecting),
+            ("chunk_processing", self.counting),
+            ("sorting", self.sorting),
+            ("output", self.output),
+        ];
+        let total: Duration = stages.iter().map(|(_, time)| *time).sum();
+        let mut summary = String::new();
+        for (stage, time) in stages {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            summary.push_str(&format!(
+                "{}: {} us ({:.2}%)\n",
+                stage,
+                time.as_micros(),
+                share
+            ));
+        }
+        summary.push_str(&format!("total: {} us\n", total.as_micros()));
+        summary.push_str(&format!(
+            "sleep: {} us, not part of the total\n",
+            self.sleeping.as_micros()
+        ));
+        summary
+    }
+}
+
+impl ChunkObserver for BenchmarkProfile {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn round_read(&mut self, reading: Duration, collecting: Duration) {
+        self.reading += reading;
+        self.collecting += collecting;
+    }
+
+    fn round_timed(&mut self, counting: Duration, slept: Duration) -> io::Result<()> {
+        self.counting += counting;
+        self.sleeping += slept;
+        Ok(())
+    }
+}
+
+/// Writes a CSV row with the size and timing of every round of chunks, for
+/// `--profile-chunks`
+struct ChunkProfile {
+    out: Box<dyn Write>,
+    /// The number of rounds profiled so far
+    rounds: usize,
+    /// The lines of the current round
+    lines: usize,
+    /// The unique words once the current round was counted
+    unique_words: usize,
+}
+
+impl ChunkProfile {
+    fn new(mut out: Box<dyn Write>) -> io::Result<Self> {
+        writeln!(
+            out,
+            "chunk_id,lines,unique_words_so_far,process_time_us,sleep_time_us"
+        )?;
+        Ok(Self {
+            out,
+            rounds: 0,
+            lines: 0,
+            unique_words: 0,
+        })
+    }
+}
+
+impl ChunkObserver for ChunkProfile {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        self.unique_words = word_counts.len();
+        Ok(())
+    }
+
+    fn line_read(&mut self, _line: &str) {
+        self.lines += 1;
+    }
+
+    fn round_timed(&mut self, counting: Duration, slept: Duration) -> io::Result<()> {
+        self.rounds += 1;
+        writeln!(
+            self.out,
+            "{},{},{},{},{}",
+            self.rounds,
+            self.lines,
+            self.unique_words,
+            counting.as_micros(),
+            slept.as_micros()
+        )?;
+        self.lines = 0;
+        // a file would otherwise only be written once the buffer is full
+        self.out.flush()
+    }
+}
+
+/// Writes the number of tokens and unique words after every round of chunks,
+/// for `--per-chunk-stats`
+struct ChunkStats {
+    out: Box<dyn Write>,
+    started: Instant,
+    /// The number of rounds written so far
+    rounds: usize,
+    /// The lines of the current round
+    lines: usize,
+}
+
+impl ChunkObserver for ChunkStats {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        self.rounds += 1;
+        let ttr = if totals.words == 0 {
+            0.0
+        } else {
+            word_counts.len() as f64 / totals.words as f64
+        };
+        writeln!(
+            self.out,
+            "chunk {}: lines={} total_tokens={} unique_words={} ttr={:.4} elapsed_ms={}",
+            self.rounds,
+            self.lines,
+            totals.words,
+            word_counts.len(),
+            ttr,
+            self.started.elapsed().as_millis()
+        )?;
+        self.lines = 0;
+        // a file would otherwise only be written once the buffer is full
+        self.out.flush()
+    }
+
+    fn line_read(&mut self, _line: &str) {
+        self.lines += 1;
+    }
+}
+
+/// Replaces the `--output` file with the counts of all inputs so far every
+/// `--output-every-n-chunks` rounds of chunks
+struct OutputSnapshots<'a> {
+    path: &'a Path,
+    every: u64,
+    output_config: &'a OutputConfig,
+    /// The counts of the inputs before the current one
+    before: &'a BTreeMap<String, usize>,
+    /// The number of rounds so far, numbered on over all inputs
+    rounds: &'a mut u64,
+}
+
+impl ChunkObserver for OutputSnapshots<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        *self.rounds += 1;
+        if !self.rounds.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        let mut counts = self.before.clone();
+        merge_counts(&mut counts, word_counts.clone());
+        let mut snapshot = Vec::new();
+        write_counts(&mut snapshot, &counts, self.output_config)?;
+        replace_file(self.path, &snapshot)
+    }
+}
+
+/// Replace the file at `path` with `contents` by writing them to a
+/// temporary file next to it and renaming that over it
+fn replace_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// The final output of `--output-every-n-chunks`, kept in memory and
+/// written with `replace_file` on every flush, so it replaces the last
+/// snapshot at once
+struct ReplacingOutput {
+    path: PathBuf,
+    buffer: Vec<u8>,
+}
+
+impl Write for ReplacingOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        replace_file(&self.path, &self.buffer)
+    }
+}
+
+/// Open the destination of `--per-chunk-stats`, exiting if it cannot be
+/// opened
+fn open_chunk_stats_or_exit(args: &Cli) -> ChunkStats {
+    let out: Box<dyn Write> = match &args.chunk_stats_output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(err) => {
+                eprintln!(
+                    "Error: cannot open chunk stats file {}: {}",
+                    path.display(),
+                    err
+                );
+                process::exit(1);
+            }
+        },
+        None => Box::new(io::stderr()),
+    };
+    ChunkStats {
+        out,
+        started: Instant::now(),
+        rounds: 0,
+        lines: 0,
+    }
+}
+
+/// Open the destination of `--profile-chunks` and write the CSV header,
+/// exiting if it cannot be opened
+fn open_profile_or_exit(args: &Cli) -> ChunkProfile {
+    let out: io::Result<Box<dyn Write>> = match &args.profile_output {
+        Some(path) => File::create(path).map(|file| Box::new(BufWriter::new(file)) as _),
+        None => Ok(Box::new(io::stderr())),
+    };
+    match out.and_then(ChunkProfile::new) {
+        Ok(profile) => profile,
+        Err(err) => {
+            let path = args.profile_output.as_deref().unwrap_or(Path::new("-"));
+            eprintln!(
+                "Error: cannot open profile file {}: {}",
+                path.display(),
+                err
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// The peak resident set size of the process in kB, as reported by Linux in
+/// `/proc/self/status`, or `None` elsewhere
+fn peak_rss_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The observer printing counts while an input is counted
+enum Streaming<'a> {
+    /// The `--stream` snapshots or live counts
+    Snapshots(Stream<'a>),
+    /// The lines of the ndjson format
+    Ndjson(NdjsonStream<'a>),
+}
+
+impl Streaming<'_> {
+    /// Finish the output of the current input
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Streaming::Snapshots(stream) => stream.finish(),
+            Streaming::Ndjson(_) => Ok(()),
+        }
+    }
+}
+
+impl ChunkObserver for Streaming<'_> {
+    fn chunk_done(
+        &mut self,
+        totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        match self {
+            Streaming::Snapshots(stream) => stream.chunk_done(totals, word_counts),
+            Streaming::Ndjson(stream) => stream.chunk_done(totals, word_counts),
+        }
+    }
+
+    fn line_read(&mut self, line: &str) {
+        match self {
+            Streaming::Snapshots(stream) => stream.line_read(line),
+            Streaming::Ndjson(stream) => stream.line_read(line),
+        }
+    }
+}
+
+/// Prints the counts of the current input after every chunk
+struct Stream<'a> {
+    /// The destination of the output
+    out: &'a mut dyn Write,
+    /// How the counts are selected and ordered
+    output_config: &'a OutputConfig,
+    /// Whether the previous counts are overwritten instead of printing
+    /// JSON snapshots
+    live: bool,
+    /// The number of lines currently shown in live mode
+    shown: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn new(out: &'a mut dyn Write, output_config: &'a OutputConfig, live: bool) -> Self {
+        Self {
+            out,
+            output_config,
+            live,
+            shown: 0,
+        }
+    }
+
+    /// Clear the live counts, which are replaced by the final output
+    fn finish(&mut self) -> io::Result<()> {
+        self.clear()?;
+        self.out.flush()
+    }
+
+    /// Move the cursor back over the lines shown in live mode and erase them
+    fn clear(&mut self) -> io::Result<()> {
+        if self.live && self.shown > 0 {
+            write!(self.out, "\x1b[{}F\x1b[J", self.shown)?;
+            self.shown = 0;
+        }
+        Ok(())
+    }
+}
+
+impl ChunkObserver for Stream<'_> {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        if !self.live {
+            // the clock can only be before the epoch if it is badly off
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            return write_snapshot(&mut self.out, now, word_counts, self.output_config);
+        }
+        self.clear()?;
+        let entries = select_entries(word_counts, self.output_config);
+        for (word, count) in &entries {
+            writeln!(self.out, "{}: {}", word, count)?;
+        }
+        self.shown = entries.len();
+        self.out.flush()
+    }
 }
 
-fn main() {
-    let args = Cli::parse();
+/// Warns on stderr about every line with fewer fields than `--column`
+struct ShortLines {
+    /// The path shown in front of every warning
+    path: String,
+    /// The field that is counted, 1-based
+    column: usize,
+    /// The char between two fields, `None` for whitespace
+    separator: Option<char>,
+    /// The number of the last line read
+    line: usize,
+}
+
+impl ShortLines {
+    /// Create the warnings for `path`, whose first `skipped` lines are not read
+    fn new(path: &Path, column: usize, separator: Option<char>, skipped: usize) -> Self {
+        Self {
+            path: path.display().to_string(),
+            column,
+            separator,
+            line: skipped,
+        }
+    }
+}
 
-    let file = File::open(&args.path).unwrap();
+impl ChunkObserver for ShortLines {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
 
-    // use larger buffer ti reduce I/O-operations
-    let reader = BufReader::with_capacity(1024 * 1024, file);
+    fn line_read(&mut self, line: &str) {
+        self.line += 1;
+        if extract_column(line, self.column, self.separator).is_none() {
+            eprintln!(
+                "Warning: {}:{} has fewer than {} fields",
+                self.path, self.line, self.column
+            );
+        }
+    }
+}
 
-    let mut word_counts: BTreeMap<String, usize> = BTreeMap::new();
+/// Warns on stderr about every line longer than `--max-line-length`
+struct LongLines {
+    /// The path shown in front of every warning
+    path: String,
+    /// The number of chars a line may have
+    max: usize,
+    /// The number of the last line read
+    line: usize,
+}
 
-    // Process in larger chunks to allow for more idle time between processing
-    for chunk in reader
-        .lines()
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap()
-        .chunks(10000)
-    {
-        // high cpu activity: DVFS might increase cpu frequency
-        process_chunk(chunk, &mut word_counts);
+impl LongLines {
+    /// Create the warnings for `path`, whose first `skipped` lines are not read
+    fn new(path: &Path, max: usize, skipped: usize) -> Self {
+        Self {
+            path: path.display().to_string(),
+            max,
+            line: skipped,
+        }
+    }
+}
+
+impl ChunkObserver for LongLines {
+    fn chunk_done(
+        &mut self,
+        _totals: &Totals,
+        _word_counts: &BTreeMap<String, usize>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn line_read(&mut self, line: &str) {
+        self.line += 1;
+        if is_longer_than(line, self.max) {
+            eprintln!(
+                "Warning: {}:{} is {} chars long, longer than {}",
+                self.path,
+                self.line,
+                line.chars().count(),
+                self.max
+            );
+        }
+    }
+
+    fn line_skipped(&mut self, line: &str) {
+        self.line_read(line);
+    }
+}
 
-        // Potential place for a short sleep to allow cpu to enter a lower power state
-        std::thread::sleep(std::time::Duration::from_millis(10));
+/// Describe how much of an input of the given `size` has been processed
+fn progress_message(totals: &Totals, size: Option<u64>) -> String {
+    match size {
+        Some(size) if size > 0 => {
+            let percent = totals.bytes as f64 / size as f64 * 100.0;
+            format!("processed {} lines ({:.1}%)", totals.lines, percent)
+        }
+        _ => format!("processed {} lines", totals.lines),
     }
+}
 
-    for (word, count) in word_counts {
-        println!("{}: {}", word, count);
+/// The warning printed once `--time-budget` stopped the counting, with the
+/// number of chunks estimated from the size of the inputs if it is known
+fn time_budget_message(totals: &Totals, config: &WordCountConfig, paths: &[PathBuf]) -> String {
+    let chunks = totals.lines_read.div_ceil(config.chunk_size);
+    let size: Option<u64> = paths
+        .iter()
+        .map(|path| {
+            let special = path == Path::new(STDIN_PATH) || path == Path::new(TEXT_INPUT);
+            (!special)
+                .then(|| fs::metadata(path).ok())
+                .flatten()
+                .map(|metadata| metadata.len())
+        })
+        .sum();
+    match size {
+        Some(size) if totals.bytes > 0 => {
+            let all = (chunks as f64 * size as f64 / totals.bytes as f64).ceil() as usize;
+            format!(
+                "time budget exceeded after processing {} of ~{} chunks ({:.1}% of the input); \
+                 results are approximate",
+                chunks,
+                all.max(chunks),
+                totals.bytes as f64 / size as f64 * 100.0
+            )
+        }
+        _ => format!(
+            "time budget exceeded after processing {} chunks; results are approximate",
+            chunks
+        ),
     }
 }
 
-/// Process a chunk of lines, updating the word counts
+/// Count all `paths` `BENCHMARK_RUNS` times and print the median time and
+/// throughput of a run to stderr
 ///
-/// # Arguments
+/// The throughput is given in decimal megabytes, like disk speeds usually are.
+fn benchmark(args: &Cli, config: &WordCountConfig, paths: &[PathBuf]) {
+    let mut times = Vec::with_capacity(BENCHMARK_RUNS);
+    let mut totals = Totals::default();
+    for _ in 0..BENCHMARK_RUNS {
+        totals = Totals::default();
+        let start = Instant::now();
+        for path in paths {
+            let mut word_counts = BTreeMap::new();
+            match count_input(args, &[], path, config, &mut word_counts, &mut ()) {
+                Ok(file_totals) => totals += file_totals,
+                Err(err) => {
+                    eprintln!("Error: cannot read {}: {}", path.display(), err);
+                    process::exit(1);
+                }
+            }
+        }
+        times.push(start.elapsed());
+    }
+    times.sort();
+    let median = times[BENCHMARK_RUNS / 2];
+    let seconds = median.as_secs_f64().max(f64::MIN_POSITIVE);
+    eprintln!(
+        "median of {} runs: {:.3} s  {:.2} MB/s  {:.0} words/s",
+        BENCHMARK_RUNS,
+        median.as_secs_f64(),
+        totals.bytes as f64 / 1e6 / seconds,
+        totals.words as f64 / seconds,
+    );
+}
+
+/// Count the words of one side of a diff, exiting if it cannot be read
+///
+/// Unlike the inputs of a normal run, a diff is meaningless without both sides.
+fn count_or_exit(path: &Path, config: &WordCountConfig) -> BTreeMap<String, usize> {
+    let mut word_counts = BTreeMap::new();
+    if let Err(err) = count_path_observed(path, config, &mut word_counts, &mut ()) {
+        eprintln!("Error: cannot read {}: {}", path.display(), err);
+        process::exit(1);
+    }
+    word_counts
+}
+
+/// Read the checkpoint to resume `input` from, exiting if it is unusable
+fn load_checkpoint(path: &Path, input: &Path) -> Checkpoint {
+    let checkpoint = match read_checkpoint(path) {
+        Ok(checkpoint) => checkpoint,
+        Err(err) => {
+            eprintln!("Error: cannot read checkpoint {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    };
+    if checkpoint.source != input {
+        eprintln!(
+            "Error: checkpoint {} was taken from {}, not {}",
+            path.display(),
+            checkpoint.source.display(),
+            input.display()
+        );
+        process::exit(1);
+    }
+    checkpoint
+}
+
+/// Check the arguments for `--dry-run` and exit, with 1 if any of them is
+/// invalid
+///
+/// Only the files the arguments name are looked at: the inputs are neither
+/// opened nor read.
+fn dry_run(args: &Cli, config: &WordCountConfig) -> ! {
+    let mut errors = validate_config(config);
+    let word_lists = [
+        (args.stopwords.as_deref(), "stopwords"),
+        (args.vocab.as_deref(), "vocab"),
+    ];
+    for (path, what) in word_lists {
+        let Some(path) = path else { continue };
+        if let Err(err) = read_word_list(path) {
+            errors.push(format!(
+                "cannot read {} file {}: {}",
+                what,
+                path.display(),
+                err
+            ));
+        }
+    }
+    if let Some(path) = &args.word_list_diff {
+        if let Err(err) = read_counts(path, OutputFormat::Text) {
+            errors.push(format!(
+                "cannot read reference file {}: {}",
+                path.display(),
+                err
+            ));
+        }
+    }
+    if let Some(path) = &args.relative_to {
+        if let Err(err) = File::open(path) {
+            errors.push(format!(
+                "cannot read baseline file {}: {}",
+                path.display(),
+                err
+            ));
+        }
+    }
+    if let Some(path) = &args.resume {
+        if let Err(err) = File::open(path) {
+            errors.push(format!(
+                "cannot read checkpoint {}: {}",
+                path.display(),
+                err
+            ));
+        }
+    }
+    if let Some(path) = &args.output {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        match fs::metadata(dir) {
+            Ok(metadata) if metadata.is_dir() && !metadata.permissions().readonly() => {}
+            Ok(_) => errors.push(format!(
+                "cannot write output file {}: {} is no writable directory",
+                path.display(),
+                dir.display()
+            )),
+            Err(err) => errors.push(format!(
+                "cannot write output file {}: {}",
+                path.display(),
+                err
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        println!("OK: all arguments validated");
+        process::exit(0);
+    }
+    for error in errors {
+        eprintln!("Error: {}", error);
+    }
+    process::exit(1);
+}
+
+/// Detect the encoding of the file at `path` for `--input-encoding-detect`,
+/// exiting if it cannot be read or the detection is less confident than
+/// `min_confidence`
+fn detect_encoding_or_exit(path: &Path, min_confidence: f64) -> Encoding {
+    let mut sample = Vec::with_capacity(4096);
+    if let Err(err) = File::open(path).and_then(|file| file.take(4096).read_to_end(&mut sample)) {
+        eprintln!("Error: cannot read {}: {}", path.display(), err);
+        process::exit(1);
+    }
+    let detected = detect_encoding(&sample);
+    let name = detected
+        .encoding
+        .to_possible_value()
+        .expect("no encoding is skipped");
+    if detected.confidence < min_confidence {
+        eprintln!(
+            "Error: cannot detect the encoding of {}, guessed {} with confidence {:.2}; \
+             specify it with --encoding",
+            path.display(),
+            name.get_name(),
+            detected.confidence
+        );
+        process::exit(1);
+    }
+    eprintln!(
+        "Detected encoding of {}: {} (confidence {:.2})",
+        path.display(),
+        name.get_name(),
+        detected.confidence
+    );
+    detected.encoding
+}
+
+/// Write the --heaps-diagnostics rows to --heaps-output or stderr, exiting
+/// if the file cannot be written
+fn write_heaps_diagnostics(args: &Cli, points: &[(usize, usize)]) {
+    let Some(path) = &args.heaps_output else {
+        // like with eprintln!, there is nowhere to report a failing stderr
+        let _ = write_vocabulary_growth(&mut io::stderr().lock(), points);
+        return;
+    };
+    let result = File::create(path).and_then(|file| {
+        let mut file = BufWriter::new(file);
+        write_vocabulary_growth(&mut file, points)?;
+        file.flush()
+    });
+    if let Err(err) = result {
+        eprintln!("Error: cannot write {}: {}", path.display(), err);
+        process::exit(1);
+    }
+}
+
+/// Report the encoding of the file at `path` on stderr for --report-encoding
 ///
-/// * `chunk` - A slice of strings representing the lines to process
-/// * `word_counts` - A mutable reference to a `BTreeMap` to store the word counts
-fn process_chunk(chunk: &[String], word_counts: &mut BTreeMap<String, usize>) {
-    for line in chunk {
-        for word in line.split_whitespace() {
-            *word_counts.entry(word.to_string()).or_insert(0) += 1;
+/// An unreadable file is left to be skipped with a warning when counting it.
+fn report_encoding(path: &Path) {
+    let Ok(bytes) = std::fs::read(path) else {
+        return;
+    };
+    let report = encoding_report(&bytes);
+    let encoding = match report.bom {
+        Some(Encoding::Utf16le) => "UTF-16LE (BOM)",
+        Some(Encoding::Utf16be) => "UTF-16BE (BOM)",
+        Some(_) => "UTF-8 (BOM)",
+        None => "UTF-8 (no BOM)",
+    };
+    eprintln!(
+        "Detected encoding of {}: {}, {} bytes, {} valid and {} invalid UTF-8 sequences",
+        path.display(),
+        encoding,
+        report.bytes,
+        report.valid_sequences,
+        report.invalid_sequences
+    );
+}
+
+/// Open the destination of the output, exiting if it cannot be opened
+fn open_output_or_exit(args: &Cli) -> Box<dyn Write> {
+    let out = match &args.output {
+        Some(path) if args.output_every_n_chunks.is_some() => Ok(Box::new(ReplacingOutput {
+            path: path.clone(),
+            buffer: Vec::new(),
+        }) as Box<dyn Write>),
+        path => open_output(path.as_deref()),
+    };
+    match out {
+        Ok(out) => match args.output_limit {
+            Some(limit) => Box::new(CountingWriter::new(out, limit)),
+            None => out,
+        },
+        Err(err) => {
+            // reported on stderr since the destination itself is unusable
+            let path = args.output.as_deref().unwrap_or(Path::new("-"));
+            eprintln!("Error: cannot open output file {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+}
+
+/// The error of a `CountingWriter` once its limit is reached
+#[derive(Debug)]
+struct OutputLimitReached(usize);
+
+impl fmt::Display for OutputLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Output truncated at {} bytes due to --output-limit",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for OutputLimitReached {}
+
+/// Counts the bytes written to the output and fails every write past
+/// `--output-limit`
+///
+/// The bytes up to the limit are still written and flushed before the first
+/// failing write, so the output is cut off at exactly the limit.
+struct CountingWriter<W> {
+    inner: W,
+    /// The number of bytes written so far
+    written: usize,
+    /// The number of bytes that may be written
+    limit: usize,
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn new(inner: W, limit: usize) -> Self {
+        Self {
+            inner,
+            written: 0,
+            limit,
+        }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.limit - self.written;
+        if room == 0 && !buf.is_empty() {
+            // the caller exits on the error, so nothing is flushed later
+            self.inner.flush()?;
+            return Err(io::Error::other(OutputLimitReached(self.limit)));
+        }
+        let n = self.inner.write(&buf[..buf.len().min(room)])?;
+        self.written += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Exit with the warning of `--output-limit` if `err` is its error
+fn exit_if_truncated(err: &io::Error) {
+    let reached = err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<OutputLimitReached>());
+    if let Some(reached) = reached {
+        eprintln!("{}", reached);
+        process::exit(1);
+    }
+}
+
+/// Unwrap the result of writing the output, exiting if `--output-limit` was
+/// reached
+fn written<T>(result: io::Result<T>) -> T {
+    if let Err(err) = &result {
+        exit_if_truncated(err);
+    }
+    result.unwrap()
+}
+
+/// Open the destination of the output, which is stdout unless `path` is given
+fn open_output(path: Option<&Path>) -> io::Result<Box<dyn Write>> {
+    let Some(path) = path else {
+        return Ok(Box::new(io::stdout().lock()));
+    };
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
+
+/// Read the counts already in the `--output` file for `--append`, exiting if
+/// they cannot be read
+///
+/// A missing file has no counts yet.
+fn load_existing_counts(args: &Cli) -> BTreeMap<String, usize> {
+    let path = args.output.as_deref().expect("--append requires --output");
+    match read_counts(path, args.format) {
+        Ok(word_counts) => word_counts,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(err) => {
+            eprintln!(
+                "Error: cannot merge into output file {}: {}",
+                path.display(),
+                err
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Count all `paths` into one map, exiting if any of them cannot be read
+fn count_all_or_exit(
+    args: &Cli,
+    inputs: Inputs,
+    config: &WordCountConfig,
+) -> BTreeMap<String, usize> {
+    let mut word_counts = BTreeMap::new();
+    for path in inputs.paths {
+        let documents = inputs.stdin_documents;
+        if let Err(err) = count_input(args, documents, path, config, &mut word_counts, &mut ()) {
+            eprintln!("Error: cannot read {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+    word_counts
+}
+
+/// Read the reference frequency list of `--word-list-diff`, exiting if it
+/// cannot be read
+///
+/// The words are lowercased when counting ignores case, adding up the counts
+/// of the words differing only in case.
+fn load_reference(path: &Path, ignore_case: bool) -> BTreeMap<String, usize> {
+    match read_counts(path, OutputFormat::Text) {
+        Ok(reference) if ignore_case => {
+            let mut lowercased = BTreeMap::new();
+            for (word, count) in reference {
+                *lowercased.entry(word.to_lowercase()).or_insert(0) += count;
+            }
+            lowercased
+        }
+        Ok(reference) => reference,
+        Err(err) => {
+            eprintln!(
+                "Error: cannot read reference file {}: {}",
+                path.display(),
+                err
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Read a word list given on the command line, exiting if it cannot be read
+///
+/// The words are lowercased when counting ignores case, so they match the
+/// normalised tokens.
+fn load_word_list(path: &Path, ignore_case: bool, what: &str) -> HashSet<String> {
+    match read_word_list(path) {
+        Ok(words) if ignore_case => words.iter().map(|word| word.to_lowercase()).collect(),
+        Ok(words) => words,
+        Err(err) => {
+            eprintln!(
+                "Error: cannot read {} file {}: {}",
+                what,
+                path.display(),
+                err
+            );
+            process::exit(1);
         }
     }
 }
 
+/// Parse the `--chunk-size` argument, rejecting chunks without any line
+fn parse_chunk_size(s: &str) -> Result<usize, String> {
+    let chunk_size: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if chunk_size == 0 {
+        return Err(String::from("a chunk must contain at least 1 line"));
+    }
+    Ok(chunk_size)
+}
+
+/// Parse the `--mattr-window` argument, rejecting empty windows
+fn parse_mattr_window(s: &str) -> Result<usize, String> {
+    let window: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if window == 0 {
+        return Err(String::from("a window must contain at least 1 token"));
+    }
+    Ok(window)
+}
+
+/// Parse the `--window` argument, rejecting windows without any line
+fn parse_window(s: &str) -> Result<usize, String> {
+    let window: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if window == 0 {
+        return Err(String::from("a window must contain at least 1 line"));
+    }
+    Ok(window)
+}
+
+/// Parse the `--truncate-token` argument, rejecting a length of 0
+fn parse_truncate_length(s: &str) -> Result<usize, String> {
+    let length: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if length == 0 {
+        return Err(String::from("a truncated word must keep at least 1 char"));
+    }
+    Ok(length)
+}
+
+/// Parse the `--ngrams` argument, rejecting n-grams without any word
+fn parse_ngrams(s: &str) -> Result<usize, String> {
+    let ngrams: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if ngrams == 0 {
+        return Err(String::from("an n-gram must contain at least 1 word"));
+    }
+    Ok(ngrams)
+}
+
+/// Parse the `--cooccurrence` window, which must have room for a pair
+fn parse_cooccurrence_window(s: &str) -> Result<usize, String> {
+    let window: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if window < 2 {
+        return Err(String::from("a window must contain at least 2 tokens"));
+    }
+    Ok(window)
+}
+
+/// Parse a `START-END`, `START-` or `-END` range of lines for `--lines`
+fn parse_line_range(s: &str) -> Result<LineRange, String> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| String::from("expected START-END, START- or -END"))?;
+    let number = |s: &str| s.parse::<usize>().map_err(|err| format!("{}", err));
+    let start = if start.is_empty() { 1 } else { number(start)? };
+    let end = if end.is_empty() {
+        None
+    } else {
+        Some(number(end)?)
+    };
+    if start == 0 {
+        return Err(String::from("lines are numbered starting at 1"));
+    }
+    if end.is_some_and(|end| end < start) {
+        return Err(String::from("the range must not end before it starts"));
+    }
+    Ok(LineRange { start, end })
+}
+
+/// Parse a `MIN-MAX`, `MIN-` or `-MAX` range of word lengths for
+/// `--length-range`
+fn parse_length_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let (min, max) = s
+        .split_once('-')
+        .ok_or_else(|| String::from("expected MIN-MAX, MIN- or -MAX"))?;
+    let number = |s: &str| s.parse::<usize>().map_err(|err| format!("{}", err));
+    let min = if min.is_empty() { 1 } else { number(min)? };
+    let max = if max.is_empty() {
+        usize::MAX
+    } else {
+        number(max)?
+    };
+    if max < min {
+        return Err(String::from("the range must not end before it starts"));
+    }
+    Ok(min..=max)
+}
+
+/// Parse the `--char-ngrams` argument, rejecting n-grams without any char
+fn parse_char_ngrams(s: &str) -> Result<usize, String> {
+    let n: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if n == 0 {
+        return Err(String::from("an n-gram must contain at least 1 char"));
+    }
+    Ok(n)
+}
+
+/// Parse the `--max-unique-words` argument, which must be at least 1
+fn parse_max_unique_words(s: &str) -> Result<usize, String> {
+    let n: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if n == 0 {
+        return Err(String::from("at least 1 unique word must be counted"));
+    }
+    Ok(n)
+}
+
+/// Parse the `--field-separator-str` argument, which must not be empty
+fn parse_field_separator(s: &str) -> Result<String, String> {
+    if s.is_empty() {
+        return Err(String::from("a separator must contain at least 1 char"));
+    }
+    Ok(s.to_string())
+}
+
+/// Parse a number of bytes with an optional binary `K`, `M` or `G` suffix
+fn parse_byte_size(s: &str) -> Result<usize, String> {
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&s[..index], 1 << 10),
+        Some((index, 'M' | 'm')) => (&s[..index], 1 << 20),
+        Some((index, 'G' | 'g')) => (&s[..index], 1 << 30),
+        _ => (s, 1),
+    };
+    let size: usize = digits.parse().map_err(|err| format!("{}", err))?;
+    size.checked_mul(multiplier)
+        .ok_or_else(|| format!("{} bytes do not fit in memory", s))
+}
+
+/// Parse the `--buffer-size` argument, rejecting tiny and huge buffers
+fn parse_buffer_size(s: &str) -> Result<usize, String> {
+    let size = parse_byte_size(s)?;
+    if !(MIN_BUFFER_SIZE..=MAX_BUFFER_SIZE).contains(&size) {
+        return Err(format!(
+            "the buffer must hold between {} bytes and 1G, not {} bytes",
+            MIN_BUFFER_SIZE, size
+        ));
+    }
+    Ok(size)
+}
+
+/// Parse the `--coverage-threshold` argument, a percentage from 0 to 100
+fn parse_percentage(s: &str) -> Result<f64, String> {
+    let pct: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(0.0..=100.0).contains(&pct) {
+        return Err(String::from("a percentage must be between 0 and 100"));
+    }
+    Ok(pct)
+}
+
+/// Parse the `--encoding-confidence` argument, a fraction from 0 to 1
+fn parse_confidence(s: &str) -> Result<f64, String> {
+    let confidence: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(0.0..=1.0).contains(&confidence) {
+        return Err(String::from("a confidence must be between 0 and 1"));
+    }
+    Ok(confidence)
+}
+
+/// Parse the `--wpl-sample-rate` and `--random-sample` arguments, a share of
+/// the lines above 0
+fn parse_sample_rate(s: &str) -> Result<f64, String> {
+    let rate: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(rate > 0.0 && rate <= 1.0) {
+        return Err(String::from("a sample rate must be above 0 and at most 1"));
+    }
+    Ok(rate)
+}
+
+/// Parse the `--dedup-fpr` argument, a probability between 0 and 1
+fn parse_false_positive_rate(s: &str) -> Result<f64, String> {
+    let rate: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(rate > 0.0 && rate < 1.0) {
+        return Err(String::from(
+            "a false positive rate must be above 0 and below 1",
+        ));
+    }
+    Ok(rate)
+}
+
+/// Parse the `--arpa-smoothing` argument, a count of at least 0
+fn parse_arpa_smoothing(s: &str) -> Result<f64, String> {
+    let k: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(k >= 0.0 && k.is_finite()) {
+        return Err(String::from("the smoothing must be a count of at least 0"));
+    }
+    Ok(k)
+}
+
+/// Parse the `--arpa-unk-prob` argument, a log10 probability leaving the
+/// words some probability next to `<unk>` and `</s>`
+fn parse_arpa_unk_prob(s: &str) -> Result<f64, String> {
+    let log_prob: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if log_prob.is_nan() || log_prob >= 0.5f64.log10() {
+        return Err(String::from(
+            "the log10 probability must be below -0.30, i.e. log10(0.5)",
+        ));
+    }
+    Ok(log_prob)
+}
+
+/// Parse the `--zipf-exponent` argument, a positive exponent
+#[cfg(feature = "plot")]
+fn parse_zipf_exponent(s: &str) -> Result<f64, String> {
+    let exponent: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(exponent > 0.0 && exponent.is_finite()) {
+        return Err(String::from("the exponent must be above 0"));
+    }
+    Ok(exponent)
+}
+
+/// Parse the `--stdin-delimiter` argument, a single character or one of the
+/// escapes `\0`, `\t`, `\n` and `\uXXXX`
+fn parse_stdin_delimiter(s: &str) -> Result<char, String> {
+    match s {
+        "\\0" => return Ok('\0'),
+        "\\t" => return Ok('\t'),
+        "\\n" => return Ok('\n'),
+        _ => {}
+    }
+    if let Some(hex) = s.strip_prefix("\\u") {
+        return hex
+            .chars()
+            .all(|c| c.is_ascii_hexdigit())
+            .then(|| u32::from_str_radix(hex, 16).ok())
+            .flatten()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid Unicode escape: {}", s));
+    }
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(String::from(
+            "the delimiter must be a single character or an escape like \\u0000",
+        )),
+    }
+}
+
+/// Parse the `--input-glob` argument, rejecting invalid patterns
+fn parse_input_glob(s: &str) -> Result<String, String> {
+    Pattern::new(s).map_err(|err| format!("invalid glob pattern: {}", err))?;
+    Ok(s.to_string())
+}
+
+/// Parse the `--sentence-end` argument, which needs at least one char
+fn parse_sentence_end(s: &str) -> Result<String, String> {
+    if s.is_empty() {
+        return Err(String::from("at least one char must end a sentence"));
+    }
+    Ok(s.to_string())
+}
+
+/// Parse the `--top-percent` argument, a percentage above 0 up to 100
+fn parse_top_percent(s: &str) -> Result<f64, String> {
+    let pct = parse_percentage(s)?;
+    if pct == 0.0 {
+        return Err(String::from("a percentage of 0 covers no words"));
+    }
+    Ok(pct)
+}
+
+/// Parse the `--threads` argument, rejecting implausibly large thread counts
+fn parse_threads(s: &str) -> Result<usize, String> {
+    let threads: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if threads > MAX_THREADS {
+        return Err(format!("at most {} threads are supported", MAX_THREADS));
+    }
+    Ok(threads)
+}
+
+/// Parse the `--column` argument, rejecting columns before the first one
+fn parse_column(s: &str) -> Result<usize, String> {
+    let column: usize = s.parse().map_err(|err| format!("{}", err))?;
+    if column == 0 {
+        return Err(String::from("columns are numbered from 1"));
+    }
+    Ok(column)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_process_chunk() {
+    fn test_progress_message() {
         // Arrange
-        let chunk = vec![
-            String::from("The quick brown fox jumps over the lazy dog"),
-            String::from("The dog barks"),
-        ];
-        let mut word_counts = BTreeMap::new();
+        let totals = Totals {
+            lines: 20,
+            bytes: 256,
+            ..Default::default()
+        };
 
-        // Act
-        process_chunk(&chunk, &mut word_counts);
+        // Act & Assert
+        assert_eq!(
+            progress_message(&totals, Some(1024)),
+            "processed 20 lines (25.0%)"
+        );
+        assert_eq!(progress_message(&totals, None), "processed 20 lines");
+        assert_eq!(progress_message(&totals, Some(0)), "processed 20 lines");
+    }
 
-        // Assert
-        assert_eq!(word_counts.get("The"), Some(&2));
-        assert_eq!(word_counts.get("the"), Some(&1));
-        assert_eq!(word_counts.get("quick"), Some(&1));
-        assert_eq!(word_counts.get("brown"), Some(&1));
-        assert_eq!(word_counts.get("fox"), Some(&1));
-        assert_eq!(word_counts.get("jumps"), Some(&1));
-        assert_eq!(word_counts.get("over"), Some(&1));
-        assert_eq!(word_counts.get("lazy"), Some(&1));
-        assert_eq!(word_counts.get("dog"), Some(&2));
-        assert_eq!(word_counts.get("barks"), Some(&1));
-        assert_eq!(word_counts.len(), 10); // Total unique words
+    #[test]
+    fn test_parse_chunk_size() {
+        assert_eq!(parse_chunk_size("1"), Ok(1));
+        assert_eq!(parse_chunk_size("500"), Ok(500));
+        assert!(parse_chunk_size("0").is_err());
+        assert!(parse_chunk_size("-3").is_err());
+        assert!(parse_chunk_size("many").is_err());
     }
 
     #[test]
-    fn test_process_chunk_empty() {
-        // Arrange
-        let chunk: Vec<String> = Vec::new();
-        let mut word_counts = BTreeMap::new();
+    fn test_parse_ngrams() {
+        assert_eq!(parse_ngrams("1"), Ok(1));
+        assert_eq!(parse_ngrams("3"), Ok(3));
+        assert!(parse_ngrams("0").is_err());
+    }
 
-        // Act
-        process_chunk(&chunk, &mut word_counts);
+    #[test]
+    fn test_parse_cooccurrence_window() {
+        assert_eq!(parse_cooccurrence_window("2"), Ok(2));
+        assert_eq!(parse_cooccurrence_window("10"), Ok(10));
+        assert!(parse_cooccurrence_window("1").is_err());
+        assert!(parse_cooccurrence_window("0").is_err());
+    }
 
-        // Assert
-        assert!(word_counts.is_empty());
+    #[test]
+    fn test_parse_length_range() {
+        assert_eq!(parse_length_range("3-5"), Ok(3..=5));
+        assert_eq!(parse_length_range("4-"), Ok(4..=usize::MAX));
+        assert_eq!(parse_length_range("-2"), Ok(1..=2));
+        assert!(parse_length_range("4").is_err());
+        assert!(parse_length_range("5-3").is_err());
     }
 
     #[test]
-    fn test_process_chunk_case_insensitive() {
-        // Arrange
-        let chunk = vec![
-            String::from("The THE the").to_lowercase(),
-            String::from("Dog dog DOG").to_lowercase(),
-        ];
-        let mut word_counts = BTreeMap::new();
+    fn test_parse_line_range() {
+        let range = |start, end| Ok(LineRange { start, end });
+        assert_eq!(parse_line_range("2-5"), range(2, Some(5)));
+        assert_eq!(parse_line_range("3-3"), range(3, Some(3)));
+        assert_eq!(parse_line_range("10-"), range(10, None));
+        assert_eq!(parse_line_range("-20"), range(1, Some(20)));
+        assert_eq!(parse_line_range("-"), range(1, None));
+        assert!(parse_line_range("5").is_err());
+        assert!(parse_line_range("0-3").is_err());
+        assert!(parse_line_range("5-2").is_err());
+        assert!(parse_line_range("a-b").is_err());
+    }
 
-        // Act
-        process_chunk(&chunk, &mut word_counts);
+    #[test]
+    fn test_parse_char_ngrams() {
+        assert_eq!(parse_char_ngrams("3"), Ok(3));
+        assert!(parse_char_ngrams("0").is_err());
+    }
 
-        // Assert
-        assert_eq!(word_counts.get("the"), Some(&3));
-        assert_eq!(word_counts.get("dog"), Some(&3));
-        assert_eq!(word_counts.len(), 2);
+    #[test]
+    fn test_parse_max_unique_words() {
+        assert_eq!(parse_max_unique_words("100"), Ok(100));
+        assert!(parse_max_unique_words("0").is_err());
+    }
+
+    #[test]
+    fn test_parse_field_separator() {
+        assert_eq!(parse_field_separator("::"), Ok(String::from("::")));
+        assert!(parse_field_separator("").is_err());
     }
 
     #[test]
-    fn test_process_chunk_sorted_keys() {
+    fn test_counting_writer_stops_at_the_limit() {
         // Arrange
-        let chunk = vec![
-            String::from("zebra apple cat"),
-            String::from("dog elephant bear"),
-        ];
-        let mut word_counts = BTreeMap::new();
+        let mut out = CountingWriter::new(Vec::new(), 5);
 
         // Act
-        process_chunk(&chunk, &mut word_counts);
+        let fits = out.write_all(b"abc");
+        let exceeds = out.write_all(b"defg");
 
         // Assert
-        let keys: Vec<_> = word_counts.keys().collect();
-        assert_eq!(keys, vec!["apple", "bear", "cat", "dog", "elephant", "zebra"]);
+        assert!(fits.is_ok());
+        let err = exceeds.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Output truncated at 5 bytes due to --output-limit"
+        );
+        assert_eq!(out.inner, b"abcde");
+    }
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("100"), Ok(100));
+        assert_eq!(parse_byte_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_byte_size("4M"), Ok(4 * 1024 * 1024));
+        assert_eq!(parse_byte_size("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_byte_size("").is_err());
+        assert!(parse_byte_size("K").is_err());
+        assert!(parse_byte_size("4MB").is_err());
+        assert!(parse_byte_size("-1K").is_err());
+    }
+
+    #[test]
+    fn test_parse_buffer_size() {
+        assert_eq!(parse_buffer_size("64"), Ok(64));
+        assert_eq!(parse_buffer_size("1G"), Ok(1024 * 1024 * 1024));
+        assert!(parse_buffer_size("63").is_err());
+        assert!(parse_buffer_size("1025M").is_err());
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(parse_percentage("80"), Ok(80.0));
+        assert_eq!(parse_percentage("99.5"), Ok(99.5));
+        assert!(parse_percentage("100.1").is_err());
+        assert!(parse_percentage("-1").is_err());
+    }
+
+    #[test]
+    fn test_parse_confidence() {
+        assert_eq!(parse_confidence("0.75"), Ok(0.75));
+        assert_eq!(parse_confidence("1"), Ok(1.0));
+        assert!(parse_confidence("1.5").is_err());
+        assert!(parse_confidence("-0.1").is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_rate() {
+        assert_eq!(parse_sample_rate("0.1"), Ok(0.1));
+        assert_eq!(parse_sample_rate("1"), Ok(1.0));
+        assert!(parse_sample_rate("0").is_err());
+        assert!(parse_sample_rate("1.5").is_err());
+    }
+
+    #[test]
+    fn test_parse_false_positive_rate() {
+        assert_eq!(parse_false_positive_rate("0.001"), Ok(0.001));
+        assert!(parse_false_positive_rate("0").is_err());
+        assert!(parse_false_positive_rate("1").is_err());
+    }
+
+    #[test]
+    fn test_parse_arpa_options() {
+        assert_eq!(parse_arpa_smoothing("0.5"), Ok(0.5));
+        assert!(parse_arpa_smoothing("-1").is_err());
+        assert_eq!(parse_arpa_unk_prob("-99"), Ok(-99.0));
+        assert!(parse_arpa_unk_prob("-0.1").is_err());
+        assert!(parse_arpa_unk_prob("0").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_stdin_delimiter() {
+        assert_eq!(parse_stdin_delimiter("|"), Ok('|'));
+        assert_eq!(parse_stdin_delimiter("\\0"), Ok('\0'));
+        assert_eq!(parse_stdin_delimiter("\\u0000"), Ok('\0'));
+        assert_eq!(parse_stdin_delimiter("\\u00e9"), Ok('é'));
+        assert!(parse_stdin_delimiter("\\u").is_err());
+        assert!(parse_stdin_delimiter("\\ud800").is_err());
+        assert!(parse_stdin_delimiter("ab").is_err());
+        assert!(parse_stdin_delimiter("").is_err());
+    }
+
+    #[test]
+    fn test_parse_input_glob() {
+        assert_eq!(
+            parse_input_glob("logs/*.log"),
+            Ok(String::from("logs/*.log"))
+        );
+        assert!(parse_input_glob("[a").is_err());
+    }
+
+    #[test]
+    fn test_parse_top_percent() {
+        assert_eq!(parse_top_percent("80"), Ok(80.0));
+        assert_eq!(parse_top_percent("100"), Ok(100.0));
+        assert!(parse_top_percent("0").is_err());
+        assert!(parse_top_percent("101").is_err());
+    }
+
+    #[test]
+    fn test_parse_threads() {
+        assert_eq!(parse_threads("0"), Ok(0));
+        assert_eq!(parse_threads("512"), Ok(512));
+        assert!(parse_threads("513").is_err());
+    }
+
+    #[test]
+    fn test_parse_column() {
+        assert_eq!(parse_column("1"), Ok(1));
+        assert!(parse_column("0").is_err());
+        assert!(parse_column("-1").is_err());
+    }
+}
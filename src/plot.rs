@@ -0,0 +1,337 @@
+//! A PNG plot of the word frequencies by rank, drawn with `plotters`
+//!
+//! `plotters` only rasterizes through `plotters-bitmap`, which brings its
+//! own font and image stack. The plot is drawn on a small in-memory backend
+//! instead, with a built-in pixel font for the labels, and encoded as a PNG
+//! with `flate2`.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::io::{self, Write};
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use plotters::prelude::*;
+use plotters_backend::text_anchor::{HPos, VPos};
+use plotters_backend::{
+    BackendColor, BackendCoord, BackendTextStyle, DrawingBackend, DrawingErrorKind,
+};
+
+/// The width of the plot in pixels
+const WIDTH: u32 = 800;
+/// The height of the plot in pixels
+const HEIGHT: u32 = 600;
+
+/// The colors of the Zipf reference lines, in the order of the exponents
+const LINE_COLORS: [RGBColor; 2] = [RGBColor(200, 40, 40), RGBColor(30, 150, 60)];
+
+/// Write a PNG scatter plot of `log10(rank)` against `log10(frequency)` of
+/// the words of `word_counts`
+///
+/// The most frequent word has rank 1. Words of the same count get
+/// consecutive ranks in alphabetical order. The plot has a reference line
+/// for Zipf's law with exponent 1, a frequency proportional to `1 / rank`,
+/// through the frequency of the first word, and one more for
+/// `zipf_exponent` when given.
+///
+/// # Arguments
+///
+/// * `out` - The destination of the PNG
+/// * `word_counts` - The counts to plot
+/// * `zipf_exponent` - The exponent of an extra Zipf reference line, if any
+pub fn write_frequency_plot<W: Write>(
+    out: &mut W,
+    word_counts: &BTreeMap<String, usize>,
+    zipf_exponent: Option<f64>,
+) -> io::Result<()> {
+    let mut counts: Vec<usize> = word_counts.values().copied().collect();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    let points: Vec<(f64, f64)> = counts
+        .iter()
+        .enumerate()
+        .map(|(index, &count)| (((index + 1) as f64).log10(), (count as f64).log10()))
+        .collect();
+
+    let mut pixels = Pixels::new(WIDTH, HEIGHT);
+    draw_plot(&mut pixels, &points, zipf_exponent)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    write_png(out, &pixels)
+}
+
+/// Draw the chart of `points`, the logarithms of the ranks and counts
+fn draw_plot(
+    pixels: &mut Pixels,
+    points: &[(f64, f64)],
+    zipf_exponent: Option<f64>,
+) -> Result<(), DrawingAreaErrorKind<Infallible>> {
+    // an axis from 0 to at least 1, with some room above the largest value
+    let axis_end = |max: f64| (max * 1.05).max(1.0);
+    let x_end = axis_end(points.last().map_or(0.0, |&(x, _)| x));
+    let top = points.first().map_or(0.0, |&(_, y)| y);
+    let y_end = axis_end(top);
+
+    let root = pixels.into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption("word frequencies by rank", ("sans-serif", 20))
+        .margin(16)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0.0..x_end, 0.0..y_end)?;
+    chart
+        .configure_mesh()
+        .x_desc("log10(rank)")
+        .y_desc("log10(frequency)")
+        .x_label_formatter(&|x| format!("{:.1}", x))
+        .y_label_formatter(&|y| format!("{:.1}", y))
+        .draw()?;
+
+    chart
+        .draw_series(
+            points
+                .iter()
+                .map(|&point| Circle::new(point, 2, BLUE.mix(0.6).filled())),
+        )?
+        .label("words")
+        .legend(|(x, y)| Circle::new((x + 10, y), 3, BLUE.filled()));
+
+    let exponents = std::iter::once(1.0).chain(zipf_exponent);
+    for (exponent, color) in exponents.zip(LINE_COLORS) {
+        // log10(frequency) = log10(top frequency) - exponent * log10(rank),
+        // ending where it leaves the chart at the bottom or the right
+        let x_last = if exponent > 0.0 {
+            (top / exponent).min(x_end)
+        } else {
+            x_end
+        };
+        let line = [(0.0, top), (x_last, top - exponent * x_last)];
+        chart
+            .draw_series(LineSeries::new(line, color.stroke_width(2)))?
+            .label(format!("zipf, α={}", exponent))
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()
+}
+
+/// An RGB image in memory that `plotters` draws on
+struct Pixels {
+    width: u32,
+    height: u32,
+    /// Three bytes per pixel, row by row from the top
+    rgb: Vec<u8>,
+}
+
+impl Pixels {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            rgb: vec![0; (width * height * 3) as usize],
+        }
+    }
+}
+
+/// The size of a glyph of the built-in font, scaled by `scale`
+fn glyph_size(scale: i32) -> (i32, i32) {
+    (GLYPH_WIDTH * scale, GLYPH_HEIGHT * scale)
+}
+
+/// The scale of the built-in font that comes closest to a font `size`
+fn text_scale(size: f64) -> i32 {
+    ((size / 10.0).round() as i32).max(1)
+}
+
+/// The width and height of `text` in the built-in font at `scale`
+fn text_size(text: &str, scale: i32) -> (i32, i32) {
+    let (width, height) = glyph_size(scale);
+    let chars = text.chars().count() as i32;
+    ((width + scale) * chars - scale, height)
+}
+
+impl DrawingBackend for &mut Pixels {
+    type ErrorType = Infallible;
+
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn ensure_prepared(&mut self) -> Result<(), DrawingErrorKind<Infallible>> {
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), DrawingErrorKind<Infallible>> {
+        Ok(())
+    }
+
+    fn draw_pixel(
+        &mut self,
+        (x, y): BackendCoord,
+        color: BackendColor,
+    ) -> Result<(), DrawingErrorKind<Infallible>> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return Ok(());
+        }
+        let offset = ((y as u32 * self.width + x as u32) * 3) as usize;
+        let (r, g, b) = color.rgb;
+        for (channel, value) in self.rgb[offset..offset + 3].iter_mut().zip([r, g, b]) {
+            let blended =
+                f64::from(value) * color.alpha + f64::from(*channel) * (1.0 - color.alpha);
+            *channel = blended.round() as u8;
+        }
+        Ok(())
+    }
+
+    fn estimate_text_size<TStyle: BackendTextStyle>(
+        &self,
+        text: &str,
+        style: &TStyle,
+    ) -> Result<(u32, u32), DrawingErrorKind<Infallible>> {
+        let (width, height) = text_size(text, text_scale(style.size()));
+        Ok((width as u32, height as u32))
+    }
+
+    fn draw_text<TStyle: BackendTextStyle>(
+        &mut self,
+        text: &str,
+        style: &TStyle,
+        pos: BackendCoord,
+    ) -> Result<(), DrawingErrorKind<Infallible>> {
+        let scale = text_scale(style.size());
+        let (width, height) = text_size(text, scale);
+        let dx = match style.anchor().h_pos {
+            HPos::Left => 0,
+            HPos::Right => -width,
+            HPos::Center => -width / 2,
+        };
+        let dy = match style.anchor().v_pos {
+            VPos::Top => 0,
+            VPos::Center => -height / 2,
+            VPos::Bottom => -height,
+        };
+        let transform = style.transform();
+        let color = style.color();
+        let (glyph_width, _) = glyph_size(scale);
+        for (index, c) in text.chars().enumerate() {
+            let left = index as i32 * (glyph_width + scale);
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            let x = left + column * scale + sx + dx;
+                            let y = row as i32 * scale + sy + dy;
+                            let (x, y) = transform.transform(x, y);
+                            self.draw_pixel((pos.0 + x, pos.1 + y), color)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write `pixels` as an 8-bit RGB PNG
+fn write_png<W: Write>(out: &mut W, pixels: &Pixels) -> io::Result<()> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&pixels.width.to_be_bytes());
+    header.extend_from_slice(&pixels.height.to_be_bytes());
+    // bit depth 8, color type RGB, deflate, no filter method, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.rgb.chunks(pixels.width as usize * 3) {
+        // every row starts with its filter type, 0 for none
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let data = encoder.finish()?;
+
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_png_chunk(out, b"IHDR", &header)?;
+    write_png_chunk(out, b"IDAT", &data)?;
+    write_png_chunk(out, b"IEND", &[])
+}
+
+/// Write a PNG chunk: its length, type, data and the CRC of type and data
+fn write_png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc.sum().to_be_bytes())
+}
+
+/// The number of columns of a glyph of the built-in font
+const GLYPH_WIDTH: i32 = 5;
+/// The number of rows of a glyph of the built-in font
+const GLYPH_HEIGHT: i32 = 7;
+
+/// The rows of the glyph of `c` in the built-in font, the highest bit of
+/// the five being the leftmost column
+///
+/// The font knows digits, letters, which are all drawn in lowercase, `α`
+/// and the punctuation of numbers and axis labels. Other characters are
+/// left blank.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_lowercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'a' => [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F],
+        'b' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E],
+        'c' => [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E],
+        'd' => [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F],
+        'e' => [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E],
+        'f' => [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08],
+        'g' => [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'h' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'i' => [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E],
+        'j' => [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C],
+        'k' => [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],
+        'l' => [0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'm' => [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11],
+        'n' => [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'o' => [0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E],
+        'p' => [0x00, 0x00, 0x1E, 0x11, 0x1E, 0x10, 0x10],
+        'q' => [0x00, 0x00, 0x0D, 0x13, 0x0F, 0x01, 0x01],
+        'r' => [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10],
+        's' => [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E],
+        't' => [0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06],
+        'u' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D],
+        'v' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'w' => [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A],
+        'x' => [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11],
+        'y' => [0x00, 0x00, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'z' => [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F],
+        'α' => [0x00, 0x00, 0x09, 0x15, 0x12, 0x12, 0x0D],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        _ => [0; 7],
+    }
+}
@@ -0,0 +1,53 @@
+//! Export of the word counts into a SQLite database
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+/// Write `word_counts` into the `word_counts` table of the database at `path`
+///
+/// Without `append` the database file is replaced. With `append` an existing
+/// table is kept and the counts of words already in it are added to. All
+/// rows are inserted in a single transaction.
+///
+/// # Arguments
+///
+/// * `path` - The path of the database file
+/// * `word_counts` - The word counts to export
+/// * `append` - Whether to add to the counts already in the database
+pub fn export_sqlite(
+    path: &Path,
+    word_counts: &BTreeMap<String, usize>,
+    append: bool,
+) -> io::Result<()> {
+    if !append {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    write_rows(path, word_counts).map_err(io::Error::other)
+}
+
+/// Upsert every entry of `word_counts` in one transaction
+fn write_rows(path: &Path, word_counts: &BTreeMap<String, usize>) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute(
+        "CREATE TABLE IF NOT EXISTS word_counts (word TEXT PRIMARY KEY, count INTEGER NOT NULL)",
+        [],
+    )?;
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO word_counts (word, count) VALUES (?1, ?2) \
+             ON CONFLICT (word) DO UPDATE SET count = count + excluded.count",
+        )?;
+        for (word, &count) in word_counts {
+            insert.execute((word, count as i64))?;
+        }
+    }
+    transaction.commit()
+}
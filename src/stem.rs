@@ -0,0 +1,208 @@
+//! The Porter stemmer, reducing English words to their stems
+//!
+//! This follows the algorithm as published by Martin Porter in 1980, with
+//! the `bli` and `logi` rules of his later reference implementation. It
+//! only knows lowercase ASCII letters: other words are left unchanged.
+
+/// Reduce `word` to its Porter stem, e.g. "running" and "runs" to "run"
+///
+/// Stemming is lossy: unrelated words may share a stem, like "universe" and
+/// "university", and a stem need not be a word itself, like "poni" for
+/// "ponies". Words of up to two letters and words with anything but
+/// lowercase ASCII letters are returned as they are.
+///
+/// ```
+/// use word_count::porter_stem;
+///
+/// assert_eq!(porter_stem("running"), "run");
+/// assert_eq!(porter_stem("connections"), "connect");
+/// ```
+pub fn porter_stem(word: &str) -> String {
+    if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    let mut word = word.as_bytes().to_vec();
+    step1ab(&mut word);
+    step1c(&mut word);
+    step2(&mut word);
+    step3(&mut word);
+    step4(&mut word);
+    step5(&mut word);
+    String::from_utf8(word).expect("only ASCII letters are stemmed")
+}
+
+/// Whether the letter at `i` is a consonant; a `y` is one unless it
+/// follows a consonant
+fn is_consonant(word: &[u8], i: usize) -> bool {
+    match word[i] {
+        b'a' | b'e' | b'i' | b'o' | b'u' => false,
+        b'y' => i == 0 || !is_consonant(word, i - 1),
+        _ => true,
+    }
+}
+
+/// The number of vowel-consonant sequences of `stem`, the `m` of the paper
+fn measure(stem: &[u8]) -> usize {
+    let mut m = 0;
+    let mut vowel_seen = false;
+    for i in 0..stem.len() {
+        if !is_consonant(stem, i) {
+            vowel_seen = true;
+        } else if vowel_seen {
+            m += 1;
+            vowel_seen = false;
+        }
+    }
+    m
+}
+
+/// Whether `stem` contains a vowel
+fn has_vowel(stem: &[u8]) -> bool {
+    (0..stem.len()).any(|i| !is_consonant(stem, i))
+}
+
+/// Whether `stem` ends with the same consonant twice
+fn ends_double_consonant(stem: &[u8]) -> bool {
+    let n = stem.len();
+    n >= 2 && stem[n - 1] == stem[n - 2] && is_consonant(stem, n - 1)
+}
+
+/// Whether `stem` ends consonant-vowel-consonant, the last one not being a
+/// `w`, `x` or `y`, like "hop" but not like "snow"
+fn ends_cvc(stem: &[u8]) -> bool {
+    let n = stem.len();
+    n >= 3
+        && is_consonant(stem, n - 3)
+        && !is_consonant(stem, n - 2)
+        && is_consonant(stem, n - 1)
+        && !matches!(stem[n - 1], b'w' | b'x' | b'y')
+}
+
+/// Replace the suffix of the first rule `word` ends with if the measure of
+/// what precedes it is above `min_measure`; later rules are not tried, so a
+/// longer suffix must come before the shorter ones it ends with
+fn replace_first(word: &mut Vec<u8>, rules: &[(&str, &str)], min_measure: usize) {
+    for (suffix, replacement) in rules {
+        if let Some(stem) = word.strip_suffix(suffix.as_bytes()) {
+            if measure(stem) > min_measure {
+                word.truncate(stem.len());
+                word.extend_from_slice(replacement.as_bytes());
+            }
+            return;
+        }
+    }
+}
+
+/// Remove plurals and `-ed` or `-ing`, e.g. "caresses" to "caress" and
+/// "hopping" to "hop"
+fn step1ab(word: &mut Vec<u8>) {
+    if word.ends_with(b"sses") || word.ends_with(b"ies") {
+        word.truncate(word.len() - 2);
+    } else if word.ends_with(b"s") && !word.ends_with(b"ss") {
+        word.pop();
+    }
+
+    if let Some(stem) = word.strip_suffix(b"eed") {
+        if measure(stem) > 0 {
+            word.pop();
+        }
+        return;
+    }
+    let stem_len = match word
+        .strip_suffix(b"ed")
+        .or_else(|| word.strip_suffix(b"ing"))
+    {
+        Some(stem) if has_vowel(stem) => stem.len(),
+        _ => return,
+    };
+    word.truncate(stem_len);
+    if word.ends_with(b"at") || word.ends_with(b"bl") || word.ends_with(b"iz") {
+        word.push(b'e');
+    } else if ends_double_consonant(word) && !matches!(word[word.len() - 1], b'l' | b's' | b'z') {
+        word.pop();
+    } else if measure(word) == 1 && ends_cvc(word) {
+        word.push(b'e');
+    }
+}
+
+/// Turn a final `y` into an `i` if the stem before it has a vowel, e.g.
+/// "happy" to "happi" but not "sky"
+fn step1c(word: &mut [u8]) {
+    let n = word.len();
+    if word[n - 1] == b'y' && has_vowel(&word[..n - 1]) {
+        word[n - 1] = b'i';
+    }
+}
+
+/// Map double suffixes to single ones, e.g. "-ization" to "-ize"
+fn step2(word: &mut Vec<u8>) {
+    const RULES: [(&str, &str); 21] = [
+        ("ational", "ate"),
+        ("tional", "tion"),
+        ("enci", "ence"),
+        ("anci", "ance"),
+        ("izer", "ize"),
+        ("bli", "ble"),
+        ("alli", "al"),
+        ("entli", "ent"),
+        ("eli", "e"),
+        ("ousli", "ous"),
+        ("ization", "ize"),
+        ("ation", "ate"),
+        ("ator", "ate"),
+        ("alism", "al"),
+        ("iveness", "ive"),
+        ("fulness", "ful"),
+        ("ousness", "ous"),
+        ("aliti", "al"),
+        ("iviti", "ive"),
+        ("biliti", "ble"),
+        ("logi", "log"),
+    ];
+    replace_first(word, &RULES, 0);
+}
+
+/// Remove or shorten `-ic-`, `-full` and `-ness` suffixes, e.g. "-icate"
+fn step3(word: &mut Vec<u8>) {
+    const RULES: [(&str, &str); 7] = [
+        ("icate", "ic"),
+        ("ative", ""),
+        ("alize", "al"),
+        ("iciti", "ic"),
+        ("ical", "ic"),
+        ("ful", ""),
+        ("ness", ""),
+    ];
+    replace_first(word, &RULES, 0);
+}
+
+/// Remove the suffixes left of a longer stem, e.g. "-ement" or "-ive"
+fn step4(word: &mut Vec<u8>) {
+    const SUFFIXES: [&str; 19] = [
+        "ement", "ance", "ence", "able", "ible", "ment", "ant", "ent", "ism", "ate", "iti", "ous",
+        "ive", "ize", "ion", "al", "er", "ic", "ou",
+    ];
+    for suffix in SUFFIXES {
+        if let Some(stem) = word.strip_suffix(suffix.as_bytes()) {
+            // `-ion` is only removed after an `s` or a `t`
+            let ion_allowed = suffix != "ion" || stem.ends_with(b"s") || stem.ends_with(b"t");
+            if measure(stem) > 1 && ion_allowed {
+                word.truncate(stem.len());
+            }
+            return;
+        }
+    }
+}
+
+/// Remove a final `e` and reduce a final `ll` of a long stem
+fn step5(word: &mut Vec<u8>) {
+    if let Some(stem) = word.strip_suffix(b"e") {
+        let m = measure(stem);
+        if m > 1 || m == 1 && !ends_cvc(stem) {
+            word.pop();
+        }
+    }
+    if word.ends_with(b"ll") && measure(word) > 1 {
+        word.pop();
+    }
+}
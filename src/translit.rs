@@ -0,0 +1,75 @@
+//! Table-based transliteration of non-Latin scripts into Latin letters
+
+/// Transliterate the Russian Cyrillic letters of `word` into Latin letters
+/// after GOST R 52535.1-2006, the scheme of Russian passports
+///
+/// Every letter maps to Latin letters only, so a transliterated word stays a
+/// single token; `ъ` becomes "ie" and `ь` is dropped. An uppercase letter
+/// becomes a capitalized replacement, e.g. "Щ" becomes "Shch". Any other
+/// char is kept as it is.
+///
+/// ```
+/// use word_count::transliterate_gost;
+///
+/// assert_eq!(transliterate_gost("слово"), "slovo");
+/// assert_eq!(transliterate_gost("Щука"), "Shchuka");
+/// ```
+pub fn transliterate_gost(word: &str) -> String {
+    let mut transliterated = String::with_capacity(word.len());
+    for c in word.chars() {
+        let lowercase = c.to_lowercase().next().unwrap_or(c);
+        let Some(latin) = gost_letter(lowercase) else {
+            transliterated.push(c);
+            continue;
+        };
+        if c != lowercase {
+            let mut chars = latin.chars();
+            transliterated.extend(chars.next().map(|first| first.to_ascii_uppercase()));
+            transliterated.push_str(chars.as_str());
+        } else {
+            transliterated.push_str(latin);
+        }
+    }
+    transliterated
+}
+
+/// The Latin letters of a lowercase Russian letter, if `c` is one
+fn gost_letter(c: char) -> Option<&'static str> {
+    let latin = match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "e",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' => "i",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "tc",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' => "ie",
+        'ы' => "y",
+        'ь' => "",
+        'э' => "e",
+        'ю' => "iu",
+        'я' => "ia",
+        _ => return None,
+    };
+    Some(latin)
+}
@@ -0,0 +1,30 @@
+//! Bindings for counting in WebAssembly, e.g. in a browser
+//!
+//! There are neither threads nor a way to block in a browser, so the text
+//! is counted sequentially, without sleeping between chunks.
+
+use std::io::Cursor;
+
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{count_reader, WordCountConfig};
+
+/// Count the words of `text`, returning an object mapping every word to its
+/// count
+#[wasm_bindgen]
+pub fn wasm_count_text(text: &str) -> JsValue {
+    let config = WordCountConfig {
+        sleep: None,
+        ..Default::default()
+    };
+    let mut word_counts = Default::default();
+    count_reader(Cursor::new(text), &config, &mut word_counts)
+        .expect("a string is always readable");
+    let counts = Object::new();
+    for (word, count) in word_counts {
+        Reflect::set(&counts, &JsValue::from(word), &JsValue::from(count as f64))
+            .expect("an object accepts every key");
+    }
+    counts.into()
+}
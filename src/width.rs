@@ -0,0 +1,118 @@
+//! The width of a text in the columns of a terminal, for padding and cutting
+//! the words of fixed-width output
+//!
+//! The wide chars are those of the East Asian Width property `W` and `F` of
+//! Unicode 15 for the CJK scripts, Hangul, the fullwidth forms and the emoji
+//! shown as pictures, told apart by the ranges below instead of the full
+//! property table, which count a few of the rarer symbols among them as
+//! wide as well. The marks and joiners extending a grapheme cluster take up
+//! no column, and the ambiguous chars one.
+
+use crate::grapheme::is_extend;
+
+/// The number of terminal columns `text` takes up, e.g. 2 for "日" or "😀"
+/// and 1 for "é", whether it is written as one char or with a combining
+/// accent
+///
+/// ```
+/// use word_count::display_width;
+///
+/// assert_eq!(display_width("日本語"), 6);
+/// assert_eq!(display_width("cafe\u{301}"), 4);
+/// ```
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// The number of terminal columns `c` takes up: 0, 1 or 2
+pub(crate) fn char_width(c: char) -> usize {
+    if c.is_control() || is_extend(c) || ('\u{200B}'..='\u{200F}').contains(&c) {
+        0
+    } else if is_wide(c) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Whether `c` takes up two columns
+fn is_wide(c: char) -> bool {
+    const RANGES: [(char, char); 61] = [
+        ('\u{1100}', '\u{115F}'),
+        ('\u{231A}', '\u{231B}'),
+        ('\u{2329}', '\u{232A}'),
+        ('\u{23E9}', '\u{23EC}'),
+        ('\u{23F0}', '\u{23F0}'),
+        ('\u{23F3}', '\u{23F3}'),
+        ('\u{25FD}', '\u{25FE}'),
+        ('\u{2614}', '\u{2615}'),
+        ('\u{2648}', '\u{2653}'),
+        ('\u{267F}', '\u{267F}'),
+        ('\u{2693}', '\u{2693}'),
+        ('\u{26A1}', '\u{26A1}'),
+        ('\u{26AA}', '\u{26AB}'),
+        ('\u{26BD}', '\u{26BE}'),
+        ('\u{26C4}', '\u{26C5}'),
+        ('\u{26CE}', '\u{26CE}'),
+        ('\u{26D4}', '\u{26D4}'),
+        ('\u{26EA}', '\u{26EA}'),
+        ('\u{26F2}', '\u{26F3}'),
+        ('\u{26F5}', '\u{26F5}'),
+        ('\u{26FA}', '\u{26FA}'),
+        ('\u{26FD}', '\u{26FD}'),
+        ('\u{2705}', '\u{2705}'),
+        ('\u{270A}', '\u{270B}'),
+        ('\u{2728}', '\u{2728}'),
+        ('\u{274C}', '\u{274C}'),
+        ('\u{274E}', '\u{274E}'),
+        ('\u{2753}', '\u{2755}'),
+        ('\u{2757}', '\u{2757}'),
+        ('\u{2795}', '\u{2797}'),
+        ('\u{27B0}', '\u{27B0}'),
+        ('\u{27BF}', '\u{27BF}'),
+        ('\u{2B1B}', '\u{2B1C}'),
+        ('\u{2B50}', '\u{2B50}'),
+        ('\u{2B55}', '\u{2B55}'),
+        ('\u{2E80}', '\u{303E}'),
+        ('\u{3041}', '\u{33FF}'),
+        ('\u{3400}', '\u{4DBF}'),
+        ('\u{4E00}', '\u{9FFF}'),
+        ('\u{A000}', '\u{A4CF}'),
+        ('\u{A960}', '\u{A97F}'),
+        ('\u{AC00}', '\u{D7A3}'),
+        ('\u{F900}', '\u{FAFF}'),
+        ('\u{FE10}', '\u{FE19}'),
+        ('\u{FE30}', '\u{FE6F}'),
+        ('\u{FF00}', '\u{FF60}'),
+        ('\u{FFE0}', '\u{FFE6}'),
+        ('\u{16FE0}', '\u{16FE4}'),
+        ('\u{17000}', '\u{18CFF}'),
+        ('\u{1B000}', '\u{1B2FF}'),
+        ('\u{1F004}', '\u{1F004}'),
+        ('\u{1F0CF}', '\u{1F0CF}'),
+        ('\u{1F18E}', '\u{1F18E}'),
+        ('\u{1F191}', '\u{1F19A}'),
+        ('\u{1F200}', '\u{1F251}'),
+        ('\u{1F300}', '\u{1F64F}'),
+        ('\u{1F680}', '\u{1F6FF}'),
+        ('\u{1F7E0}', '\u{1F7EB}'),
+        ('\u{1F90C}', '\u{1F9FF}'),
+        ('\u{1FA70}', '\u{1FAFF}'),
+        ('\u{20000}', '\u{3FFFD}'),
+    ];
+    RANGES
+        .iter()
+        .any(|&(first, last)| (first..=last).contains(&c))
+}
+
+/// The start of `text` that fits in `width` columns
+pub(crate) fn take_columns(text: &str, width: usize) -> &str {
+    let mut taken = 0;
+    for (index, c) in text.char_indices() {
+        taken += char_width(c);
+        if taken > width {
+            return &text[..index];
+        }
+    }
+    text
+}
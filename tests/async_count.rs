@@ -0,0 +1,52 @@
+#![cfg(feature = "tokio")]
+
+use std::collections::BTreeMap;
+
+use word_count::{count_path_async, count_words_async, Totals, WordCountConfig};
+
+#[tokio::test]
+async fn test_count_words_async() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corpus.txt");
+    std::fs::write(&path, "the cat\nthe dog\n\nthe end").unwrap();
+    let config = WordCountConfig {
+        chunk_size: 1,
+        sleep: None,
+        ..Default::default()
+    };
+
+    // Act
+    let word_counts = count_words_async(path, config).await.unwrap();
+
+    // Assert
+    assert_eq!(word_counts.get("the"), Some(&3));
+    assert_eq!(word_counts.get("end"), Some(&1));
+    assert_eq!(word_counts.len(), 4);
+}
+
+#[tokio::test]
+async fn test_count_path_async_matches_sync_totals() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corpus.txt");
+    std::fs::write(&path, "a b\r\nb c\n\nd").unwrap();
+    let config = WordCountConfig {
+        chunk_size: 2,
+        sleep: None,
+        ngrams: 2,
+        ..Default::default()
+    };
+    let mut sync_counts = BTreeMap::new();
+    let sync_totals = word_count::count_path(&path, &config, &mut sync_counts).unwrap();
+    let mut async_counts = BTreeMap::new();
+
+    // Act
+    let async_totals: Totals = count_path_async(&path, &config, &mut async_counts, &mut ())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(async_counts, sync_counts);
+    assert_eq!(async_totals, sync_totals);
+}
@@ -0,0 +1,3284 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the binary with `args`, feeding `input` through stdin, and return its stdout
+fn run_with_stdin(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_reads_stdin_without_path() {
+    // Act
+    let stdout = run_with_stdin(&[], "the cat\nthe dog\n");
+
+    // Assert
+    assert_eq!(stdout, "cat: 1\ndog: 1\nthe: 2\n");
+}
+
+#[test]
+fn test_reads_stdin_with_dash() {
+    // Act
+    let stdout = run_with_stdin(&["-"], "b a b\n");
+
+    // Assert
+    assert_eq!(stdout, "a: 1\nb: 2\n");
+}
+
+#[test]
+fn test_sort_flags_conflict() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--sort-by-count", "--sort-asc", "-"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn test_csv_output_parses_back() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--format", "csv", "--sort-by-count"],
+        "a b, b, c \"d\"\nb, a\n",
+    );
+
+    // Assert
+    let mut reader = csv::Reader::from_reader(stdout.as_bytes());
+    assert_eq!(reader.headers().unwrap(), vec!["word", "count"]);
+    let rows: Vec<(String, usize)> = reader.deserialize().map(Result::unwrap).collect();
+    assert_eq!(
+        rows,
+        vec![
+            (String::from("b,"), 3),
+            (String::from("a"), 2),
+            (String::from("\"d\""), 1),
+            (String::from("c"), 1),
+        ]
+    );
+    assert_eq!(rows.iter().map(|(_, count)| count).sum::<usize>(), 7);
+}
+
+#[test]
+fn test_csv_output_without_header() {
+    // Act
+    let stdout = run_with_stdin(&["--format", "csv", "--no-header"], "x y x\n");
+
+    // Assert
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(stdout.as_bytes());
+    let rows: Vec<(String, usize)> = reader.deserialize().map(Result::unwrap).collect();
+    assert_eq!(rows, vec![(String::from("x"), 2), (String::from("y"), 1)]);
+}
+
+#[test]
+fn test_no_sleep() {
+    // Act
+    let stdout = run_with_stdin(&["--no-sleep", "--chunk-size", "1"], "a\nb\na\n");
+
+    // Assert
+    assert_eq!(stdout, "a: 2\nb: 1\n");
+}
+
+#[test]
+fn test_sleep_flags_conflict() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--sleep-ms", "5", "-"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_missing_stopwords_file() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--stopwords", "/nonexistent/stopwords.txt", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot read stopwords file"));
+}
+
+#[test]
+fn test_count_lines() {
+    // Act
+    let stdout = run_with_stdin(&["--count-lines"], "a b\n\nc\nlast line");
+
+    // Assert
+    assert_eq!(stdout, "3\n");
+}
+
+#[test]
+fn test_count_lines_and_words() {
+    // Act
+    let stdout = run_with_stdin(&["--count-lines", "--count-words"], "a b\n\nc\n");
+
+    // Assert
+    assert_eq!(stdout, "lines: 2  words: 3\n");
+}
+
+#[test]
+fn test_count_bytes_matches_input_length() {
+    // Arrange
+    let input = "grüße\r\nworld\n\nno newline";
+
+    // Act
+    let stdout = run_with_stdin(&["--count-bytes"], input);
+
+    // Assert
+    assert_eq!(stdout, format!("{}\n", input.len()));
+}
+
+#[test]
+fn test_count_all_totals() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--count-lines",
+            "--count-words",
+            "--count-chars",
+            "--count-bytes",
+        ],
+        "grüße world\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "lines: 1  words: 2  chars: 12  bytes: 14\n");
+}
+
+#[test]
+fn test_count_graphemes() {
+    // Arrange
+    // a family of five chars, a thumb with a skin tone, a flag of two
+    // regional indicators and an `e` with a combining accent
+    let input = "👨\u{200D}👩\u{200D}👧 👍🏽 🇩🇪 Cafe\u{301}\r\n";
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--count-chars",
+            "--count-graphemes",
+            "--ignore-case",
+            "--no-sleep",
+        ],
+        input,
+    );
+    let graphemes = run_with_stdin(&["--count-graphemes", "--no-sleep"], input);
+
+    // Assert
+    assert_eq!(stdout, "chars: 19  graphemes: 11\n");
+    assert_eq!(graphemes, "11\n");
+}
+
+#[test]
+fn test_sort_by_first_seen() {
+    // Arrange
+    let input = "the quick fox\nand the lazy dog\nquick\n";
+
+    // Act
+    let stdout = run_with_stdin(&["--sort-by-first-seen", "--no-sleep"], input);
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "the: 2\nquick: 2\nfox: 1\nand: 1\nlazy: 1\ndog: 1\n"
+    );
+}
+
+#[test]
+fn test_output_file_truncates_and_appends() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("output.txt");
+    std::fs::write(&input, "a b a c\n").unwrap();
+    std::fs::write(&output, "stale content\n").unwrap();
+    let run = |extra: &[&str]| {
+        let status = Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .arg("--output")
+            .arg(&output)
+            .args(extra)
+            .arg(&input)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+
+    // Act
+    run(&[]);
+    let truncated = std::fs::read_to_string(&output).unwrap();
+    std::fs::write(&output, "a: 5\nzebra: 1\n").unwrap();
+    run(&["--append"]);
+    let merged = std::fs::read_to_string(&output).unwrap();
+
+    // Assert
+    assert_eq!(truncated, "a: 2\nb: 1\nc: 1\n");
+    assert_eq!(merged, "a: 7\nb: 1\nc: 1\nzebra: 1\n");
+}
+
+#[test]
+fn test_append_merges_json_and_rejects_malformed_files() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let json = dir.path().join("counts.json");
+    let malformed = dir.path().join("counts.txt");
+    std::fs::write(&json, r#"{"a":1,"b":2}"#).unwrap();
+    std::fs::write(&malformed, "not a count\n").unwrap();
+    let run = |output: &std::path::Path, extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(["--append", "--no-sleep", "--process-text", "a c"])
+            .arg("--output")
+            .arg(output)
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let merged = run(&json, &["--format", "json"]);
+    let rejected = run(&malformed, &[]);
+
+    // Assert
+    assert!(merged.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&json).unwrap(),
+        "{\"a\":2,\"b\":2,\"c\":1}\n"
+    );
+    assert!(!rejected.status.success());
+    assert!(
+        String::from_utf8_lossy(&rejected.stderr).contains("line 1 is not a `word: count` line")
+    );
+    assert_eq!(
+        std::fs::read_to_string(&malformed).unwrap(),
+        "not a count\n"
+    );
+}
+
+#[test]
+fn test_append_writes_all_merged_counts() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("counts.txt");
+    std::fs::write(&output, "a: 5\nb: 3\nc: 1\n").unwrap();
+
+    // Act
+    let status = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--append",
+            "--top",
+            "1",
+            "--min-count",
+            "2",
+            "--color",
+            "always",
+        ])
+        .args(["--no-sleep", "--process-text", "a"])
+        .arg("--output")
+        .arg(&output)
+        .status()
+        .unwrap();
+
+    // Assert
+    assert!(status.success());
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "a: 6\nb: 3\nc: 1\n"
+    );
+}
+
+#[test]
+fn test_append_rejects_total_only() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("counts.txt");
+    std::fs::write(&output, "a: 5\n").unwrap();
+
+    // Act
+    let result = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--append",
+            "--total-only",
+            "--no-sleep",
+            "--process-text",
+            "a",
+        ])
+        .arg("--output")
+        .arg(&output)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("cannot be used with"));
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "a: 5\n");
+}
+
+#[test]
+fn test_output_file_cannot_be_opened() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--output", "/nonexistent/dir/out.txt", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot open output file"));
+}
+
+#[test]
+fn test_invalid_split_regex() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--split-regex", "(unclosed", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unclosed group"));
+}
+
+#[test]
+fn test_word_separator_regex() {
+    // Act
+    let punctuation = run_with_stdin(
+        &["--word-separator-regex", "[.,;!?]+ *", "--no-sleep"],
+        "one. two,three!! one\n",
+    );
+    let zero_width = run_with_stdin(&["--word-separator-regex", "", "--no-sleep"], "aba\n");
+
+    // Assert
+    assert_eq!(punctuation, "one: 2\nthree: 1\ntwo: 1\n");
+    assert_eq!(zero_width, "a: 2\nb: 1\n");
+}
+
+#[test]
+fn test_show_stats() {
+    // Act
+    let stdout = run_with_stdin(&["--show-stats", "--no-sleep"], "a b a\n");
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "a: 2\nb: 1\ntotal words: 3  unique words: 2  TTR: 0.6667  MATTR: 0.6667\n"
+    );
+}
+
+#[test]
+fn test_words_per_sentence() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--words-per-sentence", "--top", "0", "--no-sleep"],
+        "Dr. Smith is here. He\nsays hi!\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "total words: 7  unique words: 7  TTR: 1.0000  MATTR: 1.0000  avg words/sentence: 3.50\n"
+    );
+}
+
+#[test]
+fn test_chars_top_by_count() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--chars",
+            "--no-whitespace-chars",
+            "-c",
+            "--top",
+            "2",
+            "--no-sleep",
+        ],
+        "three tree\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "e: 4\nr: 2\n");
+}
+
+#[test]
+fn test_diff_requires_two_files() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--diff", "Cargo.toml"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exactly two files"));
+}
+
+#[test]
+fn test_diff_two_files() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.txt");
+    let new = dir.path().join("new.txt");
+    std::fs::write(&old, "a a b c\n").unwrap();
+    std::fs::write(&new, "a b b b d\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--diff", "--no-sleep"])
+        .args([&old, &new])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let expected = format!(
+        "=== only in {} ===\nc: 1\n=== only in {} ===\nd: 1\n=== changed ===\nb: +2\na: -1\n",
+        old.display(),
+        new.display()
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn test_checkpoint_and_resume() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("log.txt");
+    let checkpoint = dir.path().join("log.checkpoint");
+    std::fs::write(&input, "a b\nb c\n").unwrap();
+    let first = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--chunk-size", "1", "--checkpoint"])
+        .args([&checkpoint, &input])
+        .output()
+        .unwrap();
+    assert!(first.status.success());
+    // the input grows after the first run was interrupted
+    std::fs::write(&input, "a b\nb c\nc d\n").unwrap();
+
+    // Act
+    let resumed = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--resume"])
+        .args([&checkpoint, &input])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(resumed.status.success());
+    assert_eq!(
+        String::from_utf8(resumed.stdout).unwrap(),
+        "a: 1\nb: 2\nc: 2\nd: 1\n"
+    );
+}
+
+#[test]
+fn test_stream_prints_snapshot_per_chunk() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--stream",
+            "--no-sleep",
+            "--chunk-size",
+            "1",
+            "--top",
+            "1",
+            "-c",
+        ],
+        "a b\nb\n",
+    );
+
+    // Assert
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("{\"timestamp_ms\":"));
+    assert!(lines[0].ends_with("\"counts\":[{\"word\":\"a\",\"count\":1}]}"));
+    assert!(lines[1].ends_with("\"counts\":[{\"word\":\"b\",\"count\":2}]}"));
+    assert_eq!(lines[2], "b: 2");
+}
+
+#[test]
+fn test_watch_recounts_on_change() {
+    // Arrange
+    use std::io::{BufRead, BufReader};
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("log.txt");
+    std::fs::write(&input, "a\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--watch", "--watch-interval", "20", "--no-sleep"])
+        .arg(&input)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut first = String::new();
+    stdout.read_line(&mut first).unwrap();
+
+    // Act
+    std::fs::write(&input, "a a b\n").unwrap();
+    let mut second = String::new();
+    stdout.read_line(&mut second).unwrap();
+    stdout.read_line(&mut second).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    // Assert
+    assert_eq!(first, "a: 1\n");
+    assert_eq!(second, "a: 2\nb: 1\n");
+}
+
+#[test]
+fn test_recursive_aggregates_directory() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("one.txt"), "a b\n").unwrap();
+    std::fs::write(dir.path().join("two.txt"), "b c\n").unwrap();
+    std::fs::write(dir.path().join("sub").join("three.txt"), "c c\n").unwrap();
+    std::fs::write(dir.path().join("skipped.md"), "a\n").unwrap();
+    std::fs::write(dir.path().join("binary.txt"), b"a\0b").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--recursive", "--glob", "*.txt", "--no-sleep"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "a: 1\nb: 2\nc: 3\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("binary file"));
+}
+
+#[test]
+fn test_normalize() {
+    // Act
+    let stdout = run_with_stdin(&["--normalize", "--no-sleep"], "a b b b\n");
+
+    // Assert
+    assert_eq!(stdout, "a: 250000.00\nb: 750000.00\n");
+}
+
+#[test]
+fn test_jsonlines_output_parses_back() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--format", "jsonlines", "-c", "--no-sleep"],
+        "b a b \"quoted\"\n",
+    );
+
+    // Assert
+    let entries: Vec<(String, u64)> = stdout
+        .lines()
+        .map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            (
+                value["word"].as_str().unwrap().to_string(),
+                value["count"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (String::from("b"), 2),
+            (String::from("\"quoted\""), 1),
+            (String::from("a"), 1),
+        ]
+    );
+}
+
+#[test]
+fn test_process_text() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--process-text",
+            "hello world hello",
+            "--sort-by-count",
+            "--no-sleep",
+        ])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "hello: 2\nworld: 1\n"
+    );
+}
+
+#[test]
+fn test_process_text_lines() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--process-text",
+            "a b\n\nc",
+            "--count-lines",
+            "--count-words",
+        ])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "lines: 2  words: 3\n"
+    );
+}
+
+#[test]
+fn test_process_text_conflicts_with_paths() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--process-text", "a", "Cargo.toml"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_per_file_without_aggregate() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    std::fs::write(&first, "a b b\n").unwrap();
+    std::fs::write(&second, "b c\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--per-file",
+            "--no-aggregate",
+            "--top",
+            "1",
+            "-c",
+            "--no-sleep",
+        ])
+        .args([&first, &second])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let expected = format!(
+        "=== {} ===\nb: 2\n=== {} ===\nb: 1\n",
+        first.display(),
+        second.display()
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn test_vocab_only_counts_listed_words() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let vocab = dir.path().join("vocab.txt");
+    std::fs::write(&vocab, "cat\ndog\nfox\n").unwrap();
+
+    // Act
+    let stdout = run_with_stdin(
+        &["--vocab", vocab.to_str().unwrap(), "--no-sleep"],
+        "the quick brown fox jumps over the lazy dog while the cat sleeps",
+    );
+
+    // Assert
+    assert_eq!(stdout, "cat: 1\ndog: 1\nfox: 1\n");
+}
+
+#[test]
+fn test_color_auto_is_plain_without_terminal() {
+    // Act
+    let auto = run_with_stdin(&["-c", "--no-sleep"], "b a b");
+    let always = run_with_stdin(&["--color=always", "-c", "--no-sleep"], "b a b");
+
+    // Assert
+    assert_eq!(auto, "b: 2\na: 1\n");
+    assert_eq!(
+        always,
+        "\x1b[36mb\x1b[0m: \x1b[1;33m2\x1b[0m\n\x1b[36ma\x1b[0m: \x1b[1;33m1\x1b[0m\n"
+    );
+}
+
+#[test]
+fn test_column_warns_about_short_lines() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--column",
+            "2",
+            "--column-separator",
+            "|",
+            "--warn-short-lines",
+            "--no-sleep",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(b"1|red fox|x\n2\n3|red\n")?;
+            child.wait_with_output()
+        })
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "fox: 1\nred: 2\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Warning: -:2 has fewer than 2 fields\n"
+    );
+}
+
+#[test]
+fn test_threads_do_not_change_the_counts() {
+    // Arrange
+    let input: String = (0..5000)
+        .map(|i| format!("w{} w{} common\n", i % 97, i % 13))
+        .collect();
+    let run = |threads: &str| {
+        run_with_stdin(
+            &[
+                "--parallel",
+                "--threads",
+                threads,
+                "--chunk-size",
+                "64",
+                "--no-sleep",
+            ],
+            &input,
+        )
+    };
+
+    // Act
+    let single = run("1");
+    let several = run("4");
+
+    // Assert
+    assert!(single.contains("common: 5000\n"));
+    assert_eq!(single, several);
+}
+
+#[test]
+fn test_first_seen_line() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--first-seen-line", "--parallel", "--no-sleep"],
+        "the cat\nthe dog\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "cat: 1 (first seen: line 1)\ndog: 1 (first seen: line 2)\nthe: 2 (first seen: line 1)\n"
+    );
+}
+
+#[test]
+fn test_lines_range() {
+    // Act
+    let middle = run_with_stdin(&["--lines", "2-3", "--no-sleep"], "a\nb\nc\nd\n");
+    let head = run_with_stdin(&["--lines", "-2", "--no-sleep"], "a\nb\nc\nd\n");
+
+    // Assert
+    assert_eq!(middle, "b: 1\nc: 1\n");
+    assert_eq!(head, "a: 1\nb: 1\n");
+}
+
+#[test]
+fn test_top_percent() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--top-percent", "80", "--no-sleep"],
+        "a a a a a b b b c d\n",
+    );
+    let json = run_with_stdin(
+        &["--top-percent", "80", "--format", "json", "--no-sleep"],
+        "a a a a a b b b c d\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "a: 5\nb: 3\n2 words cover 80% of the corpus (10 total tokens)\n"
+    );
+    // the summary goes to stderr so the json stays valid
+    assert_eq!(json, "{\"a\":5,\"b\":3}\n");
+}
+
+#[test]
+fn test_count_sentences() {
+    // Act
+    let alone = run_with_stdin(
+        &["--count-sentences", "--no-sleep"],
+        "One two. Three!\nFour?\n",
+    );
+    let with_words = run_with_stdin(
+        &["--count-sentences", "--count-words", "--no-sleep"],
+        "One two. Three!\nFour?\n",
+    );
+    let custom = run_with_stdin(
+        &["--count-sentences", "--sentence-end", ";", "--no-sleep"],
+        "One; two. Three;\n",
+    );
+
+    // Assert
+    assert_eq!(alone, "3\n");
+    assert_eq!(with_words, "sentences: 3  words: 4  avg: 1.33 wps\n");
+    assert_eq!(custom, "2\n");
+}
+
+#[test]
+fn test_null_separated() {
+    // Act
+    let stdout = run_with_stdin(&["-0", "-c", "--no-sleep"], "b a b\n");
+
+    // Assert
+    assert_eq!(stdout, "b\x002\x00a\x001\x00");
+}
+
+#[test]
+fn test_field_separator() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--field-separator", ":", "--no-sleep"],
+        "/usr/bin:/bin:/usr/bin\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "/bin: 1\n/usr/bin: 2\n");
+}
+
+#[test]
+fn test_histogram() {
+    // Arrange
+    let run = |args: &[&str], lang: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(args)
+            .args(["--no-sleep", "--process-text", "a b b c c c c"])
+            .env("COLUMNS", "13")
+            .env_remove("LC_ALL")
+            .env_remove("LC_CTYPE")
+            .env("LANG", lang)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Act
+    let unicode = run(&["--histogram", "2"], "en_US.UTF-8");
+    let ascii = run(&["--histogram"], "C");
+    let custom = run(&["--histogram", "--histogram-char", "*"], "C");
+
+    // Assert
+    assert_eq!(unicode, "c  |██████| 4\nb  |███| 2\n");
+    assert_eq!(ascii, "c  |######| 4\nb  |###| 2\na  |##| 1\n");
+    assert_eq!(custom, "c  |******| 4\nb  |***| 2\na  |**| 1\n");
+}
+
+#[test]
+fn test_benchmark_prints_only_timings() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--benchmark", "--no-sleep", "--process-text", "a b a"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("median of 3 runs: "), "{}", stderr);
+    assert!(stderr.contains(" MB/s "));
+    assert!(stderr.trim_end().ends_with(" words/s"));
+}
+
+#[test]
+fn test_ndjson_streams_every_chunk() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--format", "ndjson", "--chunk-size", "1", "--no-sleep"],
+        "the cat\nthe\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "{\"word\":\"cat\",\"count\":1}\n\
+         {\"word\":\"the\",\"count\":1}\n\
+         {\"word\":\"the\",\"count\":2}\n\
+         {\"_summary\":{\"total_words\":3,\"unique_words\":2}}\n"
+    );
+}
+
+#[test]
+fn test_verbose_reports_on_stderr() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["-v", "--no-sleep", "--process-text", "a b a"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a: 2\nb: 1\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("lines read: 1"), "{}", stderr);
+    assert!(stderr.contains("unique words: 2"), "{}", stderr);
+    assert!(stderr.contains("map memory: ~"), "{}", stderr);
+    if cfg!(target_os = "linux") {
+        assert!(stderr.contains("peak RSS: "), "{}", stderr);
+    }
+}
+
+#[test]
+fn test_bottom() {
+    // Act
+    let ties = run_with_stdin(&["--bottom", "1", "--no-sleep"], "a b b c\n");
+    let no_ties = run_with_stdin(
+        &["--bottom", "1", "--bottom-ties", "false", "--no-sleep"],
+        "a b b c\n",
+    );
+
+    // Assert
+    assert_eq!(ties, "a: 1\nc: 1\n");
+    assert_eq!(no_ties, "a: 1\n");
+}
+
+#[test]
+fn test_cooccurrence() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let pairs = dir.path().join("pairs.tsv");
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--cooccurrence",
+            "2",
+            "--cooccurrence-output",
+            pairs.to_str().unwrap(),
+            "--no-sleep",
+        ],
+        "the cat sat\nsat the\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "cat: 1\nsat: 2\nthe: 2\n");
+    assert_eq!(
+        std::fs::read_to_string(&pairs).unwrap(),
+        "cat\tsat\t1\ncat\tthe\t1\nsat\tthe\t1\n"
+    );
+}
+
+#[test]
+fn test_quiet_prints_only_the_summary() {
+    // Arrange
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(args)
+            .args(["--no-sleep", "--process-text", "a b a"])
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let with_stats = run(&["--quiet", "--show-stats"]);
+    let alone = run(&["-q"]);
+    let total = run(&["--total-only"]);
+
+    // Assert
+    let stats = "total words: 3  unique words: 2  TTR: 0.6667  MATTR: 0.6667\n";
+    assert_eq!(String::from_utf8(with_stats.stdout).unwrap(), stats);
+    assert!(with_stats.stderr.is_empty());
+    assert_eq!(String::from_utf8(alone.stdout).unwrap(), stats);
+    assert!(String::from_utf8(alone.stderr)
+        .unwrap()
+        .contains("Warning: --quiet"));
+    assert_eq!(String::from_utf8(total.stdout).unwrap(), "3\n");
+}
+
+#[test]
+fn test_max_unique_words() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--max-unique-words", "2", "--no-sleep"])
+        .args(["--process-text", "a b c a d b c"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a: 2\nb: 2\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Warning: 3 words dropped due to --max-unique-words limit\n"
+    );
+}
+
+#[test]
+fn test_seed_reproduces_the_output() {
+    // Arrange
+    let text: String = (0..500).map(|i| format!("word{} ", i % 97)).collect();
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(["--use-hashmap", "--seed", "42", "--max-unique-words", "10"])
+            .args(["--no-sleep", "--process-text", &text])
+            .output()
+            .unwrap()
+            .stdout
+    };
+
+    // Act
+    let first = run();
+    let second = run();
+
+    // Assert
+    assert_eq!(
+        String::from_utf8(first.clone()).unwrap().lines().count(),
+        10
+    );
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_hash_algorithm_requires_a_hash_map() {
+    // Arrange
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args([
+                "--hash-algorithm",
+                "fnv",
+                "--no-sleep",
+                "--process-text",
+                "a b a",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let alone = run(&[]);
+    let hashed = run(&["--use-hashmap"]);
+
+    // Assert
+    assert!(!alone.status.success());
+    assert!(String::from_utf8_lossy(&alone.stderr)
+        .contains("--hash-algorithm requires --use-hashmap or --parallel"));
+    assert!(hashed.status.success());
+    assert_eq!(String::from_utf8(hashed.stdout).unwrap(), "a: 2\nb: 1\n");
+}
+
+#[test]
+fn test_paragraph_mode() {
+    // Arrange
+    let text = "\n\nthe cat sat\non the mat\n\n\n  \nit slept\n\n";
+
+    // Act
+    let per_paragraph =
+        run_with_stdin(&["--paragraph-mode", "--per-paragraph", "--no-sleep"], text);
+    let stats = run_with_stdin(&["--paragraph-mode", "--quiet", "--no-sleep"], text);
+
+    // Assert
+    assert_eq!(per_paragraph, "paragraph 1: 6\nparagraph 2: 2\n");
+    assert!(stats.ends_with("  paragraphs: 2  avg words/paragraph: 4.00  longest paragraph: 6\n"));
+}
+
+#[test]
+fn test_avg_word_length() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--avg-word-length", "--quiet", "--no-sleep"],
+        "a bb bb cccc\n",
+    );
+
+    // Assert
+    assert!(stdout.ends_with("  avg word length: 2.25  median: 2  mode: 2\n"));
+}
+
+#[test]
+fn test_skip_long_lines() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("minified.txt");
+    std::fs::write(&path, format!("a b\n{}\nb\n", "x".repeat(1_000_000))).unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--skip-long-lines", "10000", "--max-line-length", "10000"])
+        .arg("--no-sleep")
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a: 1\nb: 2\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "Warning: {}:2 is 1000000 chars long, longer than 10000\n",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn test_stopwords_lang() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let stopwords = dir.path().join("stopwords.txt");
+    std::fs::write(&stopwords, "sky\n").unwrap();
+    let text = "The sky is a blue dome and the sun is a star";
+
+    // Act
+    let builtin = run_with_stdin(&["--stopwords-lang", "en", "--no-sleep"], text);
+    let stopwords = stopwords.to_str().unwrap();
+    let both = run_with_stdin(
+        &[
+            "--stopwords-lang",
+            "en",
+            "--stopwords",
+            stopwords,
+            "--no-sleep",
+        ],
+        text,
+    );
+
+    // Assert
+    assert_eq!(builtin, "blue: 1\ndome: 1\nsky: 1\nstar: 1\nsun: 1\n");
+    assert_eq!(both, "blue: 1\ndome: 1\nstar: 1\nsun: 1\n");
+}
+
+#[test]
+fn test_stem() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--stem", "--ignore-case", "--no-sleep"],
+        "Run runs running\nconnected connection\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "connect: 2\nrun: 3\n");
+}
+
+#[test]
+fn test_output_limit() {
+    // Arrange
+    let run = |limit: &str| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args([
+                "--output-limit",
+                limit,
+                "--no-sleep",
+                "--process-text",
+                "a b c",
+            ])
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let truncated = run("7");
+    let exact = run("15");
+
+    // Assert
+    assert_eq!(truncated.status.code(), Some(1));
+    assert_eq!(String::from_utf8(truncated.stdout).unwrap(), "a: 1\nb:");
+    assert_eq!(
+        String::from_utf8(truncated.stderr).unwrap(),
+        "Output truncated at 7 bytes due to --output-limit\n"
+    );
+    assert!(exact.status.success());
+    assert_eq!(
+        String::from_utf8(exact.stdout).unwrap(),
+        "a: 1\nb: 1\nc: 1\n"
+    );
+}
+
+#[test]
+fn test_include_only_alpha() {
+    // Act
+    let alpha = run_with_stdin(&["--include-only-alpha", "--no-sleep"], "hello123 café\n");
+    let alphanumeric = run_with_stdin(
+        &["--include-only-alphanumeric", "--no-sleep"],
+        "hello123 café\n",
+    );
+
+    // Assert
+    assert_eq!(alpha, "café: 1\n");
+    assert_eq!(alphanumeric, "café: 1\nhello123: 1\n");
+}
+
+#[test]
+fn test_frequency_bands() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--frequency-bands",
+            "--band-boundaries",
+            "1,3",
+            "--no-sleep",
+        ],
+        "a b b c c c d d d d\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "[1]: 1 word\n[2-3]: 2 words\n[4+]: 1 word\n");
+}
+
+#[test]
+fn test_group_by_first_char_and_script() {
+    // Arrange
+    let input = "мир и мир peace and мир\n";
+
+    // Act
+    let by_char = run_with_stdin(&["--group-by-first-char", "--no-sleep"], input);
+    let by_script = run_with_stdin(&["--group-by-script", "--no-sleep"], input);
+
+    // Assert
+    assert_eq!(
+        by_char,
+        "м (U+043C): 1 word, 3 total occurrences\n\
+         a (U+0061): 1 word, 1 total occurrences\n\
+         p (U+0070): 1 word, 1 total occurrences\n\
+         и (U+0438): 1 word, 1 total occurrences\n"
+    );
+    assert_eq!(
+        by_script,
+        "Cyrillic: 2 words, 4 total occurrences\nLatin: 2 words, 2 total occurrences\n"
+    );
+}
+
+#[test]
+fn test_word_length_distribution() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lengths.json");
+    let input = "a an the a then a\n";
+
+    // Act
+    let text = run_with_stdin(&["--word-length-distribution", "--no-sleep"], input);
+    let stdout = run_with_stdin(
+        &[
+            "--word-length-distribution",
+            "--length-dist-output",
+            path.to_str().unwrap(),
+            "--format",
+            "json",
+            "--no-sleep",
+        ],
+        input,
+    );
+
+    // Assert
+    assert_eq!(
+        text,
+        "length 1: 3 occurrences (50.00%)\nlength 2: 1 occurrences (16.67%)\n\
+         length 3: 1 occurrences (16.67%)\nlength 4: 1 occurrences (16.67%)\n"
+    );
+    assert_eq!(stdout, "{\"a\":3,\"an\":1,\"the\":1,\"then\":1}\n");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "{\"1\":3,\"2\":1,\"3\":1,\"4\":1}\n"
+    );
+}
+
+#[test]
+fn test_normalise_unicode() {
+    // Arrange
+    let input = "caf\u{E9} cafe\u{301} \u{FB01}ne fine\n";
+
+    // Act
+    let plain = run_with_stdin(&["--no-sleep"], input);
+    let nfc = run_with_stdin(&["--normalise-unicode", "nfc", "--no-sleep"], input);
+    let nfkc = run_with_stdin(&["--no-sleep", "--normalise-unicode"], input);
+
+    // Assert
+    assert_eq!(plain.lines().count(), 4);
+    assert_eq!(nfc, "caf\u{E9}: 2\nfine: 1\n\u{FB01}ne: 1\n");
+    assert_eq!(nfkc, "caf\u{E9}: 2\nfine: 2\n");
+}
+
+#[test]
+fn test_prefix_and_suffix_strip() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--prefix-strip",
+            "UN",
+            "--suffix-strip",
+            "ing",
+            "--ignore-case",
+            "--no-sleep",
+        ],
+        "Unhappy running happy ing\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "happy: 2\nrunn: 1\n");
+}
+
+#[test]
+fn test_output_format_template() {
+    // Arrange
+    let input = "the cat saw the dog\n";
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--output-format-template",
+            "{rank}. {word} ({count}) {wat}",
+            "--no-sleep",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .unwrap();
+    let json = run_with_stdin(
+        &[
+            "--output-format-template",
+            "{word}",
+            "--format",
+            "json",
+            "--no-sleep",
+        ],
+        input,
+    );
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "2. cat (1) {wat}\n2. dog (1) {wat}\n2. saw (1) {wat}\n1. the (2) {wat}\n"
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("unknown placeholder {wat}"));
+    assert_eq!(
+        json,
+        r#"{"cat":1,"dog":1,"saw":1,"the":2}"#.to_owned() + "\n"
+    );
+}
+
+#[test]
+fn test_output_format_template_adds_no_rank_to_other_formats() {
+    // Arrange
+    let input = "the cat the\n";
+    let run = |format: &str| {
+        run_with_stdin(
+            &[
+                "--output-format-template",
+                "{rank} {word}",
+                "--format",
+                format,
+                "--no-sleep",
+            ],
+            input,
+        )
+    };
+
+    // Act
+    let csv = run("csv");
+    let json_array = run("json-array");
+
+    // Assert
+    assert_eq!(csv, "word,count\ncat,1\nthe,2\n");
+    assert_eq!(
+        json_array,
+        r#"[{"word":"the","count":2},{"word":"cat","count":1}]"#.to_owned() + "\n"
+    );
+}
+
+#[test]
+fn test_show_stats_keeps_the_json_valid() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--show-stats", "--format", "json", "--no-sleep"])
+        .args(["--process-text", "the cat the"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let counts: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(counts, serde_json::json!({"cat": 1, "the": 2}));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("total words: 3  unique words: 2"));
+}
+
+#[test]
+fn test_output_width() {
+    // Arrange
+    let input = "größenwahn größenwahn café\n";
+
+    // Act
+    let text = run_with_stdin(
+        &["--output-width", "6", "--count-width", "3", "--no-sleep"],
+        input,
+    );
+    let tsv = run_with_stdin(
+        &[
+            "--output-width",
+            "6",
+            "--truncation-marker",
+            "~",
+            "--format",
+            "tsv",
+            "--no-sleep",
+        ],
+        input,
+    );
+    let json = run_with_stdin(
+        &["--output-width", "6", "--format", "json", "--no-sleep"],
+        input,
+    );
+
+    // Assert
+    assert_eq!(text, "café  :   1\ngröße…:   2\n");
+    assert_eq!(tsv, "café  \t1\ngröße~\t2\n");
+    assert!(json.contains("\"größenwahn\":2"));
+}
+
+#[test]
+fn test_case_variants() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--case-variants", "--no-sleep"],
+        "The cat saw THE dog\nthe dog saw the cat\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "cat: cat=2\ndog: dog=2\nsaw: saw=2\nthe: the=2 THE=1 The=1\n"
+    );
+}
+
+#[test]
+fn test_input_format_json() {
+    // Arrange
+    let input = "{\"msg\": \"hello world\", \"user\": {\"name\": \"ann\"}}\nnot json\n\
+                 {\"msg\": \"hello\", \"tags\": [\"x\"]}\n";
+
+    // Act
+    let all = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--input-format", "json", "--no-sleep"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .unwrap();
+    let msg = run_with_stdin(
+        &["--input-format", "json", "--json-path", "msg", "--no-sleep"],
+        input,
+    );
+
+    // Assert
+    assert_eq!(
+        String::from_utf8(all.stdout).unwrap(),
+        "ann: 1\nhello: 2\nworld: 1\nx: 1\n"
+    );
+    assert!(String::from_utf8(all.stderr)
+        .unwrap()
+        .contains("-:2 is not valid JSON"));
+    assert_eq!(msg, "hello: 2\nworld: 1\n");
+}
+
+#[test]
+fn test_dry_run() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("counts.txt");
+    let missing = dir.path().join("missing.txt");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .arg("--dry-run")
+            .args(args)
+            .arg(dir.path().join("input.txt"))
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let valid = run(&["--output", output.to_str().unwrap(), "--top", "5"]);
+    let invalid_regex = run(&["--include-pattern", "("]);
+    let unreadable = run(&["--stopwords", missing.to_str().unwrap()]);
+    let negative = run(&["--chunk-size=-5"]);
+
+    // Assert
+    assert!(valid.status.success());
+    assert_eq!(
+        String::from_utf8(valid.stdout).unwrap(),
+        "OK: all arguments validated\n"
+    );
+    assert!(!output.exists());
+    assert_eq!(invalid_regex.status.code(), Some(2));
+    assert!(String::from_utf8(invalid_regex.stderr)
+        .unwrap()
+        .contains("--include-pattern"));
+    assert_eq!(unreadable.status.code(), Some(1));
+    assert!(String::from_utf8(unreadable.stderr)
+        .unwrap()
+        .starts_with("Error: cannot read stopwords file"));
+    assert_eq!(negative.status.code(), Some(2));
+    assert!(String::from_utf8(negative.stderr)
+        .unwrap()
+        .contains("--chunk-size"));
+}
+
+#[test]
+fn test_input_format_csv() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("reviews.csv");
+    std::fs::write(
+        &input,
+        "id,text,rating\n\
+         1,\"good, very good\",5\n\
+         2,\"bad\nvery bad\",1\n\
+         3,good,4\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(["--input-format", "csv", "--no-sleep"])
+            .args(args)
+            .arg(&input)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Act
+    let by_name = run(&[
+        "--csv-text-column",
+        "text",
+        "--count-lines",
+        "--count-words",
+    ]);
+    let by_index = run(&["--csv-text-column", "2", "--csv-has-header"]);
+    let two_columns = run(&[
+        "--csv-text-column",
+        "text",
+        "--csv-text-column",
+        "rating",
+        "--total-only",
+    ]);
+
+    // Assert
+    // the header is skipped and the quoted line break is a single record
+    assert_eq!(by_name, "lines: 3  words: 7\n");
+    assert_eq!(by_index, "bad: 2\ngood: 2\ngood,: 1\nvery: 2\n");
+    assert_eq!(two_columns, "10\n");
+}
+
+#[test]
+fn test_tfidf() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    std::fs::write(&first, "a a b\n").unwrap();
+    std::fs::write(&second, "a c\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--tfidf", "--tfidf-variant", "sublinear", "--no-sleep"])
+        .arg(&first)
+        .arg(&second)
+        .output()
+        .unwrap();
+
+    // Assert
+    // idf(a) = ln(2/2) = 0, idf(b) = idf(c) = ln(2/1) and tf(a) = 1 + ln(2)
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "=== {} ===\nword\ttf\tidf\ttfidf\nb\t1.000000\t0.693147\t0.693147\n\
+             a\t1.693147\t0.000000\t0.000000\n\
+             === {} ===\nword\ttf\tidf\ttfidf\nc\t1.000000\t0.693147\t0.693147\n\
+             a\t1.000000\t0.000000\t0.000000\n",
+            first.display(),
+            second.display()
+        )
+    );
+}
+
+#[test]
+fn test_relative_to() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let baseline = dir.path().join("baseline.txt");
+    std::fs::write(&baseline, "a a b b b b d d\n").unwrap();
+    let baseline = baseline.to_str().unwrap();
+    let input = "a a a a b b c c\n";
+
+    // Act
+    let stdout = run_with_stdin(&["--relative-to", baseline, "--no-sleep"], input);
+    let sorted = run_with_stdin(
+        &[
+            "--relative-to",
+            baseline,
+            "--laplace-smoothing",
+            "--sort-by-ratio",
+            "--no-sleep",
+        ],
+        input,
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "a: 4 (\u{d7}2.00 vs baseline)\nb: 2 (\u{d7}0.50 vs baseline)\nc: 2 [new]\n"
+    );
+    assert_eq!(
+        sorted,
+        "c: 2 (\u{d7}3.00 vs baseline) [new]\na: 4 (\u{d7}1.67 vs baseline)\n\
+         b: 2 (\u{d7}0.60 vs baseline)\n"
+    );
+}
+
+#[test]
+fn test_force_utf8() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scraped.txt");
+    std::fs::write(&input, b"caf\xe9 ok\nok\n").unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(args)
+            .arg("--no-sleep")
+            .arg(&input)
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let strict = run(&[]);
+    let forced = run(&["--force-utf8", "--verbose"]);
+
+    // Assert
+    assert!(strict.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("did not contain valid UTF-8"));
+    assert_eq!(
+        String::from_utf8(forced.stdout).unwrap(),
+        "caf\u{fffd}: 1\nok: 2\n"
+    );
+    assert!(String::from_utf8_lossy(&forced.stderr).contains("1 invalid UTF-8 sequences replaced"));
+}
+
+#[test]
+fn test_word_list_diff() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let reference = dir.path().join("reference.txt");
+    std::fs::write(&reference, "the: 5\ncat: 1\ndog: 2\n").unwrap();
+    let reference = reference.to_str().unwrap();
+
+    // Act
+    let stdout = run_with_stdin(
+        &["--word-list-diff", reference, "--no-sleep"],
+        "the cat the cat\nzebu\n",
+    );
+    let missing = run_with_stdin(
+        &[
+            "--word-list-diff",
+            reference,
+            "--show-missing",
+            "--no-sleep",
+        ],
+        "the\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "cat: 2 (reference: 1, delta: +1)\nthe: 2 (reference: 5, delta: -3)\nzebu: 1 [new]\n"
+    );
+    assert_eq!(
+        missing,
+        "cat: [missing] (reference: 1)\ndog: [missing] (reference: 2)\nthe: 1 (reference: 5, delta: -4)\n"
+    );
+}
+
+#[test]
+fn test_input_encoding_detect() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let latin1 = dir.path().join("latin1.txt");
+    std::fs::write(&latin1, b"caf\xe9 na\xefve caf\xe9\n").unwrap();
+    let garbled = dir.path().join("garbled.txt");
+    std::fs::write(&garbled, b"\x81\x82\x83\xe9\n").unwrap();
+    let run = |path: &std::path::Path| {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args(["--input-encoding-detect", "--no-sleep"])
+            .arg(path)
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let detected = run(&latin1);
+    let failed = run(&garbled);
+
+    // Assert
+    assert!(detected.status.success());
+    assert_eq!(
+        String::from_utf8(detected.stdout).unwrap(),
+        "café: 2\nnaïve: 1\n"
+    );
+    assert!(String::from_utf8(detected.stderr)
+        .unwrap()
+        .contains("latin1.txt: latin1 (confidence 1.00)"));
+    assert_eq!(failed.status.code(), Some(1));
+    assert!(String::from_utf8(failed.stderr)
+        .unwrap()
+        .contains("specify it with --encoding"));
+}
+
+#[test]
+fn test_profile_chunks() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let profile = dir.path().join("profile.csv");
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--profile-chunks",
+            "--profile-output",
+            profile.to_str().unwrap(),
+            "--chunk-size",
+            "2",
+            "--no-sleep",
+        ],
+        "a b\nb c\nc d\nd\ne\n",
+    );
+    let csv = std::fs::read_to_string(&profile).unwrap();
+
+    // Assert
+    assert_eq!(stdout, "a: 1\nb: 2\nc: 2\nd: 2\ne: 1\n");
+    let mut rows = csv.lines();
+    assert_eq!(
+        rows.next(),
+        Some("chunk_id,lines,unique_words_so_far,process_time_us,sleep_time_us")
+    );
+    let rows: Vec<Vec<u128>> = rows
+        .map(|row| row.split(',').map(|field| field.parse().unwrap()).collect())
+        .collect();
+    assert_eq!(rows.len(), 3);
+    let counts: Vec<_> = rows.iter().map(|row| (row[0], row[1], row[2])).collect();
+    assert_eq!(counts, [(1, 2, 3), (2, 2, 4), (3, 1, 5)]);
+    assert!(rows.iter().all(|row| row.len() == 5 && row[4] == 0));
+}
+
+#[test]
+fn test_benchmark_profile() {
+    // Arrange
+    let input = "the quick brown fox jumps over the lazy dog\n".repeat(5000);
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--benchmark-profile", "--chunk-size", "100", "--no-sleep"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    let stages: Vec<&str> = lines
+        .iter()
+        .map(|line| line.split_once(": ").unwrap().0)
+        .collect();
+    assert_eq!(
+        stages,
+        [
+            "file_io",
+            "line_collection",
+            "chunk_processing",
+            "sorting",
+            "output",
+            "total",
+            "sleep"
+        ]
+    );
+    let shares: Vec<f64> = lines[..5]
+        .iter()
+        .map(|line| {
+            let (_, share) = line.split_once(" us (").unwrap();
+            share.trim_end_matches("%)").parse().unwrap()
+        })
+        .collect();
+    let sum: f64 = shares.iter().sum();
+    // every share is rounded to two decimal places
+    assert!((sum - 100.0).abs() <= 0.03, "the shares add up to {}", sum);
+    assert!(lines[6].ends_with(" us, not part of the total"));
+}
+
+#[test]
+fn test_output_every_n_chunks() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("counts.txt");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--output-every-n-chunks",
+            "1",
+            "--checkpoint-format",
+            "json",
+        ])
+        .args(["--chunk-size", "2", "--no-sleep", "--output"])
+        .arg(&output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    // every read of the output must be a complete snapshot
+    let wait_for = |expected: serde_json::Value| {
+        for _ in 0..500 {
+            if let Ok(text) = std::fs::read_to_string(&output) {
+                let snapshot: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if snapshot == expected {
+                    return;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("no snapshot {} was written", expected);
+    };
+
+    // Act
+    stdin.write_all(b"a b\nb\n").unwrap();
+    wait_for(serde_json::json!({"a": 1, "b": 2}));
+    stdin.write_all(b"c\na\n").unwrap();
+    wait_for(serde_json::json!({"a": 2, "b": 2, "c": 1}));
+    drop(stdin);
+    let status = child.wait().unwrap();
+
+    // Assert
+    assert!(status.success());
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "a: 2\nb: 2\nc: 1\n"
+    );
+    assert!(!dir.path().join("counts.txt.tmp").exists());
+}
+
+#[test]
+fn test_per_chunk_stats() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let stats = dir.path().join("stats.txt");
+
+    // Act
+    run_with_stdin(
+        &[
+            "--per-chunk-stats",
+            "--chunk-stats-output",
+            stats.to_str().unwrap(),
+            "--chunk-size",
+            "2",
+            "--no-sleep",
+        ],
+        "a b\nb c\nc d\nd\ne\n",
+    );
+    let stats = std::fs::read_to_string(&stats).unwrap();
+
+    // Assert
+    let rows: Vec<Vec<(&str, &str)>> = stats
+        .lines()
+        .map(|line| {
+            let (_, fields) = line.split_once(": ").unwrap();
+            fields
+                .split(' ')
+                .map(|field| field.split_once('=').unwrap())
+                .collect()
+        })
+        .collect();
+    let field = |row: &[(&str, &str)], name: &str| {
+        row.iter()
+            .find(|(key, _)| *key == name)
+            .unwrap()
+            .1
+            .to_string()
+    };
+    let tokens: Vec<usize> = rows
+        .iter()
+        .map(|row| field(row, "total_tokens").parse().unwrap())
+        .collect();
+    assert!(stats.starts_with("chunk 1: lines=2 total_tokens=4 unique_words=3 ttr=0.7500 "));
+    assert_eq!(tokens, [4, 7, 8]);
+    assert!(tokens.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(field(&rows[2], "unique_words"), "5");
+}
+
+#[test]
+fn test_words_per_line() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--words-per-line", "--quiet", "--no-sleep"],
+        "a b c\n\nd\ne f g h\n",
+    );
+
+    // Assert
+    assert!(stdout.ends_with("  words/line min: 0  max: 4  mean: 2.00  median: 2\n"));
+}
+
+#[test]
+fn test_count_unique_per_line() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--count-unique-per-line", "--quiet", "--no-sleep"],
+        "the cat sat down\nthe cat sat down\n",
+    );
+    let bucketed = run_with_stdin(
+        &[
+            "--count-unique-per-line",
+            "--upl-histogram-buckets",
+            "3",
+            "--quiet",
+            "--no-sleep",
+        ],
+        "the cat sat down\nthe cat sat down\n",
+    );
+
+    // Assert
+    // all four words are new on line 1 and none on the identical line 2
+    assert!(stdout.ends_with("  new words/line mean: 2.00  median: 2\n"));
+    // line 1 lands in the last bucket, standing for 2 or more
+    assert!(bucketed.ends_with("  new words/line mean: 2.00  median: 1\n"));
+}
+
+#[test]
+fn test_output_sort_key() {
+    // Act
+    let length = run_with_stdin(
+        &["--output-sort-key", "length-desc", "--no-sleep"],
+        "bb a ccc a\n",
+    );
+    let rank = run_with_stdin(&["--output-sort-key", "rank", "--no-sleep"], "bb a ccc a\n");
+    let first_seen = run_with_stdin(
+        &[
+            "--output-sort-key",
+            "first-seen",
+            "--first-seen-line",
+            "--no-sleep",
+        ],
+        "ccc\nbb a\n",
+    );
+    let missing = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--output-sort-key", "first-seen"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    // Assert
+    assert_eq!(length, "ccc: 1\nbb: 1\na: 2\n");
+    assert_eq!(rank, "a: 2\nbb: 1\nccc: 1\n");
+    assert_eq!(
+        first_seen,
+        "ccc: 1 (first seen: line 1)\na: 1 (first seen: line 2)\nbb: 1 (first seen: line 2)\n"
+    );
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[test]
+fn test_max_memory() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--max-memory", "1", "--chunk-size", "1", "--no-sleep"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(b"a b\nc a\n")?;
+            child.wait_with_output()
+        })
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a: 2\nb: 1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Warning: 1 words dropped due to --max-memory limit\n"
+    );
+}
+
+#[test]
+fn test_compare_dirs() {
+    // Arrange
+    let first = tempfile::tempdir().unwrap();
+    let second = tempfile::tempdir().unwrap();
+    std::fs::write(first.path().join("a.txt"), "cat cat cat dog\n").unwrap();
+    std::fs::write(first.path().join("b.txt"), "owl\n").unwrap();
+    std::fs::write(second.path().join("c.txt"), "cat dog dog emu\n").unwrap();
+    let (first, second) = (
+        first.path().to_str().unwrap(),
+        second.path().to_str().unwrap(),
+    );
+
+    // Act
+    let text = run_with_stdin(&["--compare-dirs", first, second, "--no-sleep"], "");
+    let csv = run_with_stdin(
+        &[
+            "--compare-dirs",
+            first,
+            second,
+            "--log-likelihood",
+            "--format",
+            "csv",
+            "--no-sleep",
+        ],
+        "",
+    );
+
+    // Assert
+    assert_eq!(
+        text,
+        format!(
+            "=== in both ===\ncat: 3 / 1 (ratio 3.00)\ndog: 1 / 2 (ratio 0.50)\n\
+             === only in {} ===\nowl: 1\n=== only in {} ===\nemu: 1\n",
+            first, second
+        )
+    );
+    let mut rows = csv.lines();
+    assert_eq!(rows.next(), Some("word,first,second,ratio,log_likelihood"));
+    let words: Vec<_> = rows.map(|row| row.split(',').next().unwrap()).collect();
+    assert_eq!(words, ["cat", "dog", "owl", "emu"]);
+}
+
+#[test]
+fn test_split_on_camelcase() {
+    // Act
+    let camel = run_with_stdin(
+        &["--split-on-camelcase", "--no-sleep"],
+        "BufReader processChunk MAX_LEN\n",
+    );
+    let both = run_with_stdin(
+        &[
+            "--split-on-camelcase",
+            "--split-on-underscore",
+            "--ignore-case",
+            "--no-sleep",
+        ],
+        "BufReader processChunk MAX_LEN read_buf\n",
+    );
+
+    // Assert
+    assert_eq!(
+        camel,
+        "Buf: 1\nChunk: 1\nMAX_LEN: 1\nReader: 1\nprocess: 1\n"
+    );
+    assert_eq!(
+        both,
+        "buf: 2\nchunk: 1\nlen: 1\nmax: 1\nprocess: 1\nread: 1\nreader: 1\n"
+    );
+}
+
+#[test]
+fn test_split_hyphenated() {
+    // Act
+    let split = run_with_stdin(&["--split-hyphenated", "--no-sleep"], "well-known 3-D\n");
+    let numbers = run_with_stdin(
+        &[
+            "--split-hyphenated",
+            "--no-split-hyphenated-numbers",
+            "--no-sleep",
+        ],
+        "well-known 3-D\n",
+    );
+
+    // Assert
+    assert_eq!(split, "3: 1\nD: 1\nknown: 1\nwell: 1\n");
+    assert_eq!(numbers, "3-D: 1\nknown: 1\nwell: 1\n");
+}
+
+#[test]
+fn test_input_glob_counts_matching_files() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("one.txt"), "a b\n").unwrap();
+    std::fs::write(dir.path().join("two.txt"), "b c\n").unwrap();
+    std::fs::write(dir.path().join("three.md"), "d\n").unwrap();
+    let pattern = dir.path().join("*.txt");
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--input-glob"])
+        .arg(&pattern)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "a: 1\nb: 2\nc: 1\n"
+    );
+}
+
+#[test]
+fn test_input_glob_without_matches_warns() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let pattern = dir.path().join("*.txt");
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--input-glob"])
+        .arg(&pattern)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("matches no files"));
+}
+
+#[test]
+fn test_window_splits_lines() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--window",
+            "2",
+            "--top",
+            "1",
+            "--output-sort-key",
+            "count-desc",
+            "--no-sleep",
+        ],
+        "a b\nb\nc\nc d\ne\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "=== Window 1 (lines 1-2) ===\nb: 2\n=== Window 2 (lines 3-4) ===\nc: 2\n\
+         === Window 3 (lines 5-5) ===\ne: 1\n"
+    );
+}
+
+#[test]
+fn test_window_overlap() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--window", "2", "--window-overlap", "1", "--no-sleep"],
+        "a\nb\nc\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "=== Window 1 (lines 1-2) ===\na: 1\nb: 1\n=== Window 2 (lines 2-3) ===\nb: 1\nc: 1\n"
+    );
+}
+
+#[test]
+fn test_ci_mode_assertions_pass() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("docs.txt");
+    std::fs::write(&path, "TODO fix\ntodo later\nlicense\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--ignore-case", "--ci-mode"])
+        .args(["--assert-max-count", "TODO", "2"])
+        .args(["--assert-min-count", "license", "1"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_assertion_words_are_normalised_like_tokens() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("docs.txt");
+    std::fs::write(&path, "running, runs\nrun\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--no-sleep",
+            "--ignore-case",
+            "--strip-punct",
+            "--stem",
+            "--ci-mode",
+        ])
+        .args(["--assert-min-count", "Running!", "3"])
+        .args(["--assert-max-count", "RUNS", "3"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_ci_mode_reports_every_failed_assertion() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("docs.txt");
+    std::fs::write(&path, "todo fix\ntodo later\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--ci-mode"])
+        .args(["--assert-max-count", "todo", "1"])
+        .args(["--assert-max-count", "fix", "1"])
+        .args(["--assert-min-count", "license", "1"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Assertion failed: \"todo\" is counted 2 times, at most 1 allowed\n\
+         Assertion failed: \"license\" is counted 0 times, at least 1 required\n"
+    );
+}
+
+#[test]
+fn test_assert_word_absent() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("docs.txt");
+    std::fs::write(&path, "TODO fix\nthis is unsafe\ntodo later\n").unwrap();
+    let check = |words: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_word_count"));
+        command.args(["--no-sleep", "--ignore-case", "--strip-punct", "--ci-mode"]);
+        for word in words {
+            command.args(["--assert-word-absent", word]);
+        }
+        command.arg(&path).output().unwrap()
+    };
+
+    // Act
+    let absent = check(&["release"]);
+    let present = check(&["Todo", "release", "unsafe!"]);
+
+    // Assert
+    assert_eq!(absent.status.code(), Some(0));
+    assert!(absent.stderr.is_empty());
+    assert_eq!(present.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(present.stderr).unwrap(),
+        "Assertion failed: \"todo\" is counted 2 times, but must not appear\n\
+         Assertion failed: \"unsafe\" is counted 1 times, but must not appear\n"
+    );
+}
+
+#[test]
+fn test_sample_lines() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.txt");
+    let input = "first line\nsecond line\nthird line\n";
+
+    // Act
+    let mut child = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--sample-lines", "3", "--top", "1", "--no-sleep"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = run_with_stdin(
+        &[
+            "--sample-lines",
+            "2",
+            "--sample-output",
+            path.to_str().unwrap(),
+            "--no-sleep",
+        ],
+        input,
+    );
+
+    // Assert
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "first: 1\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), input);
+    assert_eq!(stdout.lines().count(), 4);
+    let sample = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(sample.lines().count(), 2);
+    assert!(sample.lines().all(|line| input.lines().any(|l| l == line)));
+}
+
+#[test]
+fn test_ignore_comments() {
+    // Arrange
+    let input = "// a helper\nfn helper() {} /* unused\nfor now */ fn main() {}\n";
+
+    // Act
+    let stdout = run_with_stdin(
+        &["--ignore-comments", "rust", "--strip-punct", "--no-sleep"],
+        input,
+    );
+
+    // Assert
+    assert_eq!(stdout, "fn: 2\nhelper: 1\nmain: 1\n");
+}
+
+#[test]
+fn test_line_regex() {
+    // Arrange
+    let input = "INFO start\nERROR disk full\nINFO stop\n";
+
+    // Act
+    let all = run_with_stdin(&["--line-regex", ".", "--no-sleep"], input);
+    let unfiltered = run_with_stdin(&["--no-sleep"], input);
+    let none = run_with_stdin(&["--line-regex", "WARN", "--no-sleep"], input);
+    let errors = run_with_stdin(&["--line-regex", "^ERROR", "--no-sleep"], input);
+    let others = run_with_stdin(
+        &[
+            "--line-regex",
+            "^ERROR",
+            "--line-regex-invert",
+            "--no-sleep",
+        ],
+        input,
+    );
+
+    // Assert
+    assert_eq!(all, unfiltered);
+    assert_eq!(none, "");
+    assert_eq!(errors, "ERROR: 1\ndisk: 1\nfull: 1\n");
+    assert_eq!(others, "INFO: 2\nstart: 1\nstop: 1\n");
+}
+
+#[test]
+fn test_deduplicate_lines() {
+    // Arrange
+    let mut child = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--deduplicate-lines",
+            "--per-line-dedup-report",
+            "--no-sleep",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Act
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all("a b\na b\nc\na b\n".as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    // Assert
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "a: 1\nb: 1\nc: 1\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "2 duplicate lines skipped\n"
+    );
+}
+
+#[test]
+fn test_replace_numbers() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--replace-numbers", "--replace-urls", "--no-sleep"],
+        "123 456 apples\nhttps://example.com 7\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "<NUM>: 3\n<URL>: 1\napples: 1\n");
+}
+
+#[test]
+fn test_longest_words() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--longest-words",
+            "1",
+            "--shortest-words",
+            "1",
+            "--no-sleep",
+        ],
+        "a naïve cat\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "a: 1\ncat: 1\nnaïve: 1\n=== longest words ===\nnaïve: 5 chars (count: 1)\n\
+         === shortest words ===\na: 1 chars (count: 1)\n"
+    );
+}
+
+#[test]
+fn test_summarize_only() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("doc.txt");
+    std::fs::write(&path, "the cat\nthe dog\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--summarize-only", "--no-sleep"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let json = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--summarize-only",
+            "--count-lines",
+            "--format",
+            "json",
+            "--no-sleep",
+        ])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.starts_with("total words: 4  unique words: 3  TTR: 0.7500"));
+    assert!(output.stderr.is_empty());
+    let summary: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(summary["total_words"], 4);
+    assert_eq!(summary["lines"], 2);
+    assert!(summary.get("the").is_none());
+}
+
+#[test]
+fn test_tokenize_code() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--tokenize-code", "--code-lang", "python", "--no-sleep"],
+        "def read_file(path):  # open it\n    return open(path).read()\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "def: 1\nfile: 1\nit: 1\nopen: 2\npath: 2\nread: 2\nreturn: 1\n"
+    );
+}
+
+#[test]
+fn test_top_per_length() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--top-per-length", "--length-range", "3-4", "--no-sleep"],
+        "the cat and the dog\nthe tree a tree\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "length 3: [the: 3]\nlength 4: [tree: 2]\n");
+}
+
+#[test]
+fn test_export_arpa() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("model.arpa");
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--no-sleep",
+            "--arpa-smoothing",
+            "0.5",
+            "--arpa-unk-prob",
+            "-3",
+        ])
+        .arg("--export-arpa")
+        .arg(&path)
+        .arg("--process-text")
+        .arg("the cat saw the dog")
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let model = std::fs::read_to_string(&path).unwrap();
+    assert!(model.starts_with("\\data\\\nngram 1=7\n\n\\1-grams:\n"));
+    assert!(model.ends_with("\n\n\\end\\\n"));
+    let entries: Vec<(f64, &str)> = model
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(log_prob, word)| (log_prob.parse().unwrap(), word))
+        .collect();
+    assert_eq!(entries.len(), 7);
+    let sum: f64 = entries
+        .iter()
+        .filter(|(_, word)| *word != "<s>")
+        .map(|(log_prob, _)| 10f64.powf(*log_prob))
+        .sum();
+    assert!((sum - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_truncate_token() {
+    // Arrange
+    let long = "a".repeat(100);
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--truncate-token",
+            "20",
+            "--truncate-marker",
+            "",
+            "--no-sleep",
+        ],
+        &format!("{} short\n", long),
+    );
+    let marked = run_with_stdin(&["--truncate-token", "20", "--no-sleep"], &long);
+
+    // Assert
+    assert_eq!(stdout, format!("{}: 1\nshort: 1\n", "a".repeat(20)));
+    assert_eq!(marked, format!("{}…: 1\n", "a".repeat(20)));
+}
+
+#[test]
+fn test_time_budget_prints_partial_counts() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("long.txt");
+    std::fs::write(&path, "first line\n".repeat(1000)).unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--time-budget", "0", "--chunk-size", "10", "--no-sleep"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "first: 10\nline: 10\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Warning: time budget exceeded after processing 1 of ~100 chunks (1.0% of the input); \
+         results are approximate\n"
+    );
+}
+
+#[test]
+fn test_time_budget_on_stdin_leaves_out_the_estimate() {
+    // Arrange
+    let mut child = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--time-budget",
+            "0",
+            "--chunk-size",
+            "10",
+            "--no-sleep",
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Act
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all("first line\n".repeat(1000).as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Warning: time budget exceeded after processing 1 chunks; results are approximate\n"
+    );
+}
+
+#[test]
+fn test_stdin_delimiter() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--stdin-delimiter",
+            "\\u0000",
+            "--per-file",
+            "--no-aggregate",
+            "--no-sleep",
+        ],
+        "a b b\n\0b c\n\0",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "=== <stdin 1> ===\na: 1\nb: 2\n=== <stdin 2> ===\nb: 1\nc: 1\n"
+    );
+}
+
+#[test]
+fn test_min_df() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    std::fs::write(&first, "the cat the cat\n").unwrap();
+    std::fs::write(&second, "the dog\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--min-df", "2", "--no-sleep"])
+        .args([&first, &second])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "the: 3\n");
+}
+
+#[test]
+fn test_max_df() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    std::fs::write(&first, "the cat the cat\n").unwrap();
+    std::fs::write(&second, "the dog\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--max-df", "1", "--no-sleep"])
+        .args([&first, &second])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "cat: 2\ndog: 1\n"
+    );
+}
+
+#[test]
+fn test_document_frequency_rejects_append() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.txt");
+    std::fs::write(&first, "the cat\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--min-df", "2", "--append", "--output"])
+        .arg(dir.path().join("counts.txt"))
+        .arg(&first)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn test_entropy_and_perplexity() {
+    // Act
+    let stdout = run_with_stdin(
+        &["--entropy", "--perplexity", "--quiet", "--no-sleep"],
+        "a b c d\nd c b a\n",
+    );
+
+    // Assert
+    assert_eq!(
+        stdout,
+        "total words: 8  unique words: 4  TTR: 0.5000  MATTR: 0.5000  entropy: 2.0000 bits  \
+         normalized entropy: 1.0000  perplexity: 4.00\n"
+    );
+}
+
+#[test]
+fn test_sentence_per_line() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tokens.txt");
+    std::fs::write(&path, "Dr. Smith went home .\nHe slept !\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--sentence-per-line",
+            "--count-sentences",
+            "--count-lines",
+            "--no-sleep",
+        ])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "lines: 2  sentences: 2\n"
+    );
+}
+
+#[test]
+fn test_pretokenized_conflicts_with_splitting() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--pretokenized", "--split-on-camelcase"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_ngram_separator() {
+    // Act
+    let default = run_with_stdin(&["--ngrams", "2", "--no-sleep"], "the cat sat\n");
+    let separated = run_with_stdin(
+        &["--ngrams", "2", "--ngram-separator", "|", "--no-sleep"],
+        "the cat sat\n",
+    );
+    let formatted = run_with_stdin(
+        &["--ngrams", "3", "--ngram-format", "[{3}/{1}]", "--no-sleep"],
+        "the cat sat\n",
+    );
+
+    // Assert
+    assert_eq!(default, "cat_sat: 1\nthe_cat: 1\n");
+    assert_eq!(separated, "cat|sat: 1\nthe|cat: 1\n");
+    assert_eq!(formatted, "[sat/the]: 1\n");
+}
+
+#[test]
+fn test_ngram_format_beyond_the_ngrams() {
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--ngrams", "2", "--ngram-format", "{1}_{3}"])
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_weight_by_position() {
+    // Arrange
+    let input = format!("late early\n{}late\n", "\n".repeat(998));
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--weight-by-position",
+            "--output-sort-key",
+            "count-desc",
+            "--no-sleep",
+        ],
+        &input,
+    );
+
+    // Assert
+    assert_eq!(stdout, "late: 1.1265\nearly: 1.0000\n");
+}
+
+#[test]
+fn test_show_context() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("doc.txt");
+    let context_path = dir.path().join("kwic.txt");
+    std::fs::write(&path, "the cat sat\na cat\n").unwrap();
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "-i",
+            "--show-context",
+            "cat",
+            "2",
+            "--top",
+            "0",
+            "--no-sleep",
+        ],
+        "The cat sat on the mat\nthe dog saw a CAT today\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--show-context", "cat", "1", "--context-output"])
+        .arg(&context_path)
+        .args(["--no-sleep"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert_eq!(stdout, "  The  cat  sat on\nsaw a  CAT  today\n");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "a: 1\ncat: 2\nsat: 1\nthe: 1\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&context_path).unwrap(),
+        "  a  cat\nthe  cat  sat\n"
+    );
+}
+
+#[test]
+fn test_context_window_frequency() {
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "-i",
+            "--stem",
+            "--context-window-frequency",
+            "Cats",
+            "1",
+            "--top",
+            "0",
+            "--no-sleep",
+        ],
+        "the cat sat\na cat ran\nThe CAT sat down\ncat food\n",
+    );
+
+    // Assert
+    assert_eq!(stdout, "cat: 3 unique contexts in 4 occurrences (0.75)\n");
+}
+
+#[test]
+fn test_report_encoding() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bom.txt");
+    std::fs::write(&path, b"\xef\xbb\xbfna\xc3\xafve cat\n").unwrap();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--report-encoding", "--no-sleep"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "Detected encoding of {}: UTF-8 (BOM), 14 bytes, 10 valid and 0 invalid UTF-8 sequences\n",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn test_log_scale() {
+    // Act
+    let log10 = run_with_stdin(&["--log-scale", "--no-sleep"], "a b b b b b b b b b b\n");
+    let ln = run_with_stdin(&["--log-scale", "--natural-log", "--no-sleep"], "a a\n");
+
+    // Assert
+    assert_eq!(log10, "a: 0.0000\nb: 1.0000\n");
+    assert_eq!(ln, "a: 0.6931\n");
+}
+
+#[test]
+fn test_heaps_diagnostics() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("growth.tsv");
+    let input: String = (0..40)
+        .map(|i| format!("the w{} w{}\n", i, i / 2))
+        .collect();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--heaps-diagnostics", "--heaps-fit", "--chunk-size", "10"])
+        .args(["--top", "0", "--no-sleep", "--heaps-output"])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let points: Vec<(usize, usize)> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (words, unique) = line.split_once('\t').unwrap();
+            (words.parse().unwrap(), unique.parse().unwrap())
+        })
+        .collect();
+    assert_eq!(points.len(), 4);
+    assert_eq!(points.last(), Some(&(120, 41)));
+    assert!(points
+        .windows(2)
+        .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Heaps' law fit: K = "));
+}
+
+#[test]
+fn test_merge() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let parts = ["the cat saw the dog\n", "the dog: a dog\n"];
+    let count_files: Vec<_> = parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let part_path = dir.path().join(format!("part{}.txt", index));
+            let counts_path = dir.path().join(format!("part{}.counts", index));
+            std::fs::write(&part_path, part).unwrap();
+            let status = Command::new(env!("CARGO_BIN_EXE_word_count"))
+                .args(["--no-sleep", "--output"])
+                .arg(&counts_path)
+                .arg(&part_path)
+                .status()
+                .unwrap();
+            assert!(status.success());
+            counts_path
+        })
+        .collect();
+
+    // Act
+    let merged = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .arg("--merge")
+        .args(&count_files)
+        .output()
+        .unwrap();
+    let top = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args([
+            "--output-sort-key",
+            "count-desc",
+            "--top",
+            "2",
+            "--format",
+            "json",
+            "--merge",
+        ])
+        .args(&count_files)
+        .args(["--merge-format", "text"])
+        .output()
+        .unwrap();
+    let single_pass = run_with_stdin(&["--no-sleep"], &parts.concat());
+
+    // Assert
+    assert!(merged.status.success());
+    assert_eq!(String::from_utf8(merged.stdout).unwrap(), single_pass);
+    assert_eq!(
+        String::from_utf8(top.stdout).unwrap(),
+        "{\"the\":3,\"dog\":2}\n"
+    );
+}
+
+#[test]
+fn test_random_sample() {
+    // Arrange
+    let input: String = (0..200).map(|i| format!("the w{}\n", i % 7)).collect();
+
+    // Act
+    let full = run_with_stdin(&["--no-sleep"], &input);
+    let sampled = run_with_stdin(&["--random-sample", "1.0", "--no-sleep"], &input);
+    let unscaled = run_with_stdin(
+        &[
+            "--random-sample",
+            "0.5",
+            "--no-scale-estimates",
+            "--seed",
+            "3",
+            "--top",
+            "1",
+            "--no-sleep",
+        ],
+        &input,
+    );
+
+    // Assert
+    assert_eq!(sampled, full);
+    let (word, count) = unscaled.trim_end().split_once(": ").unwrap();
+    assert_eq!(word, "the");
+    assert!((50..150).contains(&count.parse::<usize>().unwrap()));
+}
+
+#[test]
+fn test_random_sample_differs_per_input() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let input: String = (0..200).map(|i| format!("w{}\n", i)).collect();
+    let paths = ["a.txt", "b.txt"].map(|name| dir.path().join(name));
+    for path in &paths {
+        std::fs::write(path, &input).unwrap();
+    }
+    let sample = || {
+        Command::new(env!("CARGO_BIN_EXE_word_count"))
+            .args([
+                "--random-sample",
+                "0.5",
+                "--no-scale-estimates",
+                "--per-file",
+            ])
+            .args(["--seed", "3", "--no-sleep"])
+            .args(&paths)
+            .output()
+            .unwrap()
+    };
+
+    // Act
+    let first = String::from_utf8(sample().stdout).unwrap();
+    let second = String::from_utf8(sample().stdout).unwrap();
+
+    // Assert
+    assert_eq!(first, second);
+    let (a, b) = first.split_once("b.txt").unwrap();
+    let words = |counts: &str| -> Vec<String> {
+        counts
+            .lines()
+            .filter(|line| line.starts_with('w'))
+            .map(String::from)
+            .collect()
+    };
+    assert_ne!(words(a), words(b));
+}
+
+#[test]
+fn test_every_nth_word() {
+    // Arrange
+    let input: String = (0..60).map(|i| format!("the w{}\n", i % 3)).collect();
+
+    // Act
+    let full = run_with_stdin(&["--no-sleep"], &input);
+    let every_word = run_with_stdin(&["--every-nth-word", "1", "--no-sleep"], &input);
+    let every_line = run_with_stdin(&["--every-nth-line", "1", "--no-sleep"], &input);
+    let scaled = run_with_stdin(&["--every-nth-word", "2", "--no-sleep"], &input);
+    let unscaled = run_with_stdin(
+        &[
+            "--every-nth-word",
+            "2",
+            "--every-nth-line",
+            "3",
+            "--no-scale-estimates",
+            "--no-sleep",
+        ],
+        &input,
+    );
+
+    // Assert
+    assert_eq!(every_word, full);
+    assert_eq!(every_line, full);
+    assert_eq!(scaled, "the: 120\n");
+    // every other word of the first of every three lines, all "the w0"
+    assert_eq!(unscaled, "the: 20\n");
+}
+
+#[test]
+fn test_write_vocab() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let by_word = dir.path().join("by_word.txt");
+    let by_count = dir.path().join("by_count.txt");
+    let input = "the cat and the dog and the bird\n";
+
+    // Act
+    let stdout = run_with_stdin(
+        &["--write-vocab", by_word.to_str().unwrap(), "--no-sleep"],
+        input,
+    );
+    run_with_stdin(
+        &[
+            "--write-vocab",
+            by_count.to_str().unwrap(),
+            "--vocab-sorted-by-count",
+            "--no-sleep",
+        ],
+        input,
+    );
+
+    // Assert
+    assert_eq!(stdout, "and: 2\nbird: 1\ncat: 1\ndog: 1\nthe: 3\n");
+    let vocab = std::fs::read_to_string(&by_word).unwrap();
+    let words: Vec<&str> = vocab.lines().collect();
+    let unique: std::collections::HashSet<&str> = words.iter().copied().collect();
+    assert_eq!(words, ["and", "bird", "cat", "dog", "the"]);
+    assert_eq!(unique.len(), words.len());
+    assert_eq!(
+        std::fs::read_to_string(&by_count).unwrap(),
+        "the\nand\nbird\ncat\ndog\n"
+    );
+}
+
+#[test]
+fn test_extract_urls_and_emails() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let urls = dir.path().join("urls.txt");
+    let emails = dir.path().join("emails.txt");
+    let input = "http://example.com hello\nwrite to bob@example.com or http://example.com.\n";
+
+    // Act
+    let stdout = run_with_stdin(
+        &[
+            "--extract-urls",
+            "--url-output",
+            urls.to_str().unwrap(),
+            "--extract-emails",
+            "--email-output",
+            emails.to_str().unwrap(),
+            "--no-sleep",
+        ],
+        input,
+    );
+    let without_table = run_with_stdin(&["--extract-urls", "--no-sleep"], input);
+
+    // Assert
+    assert_eq!(stdout, "hello: 1\nor: 1\nto: 1\nwrite: 1\n");
+    assert_eq!(
+        std::fs::read_to_string(&urls).unwrap(),
+        "http://example.com: 2\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&emails).unwrap(),
+        "bob@example.com: 1\n"
+    );
+    assert_eq!(
+        without_table,
+        "bob@example.com: 1\nhello: 1\nor: 1\nto: 1\nwrite: 1\n"
+    );
+}
+
+#[test]
+fn test_word_cloud_json() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cloud.json");
+    let input: String = (1..=30)
+        .map(|i| format!("{}\n", vec![format!("w{}", i); i * i].join(" ")))
+        .collect();
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--word-cloud-top", "20", "--word-cloud-json-scale", "log"])
+        .args(["--top", "0", "--no-sleep", "--word-cloud-json"])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let cloud: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let words = cloud.as_array().unwrap();
+    assert_eq!(words.len(), 20);
+    assert_eq!(words[0]["text"], "w30");
+    assert_eq!(words[0]["weight"], 100);
+    assert_eq!(words[19]["weight"], 1);
+    assert!(words
+        .iter()
+        .all(|word| (1..=100).contains(&word["weight"].as_u64().unwrap())));
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "plot")]
+
+use std::process::Command;
+
+#[test]
+fn test_word_frequency_plot() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("zipf.png");
+
+    // Act
+    let output = Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--zipf-exponent", "1.2"])
+        .arg("--word-frequency-plot")
+        .arg(&path)
+        .arg("--process-text")
+        .arg("the cat saw the dog and the dog saw a cat")
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let png = std::fs::read(&path).unwrap();
+    assert!(!png.is_empty());
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+}
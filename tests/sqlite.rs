@@ -0,0 +1,76 @@
+#![cfg(feature = "sqlite")]
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite::Connection;
+use word_count::export_sqlite;
+
+/// Read the whole `word_counts` table of the database at `path`
+fn query_counts(path: &Path) -> BTreeMap<String, usize> {
+    let connection = Connection::open(path).unwrap();
+    let mut query = connection
+        .prepare("SELECT word, count FROM word_counts")
+        .unwrap();
+    query
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn test_export_sqlite() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("counts.db");
+    let word_counts = BTreeMap::from([(String::from("a"), 2), (String::from("b"), 1)]);
+    // an old database is replaced
+    export_sqlite(&path, &BTreeMap::from([(String::from("old"), 1)]), false).unwrap();
+
+    // Act
+    export_sqlite(&path, &word_counts, false).unwrap();
+
+    // Assert
+    assert_eq!(query_counts(&path), word_counts);
+}
+
+#[test]
+fn test_export_sqlite_append() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("counts.db");
+    export_sqlite(&path, &BTreeMap::from([(String::from("a"), 2)]), false).unwrap();
+    let word_counts = BTreeMap::from([(String::from("a"), 1), (String::from("b"), 1)]);
+
+    // Act
+    export_sqlite(&path, &word_counts, true).unwrap();
+
+    // Assert
+    assert_eq!(
+        query_counts(&path),
+        BTreeMap::from([(String::from("a"), 3), (String::from("b"), 1)])
+    );
+}
+
+#[test]
+fn test_cli_export_sqlite() {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("counts.db");
+
+    // Act
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_word_count"))
+        .args(["--no-sleep", "--top", "1", "--export-sqlite"])
+        .arg(&path)
+        .arg("Cargo.toml")
+        .output()
+        .unwrap();
+
+    // Assert
+    assert!(output.status.success());
+    let exported = query_counts(&path);
+    // everything is exported, not just the printed entries
+    assert!(exported.len() > 1);
+    assert_eq!(exported.get("[package]"), Some(&1));
+}
@@ -0,0 +1,8 @@
+// Run with `make wasm-test`, which builds the module into pkg/ first
+const assert = require("node:assert/strict");
+
+const { wasm_count_text } = require("../../pkg/word_count.js");
+
+const counts = wasm_count_text("the cat\nthe dog\n");
+assert.deepEqual({ ...counts }, { cat: 1, dog: 1, the: 2 });
+console.log("wasm_count_text: ok");